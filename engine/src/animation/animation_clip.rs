@@ -1,42 +1,231 @@
 use super::clip::ClipReference;
+use super::constraint::Constraint;
 use super::cubic_bezier::CubicBezier;
+use super::easing::Easing;
+use super::expression::Expression;
+use super::modifier::Modifier;
+use super::modifier::SpringState;
 use super::property::PropertyValue;
+use super::spline_path::SplinePath;
+use super::tempo_map::BeatPosition;
+use crate::math::ColorSpace;
 use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::slice;
 
 pub struct AnimationClip {
     pub target_clip: ClipReference,
-    //pub time_property: AnimatedPropertyField,
+    /// Optional curve remapping the clip's local time. Its value is the progress through the
+    /// clip, where 0 is the clip's start and 1 is its end.
+    pub time_property: Option<AnimatedPropertyField>,
+    pub blend_mode: BlendMode,
+    /// Optional curve controlling how strongly the clip is blended onto its target. Without one,
+    /// the clip has a weight of 1.
+    pub weight_property: Option<AnimatedPropertyField>,
     pub properties: Vec<AnimatedProperty>,
-    //pub is_time_collapsed: bool,
+    pub is_time_collapsed: bool,
+    pub is_weight_collapsed: bool,
+}
+
+impl AnimationClip {
+    /// Every keyframed field in the clip, including the time and weight fields.
+    pub fn fields(&self) -> impl Iterator<Item = &AnimatedPropertyField> {
+        self.time_property
+            .iter()
+            .chain(self.weight_property.iter())
+            .chain(
+                self.properties
+                    .iter()
+                    .flat_map(|property| property.target.fields()),
+            )
+    }
+
+    pub fn fields_mut(&mut self) -> impl Iterator<Item = &mut AnimatedPropertyField> {
+        self.time_property
+            .iter_mut()
+            .chain(self.weight_property.iter_mut())
+            .chain(
+                self.properties
+                    .iter_mut()
+                    .flat_map(|property| property.target.fields_mut()),
+            )
+    }
+}
+
+/// Determines how an animation clip's values are combined with the value of the property they
+/// target. Clips are blended in track order, so a clip on a later track is blended on top of the
+/// result of clips on earlier tracks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Interpolates from the current value towards the animated value by the clip's weight.
+    Replace,
+    /// Adds the animated value, scaled by the clip's weight, to the current value.
+    Additive,
+    /// Multiplies the current value by the animated value, interpolated from 1 by the clip's
+    /// weight.
+    Multiply,
+}
+
+impl Default for BlendMode {
+    fn default() -> Self {
+        BlendMode::Replace
+    }
 }
 
 pub struct AnimatedProperty {
     pub group_index: usize,
     pub property_index: usize,
     pub target: AnimatedPropertyTarget,
+    pub modifiers: Vec<Modifier>,
     pub is_collapsed: bool,
+    /// The state of the property's spring modifier the last time it was evaluated, so playing or
+    /// scrubbing forwards doesn't integrate from the start of the clip every frame. It has to be
+    /// cleared when the animation is edited.
+    pub spring_cache: RefCell<Option<SpringState>>,
 }
 
 pub enum AnimatedPropertyTarget {
     Joined(AnimatedPropertyField),
     Separate(Vec<AnimatedPropertyField>),
+    Expression(Expression),
+    /// Moves a `Vec3` property along a path, where the path's progress is keyframed.
+    Path(SplinePath),
+    /// Sets the property from other properties once every clip has been animated.
+    Constraint(Constraint),
+}
+
+impl AnimatedPropertyTarget {
+    /// The keyframed fields of the target. Expressions and constraints don't have any, and paths
+    /// only have their progress.
+    pub fn fields(&self) -> &[AnimatedPropertyField] {
+        match self {
+            AnimatedPropertyTarget::Joined(field) => slice::from_ref(field),
+            AnimatedPropertyTarget::Separate(fields) => fields,
+            AnimatedPropertyTarget::Expression(_) | AnimatedPropertyTarget::Constraint(_) => &[],
+            AnimatedPropertyTarget::Path(path) => slice::from_ref(&path.progress),
+        }
+    }
+
+    pub fn fields_mut(&mut self) -> &mut [AnimatedPropertyField] {
+        match self {
+            AnimatedPropertyTarget::Joined(field) => slice::from_mut(field),
+            AnimatedPropertyTarget::Separate(fields) => fields,
+            AnimatedPropertyTarget::Expression(_) | AnimatedPropertyTarget::Constraint(_) => {
+                &mut []
+            }
+            AnimatedPropertyTarget::Path(path) => slice::from_mut(&mut path.progress),
+        }
+    }
 }
 
 pub struct AnimatedPropertyField {
     pub local_offset_frames: i32,
+    /// If set, the first keyframe is moved to this position when the tempo changes.
+    #[cfg(debug_assertions)]
+    pub start_anchor: Option<BeatPosition>,
     pub start_value: PropertyValue,
     pub segments: Vec<CurveSegment>,
+    pub pre_extrapolation: Extrapolation,
+    pub post_extrapolation: Extrapolation,
+}
+
+impl AnimatedPropertyField {
+    pub fn duration_frames(&self) -> u32 {
+        self.segments
+            .iter()
+            .map(|segment| segment.duration_frames)
+            .sum()
+    }
+
+    pub fn end_value(&self) -> PropertyValue {
+        match self.segments.last() {
+            Some(segment) => segment.end_value,
+            None => self.start_value,
+        }
+    }
+
+    /// The value of a keyframe, where keyframe 0 is the start value. Indices past the end give the
+    /// last keyframe.
+    pub fn key_value(&self, key_index: usize) -> PropertyValue {
+        match key_index.checked_sub(1) {
+            None => self.start_value,
+            Some(segment_index) => match self.segments.get(segment_index) {
+                Some(segment) => segment.end_value,
+                None => self.end_value(),
+            },
+        }
+    }
+
+    /// Evaluates a segment a fraction of the way through, after the interpolation curve has
+    /// been applied.
+    pub fn interpolate_segment(&self, segment_index: usize, amount: f32) -> PropertyValue {
+        let segment = &self.segments[segment_index];
+        let start_value = self.key_value(segment_index);
+
+        match (start_value, segment.end_value) {
+            (PropertyValue::Rotation(start_rot), PropertyValue::Rotation(end_rot))
+                if segment.rotation_spline =>
+            {
+                let previous_rot = self
+                    .key_value(segment_index.max(1) - 1)
+                    .into_rotation()
+                    .unwrap();
+                let next_rot = self.key_value(segment_index + 2).into_rotation().unwrap();
+                PropertyValue::Rotation(start_rot.squad(end_rot, previous_rot, next_rot, amount))
+            }
+            _ => start_value
+                .lerp_in(segment.end_value, amount, segment.color_space)
+                .unwrap(),
+        }
+    }
+}
+
+/// Determines how a field is evaluated before its first keyframe or after its last one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extrapolation {
+    /// Holds the value of the closest keyframe.
+    Constant,
+    /// Continues along the slope of the closest keyframe.
+    Linear,
+    /// Repeats the keyed range.
+    Cycle,
+    /// Repeats the keyed range, offsetting each repetition by the difference between the first
+    /// and last keyframes.
+    CycleWithOffset,
+    /// Repeats the keyed range, alternating between forwards and backwards.
+    PingPong,
+}
+
+impl Default for Extrapolation {
+    fn default() -> Self {
+        Extrapolation::Constant
+    }
 }
 
 pub struct CurveSegment {
     pub duration_frames: u32,
+    /// If set, the keyframe at the end of the segment is moved to this position when the tempo
+    /// changes.
+    #[cfg(debug_assertions)]
+    pub end_anchor: Option<BeatPosition>,
     pub end_value: PropertyValue,
     pub interpolation: CurveInterpolation,
+    /// Interpolates rotations on a spline through the neighbouring keyframes, instead of taking
+    /// the shortest path straight to the end value. This keeps the rotation speed smooth across
+    /// keyframes. Only used for rotation fields.
+    pub rotation_spline: bool,
+    /// The colour space colours are interpolated in. Only used for colour fields.
+    pub color_space: ColorSpace,
 }
 
 pub enum CurveInterpolation {
     Linear,
     CubicBezier(CubicBezier),
+    /// Holds the start value until the end of the segment.
+    Step,
+    /// Jumps to the end value as soon as the segment starts.
+    StepNext,
+    Ease(Easing),
 }
 
 impl CurveInterpolation {
@@ -44,6 +233,21 @@ impl CurveInterpolation {
         match self {
             CurveInterpolation::Linear => t,
             CurveInterpolation::CubicBezier(bezier) => bezier.get_y_at(t),
+            CurveInterpolation::Step => {
+                if t >= 1. {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            CurveInterpolation::StepNext => {
+                if t > 0. {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            CurveInterpolation::Ease(easing) => easing.eval(t),
         }
     }
 
@@ -60,4 +264,25 @@ impl CurveInterpolation {
             _ => false,
         }
     }
+
+    pub fn is_step(&self) -> bool {
+        match self {
+            CurveInterpolation::Step => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_step_next(&self) -> bool {
+        match self {
+            CurveInterpolation::StepNext => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_ease(&self, easing: Easing) -> bool {
+        match self {
+            CurveInterpolation::Ease(this_easing) => *this_easing == easing,
+            _ => false,
+        }
+    }
 }
@@ -1,4 +1,5 @@
 use super::property::PropertyValue;
+#[cfg(feature = "d3d")]
 use crate::generator::Generator;
 use alloc::string::String;
 use alloc::vec::Vec;
@@ -21,7 +22,8 @@ impl ClipReference {
 pub struct ClipPropertyValue {
     pub value: PropertyValue,
     pub is_overridden: bool,
-    pub targeted_by: Option<ClipReference>,
+    /// Every animation clip contributing to this value, in the order they were blended.
+    pub targeted_by: Vec<ClipReference>,
 }
 
 pub struct ActiveClip {
@@ -33,12 +35,15 @@ pub struct ActiveClip {
     pub properties: Vec<Vec<ClipPropertyValue>>,
 }
 
+/// The active clips, which must be ordered by their track index so animation clips are blended in
+/// a consistent order.
 pub trait ActiveClipMap {
     fn active_clips(&self) -> &[ActiveClip];
     fn active_clips_mut(&mut self) -> &mut [ActiveClip];
     fn get_clip_index(&self, reference: ClipReference) -> Option<usize>;
 }
 
+#[cfg(feature = "d3d")]
 pub trait GeneratorClipMap {
     fn try_get_clip(&self, reference: ClipReference) -> Option<&dyn Generator>;
     fn try_get_clip_mut(&mut self, reference: ClipReference) -> Option<&mut dyn Generator>;
@@ -1,20 +1,49 @@
-use super::animation_clip::{AnimatedPropertyField, AnimatedPropertyTarget};
+use super::animation_clip::{
+    AnimatedPropertyField, AnimatedPropertyTarget, AnimationClip, BlendMode, Extrapolation,
+};
 use super::clip::ActiveClipMap;
-use super::property::PropertyValue;
-use super::timeline::{ClipSource, Timeline};
+use super::expression::{ExpressionVariables, EXPRESSION_FRAME_RATE};
+use super::modifier::{apply_modifiers, integrate_spring, Modifier, SPRING_STEP_SECONDS};
+use super::property::{PropertyType, PropertyValue};
+use super::spline_path::SplinePath;
+use super::timeline::{ClipSource, TimeBase, Timeline};
+use crate::math::Float;
 
-pub fn coallesce_animations(timeline: &Timeline, clip_map: &mut ActiveClipMap) {
+pub fn coallesce_animations(
+    timeline: &Timeline,
+    clip_map: &mut ActiveClipMap,
+    time_base: TimeBase,
+) {
+    coallesce(timeline, clip_map, time_base, true);
+}
+
+/// Like `coallesce_animations`, but springs are integrated from the start of their clips rather
+/// than continuing from their caches, which are left untouched. This is for evaluating frames
+/// other than the one being played.
+pub fn coallesce_animations_uncached(
+    timeline: &Timeline,
+    clip_map: &mut ActiveClipMap,
+    time_base: TimeBase,
+) {
+    coallesce(timeline, clip_map, time_base, false);
+}
+
+fn coallesce(
+    timeline: &Timeline,
+    clip_map: &mut ActiveClipMap,
+    time_base: TimeBase,
+    use_spring_caches: bool,
+) {
     for active_clip_index in 0..clip_map.active_clips_mut().len() {
         let active_clip = &clip_map.active_clips_mut()[active_clip_index];
         let active_local_time = active_clip.local_time;
         let active_reference = active_clip.reference;
-        let clip = match timeline.tracks[active_clip.track_index]
-            .clips
-            .get(active_clip.clip_index)
-        {
+        let track = &timeline.tracks[active_clip.track_index];
+        let clip = match track.clips.get(active_clip.clip_index) {
             Some(clip) => clip,
             None => continue, // the clip has been deleted
         };
+        let clip_start_frame = track.clip_start_frame(active_clip.clip_index);
 
         // If the clip is an animation, process it and apply it to the relevant buffer
         let animation_clip = match &clip.source {
@@ -26,63 +55,279 @@ pub fn coallesce_animations(timeline: &Timeline, clip_map: &mut ActiveClipMap) {
             None => continue,
         };
 
-        // Evaluate the clip's time property to use when calculating the actual fields
-        //let time_progress =
-        //    get_animation_field_value(&animation_clip.time_property, active_local_time as f32)
-        //        .into_float()
-        //        .unwrap();
-        //let progress_local_time = clip.duration_frames as f32 * time_progress;
-        let progress_local_time = active_local_time as f32;
+        let progress_local_time = get_progress_time(
+            animation_clip,
+            clip.duration_frames,
+            active_local_time as f32,
+        );
+        let weight = get_weight(animation_clip, active_local_time);
+        let expression_variables = get_expression_variables(
+            time_base,
+            (clip_start_frame + active_local_time) as f32,
+            progress_local_time / clip.duration_frames as f32,
+        );
 
         // Apply each animation property
         for animated_property in &animation_clip.properties {
+            // Paths can also point a rotation property along the direction they're moving in
+            if let AnimatedPropertyTarget::Path(path) = &animated_property.target {
+                if let Some(orient_target) = path.orient_target {
+                    let orient_property_val =
+                        &mut target_clip.properties[orient_target.group][orient_target.prop];
+                    orient_property_val.targeted_by.push(active_reference);
+
+                    if !orient_property_val.is_overridden {
+                        let progress = get_path_progress(path, progress_local_time);
+                        orient_property_val.value = blend_value(
+                            orient_property_val.value,
+                            PropertyValue::Rotation(path.orientation_at(progress)),
+                            animation_clip.blend_mode,
+                            weight,
+                        );
+                    }
+                }
+            }
+
             let target_property_val = &mut target_clip.properties[animated_property.group_index]
                 [animated_property.property_index];
 
-            target_property_val.targeted_by = Some(active_reference);
+            target_property_val.targeted_by.push(active_reference);
 
-            // skip the property if it's overridden
+            // skip the property if it's overridden, or if it's constrained since constraints are
+            // applied once every clip has been animated
             if target_property_val.is_overridden {
                 continue;
             }
+            if let AnimatedPropertyTarget::Constraint(_) = &animated_property.target {
+                continue;
+            }
 
-            let animated_value = match &animated_property.target {
-                AnimatedPropertyTarget::Joined(field) => {
-                    get_animation_field_value(field, progress_local_time)
-                }
-                AnimatedPropertyTarget::Separate(fields) => {
-                    let mut value_iter = fields.iter().map(|field| {
-                        get_animation_field_value(field, progress_local_time)
-                            .into_float()
-                            .unwrap()
-                    });
-                    let target_type = target_property_val.value.get_type();
-                    PropertyValue::from_fields(target_type, &mut value_iter).unwrap()
+            let target_type = target_property_val.value.get_type();
+            let animated_value = match get_target_value(
+                &animated_property.target,
+                target_type,
+                progress_local_time,
+                &expression_variables,
+            ) {
+                Some(value) => value,
+                None => continue, // the expression doesn't give a usable value
+            };
+
+            let spring = animated_property
+                .modifiers
+                .iter()
+                .enumerate()
+                .filter_map(|(modifier_index, modifier)| match *modifier {
+                    Modifier::Spring { frequency, damping } => {
+                        Some((modifier_index, frequency, damping))
+                    }
+                    _ => None,
+                })
+                .next();
+            let animated_value = match spring {
+                Some((spring_index, frequency, damping)) => {
+                    // The spring follows the value with the modifiers before it applied, from the
+                    // start of the clip
+                    let mut input = |local_time: f32| {
+                        let progress_time =
+                            get_progress_time(animation_clip, clip.duration_frames, local_time);
+                        let variables = get_expression_variables(
+                            time_base,
+                            clip_start_frame as f32 + local_time,
+                            progress_time / clip.duration_frames as f32,
+                        );
+                        let value = get_target_value(
+                            &animated_property.target,
+                            target_type,
+                            progress_time,
+                            &variables,
+                        )
+                        .unwrap_or(animated_value);
+                        apply_modifiers(
+                            &animated_property.modifiers[..spring_index],
+                            value,
+                            get_euler_reference(&animated_property.target, value),
+                            progress_time,
+                        )
+                    };
+                    let sprung_value = integrate_spring(
+                        frequency,
+                        damping,
+                        target_type,
+                        active_local_time as f32,
+                        time_base.seconds_to_frame(SPRING_STEP_SECONDS),
+                        &mut input,
+                        if use_spring_caches {
+                            Some(&animated_property.spring_cache)
+                        } else {
+                            None
+                        },
+                    );
+
+                    apply_modifiers(
+                        &animated_property.modifiers[spring_index + 1..],
+                        sprung_value,
+                        sprung_value,
+                        progress_local_time,
+                    )
                 }
+                None => apply_modifiers(
+                    &animated_property.modifiers,
+                    animated_value,
+                    get_euler_reference(&animated_property.target, animated_value),
+                    progress_local_time,
+                ),
             };
 
-            target_property_val.value = animated_value;
+            target_property_val.value = blend_value(
+                target_property_val.value,
+                animated_value,
+                animation_clip.blend_mode,
+                weight,
+            );
+        }
+    }
+}
+
+/// Evaluates the target of an animated property, before any modifiers are applied. Returns `None`
+/// if the target is an expression that doesn't give a value of the right type, or a constraint.
+fn get_target_value(
+    target: &AnimatedPropertyTarget,
+    target_type: PropertyType,
+    progress_local_time: f32,
+    expression_variables: &ExpressionVariables,
+) -> Option<PropertyValue> {
+    match target {
+        AnimatedPropertyTarget::Joined(field) => {
+            Some(get_animation_field_value(field, progress_local_time))
+        }
+        AnimatedPropertyTarget::Separate(fields) => {
+            let mut value_iter = fields.iter().map(|field| {
+                get_animation_field_value(field, progress_local_time)
+                    .into_float()
+                    .unwrap()
+            });
+            PropertyValue::from_fields(target_type, &mut value_iter)
+        }
+        AnimatedPropertyTarget::Expression(expression) => {
+            expression.evaluate(expression_variables, target_type)
+        }
+        AnimatedPropertyTarget::Path(path) => Some(PropertyValue::Vec3(
+            path.position_at(get_path_progress(path, progress_local_time)),
+        )),
+        AnimatedPropertyTarget::Constraint(_) => None,
+    }
+}
+
+/// Finds the rotation that modifiers convert an animated value's euler angles near, so keyframed
+/// rotations are modified in the same angles they're keyed in.
+fn get_euler_reference(target: &AnimatedPropertyTarget, value: PropertyValue) -> PropertyValue {
+    match target {
+        AnimatedPropertyTarget::Joined(field) => field.start_value,
+        _ => value,
+    }
+}
+
+fn get_expression_variables(
+    time_base: TimeBase,
+    frame: f32,
+    clip_progress: f32,
+) -> ExpressionVariables {
+    let seconds = time_base.frame_to_seconds(frame);
+    ExpressionVariables {
+        t: seconds,
+        frame: seconds * EXPRESSION_FRAME_RATE,
+        beat: time_base.tempo_map.seconds_to_beats(seconds),
+        clip_t: clip_progress,
+    }
+}
+
+/// Evaluates an animation clip's time property to find the time its fields are evaluated at.
+pub fn get_progress_time(
+    animation_clip: &AnimationClip,
+    duration_frames: u32,
+    local_time: f32,
+) -> f32 {
+    match &animation_clip.time_property {
+        Some(time_property) => {
+            let time_progress = get_animation_field_value(time_property, local_time)
+                .into_float()
+                .unwrap();
+            duration_frames as f32 * time_progress
         }
+        None => local_time,
     }
 }
 
-fn get_animation_field_value(field: &AnimatedPropertyField, local_clip_time: f32) -> PropertyValue {
+/// Evaluates an animation clip's weight. The weight isn't affected by time remapping, so it can be
+/// used to fade the clip in and out.
+pub fn get_weight(animation_clip: &AnimationClip, local_time: u32) -> f32 {
+    match &animation_clip.weight_property {
+        Some(weight_property) => get_animation_field_value(weight_property, local_time as f32)
+            .into_float()
+            .unwrap(),
+        None => 1.,
+    }
+}
+
+pub fn blend_value(
+    current_value: PropertyValue,
+    animated_value: PropertyValue,
+    blend_mode: BlendMode,
+    weight: f32,
+) -> PropertyValue {
+    match blend_mode {
+        BlendMode::Replace if weight == 1. => animated_value,
+        BlendMode::Replace => current_value.lerp(animated_value, weight).unwrap(),
+        BlendMode::Additive => current_value.add(animated_value, weight).unwrap(),
+        BlendMode::Multiply => current_value.multiply(animated_value, weight).unwrap(),
+    }
+}
+
+fn get_path_progress(path: &SplinePath, local_clip_time: f32) -> f32 {
+    get_animation_field_value(&path.progress, local_clip_time)
+        .into_float()
+        .unwrap()
+}
+
+pub fn get_animation_field_value(
+    field: &AnimatedPropertyField,
+    local_clip_time: f32,
+) -> PropertyValue {
     let local_field_time = local_clip_time - field.local_offset_frames as f32;
+    let field_duration = field.duration_frames() as f32;
+
     if local_field_time < 0. {
-        return field.start_value;
+        extrapolate_field_value(
+            field,
+            field.pre_extrapolation,
+            local_field_time,
+            field_duration,
+        )
+    } else if local_field_time > field_duration {
+        extrapolate_field_value(
+            field,
+            field.post_extrapolation,
+            local_field_time,
+            field_duration,
+        )
+    } else {
+        get_keyed_field_value(field, local_field_time)
     }
+}
 
+fn get_keyed_field_value(field: &AnimatedPropertyField, local_field_time: f32) -> PropertyValue {
     // Find the segment that's active at the current time, as well as the start time of the last one
     let mut last_end_val = field.start_value;
     let mut last_end_time = 0;
-    for segment in &field.segments {
+    for (segment_index, segment) in field.segments.iter().enumerate() {
         if local_field_time < (last_end_time + segment.duration_frames) as f32 {
             // We found a clip! Apply interpolation on it.
             let local_curve_time = local_field_time - last_end_time as f32;
             let curve_progress = local_curve_time / segment.duration_frames as f32;
             let curve_lerp = segment.interpolation.eval(curve_progress);
 
-            return last_end_val.lerp(segment.end_value, curve_lerp).unwrap();
+            return field.interpolate_segment(segment_index, curve_lerp);
         }
 
         last_end_val = segment.end_value;
@@ -93,3 +338,84 @@ fn get_animation_field_value(field: &AnimatedPropertyField, local_clip_time: f32
     // the last one.
     last_end_val
 }
+
+fn extrapolate_field_value(
+    field: &AnimatedPropertyField,
+    extrapolation: Extrapolation,
+    local_field_time: f32,
+    field_duration: f32,
+) -> PropertyValue {
+    // A field with a single keyframe has no range to repeat or slope to follow
+    if field_duration <= 0. {
+        return field.start_value;
+    }
+
+    match extrapolation {
+        Extrapolation::Constant => {
+            if local_field_time < 0. {
+                field.start_value
+            } else {
+                field.end_value()
+            }
+        }
+        Extrapolation::Linear => {
+            // Continue along the slope of the first or last frame of the curve. Lerping with an
+            // amount outside of 0-1 does the extrapolation for us.
+            let slope_frames = field_duration.min(1.);
+            if local_field_time < 0. {
+                let inner_value = get_keyed_field_value(field, slope_frames);
+                field
+                    .start_value
+                    .lerp(inner_value, local_field_time / slope_frames)
+                    .unwrap()
+            } else {
+                let inner_value = get_keyed_field_value(field, field_duration - slope_frames);
+                field
+                    .end_value()
+                    .lerp(
+                        inner_value,
+                        (field_duration - local_field_time) / slope_frames,
+                    )
+                    .unwrap()
+            }
+        }
+        Extrapolation::Cycle | Extrapolation::CycleWithOffset | Extrapolation::PingPong => {
+            let cycle_index = (local_field_time / field_duration).floor();
+            let cycle_time = local_field_time - cycle_index * field_duration;
+
+            match extrapolation {
+                Extrapolation::CycleWithOffset => offset_value(
+                    get_keyed_field_value(field, cycle_time),
+                    field.start_value,
+                    field.end_value(),
+                    cycle_index,
+                ),
+                Extrapolation::PingPong if cycle_index as i32 % 2 != 0 => {
+                    get_keyed_field_value(field, field_duration - cycle_time)
+                }
+                _ => get_keyed_field_value(field, cycle_time),
+            }
+        }
+    }
+}
+
+/// Offsets a value by the difference between the start and end values, multiplied by the number
+/// of cycles. This is done per-field, so rotations are offset in euler angles, which are kept
+/// close to the start value's so the offset doesn't flip to an equivalent set of angles.
+fn offset_value(
+    value: PropertyValue,
+    start_value: PropertyValue,
+    end_value: PropertyValue,
+    cycles: f32,
+) -> PropertyValue {
+    // Clip references can't be offset, so they just repeat
+    if let PropertyValue::ClipReference(_) = value {
+        return value;
+    }
+
+    let mut offset_fields = value
+        .fields_near(start_value)
+        .zip(start_value.fields().zip(end_value.fields_near(start_value)))
+        .map(|(field, (start_field, end_field))| field + (end_field - start_field) * cycles);
+    PropertyValue::from_fields(value.get_type(), &mut offset_fields).unwrap()
+}
@@ -0,0 +1,273 @@
+use super::animation_clip::AnimatedPropertyTarget;
+use super::clip::{ActiveClip, ActiveClipMap, ClipReference};
+use super::coallesce::{blend_value, get_progress_time, get_weight};
+use super::evaluate::evaluate_animations_of;
+use super::modifier::apply_modifiers;
+use super::property::PropertyValue;
+use super::timeline::{ClipSource, TimeBase, Timeline};
+use crate::binding::PropertyBinding;
+use crate::math::{Float, Quaternion, Vector3};
+use core::cell::RefCell;
+
+/// How far apart damped follow constraints sample their target, in seconds. Like springs, the
+/// step is fixed in seconds rather than frames so the tool and the player take the same steps.
+pub const DAMPED_FOLLOW_STEP_SECONDS: f32 = 1. / 60.;
+
+/// A `Vec3` property of another clip that a constraint follows.
+#[derive(Clone, Copy)]
+pub struct ConstraintTarget {
+    pub clip: ClipReference,
+    pub property: PropertyBinding,
+}
+
+/// Sets a property from the position of another clip once every clip has been animated and its
+/// drivers resolved. The result is blended onto the property like any other animated value.
+pub enum Constraint {
+    /// Points a `Rotation` property from a `Vec3` property of the same clip towards the target.
+    LookAt {
+        eye: PropertyBinding,
+        target: ConstraintTarget,
+    },
+    /// Keeps a `Vec3` property at an offset from the target.
+    Follow {
+        target: ConstraintTarget,
+        offset: Vector3,
+    },
+    /// Like `Follow`, but trails behind the target by moving towards it a little every step, so
+    /// each of its past positions has an influence that halves every `half_life` seconds. Past
+    /// positions only have the target's animations applied, not drivers or constraints.
+    DampedFollow {
+        target: ConstraintTarget,
+        offset: Vector3,
+        half_life: f32,
+        /// The state the last time the constraint was evaluated, so playing or scrubbing forwards
+        /// doesn't step from the start of the clip every frame. It has to be cleared when the
+        /// animation is edited.
+        cache: RefCell<Option<FollowState>>,
+    },
+}
+
+/// A damped follow's position after a number of steps from the start of its clip.
+#[derive(Clone, Copy)]
+pub struct FollowState {
+    step: u32,
+    position: Vector3,
+}
+
+impl Constraint {
+    pub fn target(&self) -> ConstraintTarget {
+        match *self {
+            Constraint::LookAt { target, .. }
+            | Constraint::Follow { target, .. }
+            | Constraint::DampedFollow { target, .. } => target,
+        }
+    }
+
+    pub fn target_mut(&mut self) -> &mut ConstraintTarget {
+        match self {
+            Constraint::LookAt { target, .. }
+            | Constraint::Follow { target, .. }
+            | Constraint::DampedFollow { target, .. } => target,
+        }
+    }
+
+    /// Forgets the state cached between frames, if the constraint has any.
+    pub fn clear_cache(&self) {
+        if let Constraint::DampedFollow { cache, .. } = self {
+            *cache.borrow_mut() = None;
+        }
+    }
+
+    /// Finds the constrained value of a property of an active clip, or returns `None` if the
+    /// target isn't active or the positions can't be used.
+    fn evaluate(
+        &self,
+        timeline: &Timeline,
+        clip_map: &ActiveClipMap,
+        constrained_clip_index: usize,
+        frame: u32,
+        local_time: u32,
+        time_base: TimeBase,
+    ) -> Option<PropertyValue> {
+        let target = self.target();
+        let active_clips = clip_map.active_clips();
+        let target_position = get_position(
+            &active_clips[clip_map.get_clip_index(target.clip)?],
+            target.property,
+        )?;
+
+        match self {
+            &Constraint::LookAt { eye, .. } => {
+                let eye_position = get_position(&active_clips[constrained_clip_index], eye)?;
+                let direction = target_position - eye_position;
+                if direction.length_squared() == 0. {
+                    return None;
+                }
+                Some(PropertyValue::Rotation(Quaternion::look_along(
+                    direction.unit(),
+                )))
+            }
+            &Constraint::Follow { offset, .. } => {
+                Some(PropertyValue::Vec3(target_position + offset))
+            }
+            Constraint::DampedFollow {
+                offset,
+                half_life,
+                cache,
+                ..
+            } => Some(PropertyValue::Vec3(
+                damped_position(
+                    timeline,
+                    target,
+                    target_position,
+                    frame - local_time,
+                    local_time,
+                    *half_life,
+                    time_base,
+                    cache,
+                ) + *offset,
+            )),
+        }
+    }
+}
+
+fn get_position(active_clip: &ActiveClip, binding: PropertyBinding) -> Option<Vector3> {
+    let prop = active_clip
+        .properties
+        .get(binding.group)
+        .and_then(|group| group.get(binding.prop))?;
+    match prop.value {
+        PropertyValue::Vec3(position) => Some(position),
+        _ => None,
+    }
+}
+
+/// Finds the position of the target at a frame from its own animations.
+fn animated_position(
+    timeline: &Timeline,
+    target: ConstraintTarget,
+    frame: u32,
+    time_base: TimeBase,
+) -> Option<Vector3> {
+    evaluate_animations_of(timeline, target.clip, frame, time_base)
+        .and_then(|active_clip| get_position(&active_clip, target.property))
+}
+
+/// Follows the target's position with a filter that starts on the target at the start of the
+/// constraint's clip, and moves part of the way towards it each fixed step. The result only
+/// depends on the time, and each step only needs the target's position once. If `cache` holds a
+/// state from before `local_time`, stepping continues from it and the cache is updated.
+fn damped_position(
+    timeline: &Timeline,
+    target: ConstraintTarget,
+    current_position: Vector3,
+    clip_start_frame: u32,
+    local_time: u32,
+    half_life: f32,
+    time_base: TimeBase,
+    cache: &RefCell<Option<FollowState>>,
+) -> Vector3 {
+    let step_frames = time_base.seconds_to_frame(DAMPED_FOLLOW_STEP_SECONDS);
+    if half_life <= 0. || step_frames <= 0. {
+        return current_position;
+    }
+
+    let last_step = (local_time as f32 / step_frames).floor() as u32;
+    let cached_state = *cache.borrow();
+    let mut state = match cached_state {
+        Some(state) if state.step <= last_step => state,
+        _ => FollowState {
+            step: 0,
+            position: animated_position(timeline, target, clip_start_frame, time_base)
+                .unwrap_or(current_position),
+        },
+    };
+
+    // Steps are taken towards the target's position at the end of each step. If the target
+    // isn't active then, the position is left where it is.
+    let step_weight = 1. - (-DAMPED_FOLLOW_STEP_SECONDS / half_life).exp2();
+    while state.step < last_step {
+        state.step += 1;
+        let step_frame = clip_start_frame + (state.step as f32 * step_frames).round() as u32;
+        if let Some(position) = animated_position(timeline, target, step_frame, time_base) {
+            state.position = state.position + (position - state.position) * step_weight;
+        }
+    }
+    *cache.borrow_mut() = Some(state);
+
+    // Finish with a partial step up to the current position, which isn't cached since it isn't a
+    // whole step
+    let remaining_seconds =
+        time_base.frame_to_seconds(local_time as f32 - last_step as f32 * step_frames);
+    let remaining_weight = 1. - (-remaining_seconds / half_life).exp2();
+    state.position + (current_position - state.position) * remaining_weight
+}
+
+/// Applies the constraints of every active animation clip, once animations and drivers have been
+/// applied. Constraints are applied in track order, so a constraint can follow a property set by a
+/// constraint on an earlier track. Constraints whose target clip isn't active are skipped.
+pub fn apply_constraints(timeline: &Timeline, clip_map: &mut ActiveClipMap, time_base: TimeBase) {
+    for active_clip_index in 0..clip_map.active_clips().len() {
+        let active_clip = &clip_map.active_clips()[active_clip_index];
+        let active_local_time = active_clip.local_time;
+        let track = &timeline.tracks[active_clip.track_index];
+        let clip = match track.clips.get(active_clip.clip_index) {
+            Some(clip) => clip,
+            None => continue, // the clip has been deleted
+        };
+        let frame = track.clip_start_frame(active_clip.clip_index) + active_local_time;
+
+        let animation_clip = match &clip.source {
+            ClipSource::Animation(animation) => animation,
+            _ => continue,
+        };
+        let constrained_clip_index = match clip_map.get_clip_index(animation_clip.target_clip) {
+            Some(index) => index,
+            None => continue,
+        };
+        let progress_local_time = get_progress_time(
+            animation_clip,
+            clip.duration_frames,
+            active_local_time as f32,
+        );
+        let weight = get_weight(animation_clip, active_local_time);
+
+        for animated_property in &animation_clip.properties {
+            let constraint = match &animated_property.target {
+                AnimatedPropertyTarget::Constraint(constraint) => constraint,
+                _ => continue,
+            };
+            let constrained_value = match constraint.evaluate(
+                timeline,
+                clip_map,
+                constrained_clip_index,
+                frame,
+                active_local_time,
+                time_base,
+            ) {
+                Some(value) => value,
+                None => continue,
+            };
+            let constrained_value = apply_modifiers(
+                &animated_property.modifiers,
+                constrained_value,
+                constrained_value,
+                progress_local_time,
+            );
+
+            let target_property_val = &mut clip_map.active_clips_mut()[constrained_clip_index]
+                .properties[animated_property.group_index][animated_property.property_index];
+            if target_property_val.is_overridden
+                || target_property_val.value.get_type() != constrained_value.get_type()
+            {
+                continue;
+            }
+            target_property_val.value = blend_value(
+                target_property_val.value,
+                constrained_value,
+                animation_clip.blend_mode,
+                weight,
+            );
+        }
+    }
+}
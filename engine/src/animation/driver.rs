@@ -0,0 +1,303 @@
+use super::animation_clip::CurveInterpolation;
+use super::clip::{ActiveClipMap, ClipReference};
+use super::property::{PropertyType, PropertyValue};
+use super::timeline::Timeline;
+use crate::binding::PropertyBinding;
+use alloc::vec::Vec;
+
+/// Makes a property follow a property of another clip, after both clips have been animated.
+pub struct PropertyDriver {
+    pub source_clip: ClipReference,
+    pub source: PropertyBinding,
+    pub mapping: DriverMapping,
+}
+
+impl PropertyDriver {
+    pub fn new(source_clip: ClipReference, source: PropertyBinding) -> Self {
+        PropertyDriver {
+            source_clip,
+            source,
+            mapping: DriverMapping::default(),
+        }
+    }
+}
+
+/// How each field of the source value is changed before it's used. Clip references and gradients
+/// are used as they are.
+pub enum DriverMapping {
+    /// Multiplies the field by `scale` and then adds `offset`.
+    Linear { scale: f32, offset: f32 },
+    /// Maps the field from the input range to the output range along a curve. Fields outside of
+    /// the input range are clamped to it.
+    Remap {
+        input_min: f32,
+        input_max: f32,
+        output_min: f32,
+        output_max: f32,
+        curve: CurveInterpolation,
+    },
+}
+
+impl Default for DriverMapping {
+    fn default() -> Self {
+        DriverMapping::Linear {
+            scale: 1.,
+            offset: 0.,
+        }
+    }
+}
+
+impl DriverMapping {
+    pub fn map_field(&self, field: f32) -> f32 {
+        match self {
+            DriverMapping::Linear { scale, offset } => field * scale + offset,
+            DriverMapping::Remap {
+                input_min,
+                input_max,
+                output_min,
+                output_max,
+                curve,
+            } => {
+                let input_range = input_max - input_min;
+                let t = if input_range == 0. {
+                    0.
+                } else {
+                    ((field - input_min) / input_range).max(0.).min(1.)
+                };
+                output_min + curve.eval(t) * (output_max - output_min)
+            }
+        }
+    }
+
+    /// Maps a source value to a value of the target type, or returns `None` if the source doesn't
+    /// have enough fields for it.
+    pub fn map_value(
+        &self,
+        value: PropertyValue,
+        target_type: PropertyType,
+    ) -> Option<PropertyValue> {
+        match value {
+            PropertyValue::ClipReference(_) | PropertyValue::Gradient(_) => {
+                if value.get_type() == target_type {
+                    Some(value)
+                } else {
+                    None
+                }
+            }
+            _ => PropertyValue::from_fields(
+                target_type,
+                &mut value.fields().map(|field| self.map_field(field)),
+            ),
+        }
+    }
+}
+
+/// A driven property, found by its clip's index in a list along with the property's binding.
+#[derive(Clone, Copy)]
+struct DriverNode {
+    clip_index: usize,
+    binding: PropertyBinding,
+}
+
+impl DriverNode {
+    fn is_property(self, clip_index: usize, binding: PropertyBinding) -> bool {
+        self.clip_index == clip_index
+            && self.binding.group == binding.group
+            && self.binding.prop == binding.prop
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum VisitState {
+    Unvisited,
+    Visiting,
+    Ordered,
+    Cyclic,
+}
+
+/// Orders drivers so each one comes after the driver of its source property, if that's driven
+/// too. `sources` has the index of the driver of each driver's source. Drivers that are part of a
+/// cycle, or follow a property that is, are returned separately.
+fn dependency_order(sources: &[Option<usize>]) -> (Vec<usize>, Vec<usize>) {
+    let mut states = Vec::new();
+    states.resize(sources.len(), VisitState::Unvisited);
+    let mut order = Vec::new();
+    order.reserve(sources.len());
+
+    for driver_index in 0..sources.len() {
+        // Walk back along the chain of sources until we find one that's already been visited.
+        // Each driver has at most one source, so the chain can't branch.
+        let mut chain = Vec::new();
+        let mut current_index = Some(driver_index);
+        let mut chain_state = VisitState::Ordered;
+        while let Some(index) = current_index {
+            match states[index] {
+                VisitState::Unvisited => {
+                    states[index] = VisitState::Visiting;
+                    chain.push(index);
+                    current_index = sources[index];
+                }
+                VisitState::Visiting | VisitState::Cyclic => {
+                    chain_state = VisitState::Cyclic;
+                    break;
+                }
+                VisitState::Ordered => break,
+            }
+        }
+
+        // The chain was built from the driven property back to the first source, so it's ordered
+        // in reverse
+        for &index in chain.iter().rev() {
+            states[index] = chain_state;
+            if chain_state == VisitState::Ordered {
+                order.push(index);
+            }
+        }
+    }
+
+    let cyclic = (0..sources.len())
+        .filter(|&index| states[index] == VisitState::Cyclic)
+        .collect();
+    (order, cyclic)
+}
+
+/// Finds the index of each driver's source in a list of driven properties.
+fn find_sources(nodes: &[DriverNode], source_nodes: &[Option<DriverNode>]) -> Vec<Option<usize>> {
+    source_nodes
+        .iter()
+        .map(|source_node| {
+            source_node.and_then(|source_node| {
+                nodes
+                    .iter()
+                    .position(|node| node.is_property(source_node.clip_index, source_node.binding))
+            })
+        })
+        .collect()
+}
+
+/// Finds every property in the timeline whose driver is part of a cycle, or follows a property
+/// that is. These are left with their animated values when the drivers are resolved. Each clip is
+/// identified by its track and clip index.
+pub fn find_driver_cycles(timeline: &Timeline) -> Vec<(usize, usize, PropertyBinding)> {
+    let clips: Vec<_> = timeline
+        .tracks
+        .iter()
+        .enumerate()
+        .flat_map(|(track_index, track)| {
+            track
+                .clips
+                .iter()
+                .enumerate()
+                .map(move |(clip_index, clip)| (track_index, clip_index, clip))
+        })
+        .collect();
+
+    let mut nodes = Vec::new();
+    let mut source_nodes = Vec::new();
+    for (list_index, (_, _, clip)) in clips.iter().enumerate() {
+        for (group_index, group) in clip.property_groups.iter().enumerate() {
+            for (prop_index, default) in group.defaults.iter().enumerate() {
+                if let Some(driver) = &default.driver {
+                    nodes.push(DriverNode {
+                        clip_index: list_index,
+                        binding: PropertyBinding::new(group_index, prop_index),
+                    });
+                    source_nodes.push(
+                        clips
+                            .iter()
+                            .position(|(_, _, clip)| clip.id == driver.source_clip.clip_id())
+                            .map(|source_index| DriverNode {
+                                clip_index: source_index,
+                                binding: driver.source,
+                            }),
+                    );
+                }
+            }
+        }
+    }
+
+    let (_, cyclic) = dependency_order(&find_sources(&nodes, &source_nodes));
+    cyclic
+        .into_iter()
+        .map(|node_index| {
+            let node = nodes[node_index];
+            let (track_index, clip_index, _) = clips[node.clip_index];
+            (track_index, clip_index, node.binding)
+        })
+        .collect()
+}
+
+/// Sets every driven property of the active clips to the value of its source, once animations
+/// have been applied. Drivers are resolved in dependency order, so a property can follow another
+/// driven property. Drivers whose source clip isn't active, or that are part of a cycle, are
+/// skipped.
+pub fn resolve_drivers(timeline: &Timeline, clip_map: &mut ActiveClipMap) {
+    let mut nodes = Vec::new();
+    let mut source_nodes = Vec::new();
+    let mut drivers = Vec::new();
+    for (active_clip_index, active_clip) in clip_map.active_clips().iter().enumerate() {
+        let clip = match timeline.tracks[active_clip.track_index]
+            .clips
+            .get(active_clip.clip_index)
+        {
+            Some(clip) => clip,
+            None => continue, // the clip has been deleted
+        };
+
+        for (group_index, group) in clip.property_groups.iter().enumerate() {
+            for (prop_index, default) in group.defaults.iter().enumerate() {
+                let driver = match &default.driver {
+                    Some(driver) => driver,
+                    None => continue,
+                };
+                let source_clip_index = match clip_map.get_clip_index(driver.source_clip) {
+                    Some(index) => index,
+                    None => continue,
+                };
+
+                let binding = PropertyBinding::new(group_index, prop_index);
+                nodes.push(DriverNode {
+                    clip_index: active_clip_index,
+                    binding,
+                });
+                source_nodes.push(Some(DriverNode {
+                    clip_index: source_clip_index,
+                    binding: driver.source,
+                }));
+                drivers.push((
+                    driver,
+                    clip.schema.groups[group_index].properties[prop_index].value_type,
+                ));
+            }
+        }
+    }
+    if nodes.is_empty() {
+        return;
+    }
+
+    let (order, _) = dependency_order(&find_sources(&nodes, &source_nodes));
+    for node_index in order {
+        let node = nodes[node_index];
+        let source_node = source_nodes[node_index].unwrap();
+        let (driver, target_type) = drivers[node_index];
+
+        let active_clips = clip_map.active_clips_mut();
+        let source_value = match active_clips[source_node.clip_index]
+            .properties
+            .get(source_node.binding.group)
+            .and_then(|group| group.get(source_node.binding.prop))
+        {
+            Some(source_prop) => source_prop.value,
+            None => continue,
+        };
+
+        let target_prop =
+            &mut active_clips[node.clip_index].properties[node.binding.group][node.binding.prop];
+        if target_prop.is_overridden {
+            continue;
+        }
+        if let Some(driven_value) = driver.mapping.map_value(source_value, target_type) {
+            target_prop.value = driven_value;
+        }
+    }
+}
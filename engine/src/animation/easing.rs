@@ -0,0 +1,202 @@
+use crate::math::Float;
+use core::f32::consts::PI;
+
+const BACK_C1: f32 = 1.70158;
+const BACK_C2: f32 = BACK_C1 * 1.525;
+const BACK_C3: f32 = BACK_C1 + 1.;
+const ELASTIC_C4: f32 = (2. * PI) / 3.;
+const ELASTIC_C5: f32 = (2. * PI) / 4.5;
+const BOUNCE_N1: f32 = 7.5625;
+const BOUNCE_D1: f32 = 2.75;
+
+/// A named easing preset. All presets map 0 to 0 and 1 to 1, although back and elastic easings
+/// overshoot in between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+impl Easing {
+    /// Every easing, in the same order as their discriminants. The exporter and player use an
+    /// easing's index in this list to identify it.
+    pub const ALL: [Easing; 18] = [
+        Easing::QuadIn,
+        Easing::QuadOut,
+        Easing::QuadInOut,
+        Easing::CubicIn,
+        Easing::CubicOut,
+        Easing::CubicInOut,
+        Easing::ExpoIn,
+        Easing::ExpoOut,
+        Easing::ExpoInOut,
+        Easing::BackIn,
+        Easing::BackOut,
+        Easing::BackInOut,
+        Easing::ElasticIn,
+        Easing::ElasticOut,
+        Easing::ElasticInOut,
+        Easing::BounceIn,
+        Easing::BounceOut,
+        Easing::BounceInOut,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Finds the easing with an index from `index`, or `None` if the index is out of range.
+    pub fn from_index(index: usize) -> Option<Easing> {
+        Easing::ALL.get(index).cloned()
+    }
+
+    #[cfg(debug_assertions)]
+    pub fn name(self) -> &'static str {
+        match self {
+            Easing::QuadIn => "Quad in",
+            Easing::QuadOut => "Quad out",
+            Easing::QuadInOut => "Quad in/out",
+            Easing::CubicIn => "Cubic in",
+            Easing::CubicOut => "Cubic out",
+            Easing::CubicInOut => "Cubic in/out",
+            Easing::ExpoIn => "Expo in",
+            Easing::ExpoOut => "Expo out",
+            Easing::ExpoInOut => "Expo in/out",
+            Easing::BackIn => "Back in",
+            Easing::BackOut => "Back out",
+            Easing::BackInOut => "Back in/out",
+            Easing::ElasticIn => "Elastic in",
+            Easing::ElasticOut => "Elastic out",
+            Easing::ElasticInOut => "Elastic in/out",
+            Easing::BounceIn => "Bounce in",
+            Easing::BounceOut => "Bounce out",
+            Easing::BounceInOut => "Bounce in/out",
+        }
+    }
+
+    pub fn eval(self, t: f32) -> f32 {
+        match self {
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1. - (1. - t) * (1. - t),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2. * t * t
+                } else {
+                    1. - (-2. * t + 2.).powi(2) / 2.
+                }
+            }
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1. - (1. - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4. * t * t * t
+                } else {
+                    1. - (-2. * t + 2.).powi(3) / 2.
+                }
+            }
+            Easing::ExpoIn => {
+                if t <= 0. {
+                    0.
+                } else {
+                    (10. * t - 10.).exp2()
+                }
+            }
+            Easing::ExpoOut => {
+                if t >= 1. {
+                    1.
+                } else {
+                    1. - (-10. * t).exp2()
+                }
+            }
+            Easing::ExpoInOut => {
+                if t <= 0. {
+                    0.
+                } else if t >= 1. {
+                    1.
+                } else if t < 0.5 {
+                    (20. * t - 10.).exp2() / 2.
+                } else {
+                    (2. - (-20. * t + 10.).exp2()) / 2.
+                }
+            }
+            Easing::BackIn => BACK_C3 * t * t * t - BACK_C1 * t * t,
+            Easing::BackOut => 1. + BACK_C3 * (t - 1.).powi(3) + BACK_C1 * (t - 1.).powi(2),
+            Easing::BackInOut => {
+                if t < 0.5 {
+                    ((2. * t).powi(2) * ((BACK_C2 + 1.) * 2. * t - BACK_C2)) / 2.
+                } else {
+                    ((2. * t - 2.).powi(2) * ((BACK_C2 + 1.) * (t * 2. - 2.) + BACK_C2) + 2.) / 2.
+                }
+            }
+            Easing::ElasticIn => {
+                if t <= 0. {
+                    0.
+                } else if t >= 1. {
+                    1.
+                } else {
+                    -(10. * t - 10.).exp2() * ((t * 10. - 10.75) * ELASTIC_C4).sin()
+                }
+            }
+            Easing::ElasticOut => {
+                if t <= 0. {
+                    0.
+                } else if t >= 1. {
+                    1.
+                } else {
+                    (-10. * t).exp2() * ((t * 10. - 0.75) * ELASTIC_C4).sin() + 1.
+                }
+            }
+            Easing::ElasticInOut => {
+                if t <= 0. {
+                    0.
+                } else if t >= 1. {
+                    1.
+                } else if t < 0.5 {
+                    -((20. * t - 10.).exp2() * ((20. * t - 11.125) * ELASTIC_C5).sin()) / 2.
+                } else {
+                    ((-20. * t + 10.).exp2() * ((20. * t - 11.125) * ELASTIC_C5).sin()) / 2. + 1.
+                }
+            }
+            Easing::BounceIn => 1. - bounce_out(1. - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => {
+                if t < 0.5 {
+                    (1. - bounce_out(1. - 2. * t)) / 2.
+                } else {
+                    (1. + bounce_out(2. * t - 1.)) / 2.
+                }
+            }
+        }
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    if t < 1. / BOUNCE_D1 {
+        BOUNCE_N1 * t * t
+    } else if t < 2. / BOUNCE_D1 {
+        let t = t - 1.5 / BOUNCE_D1;
+        BOUNCE_N1 * t * t + 0.75
+    } else if t < 2.5 / BOUNCE_D1 {
+        let t = t - 2.25 / BOUNCE_D1;
+        BOUNCE_N1 * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / BOUNCE_D1;
+        BOUNCE_N1 * t * t + 0.984375
+    }
+}
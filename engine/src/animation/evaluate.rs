@@ -0,0 +1,344 @@
+use super::clip::{ActiveClip, ActiveClipMap, ClipPropertyValue, ClipReference};
+use super::coallesce::{coallesce_animations, coallesce_animations_uncached};
+use super::constraint::apply_constraints;
+use super::driver::resolve_drivers;
+use super::timeline::{Clip, ClipSource, TimeBase, Timeline};
+use alloc::vec::Vec;
+
+/// Finds the clips active at a frame, with their properties set to their default values. There's
+/// at most one per track, in track order.
+pub fn active_clips_at(timeline: &Timeline, frame: u32) -> Vec<ActiveClip> {
+    active_clips_matching(timeline, frame, |_| true)
+}
+
+/// Like `active_clips_at`, but skips clips that `include` returns false for without building their
+/// properties.
+fn active_clips_matching(
+    timeline: &Timeline,
+    frame: u32,
+    include: impl Fn(&Clip) -> bool,
+) -> Vec<ActiveClip> {
+    let mut active_clips = Vec::new();
+    for (track_index, track) in timeline.tracks.iter().enumerate() {
+        let mut clip_start_frame = 0;
+        for (clip_index, clip) in track.clips.iter().enumerate() {
+            clip_start_frame += clip.offset_frames;
+            if frame < clip_start_frame {
+                break;
+            }
+            if frame < clip_start_frame + clip.duration_frames {
+                if !include(clip) {
+                    break;
+                }
+                let properties = clip
+                    .property_groups
+                    .iter()
+                    .map(|group| {
+                        group
+                            .defaults
+                            .iter()
+                            .map(|default| ClipPropertyValue {
+                                value: default.value,
+                                is_overridden: default.is_override,
+                                targeted_by: Vec::new(),
+                            })
+                            .collect()
+                    })
+                    .collect();
+
+                active_clips.push(ActiveClip {
+                    #[cfg(debug_assertions)]
+                    name: clip.name.clone(),
+                    #[cfg(not(debug_assertions))]
+                    name: Default::default(),
+                    reference: ClipReference::new(clip.id),
+                    track_index,
+                    clip_index,
+                    local_time: frame - clip_start_frame,
+                    properties,
+                });
+                break;
+            }
+            clip_start_frame += clip.duration_frames;
+        }
+    }
+    active_clips
+}
+
+/// A clip map that looks clips up by searching the list, since there are only ever a few active.
+struct ActiveClipList {
+    active_clips: Vec<ActiveClip>,
+}
+
+impl ActiveClipMap for ActiveClipList {
+    fn active_clips(&self) -> &[ActiveClip] {
+        &self.active_clips
+    }
+
+    fn active_clips_mut(&mut self) -> &mut [ActiveClip] {
+        &mut self.active_clips
+    }
+
+    fn get_clip_index(&self, reference: ClipReference) -> Option<usize> {
+        self.active_clips
+            .iter()
+            .position(|active_clip| active_clip.reference == reference)
+    }
+}
+
+/// Finds the clips active at a frame and the final values of their properties, after every
+/// animation, driver and constraint has been applied. This doesn't need any generators, so it works
+/// on timelines loaded without a device.
+pub fn evaluate(timeline: &Timeline, frame: u32, frames_per_second: f32) -> Vec<ActiveClip> {
+    let time_base = TimeBase {
+        frames_per_second,
+        tempo_map: &timeline.tempo_map,
+    };
+    let mut clip_list = ActiveClipList {
+        active_clips: active_clips_at(timeline, frame),
+    };
+    coallesce_animations(timeline, &mut clip_list, time_base);
+    resolve_drivers(timeline, &mut clip_list);
+    apply_constraints(timeline, &mut clip_list, time_base);
+    clip_list.active_clips
+}
+
+/// Finds a clip's properties at a frame with only the animation clips targeting it applied, or
+/// returns `None` if it isn't active. Drivers and constraints aren't applied, and springs are
+/// integrated without their caches so evaluating the past doesn't disturb them. Damped
+/// constraints use this to find where their targets were.
+pub fn evaluate_animations_of(
+    timeline: &Timeline,
+    reference: ClipReference,
+    frame: u32,
+    time_base: TimeBase,
+) -> Option<ActiveClip> {
+    let mut clip_list = ActiveClipList {
+        active_clips: active_clips_matching(timeline, frame, |clip| {
+            clip.id == reference.clip_id()
+                || match &clip.source {
+                    ClipSource::Animation(animation) => animation.target_clip == reference,
+                    _ => false,
+                }
+        }),
+    };
+    coallesce_animations_uncached(timeline, &mut clip_list, time_base);
+    let clip_index = clip_list.get_clip_index(reference)?;
+    Some(clip_list.active_clips.swap_remove(clip_index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::animation_clip::{
+        AnimatedProperty, AnimatedPropertyField, AnimatedPropertyTarget, AnimationClip, BlendMode,
+        CurveInterpolation, CurveSegment, Extrapolation,
+    };
+    use crate::animation::driver::{DriverMapping, PropertyDriver};
+    use crate::animation::property::PropertyValue;
+    use crate::animation::schema::GeneratorSchema;
+    use crate::animation::timeline::{Clip, ClipSource, Track};
+    use crate::math::ColorSpace;
+    use alloc::string::ToString;
+    use core::cell::RefCell;
+    use tool_derive::Properties;
+
+    #[derive(Properties)]
+    struct TestProperties {
+        #[property(default = 1)]
+        size: f32,
+        height: f32,
+    }
+
+    static TEST_SCHEMA: GeneratorSchema = GeneratorSchema {
+        #[cfg(debug_assertions)]
+        name: "Test",
+        #[cfg(feature = "d3d")]
+        instantiate_generator: |_| unreachable!(),
+        groups: TestProperties::GROUPS,
+    };
+
+    fn generator_clip(id: u32, offset_frames: u32, duration_frames: u32) -> Clip {
+        Clip {
+            id,
+            #[cfg(debug_assertions)]
+            name: "Generator".to_string(),
+            schema: &TEST_SCHEMA,
+            source: ClipSource::Unloaded,
+            offset_frames,
+            duration_frames,
+            #[cfg(debug_assertions)]
+            anchor: None,
+            property_groups: TEST_SCHEMA
+                .groups
+                .iter()
+                .map(|group| group.instantiate())
+                .collect(),
+            is_selected: false,
+        }
+    }
+
+    /// A timeline with a generator clip from frame 10 to 110, an animation clip moving its size
+    /// from 2 to 4 over frames 30 to 50 and holding it until frame 70, and a second generator clip
+    /// whose height follows the first clip's size.
+    fn test_timeline() -> Timeline {
+        let animation_clip = Clip {
+            id: 2,
+            #[cfg(debug_assertions)]
+            name: "Animation".to_string(),
+            schema: &TEST_SCHEMA,
+            source: ClipSource::Animation(AnimationClip {
+                target_clip: ClipReference::new(1),
+                time_property: None,
+                blend_mode: BlendMode::Replace,
+                weight_property: None,
+                is_time_collapsed: true,
+                is_weight_collapsed: true,
+                properties: vec![AnimatedProperty {
+                    group_index: TestProperties::SIZE_BINDING.group,
+                    property_index: TestProperties::SIZE_BINDING.prop,
+                    is_collapsed: false,
+                    target: AnimatedPropertyTarget::Joined(AnimatedPropertyField {
+                        local_offset_frames: 0,
+                        #[cfg(debug_assertions)]
+                        start_anchor: None,
+                        start_value: PropertyValue::Float(2.),
+                        segments: vec![CurveSegment {
+                            duration_frames: 20,
+                            #[cfg(debug_assertions)]
+                            end_anchor: None,
+                            end_value: PropertyValue::Float(4.),
+                            interpolation: CurveInterpolation::Linear,
+                            rotation_spline: false,
+                            color_space: ColorSpace::LinearRgb,
+                        }],
+                        pre_extrapolation: Extrapolation::Constant,
+                        post_extrapolation: Extrapolation::Constant,
+                    }),
+                    modifiers: Vec::new(),
+                    spring_cache: RefCell::new(None),
+                }],
+            }),
+            offset_frames: 30,
+            duration_frames: 40,
+            #[cfg(debug_assertions)]
+            anchor: None,
+            property_groups: Vec::new(),
+            is_selected: false,
+        };
+
+        let mut driven_clip = generator_clip(3, 0, 200);
+        let height_binding = TestProperties::HEIGHT_BINDING;
+        driven_clip.property_groups[height_binding.group].defaults[height_binding.prop].driver =
+            Some(PropertyDriver {
+                source_clip: ClipReference::new(1),
+                source: TestProperties::SIZE_BINDING,
+                mapping: DriverMapping::Linear {
+                    scale: 2.,
+                    offset: 1.,
+                },
+            });
+
+        Timeline {
+            tracks: vec![
+                Track {
+                    clips: vec![generator_clip(1, 10, 100)],
+                },
+                Track {
+                    clips: vec![animation_clip],
+                },
+                Track {
+                    clips: vec![driven_clip],
+                },
+            ],
+            ..Timeline::default()
+        }
+    }
+
+    fn properties(active_clip: &ActiveClip) -> TestProperties {
+        let groups: Vec<&[ClipPropertyValue]> = active_clip
+            .properties
+            .iter()
+            .map(|group| group.as_slice())
+            .collect();
+        TestProperties::from_properties(&groups)
+    }
+
+    fn clip_ids(active_clips: &[ActiveClip]) -> Vec<ClipReference> {
+        active_clips
+            .iter()
+            .map(|active_clip| active_clip.reference)
+            .collect()
+    }
+
+    #[test]
+    fn evaluate_finds_active_clips() {
+        let timeline = test_timeline();
+
+        let active_clips = evaluate(&timeline, 5, 60.);
+        assert!(clip_ids(&active_clips) == vec![ClipReference::new(3)]);
+
+        let active_clips = evaluate(&timeline, 40, 60.);
+        assert!(
+            clip_ids(&active_clips)
+                == vec![
+                    ClipReference::new(1),
+                    ClipReference::new(2),
+                    ClipReference::new(3)
+                ]
+        );
+        assert_eq!(active_clips[0].local_time, 30);
+        assert_eq!(active_clips[1].local_time, 10);
+        assert_eq!(active_clips[2].local_time, 40);
+
+        let active_clips = evaluate(&timeline, 110, 60.);
+        assert!(clip_ids(&active_clips) == vec![ClipReference::new(3)]);
+    }
+
+    #[test]
+    fn evaluate_applies_animations_and_drivers() {
+        let timeline = test_timeline();
+
+        // Before the animation starts, the size is its default and the driver follows it
+        let active_clips = evaluate(&timeline, 20, 60.);
+        assert_eq!(properties(&active_clips[0]).size, 1.);
+        assert_eq!(properties(&active_clips[1]).height, 3.);
+        assert!(active_clips[0].properties[0][0].targeted_by.is_empty());
+
+        // Halfway through the keyframes
+        let active_clips = evaluate(&timeline, 40, 60.);
+        assert_eq!(properties(&active_clips[0]).size, 3.);
+        assert_eq!(properties(&active_clips[2]).height, 7.);
+        assert!(active_clips[0].properties[0][0].targeted_by == vec![ClipReference::new(2)]);
+
+        // After the last keyframe the value is held until the animation clip ends
+        let active_clips = evaluate(&timeline, 60, 60.);
+        assert_eq!(properties(&active_clips[0]).size, 4.);
+        assert_eq!(properties(&active_clips[2]).height, 9.);
+
+        let active_clips = evaluate(&timeline, 80, 60.);
+        assert_eq!(properties(&active_clips[0]).size, 1.);
+        assert_eq!(properties(&active_clips[1]).height, 3.);
+    }
+
+    #[test]
+    fn evaluate_animations_of_only_animates_the_clip() {
+        let timeline = test_timeline();
+        let time_base = TimeBase {
+            frames_per_second: 60.,
+            tempo_map: &timeline.tempo_map,
+        };
+
+        let active_clip =
+            evaluate_animations_of(&timeline, ClipReference::new(1), 40, time_base).unwrap();
+        assert!(active_clip.reference == ClipReference::new(1));
+        assert_eq!(properties(&active_clip).size, 3.);
+
+        // The driver isn't applied
+        let active_clip =
+            evaluate_animations_of(&timeline, ClipReference::new(3), 40, time_base).unwrap();
+        assert_eq!(properties(&active_clip).height, 0.);
+
+        assert!(evaluate_animations_of(&timeline, ClipReference::new(1), 5, time_base).is_none());
+    }
+}
@@ -0,0 +1,166 @@
+use super::property::{PropertyType, PropertyValue};
+use crate::math::random::seeded_noise;
+use crate::math::Float;
+#[cfg(debug_assertions)]
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// The deepest the evaluation stack can get. Expressions needing more are rejected when compiling.
+pub const MAX_STACK_DEPTH: usize = 16;
+
+/// The longest bytecode can be, so its length fits in a byte when exported.
+pub const MAX_BYTECODE_LEN: usize = 255;
+
+/// The rate the `frame` variable counts at, which matches the tool's timeline.
+pub const EXPRESSION_FRAME_RATE: f32 = 60.;
+
+// Opcodes. `CONST` is followed by a little-endian f32, and `VAR` by a variable index. Everything
+// else pops its arguments off the stack and pushes its result.
+pub const OP_CONST: u8 = 0;
+pub const OP_VAR: u8 = 1;
+pub const OP_ADD: u8 = 2;
+pub const OP_SUB: u8 = 3;
+pub const OP_MUL: u8 = 4;
+pub const OP_DIV: u8 = 5;
+pub const OP_MOD: u8 = 6;
+pub const OP_POW: u8 = 7;
+pub const OP_NEG: u8 = 8;
+pub const OP_SIN: u8 = 9;
+pub const OP_COS: u8 = 10;
+pub const OP_TAN: u8 = 11;
+pub const OP_ABS: u8 = 12;
+pub const OP_FLOOR: u8 = 13;
+pub const OP_FRACT: u8 = 14;
+pub const OP_SQRT: u8 = 15;
+pub const OP_MIN: u8 = 16;
+pub const OP_MAX: u8 = 17;
+pub const OP_NOISE: u8 = 18;
+pub const OP_SMOOTHSTEP: u8 = 19;
+pub const OP_CLAMP: u8 = 20;
+pub const OP_MIX: u8 = 21;
+
+// Variable indices
+pub const VAR_T: u8 = 0;
+pub const VAR_FRAME: u8 = 1;
+pub const VAR_BEAT: u8 = 2;
+pub const VAR_CLIP_T: u8 = 3;
+
+/// A compiled expression, which is evaluated on a small stack machine. Each value left on the stack
+/// once the bytecode has finished becomes one field of the result.
+pub struct Expression {
+    #[cfg(debug_assertions)]
+    pub source: String,
+    pub bytecode: Vec<u8>,
+}
+
+/// Values available to expressions through variables.
+pub struct ExpressionVariables {
+    /// Seconds since the start of the timeline.
+    pub t: f32,
+    /// Frames since the start of the timeline, at `EXPRESSION_FRAME_RATE`.
+    pub frame: f32,
+    /// Beats since the start of the timeline.
+    pub beat: f32,
+    /// Progress through the animation clip, from 0 to 1.
+    pub clip_t: f32,
+}
+
+impl ExpressionVariables {
+    fn get(&self, index: u8) -> f32 {
+        match index {
+            VAR_T => self.t,
+            VAR_FRAME => self.frame,
+            VAR_BEAT => self.beat,
+            _ => self.clip_t,
+        }
+    }
+}
+
+impl Expression {
+    /// Evaluates the expression, returning `None` if the bytecode is malformed or doesn't produce
+    /// a value of the right type. A single result is used for every field of the value.
+    pub fn evaluate(
+        &self,
+        variables: &ExpressionVariables,
+        value_type: PropertyType,
+    ) -> Option<PropertyValue> {
+        let mut stack = [0f32; MAX_STACK_DEPTH];
+        let mut stack_len = 0;
+        let mut pc = 0;
+
+        while pc < self.bytecode.len() {
+            let opcode = self.bytecode[pc];
+            pc += 1;
+
+            // Figure out how many arguments the instruction takes, so they can be checked once
+            let arg_count = match opcode {
+                OP_CONST | OP_VAR => 0,
+                OP_NEG | OP_SIN | OP_COS | OP_TAN | OP_ABS | OP_FLOOR | OP_FRACT | OP_SQRT => 1,
+                OP_SMOOTHSTEP | OP_CLAMP | OP_MIX => 3,
+                _ => 2,
+            };
+            if stack_len < arg_count || stack_len - arg_count >= MAX_STACK_DEPTH {
+                return None;
+            }
+            stack_len -= arg_count;
+            let args = &stack[stack_len..stack_len + arg_count];
+
+            let result = match opcode {
+                OP_CONST => {
+                    let bytes = self.bytecode.get(pc..pc + 4)?;
+                    pc += 4;
+                    f32::from_bits(
+                        bytes[0] as u32
+                            | (bytes[1] as u32) << 8
+                            | (bytes[2] as u32) << 16
+                            | (bytes[3] as u32) << 24,
+                    )
+                }
+                OP_VAR => {
+                    let index = *self.bytecode.get(pc)?;
+                    pc += 1;
+                    variables.get(index)
+                }
+                OP_ADD => args[0] + args[1],
+                OP_SUB => args[0] - args[1],
+                OP_MUL => args[0] * args[1],
+                OP_DIV => args[0] / args[1],
+                OP_MOD => args[0] - (args[0] / args[1]).floor() * args[1],
+                OP_POW => args[0].pow(args[1]),
+                OP_NEG => -args[0],
+                OP_SIN => args[0].sin(),
+                OP_COS => args[0].cos(),
+                OP_TAN => args[0].tan(),
+                OP_ABS => args[0].abs(),
+                OP_FLOOR => args[0].floor(),
+                OP_FRACT => args[0] - args[0].floor(),
+                OP_SQRT => args[0].sqrt(),
+                OP_MIN => args[0].min(args[1]),
+                OP_MAX => args[0].max(args[1]),
+                OP_NOISE => seeded_noise(args[1] as u32, args[0]),
+                OP_SMOOTHSTEP => {
+                    let t = ((args[2] - args[0]) / (args[1] - args[0])).max(0.).min(1.);
+                    t * t * (3. - 2. * t)
+                }
+                OP_CLAMP => args[0].max(args[1]).min(args[2]),
+                OP_MIX => args[0] + (args[1] - args[0]) * args[2],
+                _ => return None,
+            };
+            stack[stack_len] = result;
+            stack_len += 1;
+        }
+
+        let results = &stack[..stack_len];
+        match results.len() {
+            0 => None,
+            1 => {
+                let mut fields = (0..value_type.num_fields()).map(|_| results[0]);
+                PropertyValue::from_fields(value_type, &mut fields)
+            }
+            len if len == value_type.num_fields() => {
+                PropertyValue::from_fields(value_type, &mut results.iter().cloned())
+            }
+            _ => None,
+        }
+    }
+}
@@ -1,7 +1,15 @@
 pub mod animation_clip;
 pub mod clip;
 pub mod coallesce;
+pub mod constraint;
 pub mod cubic_bezier;
+pub mod driver;
+pub mod easing;
+pub mod evaluate;
+pub mod expression;
+pub mod modifier;
 pub mod property;
 pub mod schema;
+pub mod spline_path;
+pub mod tempo_map;
 pub mod timeline;
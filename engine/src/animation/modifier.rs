@@ -0,0 +1,226 @@
+use super::property::{PropertyType, PropertyValue};
+use crate::math::random::seeded_noise;
+use crate::math::Float;
+use core::cell::RefCell;
+use core::f32::consts::PI;
+
+/// A procedural effect applied to an animated property after its keyframes have been evaluated.
+/// Modifiers apply to each field of the value separately, with rotations being modified in euler
+/// degrees. Frequencies are measured in cycles per frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Modifier {
+    /// Adds fractal value noise, with each octave doubling the frequency and halving the
+    /// amplitude of the last.
+    Noise {
+        frequency: f32,
+        amplitude: f32,
+        seed: u32,
+        octaves: u32,
+    },
+    /// Adds a sine wave. The phase is measured in cycles.
+    Sine {
+        frequency: f32,
+        amplitude: f32,
+        phase: f32,
+    },
+    /// Rounds the value to the nearest multiple of the step.
+    Quantize { step: f32 },
+    /// Limits the value to a range.
+    Clamp { min: f32, max: f32 },
+    /// Makes the value lag behind and settle on its animated value like a weight on a spring. The
+    /// frequency is the spring's natural frequency, which sets how stiff it is, and the damping is
+    /// its damping ratio: 1 settles without overshooting, and lower values overshoot and wobble.
+    /// Only the first spring in a stack has an effect, and it follows the value with the modifiers
+    /// before it applied.
+    Spring { frequency: f32, damping: f32 },
+}
+
+impl Modifier {
+    pub fn apply(&self, value: f32, field_index: usize, local_time: f32) -> f32 {
+        match *self {
+            Modifier::Noise {
+                frequency,
+                amplitude,
+                seed,
+                octaves,
+            } => {
+                // Give each field its own noise, so vectors don't just move along a diagonal
+                let field_seed = seed.wrapping_add((field_index as u32).wrapping_mul(0x632b_e5ab));
+                let mut octave_frequency = frequency;
+                let mut octave_amplitude = 1.;
+                let mut noise_sum = 0.;
+                let mut amplitude_sum = 0.;
+                for octave in 0..octaves.max(1) {
+                    noise_sum += seeded_noise(
+                        field_seed.wrapping_add(octave),
+                        local_time * octave_frequency,
+                    ) * octave_amplitude;
+                    amplitude_sum += octave_amplitude;
+                    octave_frequency *= 2.;
+                    octave_amplitude /= 2.;
+                }
+
+                value + noise_sum / amplitude_sum * amplitude
+            }
+            Modifier::Sine {
+                frequency,
+                amplitude,
+                phase,
+            } => value + (2. * PI * (local_time * frequency + phase)).sin() * amplitude,
+            Modifier::Quantize { step } => {
+                if step > 0. {
+                    (value / step + 0.5).floor() * step
+                } else {
+                    value
+                }
+            }
+            Modifier::Clamp { min, max } => value.max(min).min(max),
+            // Springs depend on earlier values too, so they're applied by `integrate_spring`
+            Modifier::Spring { .. } => value,
+        }
+    }
+}
+
+/// How far apart spring modifiers are integrated, in seconds. The step is fixed in seconds rather
+/// than frames so the tool and the player, which use different framerates, take the same steps.
+pub const SPRING_STEP_SECONDS: f32 = 1. / 240.;
+
+/// A spring's fields after integrating a number of steps from the start of its clip.
+#[derive(Clone, Copy)]
+pub struct SpringState {
+    step: u32,
+    positions: [f32; 4],
+    velocities: [f32; 4],
+}
+
+impl SpringState {
+    fn advance(
+        &mut self,
+        target: [f32; 4],
+        angular_frequency: f32,
+        damping: f32,
+        step_frames: f32,
+    ) {
+        let stiffness = angular_frequency * angular_frequency;
+        for ((position, velocity), target) in self
+            .positions
+            .iter_mut()
+            .zip(self.velocities.iter_mut())
+            .zip(target.iter())
+        {
+            let acceleration =
+                stiffness * (target - *position) - 2. * damping * angular_frequency * *velocity;
+            *velocity += acceleration * step_frames;
+            *position += *velocity * step_frames;
+        }
+    }
+}
+
+/// Returns true for types that springs can move, which are the ones with continuous fields.
+fn can_spring(value_type: PropertyType) -> bool {
+    match value_type {
+        PropertyType::Float
+        | PropertyType::Vec2
+        | PropertyType::Vec3
+        | PropertyType::Vec4
+        | PropertyType::RgbColor
+        | PropertyType::RgbaColor
+        | PropertyType::Rotation => true,
+        _ => false,
+    }
+}
+
+/// Gets the fields of a value for a spring. Rotations are converted to the euler angles closest
+/// to `reference`, so they don't wrap around while the spring is following them.
+fn spring_fields(value: PropertyValue, reference: [f32; 4]) -> [f32; 4] {
+    let mut fields = [0.; 4];
+    match value {
+        PropertyValue::Rotation(rotation) => {
+            let (x, y, z) = rotation.as_euler_near((
+                reference[0].to_radians(),
+                reference[1].to_radians(),
+                reference[2].to_radians(),
+            ));
+            fields[0] = x.to_degrees();
+            fields[1] = y.to_degrees();
+            fields[2] = z.to_degrees();
+        }
+        _ => {
+            for (field, value_field) in fields.iter_mut().zip(value.fields()) {
+                *field = value_field;
+            }
+        }
+    }
+    fields
+}
+
+/// Finds the value of a spring following `input`, which gives the followed value at a local time
+/// in frames. The spring starts at rest on the input at the start of the clip and is integrated in
+/// fixed steps, so the result only depends on the input and the time. If `cache` holds a state
+/// from before `local_time`, integration continues from it and the cache is updated.
+pub fn integrate_spring(
+    frequency: f32,
+    damping: f32,
+    value_type: PropertyType,
+    local_time: f32,
+    step_frames: f32,
+    input: &mut FnMut(f32) -> PropertyValue,
+    cache: Option<&RefCell<Option<SpringState>>>,
+) -> PropertyValue {
+    if !can_spring(value_type) || step_frames <= 0. {
+        return input(local_time);
+    }
+
+    let angular_frequency = 2. * PI * frequency;
+    let last_step = (local_time / step_frames).floor() as u32;
+    let cached_state = cache.and_then(|cache| *cache.borrow());
+    let mut state = match cached_state {
+        Some(state) if state.step <= last_step => state,
+        _ => SpringState {
+            step: 0,
+            positions: spring_fields(input(0.), [0.; 4]),
+            velocities: [0.; 4],
+        },
+    };
+
+    while state.step < last_step {
+        let target = spring_fields(input(state.step as f32 * step_frames), state.positions);
+        state.advance(target, angular_frequency, damping, step_frames);
+        state.step += 1;
+    }
+    if let Some(cache) = cache {
+        *cache.borrow_mut() = Some(state);
+    }
+
+    // Finish with a partial step up to the exact time, which isn't cached since it isn't a whole
+    // step
+    let remaining_frames = local_time - last_step as f32 * step_frames;
+    if remaining_frames > 0. {
+        let target = spring_fields(input(last_step as f32 * step_frames), state.positions);
+        state.advance(target, angular_frequency, damping, remaining_frames);
+    }
+    PropertyValue::from_fields(value_type, &mut state.positions.iter().cloned()).unwrap()
+}
+
+/// Applies each modifier in the stack to a value in order. Clip references can't be modified, so
+/// are returned as-is. Rotations are converted to the euler angles closest to those of
+/// `euler_reference`, so the modifiers don't jump when the rotation passes straight up or down.
+pub fn apply_modifiers(
+    modifiers: &[Modifier],
+    value: PropertyValue,
+    euler_reference: PropertyValue,
+    local_time: f32,
+) -> PropertyValue {
+    let value_type = value.get_type();
+    if modifiers.is_empty() || value_type == PropertyType::ClipReference {
+        return value;
+    }
+
+    let fields = value.fields_near(euler_reference);
+    let mut modified_fields = fields.enumerate().map(|(field_index, field)| {
+        modifiers.iter().fold(field, |field, modifier| {
+            modifier.apply(field, field_index, local_time)
+        })
+    });
+    PropertyValue::from_fields(value_type, &mut modified_fields).unwrap()
+}
@@ -1,8 +1,12 @@
 use super::clip::ClipReference;
 use crate::animation::clip::ClipPropertyValue;
-use crate::math::{Quaternion, RgbColor, RgbaColor, Vector2, Vector3, Vector4};
+use crate::math::{
+    ColorSpace, Float, Gradient, GradientStop, Quaternion, RgbColor, RgbaColor, Vector2, Vector3,
+    Vector4, MAX_GRADIENT_STOPS,
+};
+use core::mem;
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy)]
 pub enum PropertyType {
     Float,
     Vec2,
@@ -12,8 +16,23 @@ pub enum PropertyType {
     RgbaColor,
     Rotation,
     ClipReference,
+    Int,
+    Bool,
+    /// An index into a list of option names.
+    Enum(&'static [&'static str]),
+    Gradient,
 }
 
+/// Enum types are equal no matter what their options are, since the type of an enum value
+/// doesn't know them.
+impl PartialEq for PropertyType {
+    fn eq(&self, other: &PropertyType) -> bool {
+        mem::discriminant(self) == mem::discriminant(other)
+    }
+}
+
+impl Eq for PropertyType {}
+
 impl PropertyType {
     pub fn num_fields(self) -> usize {
         match self {
@@ -25,6 +44,11 @@ impl PropertyType {
             PropertyType::RgbaColor => 4,
             PropertyType::Rotation => 3,
             PropertyType::ClipReference => 1,
+            PropertyType::Int => 1,
+            PropertyType::Bool => 1,
+            PropertyType::Enum(_) => 1,
+            // The number of stops, then the position and colour of each stop
+            PropertyType::Gradient => 1 + MAX_GRADIENT_STOPS * 5,
         }
     }
 
@@ -38,6 +62,10 @@ impl PropertyType {
             PropertyType::RgbaColor => PropertyValue::RgbaColor(RgbaColor::new(1., 0., 1., 1.)),
             PropertyType::Rotation => PropertyValue::Rotation(Quaternion::default()),
             PropertyType::ClipReference => PropertyValue::ClipReference(None),
+            PropertyType::Int => PropertyValue::Int(0),
+            PropertyType::Bool => PropertyValue::Bool(false),
+            PropertyType::Enum(_) => PropertyValue::Enum(0),
+            PropertyType::Gradient => PropertyValue::Gradient(Gradient::default()),
         }
     }
 
@@ -47,9 +75,14 @@ impl PropertyType {
             | PropertyType::Vec2
             | PropertyType::Vec3
             | PropertyType::Vec4
-            | PropertyType::ClipReference => None,
-            PropertyType::RgbColor | PropertyType::RgbaColor => Some((0., 1.)),
+            | PropertyType::ClipReference
+            | PropertyType::Int => None,
+            PropertyType::RgbColor
+            | PropertyType::RgbaColor
+            | PropertyType::Bool
+            | PropertyType::Gradient => Some((0., 1.)),
             PropertyType::Rotation => Some((-180., 180.)),
+            PropertyType::Enum(options) => Some((0., options.len().max(1) as f32 - 1.)),
         }
     }
 }
@@ -64,6 +97,10 @@ pub enum PropertyValue {
     RgbaColor(RgbaColor),
     Rotation(Quaternion),
     ClipReference(Option<ClipReference>),
+    Int(i32),
+    Bool(bool),
+    Enum(u32),
+    Gradient(Gradient),
 }
 
 fn float_from_iter(fields: &mut dyn Iterator<Item = f32>) -> Option<f32> {
@@ -94,6 +131,21 @@ fn vec4_from_iter(fields: &mut dyn Iterator<Item = f32>) -> Option<Vector4> {
     })
 }
 
+fn gradient_from_iter(fields: &mut dyn Iterator<Item = f32>) -> Option<Gradient> {
+    let stop_count = fields
+        .next()?
+        .round()
+        .max(1.)
+        .min(MAX_GRADIENT_STOPS as f32) as usize;
+    let mut stops = [GradientStop::default(); MAX_GRADIENT_STOPS];
+    for stop in stops.iter_mut() {
+        let position = fields.next()?;
+        let color = vec4_from_iter(fields)?;
+        *stop = GradientStop::new(position, RgbaColor(color));
+    }
+    Some(Gradient::new(&stops[..stop_count]))
+}
+
 impl PropertyValue {
     pub fn from_fields(
         prop_type: PropertyType,
@@ -124,6 +176,20 @@ impl PropertyValue {
                     Some(ClipReference::new(f as u32))
                 })
             }),
+            PropertyType::Int => {
+                float_from_iter(fields).map(|f| PropertyValue::Int(f.round() as i32))
+            }
+            PropertyType::Bool => float_from_iter(fields).map(|f| PropertyValue::Bool(f >= 0.5)),
+            PropertyType::Enum(options) => float_from_iter(fields).map(|f| {
+                // The options aren't known when the type came from a value
+                let index = f.round().max(0.) as u32;
+                PropertyValue::Enum(if options.is_empty() {
+                    index
+                } else {
+                    index.min(options.len() as u32 - 1)
+                })
+            }),
+            PropertyType::Gradient => gradient_from_iter(fields).map(PropertyValue::Gradient),
         }
     }
 
@@ -183,6 +249,45 @@ impl PropertyValue {
         }
     }
 
+    pub fn into_int(self) -> Option<i32> {
+        match self {
+            PropertyValue::Int(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn into_bool(self) -> Option<bool> {
+        match self {
+            PropertyValue::Bool(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn into_enum(self) -> Option<u32> {
+        match self {
+            PropertyValue::Enum(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn into_gradient(self) -> Option<Gradient> {
+        match self {
+            PropertyValue::Gradient(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Returns true for values that jump from one to the next instead of being interpolated.
+    pub fn is_stepped(self) -> bool {
+        match self {
+            PropertyValue::ClipReference(_)
+            | PropertyValue::Int(_)
+            | PropertyValue::Bool(_)
+            | PropertyValue::Enum(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn get_type(self) -> PropertyType {
         match self {
             PropertyValue::Float(_) => PropertyType::Float,
@@ -193,6 +298,10 @@ impl PropertyValue {
             PropertyValue::RgbaColor(_) => PropertyType::RgbaColor,
             PropertyValue::Rotation(_) => PropertyType::Rotation,
             PropertyValue::ClipReference(_) => PropertyType::ClipReference,
+            PropertyValue::Int(_) => PropertyType::Int,
+            PropertyValue::Bool(_) => PropertyType::Bool,
+            PropertyValue::Enum(_) => PropertyType::Enum(&[]),
+            PropertyValue::Gradient(_) => PropertyType::Gradient,
         }
     }
 
@@ -222,9 +331,114 @@ impl PropertyValue {
             PropertyValue::ClipReference(a) => other
                 .into_clip_reference()
                 .map(|b| PropertyValue::ClipReference(if amount >= 1. { b } else { a })),
+            PropertyValue::Int(a) => other
+                .into_int()
+                .map(|b| PropertyValue::Int(if amount >= 1. { b } else { a })),
+            PropertyValue::Bool(a) => other
+                .into_bool()
+                .map(|b| PropertyValue::Bool(if amount >= 1. { b } else { a })),
+            PropertyValue::Enum(a) => other
+                .into_enum()
+                .map(|b| PropertyValue::Enum(if amount >= 1. { b } else { a })),
+            PropertyValue::Gradient(a) => other
+                .into_gradient()
+                .map(|b| PropertyValue::Gradient(a.lerp(&b, amount))),
         }
     }
 
+    /// Like `lerp`, but colours are interpolated in another colour space.
+    pub fn lerp_in(
+        self,
+        other: PropertyValue,
+        amount: f32,
+        color_space: ColorSpace,
+    ) -> Option<PropertyValue> {
+        match (self, other) {
+            (PropertyValue::RgbColor(a), PropertyValue::RgbColor(b)) => {
+                Some(PropertyValue::RgbColor(a.lerp_in(b, amount, color_space)))
+            }
+            (PropertyValue::RgbaColor(a), PropertyValue::RgbaColor(b)) => {
+                Some(PropertyValue::RgbaColor(a.lerp_in(b, amount, color_space)))
+            }
+            (PropertyValue::Gradient(a), PropertyValue::Gradient(b)) => Some(
+                PropertyValue::Gradient(a.zip_stops(&b, |stop_a, stop_b| {
+                    GradientStop::new(
+                        stop_a.position + (stop_b.position - stop_a.position) * amount,
+                        stop_a.color.lerp_in(stop_b.color, amount, color_space),
+                    )
+                })),
+            ),
+            _ => self.lerp(other, amount),
+        }
+    }
+
+    /// Adds `other` onto this value, scaled by `amount`. Rotations are composed instead of added,
+    /// gradients are added stop by stop, and stepped values other than integers are switched
+    /// over like in `lerp`.
+    pub fn add(self, other: PropertyValue, amount: f32) -> Option<PropertyValue> {
+        self.combine(other, amount, |a, b| a + b * amount)
+    }
+
+    /// Multiplies this value by `other`, which is interpolated from 1 by `amount`. Rotations are
+    /// composed instead of multiplied, gradients are multiplied stop by stop, and stepped values
+    /// other than integers are switched over like in `lerp`.
+    pub fn multiply(self, other: PropertyValue, amount: f32) -> Option<PropertyValue> {
+        self.combine(other, amount, |a, b| a * (1. + (b - 1.) * amount))
+    }
+
+    fn combine(
+        self,
+        other: PropertyValue,
+        amount: f32,
+        combine_field: impl Fn(f32, f32) -> f32,
+    ) -> Option<PropertyValue> {
+        match (self, other) {
+            (PropertyValue::Rotation(a), PropertyValue::Rotation(b)) => Some(
+                PropertyValue::Rotation((a * Quaternion::default().slerp(b, amount)).normalize()),
+            ),
+            (PropertyValue::ClipReference(_), _)
+            | (PropertyValue::Bool(_), _)
+            | (PropertyValue::Enum(_), _) => self.lerp(other, amount),
+            (PropertyValue::Gradient(a), PropertyValue::Gradient(b)) => Some(
+                PropertyValue::Gradient(a.zip_stops(&b, |stop_a, stop_b| {
+                    let color_a: [f32; 4] = stop_a.color.into();
+                    let color_b: [f32; 4] = stop_b.color.into();
+                    GradientStop::new(
+                        combine_field(stop_a.position, stop_b.position),
+                        RgbaColor::new(
+                            combine_field(color_a[0], color_b[0]),
+                            combine_field(color_a[1], color_b[1]),
+                            combine_field(color_a[2], color_b[2]),
+                            combine_field(color_a[3], color_b[3]),
+                        ),
+                    )
+                })),
+            ),
+            _ if self.get_type() == other.get_type() => {
+                let mut fields = self
+                    .fields()
+                    .zip(other.fields())
+                    .map(|(a, b)| combine_field(a, b));
+                PropertyValue::from_fields(self.get_type(), &mut fields)
+            }
+            _ => None,
+        }
+    }
+
+    /// Clamps each field of the value, with rotations clamped as euler angles in degrees. Clip
+    /// references and gradients aren't changed. The value is returned as-is if it's already in range.
+    pub fn clamp_fields(self, min: f32, max: f32) -> PropertyValue {
+        match self {
+            PropertyValue::ClipReference(_) | PropertyValue::Gradient(_) => return self,
+            _ => {}
+        }
+        if self.fields().all(|field| field >= min && field <= max) {
+            return self;
+        }
+        let mut fields = self.fields().map(|field| field.max(min).min(max));
+        PropertyValue::from_fields(self.get_type(), &mut fields).unwrap()
+    }
+
     pub fn fields(self) -> PropertyValueIter {
         // Convert rotations (quaternions) into euler angles for fields
         let iter_val = if let PropertyValue::Rotation(rot) = self {
@@ -246,6 +460,29 @@ impl PropertyValue {
             index: 0,
         }
     }
+
+    /// Like `fields`, but rotations are converted to whichever euler angles are closest to those
+    /// of `reference`. Converting each value of an animation near the same reference avoids the
+    /// angles suddenly flipping to an equivalent set partway through.
+    pub fn fields_near(self, reference: PropertyValue) -> PropertyValueIter {
+        match (self, reference) {
+            (PropertyValue::Rotation(rot), PropertyValue::Rotation(reference_rot)) => {
+                let euler_rads = rot.as_euler_near(reference_rot.as_euler());
+                PropertyValueIter {
+                    val: PropertyValue::Vec3(
+                        (
+                            euler_rads.0.to_degrees(),
+                            euler_rads.1.to_degrees(),
+                            euler_rads.2.to_degrees(),
+                        )
+                            .into(),
+                    ),
+                    index: 0,
+                }
+            }
+            _ => self.fields(),
+        }
+    }
 }
 
 impl From<f32> for PropertyValue {
@@ -296,6 +533,24 @@ impl From<Option<ClipReference>> for PropertyValue {
     }
 }
 
+impl From<i32> for PropertyValue {
+    fn from(val: i32) -> PropertyValue {
+        PropertyValue::Int(val)
+    }
+}
+
+impl From<bool> for PropertyValue {
+    fn from(val: bool) -> PropertyValue {
+        PropertyValue::Bool(val)
+    }
+}
+
+impl From<Gradient> for PropertyValue {
+    fn from(val: Gradient) -> PropertyValue {
+        PropertyValue::Gradient(val)
+    }
+}
+
 pub struct PropertyValueIter {
     val: PropertyValue,
     index: usize,
@@ -357,6 +612,33 @@ impl Iterator for PropertyValueIter {
                 }),
                 _ => None,
             },
+            PropertyValue::Int(val) => match index {
+                0 => Some(val as f32),
+                _ => None,
+            },
+            PropertyValue::Bool(val) => match index {
+                0 => Some(if val { 1. } else { 0. }),
+                _ => None,
+            },
+            PropertyValue::Enum(val) => match index {
+                0 => Some(val as f32),
+                _ => None,
+            },
+            // Every stop is included, with the last one repeated if there are less than the max
+            PropertyValue::Gradient(val) => match index {
+                0 => Some(val.stops().len() as f32),
+                _ if index < PropertyType::Gradient.num_fields() => {
+                    let stop = val.stop((index - 1) / 5);
+                    Some(match (index - 1) % 5 {
+                        0 => stop.position,
+                        1 => stop.color.r(),
+                        2 => stop.color.g(),
+                        3 => stop.color.b(),
+                        _ => stop.color.a(),
+                    })
+                }
+                _ => None,
+            },
 
             // Note: this should never happen, since when creating the iter
             // quaternions are converted into euler angles (which are vec3)
@@ -414,7 +696,57 @@ impl Gettable for Option<ClipReference> {
         val.into_clip_reference().unwrap()
     }
 }
+impl Gettable for i32 {
+    fn get(val: PropertyValue) -> i32 {
+        val.into_int().unwrap()
+    }
+}
+impl Gettable for bool {
+    fn get(val: PropertyValue) -> bool {
+        val.into_bool().unwrap()
+    }
+}
+/// Enum values are read as the index of the option.
+impl Gettable for u32 {
+    fn get(val: PropertyValue) -> u32 {
+        val.into_enum().unwrap()
+    }
+}
+impl Gettable for Gradient {
+    fn get(val: PropertyValue) -> Gradient {
+        val.into_gradient().unwrap()
+    }
+}
 
 pub fn prop<T: Gettable>(properties: &[&[ClipPropertyValue]], group: usize, prop: usize) -> T {
     T::get(properties[group][prop].value)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use core::mem;
+
+    #[test]
+    fn gradients_keep_property_values_small() {
+        assert_eq!(mem::size_of::<Gradient>(), 84);
+        assert_eq!(mem::size_of::<PropertyValue>(), 88);
+        assert_eq!(PropertyType::Gradient.num_fields(), 21);
+    }
+
+    #[test]
+    fn gradient_fields_round_trip() {
+        let gradient = Gradient::new(&[
+            GradientStop::new(0.25, RgbaColor::new(1., 0., 0., 1.)),
+            GradientStop::new(0.75, RgbaColor::new(0., 0., 1., 0.5)),
+        ]);
+        let value = PropertyValue::Gradient(gradient);
+        let fields: Vec<f32> = value.fields().collect();
+        assert_eq!(fields.len(), PropertyType::Gradient.num_fields());
+        assert!(
+            PropertyValue::from_fields(PropertyType::Gradient, &mut fields.into_iter())
+                == Some(value)
+        );
+    }
+}
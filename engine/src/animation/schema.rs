@@ -1,15 +1,21 @@
-use super::property::PropertyType;
-use crate::animation::timeline::ClipSource;
-use crate::animation::timeline::{Clip, PropertyDefault, PropertyGroup};
+use super::property::{PropertyType, PropertyValue};
+use crate::animation::timeline::{PropertyDefault, PropertyGroup};
+#[cfg(feature = "d3d")]
+use crate::animation::timeline::{Clip, ClipSource};
+#[cfg(feature = "d3d")]
 use crate::creation_context::CreationContext;
+#[cfg(feature = "d3d")]
 use crate::generator::Generator;
+#[cfg(feature = "d3d")]
 use alloc::boxed::Box;
+#[cfg(all(feature = "d3d", debug_assertions))]
 use alloc::string::ToString;
 
 #[derive(Clone, Copy)]
 pub struct GeneratorSchema {
     #[cfg(debug_assertions)]
     pub name: &'static str,
+    #[cfg(feature = "d3d")]
     pub instantiate_generator: fn(&mut CreationContext) -> Box<dyn Generator>,
     pub groups: &'static [SchemaGroup],
 }
@@ -38,17 +44,89 @@ pub struct SchemaProperty {
     #[cfg(debug_assertions)]
     pub name: &'static str,
     pub value_type: PropertyType,
+
+    /// The fields of the value new clips start with, as they'd be passed to
+    /// `PropertyValue::from_fields`. The type's default value is used if this is `None`.
+    #[cfg(debug_assertions)]
+    pub default_fields: Option<&'static [f32]>,
+
+    /// The range the editor lets the value be dragged in. It can still be typed in outside of it.
+    #[cfg(debug_assertions)]
+    pub soft_range: Option<(f32, f32)>,
+
+    /// The range every field of the value is clamped to when it's edited or loaded.
+    #[cfg(debug_assertions)]
+    pub hard_range: Option<(f32, f32)>,
+
+    /// How much the value changes for each pixel it's dragged.
+    #[cfg(debug_assertions)]
+    pub step: Option<f32>,
+
+    /// Shown after the value in the editor, e.g. "degrees".
+    #[cfg(debug_assertions)]
+    pub unit: Option<&'static str>,
+
+    /// Shown when hovering over the property in the editor.
+    #[cfg(debug_assertions)]
+    pub description: Option<&'static str>,
 }
 
 impl SchemaProperty {
     pub fn instantiate(&self) -> PropertyDefault {
         PropertyDefault {
-            value: self.value_type.default_value(),
+            value: self.default_value(),
             is_override: false,
+            driver: None,
         }
     }
+
+    #[cfg(debug_assertions)]
+    pub fn default_value(&self) -> PropertyValue {
+        self.default_fields
+            .and_then(|fields| {
+                PropertyValue::from_fields(self.value_type, &mut fields.iter().cloned())
+            })
+            .unwrap_or_else(|| self.value_type.default_value())
+    }
+
+    #[cfg(not(debug_assertions))]
+    pub fn default_value(&self) -> PropertyValue {
+        self.value_type.default_value()
+    }
 }
 
+#[cfg(debug_assertions)]
+impl SchemaProperty {
+    /// The range the editor shows and drags the value in: the soft range if there is one,
+    /// otherwise the hard range if both ends are given, or the type's range.
+    pub fn display_range(&self) -> Option<(f32, f32)> {
+        self.soft_range
+            .or(self
+                .hard_range
+                .filter(|(min, max)| min.is_finite() && max.is_finite()))
+            .or_else(|| self.value_type.value_range())
+    }
+
+    pub fn drag_step(&self) -> f32 {
+        self.step.unwrap_or(match self.value_type {
+            PropertyType::Rotation
+            | PropertyType::Int
+            | PropertyType::Bool
+            | PropertyType::Enum(_) => 1.,
+            _ => 0.01,
+        })
+    }
+
+    /// Clamps a value to the hard range, if the property has one.
+    pub fn clamp(&self, value: PropertyValue) -> PropertyValue {
+        match self.hard_range {
+            Some((min, max)) => value.clamp_fields(min, max),
+            None => value,
+        }
+    }
+}
+
+#[cfg(feature = "d3d")]
 impl GeneratorSchema {
     pub fn instantiate(
         &'static self,
@@ -65,6 +143,8 @@ impl GeneratorSchema {
             source: ClipSource::Generator((self.instantiate_generator)(context)),
             offset_frames,
             duration_frames,
+            #[cfg(debug_assertions)]
+            anchor: None,
             property_groups: self
                 .groups
                 .iter()
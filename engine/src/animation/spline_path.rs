@@ -0,0 +1,200 @@
+use super::animation_clip::AnimatedPropertyField;
+use crate::binding::PropertyBinding;
+use crate::math::{Float, Quaternion, Vector3};
+use alloc::vec::Vec;
+
+/// How many samples are taken along each span of a path when measuring its length.
+const SAMPLES_PER_SPAN: usize = 16;
+
+/// The shortest knot interval, so repeated points don't cause a division by zero.
+const MIN_KNOT_INTERVAL: f32 = 1e-4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplineKind {
+    /// Uniform Catmull-Rom. This can overshoot and form loops when points are unevenly spaced.
+    CatmullRom,
+    /// Centripetal Catmull-Rom, which never forms cusps or loops within a span.
+    Centripetal,
+}
+
+impl SplineKind {
+    fn alpha(self) -> f32 {
+        match self {
+            SplineKind::CatmullRom => 0.,
+            SplineKind::Centripetal => 0.5,
+        }
+    }
+}
+
+impl Default for SplineKind {
+    fn default() -> Self {
+        SplineKind::Centripetal
+    }
+}
+
+/// A smooth path through a list of points, for animating `Vec3` properties. The position along the
+/// path is controlled by an animated progress, which covers equal distances in equal steps so the
+/// speed along the path only depends on the progress curve.
+///
+/// `update_arc_lengths` must be called after changing the kind or points.
+pub struct SplinePath {
+    pub kind: SplineKind,
+    pub points: Vec<Vector3>,
+    /// Progress along the path, from 0 at the first point to 1 at the last.
+    pub progress: AnimatedPropertyField,
+    /// A `Rotation` property of the target clip to point along the path.
+    pub orient_target: Option<PropertyBinding>,
+
+    /// Distance along the path at evenly spaced samples of the spline, used to find how far
+    /// through the spline a distance is.
+    arc_lengths: Vec<f32>,
+}
+
+impl SplinePath {
+    pub fn new(
+        kind: SplineKind,
+        points: Vec<Vector3>,
+        progress: AnimatedPropertyField,
+        orient_target: Option<PropertyBinding>,
+    ) -> Self {
+        let mut path = SplinePath {
+            kind,
+            points,
+            progress,
+            orient_target,
+            arc_lengths: Vec::new(),
+        };
+        path.update_arc_lengths();
+        path
+    }
+
+    pub fn length(&self) -> f32 {
+        self.arc_lengths.last().cloned().unwrap_or(0.)
+    }
+
+    pub fn update_arc_lengths(&mut self) {
+        self.arc_lengths.clear();
+        if self.points.len() < 2 {
+            return;
+        }
+
+        let mut length = 0.;
+        let mut last_point = self.points[0];
+        self.arc_lengths.push(0.);
+        for span in 0..self.points.len() - 1 {
+            for sample in 1..=SAMPLES_PER_SPAN {
+                let point = self.span_point(span, sample as f32 / SAMPLES_PER_SPAN as f32);
+                length += (point - last_point).length();
+                last_point = point;
+                self.arc_lengths.push(length);
+            }
+        }
+    }
+
+    /// Evaluates the spline between two of its points. The first and last spans are given extra
+    /// control points that continue in a straight line, so the path starts and ends smoothly.
+    fn span_point(&self, span: usize, t: f32) -> Vector3 {
+        let p1 = self.points[span];
+        let p2 = self.points[span + 1];
+        let p0 = if span == 0 {
+            p1 * 2. - p2
+        } else {
+            self.points[span - 1]
+        };
+        let p3 = match self.points.get(span + 2) {
+            Some(&point) => point,
+            None => p2 * 2. - p1,
+        };
+
+        catmull_rom(p0, p1, p2, p3, self.kind.alpha(), t)
+    }
+
+    /// Finds the span and the parameter within it that's a fraction of the path's length along.
+    fn progress_to_span(&self, progress: f32) -> (usize, f32) {
+        let target_length = progress.max(0.).min(1.) * self.length();
+
+        // Binary search for the samples on either side of the target
+        let mut low_sample = 0;
+        let mut high_sample = self.arc_lengths.len() - 1;
+        while high_sample - low_sample > 1 {
+            let mid_sample = (low_sample + high_sample) / 2;
+            if self.arc_lengths[mid_sample] < target_length {
+                low_sample = mid_sample;
+            } else {
+                high_sample = mid_sample;
+            }
+        }
+
+        let low_length = self.arc_lengths[low_sample];
+        let sample_length = self.arc_lengths[high_sample] - low_length;
+        let sample_t = if sample_length > 0. {
+            (target_length - low_length) / sample_length
+        } else {
+            0.
+        };
+
+        let spline_t = (low_sample as f32 + sample_t) / SAMPLES_PER_SPAN as f32;
+        let span = (spline_t as usize).min(self.points.len() - 2);
+        (span, spline_t - span as f32)
+    }
+
+    /// The position a fraction of the way along the path. Empty paths stay at the origin.
+    pub fn position_at(&self, progress: f32) -> Vector3 {
+        match self.points.len() {
+            0 => Vector3::default(),
+            1 => self.points[0],
+            _ => {
+                let (span, t) = self.progress_to_span(progress);
+                self.span_point(span, t)
+            }
+        }
+    }
+
+    /// The direction of travel a fraction of the way along the path.
+    pub fn direction_at(&self, progress: f32) -> Vector3 {
+        if self.points.len() < 2 {
+            return Vector3::unit_z();
+        }
+
+        let (span, t) = self.progress_to_span(progress);
+        let delta =
+            self.span_point(span, (t + 0.01).min(1.)) - self.span_point(span, (t - 0.01).max(0.));
+        if delta.length_squared() > 0. {
+            delta.unit()
+        } else {
+            Vector3::unit_z()
+        }
+    }
+
+    /// A rotation that turns +Z to face along the path. It only has yaw and pitch, so the horizon
+    /// stays level.
+    pub fn orientation_at(&self, progress: f32) -> Quaternion {
+        Quaternion::look_along(self.direction_at(progress))
+    }
+}
+
+fn knot_interval(a: Vector3, b: Vector3, alpha: f32) -> f32 {
+    (b - a).length().pow(alpha).max(MIN_KNOT_INTERVAL)
+}
+
+fn lerp_knots(a: Vector3, b: Vector3, knot_a: f32, knot_b: f32, knot: f32) -> Vector3 {
+    a.lerp(b, (knot - knot_a) / (knot_b - knot_a))
+}
+
+/// Evaluates a Catmull-Rom spline between `p1` and `p2` using the Barry-Goldman pyramid, which
+/// supports non-uniform knot spacing. `alpha` is 0 for a uniform spline, 0.5 for a centripetal
+/// one and 1 for a chordal one.
+fn catmull_rom(p0: Vector3, p1: Vector3, p2: Vector3, p3: Vector3, alpha: f32, t: f32) -> Vector3 {
+    let k0 = 0.;
+    let k1 = k0 + knot_interval(p0, p1, alpha);
+    let k2 = k1 + knot_interval(p1, p2, alpha);
+    let k3 = k2 + knot_interval(p2, p3, alpha);
+    let k = k1 + (k2 - k1) * t;
+
+    let a1 = lerp_knots(p0, p1, k0, k1, k);
+    let a2 = lerp_knots(p1, p2, k1, k2, k);
+    let a3 = lerp_knots(p2, p3, k2, k3, k);
+    let b1 = lerp_knots(a1, a2, k0, k2, k);
+    let b2 = lerp_knots(a2, a3, k1, k3, k);
+    lerp_knots(b1, b2, k1, k2, k)
+}
@@ -0,0 +1,133 @@
+use crate::math::Float;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A change of tempo and/or time signature, which takes effect at the start of a bar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoChange {
+    pub bar: u32,
+    pub beats_per_minute: f32,
+    pub beats_per_bar: u32,
+}
+
+/// A position in musical time. Bars and beats are counted from 0, and positions before the start
+/// of the song have negative bars.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BeatPosition {
+    pub bar: i32,
+    pub beat: f32,
+}
+
+/// Maps between seconds and beats for a song with tempo and time signature changes.
+/// The changes must be sorted by bar, and the first one must start at bar 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TempoMap {
+    pub changes: Vec<TempoChange>,
+}
+
+#[derive(Clone, Copy)]
+struct TempoSection {
+    change: TempoChange,
+    start_beats: f32,
+    start_seconds: f32,
+}
+
+impl TempoSection {
+    fn beats_per_bar(self) -> f32 {
+        self.change.beats_per_bar as f32
+    }
+
+    fn seconds_per_beat(self) -> f32 {
+        60. / self.change.beats_per_minute
+    }
+}
+
+impl Default for TempoMap {
+    fn default() -> Self {
+        TempoMap::new(120., 4)
+    }
+}
+
+impl TempoMap {
+    pub fn new(beats_per_minute: f32, beats_per_bar: u32) -> Self {
+        TempoMap {
+            changes: vec![TempoChange {
+                bar: 0,
+                beats_per_minute,
+                beats_per_bar,
+            }],
+        }
+    }
+
+    /// Restores the ordering of the changes, e.g. after loading them. If there are multiple
+    /// changes at the same bar, the last one takes effect.
+    pub fn normalize(&mut self) {
+        if self.changes.is_empty() {
+            *self = TempoMap::default();
+        }
+        self.changes.sort_by_key(|change| change.bar);
+        self.changes[0].bar = 0;
+    }
+
+    /// Finds the last section that `is_started` returns true for. Times before the first change
+    /// use the first section.
+    fn find_section(&self, is_started: impl Fn(TempoSection) -> bool) -> TempoSection {
+        let mut section = TempoSection {
+            change: self.changes[0],
+            start_beats: 0.,
+            start_seconds: 0.,
+        };
+        for &change in &self.changes[1..] {
+            let section_beats = (change.bar - section.change.bar) as f32 * section.beats_per_bar();
+            let next_section = TempoSection {
+                change,
+                start_beats: section.start_beats + section_beats,
+                start_seconds: section.start_seconds + section_beats * section.seconds_per_beat(),
+            };
+            if !is_started(next_section) {
+                break;
+            }
+            section = next_section;
+        }
+        section
+    }
+
+    pub fn seconds_to_beats(&self, seconds: f32) -> f32 {
+        let section = self.find_section(|section| section.start_seconds <= seconds);
+        section.start_beats + (seconds - section.start_seconds) / section.seconds_per_beat()
+    }
+
+    pub fn beats_to_seconds(&self, beats: f32) -> f32 {
+        let section = self.find_section(|section| section.start_beats <= beats);
+        section.start_seconds + (beats - section.start_beats) * section.seconds_per_beat()
+    }
+
+    pub fn beats_to_position(&self, beats: f32) -> BeatPosition {
+        let section = self.find_section(|section| section.start_beats <= beats);
+        let section_beats = beats - section.start_beats;
+        let section_bars = (section_beats / section.beats_per_bar()).floor();
+        BeatPosition {
+            bar: section.change.bar as i32 + section_bars as i32,
+            beat: section_beats - section_bars * section.beats_per_bar(),
+        }
+    }
+
+    pub fn position_to_beats(&self, position: BeatPosition) -> f32 {
+        let section = self.find_section(|section| section.change.bar as i32 <= position.bar);
+        section.start_beats
+            + (position.bar - section.change.bar as i32) as f32 * section.beats_per_bar()
+            + position.beat
+    }
+
+    pub fn beats_per_bar_at(&self, beats: f32) -> u32 {
+        self.find_section(|section| section.start_beats <= beats)
+            .change
+            .beats_per_bar
+    }
+
+    /// Returns true if the beat is the first in its bar. Since changes happen at the start of
+    /// bars, this is only true for whole beats.
+    pub fn is_bar_start(&self, beats: f32) -> bool {
+        self.beats_to_position(beats).beat == 0.
+    }
+}
@@ -1,7 +1,14 @@
-use super::animation_clip::AnimationClip;
+use super::animation_clip::{AnimatedPropertyTarget, AnimationClip};
+use super::clip::ClipReference;
+use super::driver::PropertyDriver;
 use super::property::PropertyValue;
 use super::schema::GeneratorSchema;
+use super::tempo_map::{BeatPosition, TempoMap};
+#[cfg(feature = "d3d")]
+use crate::creation_context::CreationContext;
+#[cfg(feature = "d3d")]
 use crate::generator::Generator;
+#[cfg(feature = "d3d")]
 use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::vec::Vec;
@@ -9,6 +16,73 @@ use alloc::vec::Vec;
 #[derive(Default)]
 pub struct Timeline {
     pub tracks: Vec<Track>,
+    pub tempo_map: TempoMap,
+}
+
+impl Timeline {
+    /// Forgets the state cached between frames by animated properties. This needs to be done
+    /// whenever an animation is edited, since the cache assumes the animation hasn't changed.
+    pub fn clear_animation_caches(&self) {
+        for clip in self.tracks.iter().flat_map(|track| track.clips.iter()) {
+            if let ClipSource::Animation(animation) = &clip.source {
+                for property in &animation.properties {
+                    *property.spring_cache.borrow_mut() = None;
+                    if let AnimatedPropertyTarget::Constraint(constraint) = &property.target {
+                        constraint.clear_cache();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Like `clear_animation_caches`, but only forgets what an edit to one clip could have
+    /// changed: the state cached by its own animations, and by constraints following it or the
+    /// clip it animates.
+    pub fn clear_clip_animation_caches(&self, edited_clip: ClipReference) {
+        let clips = || self.tracks.iter().flat_map(|track| track.clips.iter());
+        let animated_clip = clips()
+            .find(|clip| clip.id == edited_clip.clip_id())
+            .and_then(|clip| match &clip.source {
+                ClipSource::Animation(animation) => Some(animation.target_clip),
+                _ => None,
+            });
+
+        for clip in clips() {
+            if let ClipSource::Animation(animation) = &clip.source {
+                let is_edited = clip.id == edited_clip.clip_id();
+                for property in &animation.properties {
+                    if is_edited {
+                        *property.spring_cache.borrow_mut() = None;
+                    }
+                    if let AnimatedPropertyTarget::Constraint(constraint) = &property.target {
+                        let target_clip = constraint.target().clip;
+                        if is_edited
+                            || target_clip == edited_clip
+                            || Some(target_clip) == animated_clip
+                        {
+                            constraint.clear_cache();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "d3d")]
+impl Timeline {
+    /// Creates the generators for any clips that were loaded without them.
+    pub fn instantiate_generators(&mut self, context: &mut CreationContext) {
+        for clip in self
+            .tracks
+            .iter_mut()
+            .flat_map(|track| track.clips.iter_mut())
+        {
+            if let ClipSource::Unloaded = clip.source {
+                clip.source = ClipSource::Generator((clip.schema.instantiate_generator)(context));
+            }
+        }
+    }
 }
 
 #[derive(Default)]
@@ -16,16 +90,65 @@ pub struct Track {
     pub clips: Vec<Clip>,
 }
 
+impl Track {
+    pub fn clip_start_frame(&self, clip_index: usize) -> u32 {
+        self.clips[..clip_index]
+            .iter()
+            .map(|clip| clip.offset_frames + clip.duration_frames)
+            .sum::<u32>()
+            + self.clips[clip_index].offset_frames
+    }
+}
+
+/// Describes how timeline frames map to real time and to the music.
+#[derive(Clone, Copy)]
+pub struct TimeBase<'map> {
+    pub frames_per_second: f32,
+    pub tempo_map: &'map TempoMap,
+}
+
+impl<'map> TimeBase<'map> {
+    pub fn frame_to_seconds(self, frame: f32) -> f32 {
+        frame / self.frames_per_second
+    }
+
+    pub fn seconds_to_frame(self, seconds: f32) -> f32 {
+        seconds * self.frames_per_second
+    }
+
+    pub fn frame_to_beats(self, frame: f32) -> f32 {
+        self.tempo_map
+            .seconds_to_beats(self.frame_to_seconds(frame))
+    }
+
+    pub fn beats_to_frame(self, beats: f32) -> f32 {
+        self.seconds_to_frame(self.tempo_map.beats_to_seconds(beats))
+    }
+
+    pub fn frame_to_position(self, frame: f32) -> BeatPosition {
+        self.tempo_map.beats_to_position(self.frame_to_beats(frame))
+    }
+
+    pub fn position_to_frame(self, position: BeatPosition) -> f32 {
+        self.beats_to_frame(self.tempo_map.position_to_beats(position))
+    }
+}
+
 pub enum ClipSource {
+    #[cfg(feature = "d3d")]
     Generator(Box<dyn Generator>),
+    /// A generator clip that hasn't had its generator created, so the timeline can be loaded and
+    /// evaluated without a device. Its properties are animated as usual, but it isn't rendered.
+    /// Without the `d3d` feature, every generator clip is unloaded.
+    Unloaded,
     Animation(AnimationClip),
 }
 
 impl ClipSource {
     pub fn is_generator(&self) -> bool {
         match self {
-            ClipSource::Generator(_) => true,
-            _ => false,
+            ClipSource::Animation(_) => false,
+            _ => true,
         }
     }
 
@@ -36,6 +159,7 @@ impl ClipSource {
         }
     }
 
+    #[cfg(feature = "d3d")]
     pub fn generator(&self) -> Option<&dyn Generator> {
         match self {
             ClipSource::Generator(gen) => Some(gen.as_ref()),
@@ -43,6 +167,7 @@ impl ClipSource {
         }
     }
 
+    #[cfg(feature = "d3d")]
     pub fn generator_mut(&mut self) -> Option<&mut dyn Generator> {
         match self {
             ClipSource::Generator(gen) => Some(gen.as_mut()),
@@ -74,11 +199,20 @@ pub struct Clip {
 
     pub offset_frames: u32,
     pub duration_frames: u32,
+    /// If set, the clip's start and end are moved to these positions when the tempo changes.
+    #[cfg(debug_assertions)]
+    pub anchor: Option<ClipAnchor>,
 
     pub property_groups: Vec<PropertyGroup>,
     pub is_selected: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipAnchor {
+    pub start: BeatPosition,
+    pub end: BeatPosition,
+}
+
 pub struct PropertyGroup {
     pub defaults: Vec<PropertyDefault>,
 }
@@ -86,4 +220,6 @@ pub struct PropertyGroup {
 pub struct PropertyDefault {
     pub value: PropertyValue,
     pub is_override: bool,
+    /// If set, the property follows another clip's property instead of being animated.
+    pub driver: Option<PropertyDriver>,
 }
@@ -3,12 +3,15 @@ use super::prelude::*;
 pub static CLEAR_FLUID_SCHEMA: GeneratorSchema = GeneratorSchema {
     #[cfg(debug_assertions)]
     name: "Clear Fluid",
+    #[cfg(feature = "d3d")]
     instantiate_generator: |context| Box::new(ClearFluid),
     groups: &[],
 };
 
+#[cfg(feature = "d3d")]
 pub struct ClearFluid;
 
+#[cfg(feature = "d3d")]
 impl Generator for ClearFluid {
     fn update(
         &mut self,
@@ -1,22 +1,24 @@
 use super::prelude::*;
+use crate::math::RgbaColor;
 
 pub static CLEAR_SOLID_SCENE_SCHEMA: GeneratorSchema = GeneratorSchema {
     #[cfg(debug_assertions)]
     name: "Clear Solid",
+    #[cfg(feature = "d3d")]
     instantiate_generator: |_| Box::new(ClearSolidScene),
-    groups: &[SchemaGroup {
-        #[cfg(debug_assertions)]
-        name: "",
-        properties: &[SchemaProperty {
-            #[cfg(debug_assertions)]
-            name: "color",
-            value_type: PropertyType::RgbaColor,
-        }],
-    }],
+    groups: ClearSolidProperties::GROUPS,
 };
 
+#[derive(Properties)]
+struct ClearSolidProperties {
+    #[property(default = "0, 0, 0, 1")]
+    color: RgbaColor,
+}
+
+#[cfg(feature = "d3d")]
 pub struct ClearSolidScene;
 
+#[cfg(feature = "d3d")]
 impl Generator for ClearSolidScene {
     fn update(
         &mut self,
@@ -26,6 +28,7 @@ impl Generator for ClearSolidScene {
         _local_frame: u32,
         properties: &[&[ClipPropertyValue]],
     ) {
-        io.clear(context.devcon, prop(properties, 0, 0));
+        let properties = ClearSolidProperties::from_properties(properties);
+        io.clear(context.devcon, properties.color);
     }
 }
@@ -1,11 +1,19 @@
 use super::prelude::*;
+#[cfg(feature = "d3d")]
 use crate::blend_state::{BlendRenderTargetConfig, BlendState};
+#[cfg(feature = "d3d")]
 use crate::buffer::{Buffer, InitialData};
+#[cfg(feature = "d3d")]
 use crate::math::RgbaColor;
+#[cfg(feature = "d3d")]
 use crate::renderer::common::PostRenderer;
+#[cfg(feature = "d3d")]
 use crate::texture::{from_wmf, ShaderResource2D, Texture2D};
+#[cfg(feature = "d3d")]
 use crate::viewport::Viewport;
+#[cfg(feature = "d3d")]
 use core::ptr;
+#[cfg(feature = "d3d")]
 use winapi::um::d3d11::{
     D3D11_BIND_CONSTANT_BUFFER, D3D11_BLEND_INV_SRC_ALPHA, D3D11_BLEND_ONE, D3D11_BLEND_OP_ADD,
     D3D11_VIEWPORT,
@@ -14,24 +22,25 @@ use winapi::um::d3d11::{
 pub static CREDITS_SCENE_SCHEMA: GeneratorSchema = GeneratorSchema {
     #[cfg(debug_assertions)]
     name: "Credits Scene",
+    #[cfg(feature = "d3d")]
     instantiate_generator: |context| Box::new(CreditsScene::new(context)),
-    groups: &[SchemaGroup {
-        #[cfg(debug_assertions)]
-        name: "",
-        properties: &[SchemaProperty {
-            #[cfg(debug_assertions)]
-            name: "opacity",
-            value_type: PropertyType::Float,
-        }],
-    }],
+    groups: CreditsProperties::GROUPS,
 };
 
+#[derive(Properties)]
+struct CreditsProperties {
+    #[property(default = 1, min = 0, max = 1)]
+    opacity: f32,
+}
+
+#[cfg(feature = "d3d")]
 #[derive(Clone, Copy)]
 #[repr(C)]
 struct CreditData {
     fade: f32,
 }
 
+#[cfg(feature = "d3d")]
 pub struct CreditsScene {
     tex: Texture2D,
     blend: BlendState,
@@ -39,6 +48,7 @@ pub struct CreditsScene {
     credit_data: Buffer<CreditData>,
 }
 
+#[cfg(feature = "d3d")]
 impl CreditsScene {
     pub fn new(context: &mut CreationContext) -> Self {
         let emf_bytes = include_bytes!("../../resources/credits.emf");
@@ -75,6 +85,7 @@ impl CreditsScene {
     }
 }
 
+#[cfg(feature = "d3d")]
 impl Generator for CreditsScene {
     fn update(
         &mut self,
@@ -84,10 +95,11 @@ impl Generator for CreditsScene {
         local_frame: u32,
         properties: &[&[ClipPropertyValue]],
     ) {
+        let properties = CreditsProperties::from_properties(properties);
         self.credit_data.upload(
             context.devcon,
             CreditData {
-                fade: prop(properties, 0, 0),
+                fade: properties.opacity,
             },
         );
 
@@ -1,12 +1,20 @@
 use super::prelude::*;
+#[cfg(feature = "d3d")]
 use crate::buffer::{Buffer, InitialData};
-use crate::math::{RgbColor, RgbaColor, Vector3};
+#[cfg(feature = "d3d")]
+use crate::math::RgbColor;
+use crate::math::{RgbaColor, Vector3};
+#[cfg(feature = "d3d")]
 use crate::renderer::common::PostRenderer;
+#[cfg(feature = "d3d")]
 use core::ptr;
+#[cfg(feature = "d3d")]
 use winapi::um::d3d11::D3D11_BIND_CONSTANT_BUFFER;
 
+#[cfg(feature = "d3d")]
 const GRADIENT_COUNT: usize = 9;
 
+#[cfg(feature = "d3d")]
 #[derive(Clone, Copy)]
 #[repr(C)]
 struct GradientData {
@@ -23,200 +31,38 @@ struct GradientData {
 pub static GRADIENT_SCENE_SCHEMA: GeneratorSchema = GeneratorSchema {
     #[cfg(debug_assertions)]
     name: "Gradient Scene",
+    #[cfg(feature = "d3d")]
     instantiate_generator: |context| Box::new(GradientScene::new(context)),
-    groups: &[
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "brightness",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "rocket pos",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "rocket size",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "separation",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "heights",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "h1",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "h2",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "h3",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "h4",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "h5",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "h6",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "h7",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "h8",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "h9",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "curves",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c1",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c2",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c3",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c4",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c5",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c6",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c7",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c8",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c9",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "colors",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c1",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c2",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c3",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c4",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c5",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c6",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c7",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c8",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c9",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c10",
-                    value_type: PropertyType::RgbaColor,
-                },
-            ],
-        },
-    ],
+    groups: GradientProperties::GROUPS,
 };
 
+#[derive(Properties)]
+struct GradientProperties {
+    brightness: f32,
+    rocket_pos: Vector3,
+    rocket_size: f32,
+    separation: f32,
+
+    #[group = "heights"]
+    #[property(name = "h")]
+    heights: [f32; 9],
+
+    #[group = "curves"]
+    #[property(name = "c")]
+    curves: [f32; 9],
+
+    #[group = "colors"]
+    #[property(name = "c")]
+    colors: [RgbaColor; 10],
+}
+
+#[cfg(feature = "d3d")]
 pub struct GradientScene {
     gradient_data: Buffer<GradientData>,
     renderer: PostRenderer,
 }
 
+#[cfg(feature = "d3d")]
 impl GradientScene {
     pub fn new(context: &mut CreationContext) -> Self {
         GradientScene {
@@ -230,6 +76,7 @@ impl GradientScene {
     }
 }
 
+#[cfg(feature = "d3d")]
 impl Generator for GradientScene {
     fn update(
         &mut self,
@@ -239,23 +86,23 @@ impl Generator for GradientScene {
         _local_frame: u32,
         properties: &[&[ClipPropertyValue]],
     ) {
+        let properties = GradientProperties::from_properties(properties);
         {
             let mapped_data = self.gradient_data.map(context.devcon);
             let gradient_data = &mut mapped_data.slice_mut()[0];
-            gradient_data.rocket_pos = prop(properties, 0, 1);
-            gradient_data.rocket_size = prop(properties, 0, 2);
-            gradient_data.booster_separation = prop(properties, 0, 3);
-            gradient_data.brightness = prop(properties, 0, 0);
+            gradient_data.rocket_pos = properties.rocket_pos;
+            gradient_data.rocket_size = properties.rocket_size;
+            gradient_data.booster_separation = properties.separation;
+            gradient_data.brightness = properties.brightness;
 
             let mut last_height = 0.;
             for height_index in 0..GRADIENT_COUNT {
-                last_height += prop::<f32>(properties, 1, height_index);
+                last_height += properties.heights[height_index];
                 gradient_data.height_curves[height_index].0 = last_height;
-                gradient_data.height_curves[height_index].1 = prop(properties, 2, height_index);
+                gradient_data.height_curves[height_index].1 = properties.curves[height_index];
             }
             for color_index in 0..(GRADIENT_COUNT + 1) {
-                gradient_data.colors[color_index].0 =
-                    prop::<RgbaColor>(properties, 3, color_index).premult();
+                gradient_data.colors[color_index].0 = properties.colors[color_index].premult();
             }
         }
 
@@ -1,202 +1,81 @@
 use super::prelude::*;
+use crate::math::{RgbaColor, Vector2, Vector3};
+#[cfg(feature = "d3d")]
 use crate::renderer::bloom_renderer::{CompositeData, ExtractData};
+#[cfg(feature = "d3d")]
 use crate::renderer::grading_renderer::GradingParameters;
 
 pub static GRADING_EFFECT_SCHEMA: GeneratorSchema = GeneratorSchema {
     #[cfg(debug_assertions)]
     name: "Grading",
+    #[cfg(feature = "d3d")]
     instantiate_generator: |_| Box::new(GradingEffect),
-    groups: &[
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "camera",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "exposure",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "vignette offset",
-                    value_type: PropertyType::Vec2,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "vignette strength",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "vignette size",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "vignette power",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "chromab",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "grain",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "fade",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "bloom",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "shape",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "multiplier",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "bias",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "power",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "amount",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "size",
-                    value_type: PropertyType::Vec2,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "bloom extract",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "extract multiplier",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "extract bias",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "extract power",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "extract amount",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "grading",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "curve",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "gradient amt",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "gradient start",
-                    value_type: PropertyType::Vec2,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "gradient a",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "gradient end",
-                    value_type: PropertyType::Vec2,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "gradient b",
-                    value_type: PropertyType::RgbaColor,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "tonemapping",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "a",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "b",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "c",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "d",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "e",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "f",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "w",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-    ],
+    groups: GradingProperties::GROUPS,
 };
 
+#[derive(Properties)]
+struct GradingProperties {
+    #[group = "camera"]
+    exposure: f32,
+    vignette_offset: Vector2,
+    vignette_strength: f32,
+    vignette_size: f32,
+    vignette_power: f32,
+    chromab: f32,
+    grain: f32,
+    /// Fades the image to black at 0.
+    #[property(default = 1, min = 0, max = 1)]
+    fade: f32,
+
+    #[group = "bloom"]
+    #[property(name = "shape")]
+    bloom_shape: f32,
+    #[property(name = "multiplier")]
+    bloom_multiplier: f32,
+    #[property(name = "bias")]
+    bloom_bias: f32,
+    #[property(name = "power")]
+    bloom_power: f32,
+    #[property(name = "amount")]
+    bloom_amount: f32,
+    #[property(name = "size")]
+    bloom_size: Vector2,
+
+    #[group = "bloom extract"]
+    extract_multiplier: f32,
+    extract_bias: f32,
+    extract_power: f32,
+    extract_amount: f32,
+
+    #[group = "grading"]
+    curve: Vector3,
+    gradient_amt: f32,
+    gradient_start: Vector2,
+    gradient_a: RgbaColor,
+    gradient_end: Vector2,
+    gradient_b: RgbaColor,
+
+    #[group = "tonemapping"]
+    #[property(name = "a")]
+    tonemap_a: f32,
+    #[property(name = "b")]
+    tonemap_b: f32,
+    #[property(name = "c")]
+    tonemap_c: f32,
+    #[property(name = "d")]
+    tonemap_d: f32,
+    #[property(name = "e")]
+    tonemap_e: f32,
+    #[property(name = "f")]
+    tonemap_f: f32,
+    #[property(name = "w")]
+    tonemap_w: f32,
+}
+
+#[cfg(feature = "d3d")]
 pub struct GradingEffect;
 
+#[cfg(feature = "d3d")]
 impl Generator for GradingEffect {
     fn update(
         &mut self,
@@ -206,26 +85,28 @@ impl Generator for GradingEffect {
         _local_frame: u32,
         properties: &[&[ClipPropertyValue]],
     ) {
+        let properties = GradingProperties::from_properties(properties);
+
         // Render bloom
         let extract_data = ExtractData {
-            multiplier: properties[2][0].value.into_float().unwrap(),
-            bias: properties[2][1].value.into_float().unwrap(),
-            power: properties[2][2].value.into_float().unwrap(),
-            amount: properties[2][3].value.into_float().unwrap(),
+            multiplier: properties.extract_multiplier,
+            bias: properties.extract_bias,
+            power: properties.extract_power,
+            amount: properties.extract_amount,
         };
         let composite_data = CompositeData {
-            shape: properties[1][0].value.into_float().unwrap(),
-            multiplier: properties[1][1].value.into_float().unwrap(),
-            bias: properties[1][2].value.into_float().unwrap(),
-            power: properties[1][3].value.into_float().unwrap(),
-            amount: properties[1][4].value.into_float().unwrap(),
+            shape: properties.bloom_shape,
+            multiplier: properties.bloom_multiplier,
+            bias: properties.bloom_bias,
+            power: properties.bloom_power,
+            amount: properties.bloom_amount,
         };
         io.swap_lit();
         renderers.bloom.render(
             context,
             io.read_output(),
             io.write_output(),
-            properties[1][5].value.into_vec2().unwrap(),
+            properties.bloom_size,
             extract_data,
             composite_data,
         );
@@ -236,27 +117,27 @@ impl Generator for GradingEffect {
         renderers.grading.render(
             context,
             GradingParameters {
-                exposure: properties[0][0].value.into_float().unwrap(),
-                fade: properties[0][7].value.into_float().unwrap(),
-                curve: properties[3][0].value.into_vec3().unwrap(),
-                vignette_offset: properties[0][1].value.into_vec2().unwrap(),
-                vignette_strength: properties[0][2].value.into_float().unwrap(),
-                vignette_size: properties[0][3].value.into_float().unwrap(),
-                vignette_power: properties[0][4].value.into_float().unwrap(),
+                exposure: properties.exposure,
+                fade: properties.fade,
+                curve: properties.curve,
+                vignette_offset: properties.vignette_offset,
+                vignette_strength: properties.vignette_strength,
+                vignette_size: properties.vignette_size,
+                vignette_power: properties.vignette_power,
 
-                gradient_dry_wet: properties[3][1].value.into_float().unwrap(),
-                gradient_pos_a: properties[3][2].value.into_vec2().unwrap(),
-                gradient_color_a: properties[3][3].value.into_rgba_color().unwrap().premult(),
-                gradient_pos_b: properties[3][4].value.into_vec2().unwrap(),
-                gradient_color_b: properties[3][5].value.into_rgba_color().unwrap().premult(),
+                gradient_dry_wet: properties.gradient_amt,
+                gradient_pos_a: properties.gradient_start,
+                gradient_color_a: properties.gradient_a.premult(),
+                gradient_pos_b: properties.gradient_end,
+                gradient_color_b: properties.gradient_b.premult(),
 
-                tonemap_a: properties[4][0].value.into_float().unwrap(),
-                tonemap_b: properties[4][1].value.into_float().unwrap(),
-                tonemap_c: properties[4][2].value.into_float().unwrap(),
-                tonemap_d: properties[4][3].value.into_float().unwrap(),
-                tonemap_e: properties[4][4].value.into_float().unwrap(),
-                tonemap_f: properties[4][5].value.into_float().unwrap(),
-                tonemap_w: properties[4][6].value.into_float().unwrap(),
+                tonemap_a: properties.tonemap_a,
+                tonemap_b: properties.tonemap_b,
+                tonemap_c: properties.tonemap_c,
+                tonemap_d: properties.tonemap_d,
+                tonemap_e: properties.tonemap_e,
+                tonemap_f: properties.tonemap_f,
+                tonemap_w: properties.tonemap_w,
             },
             io.read_output(),
             io.write_output(),
@@ -276,8 +157,8 @@ impl Generator for GradingEffect {
         io.swap_lit();
         renderers.chromab.render(
             context,
-            properties[0][5].value.into_float().unwrap(),
-            properties[0][6].value.into_float().unwrap(),
+            properties.chromab,
+            properties.grain,
             io.read_output(),
             io.write_output(),
         );
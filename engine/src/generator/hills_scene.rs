@@ -1,23 +1,39 @@
 use super::prelude::*;
+#[cfg(feature = "d3d")]
 use crate::blend_state::{BlendRenderTargetConfig, BlendState};
+#[cfg(feature = "d3d")]
 use crate::buffer::{Buffer, InitialData};
+#[cfg(feature = "d3d")]
 use crate::camera::CameraBuffer;
 use crate::math::{Matrix4, Quaternion, RgbColor, RgbaColor, Vector2, Vector3, Vector4};
+#[cfg(feature = "d3d")]
 use crate::mesh::{primitives, Mesh};
+#[cfg(feature = "d3d")]
 use crate::object::MeshObject;
+#[cfg(feature = "d3d")]
 use crate::raster_state::RasterState;
+#[cfg(feature = "d3d")]
 use crate::renderer::clouds_renderer::CloudsData;
+#[cfg(feature = "d3d")]
 use crate::renderer::common::{GaussBlurRenderer, PostRenderer, StandardRenderer};
+#[cfg(feature = "d3d")]
 use crate::resources::shader_manager::ComputeKey;
+#[cfg(feature = "d3d")]
 use crate::shader_view::ShaderView;
+#[cfg(feature = "d3d")]
 use crate::texture::{
     AddressMode, DepthStencil, PingPong2D, RenderTarget2D, Sampler, ShaderResource2D, Texture2D,
     Texture3D,
 };
+#[cfg(feature = "d3d")]
 use crate::unordered_view::UnorderedView;
+#[cfg(feature = "d3d")]
 use crate::viewport::Viewport;
+#[cfg(feature = "d3d")]
 use core::{f32, ptr};
+#[cfg(feature = "d3d")]
 use winapi::shared::dxgiformat::{DXGI_FORMAT_R32G32B32A32_FLOAT, DXGI_FORMAT_R32_FLOAT};
+#[cfg(feature = "d3d")]
 use winapi::um::d3d11::{
     D3D11_BIND_CONSTANT_BUFFER, D3D11_BIND_SHADER_RESOURCE, D3D11_BIND_UNORDERED_ACCESS,
     D3D11_BLEND_INV_SRC_ALPHA, D3D11_BLEND_ONE, D3D11_BLEND_OP_ADD,
@@ -27,107 +43,52 @@ use winapi::um::d3d11::{
 pub static HILLS_SCENE_SCHEMA: GeneratorSchema = GeneratorSchema {
     #[cfg(debug_assertions)]
     name: "Hills Scene",
+    #[cfg(feature = "d3d")]
     instantiate_generator: |context| Box::new(HillsScene),
-    groups: &[
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "terrain",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "center",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "size",
-                    value_type: PropertyType::Vec3,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "light map",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "position",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "x range",
-                    value_type: PropertyType::Vec2,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "y range",
-                    value_type: PropertyType::Vec2,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "z range",
-                    value_type: PropertyType::Vec2,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "fog",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "color",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "exp",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "density",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "clouds",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "y",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "height",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "map offset",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "color",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "scatter color",
-                    value_type: PropertyType::RgbaColor,
-                },
-            ],
-        },
-    ],
+    groups: HillsSceneProperties::GROUPS,
 };
 
+#[derive(Properties)]
+pub struct HillsSceneProperties {
+    #[group = "terrain"]
+    #[property(name = "center")]
+    pub terrain_center: Vector3,
+    #[property(name = "size")]
+    pub terrain_size: Vector3,
+
+    #[group = "light map"]
+    #[property(name = "position")]
+    pub light_map_pos: Vector3,
+    #[property(name = "x range")]
+    pub light_map_x_clip: Vector2,
+    #[property(name = "y range")]
+    pub light_map_y_clip: Vector2,
+    #[property(name = "z range")]
+    pub light_map_z_clip: Vector2,
+
+    #[group = "fog"]
+    #[property(name = "color")]
+    pub fog_color: RgbaColor,
+    #[property(name = "exp")]
+    pub fog_exp: f32,
+    #[property(name = "density")]
+    pub fog_density: f32,
+
+    #[group = "clouds"]
+    #[property(name = "y")]
+    pub cloud_y: f32,
+    #[property(name = "height")]
+    pub cloud_height: f32,
+    pub map_offset: Vector3,
+    #[property(name = "color")]
+    pub sky_color: RgbaColor,
+    pub scatter_color: RgbaColor,
+}
+
+#[cfg(feature = "d3d")]
 pub struct HillsScene;
 
+#[cfg(feature = "d3d")]
 impl Generator for HillsScene {
     fn update(
         &mut self,
@@ -143,7 +104,7 @@ impl Generator for HillsScene {
             &mut renderers.clouds,
             &mut renderers.shadow_map,
             &mut renderers.godray,
-            properties
+            &HillsSceneProperties::from_properties(properties),
         );
     }
 }
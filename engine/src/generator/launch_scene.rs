@@ -1,199 +1,95 @@
 use super::prelude::*;
+use crate::math::{Quaternion, RgbaColor, Vector2, Vector3};
+#[cfg(feature = "d3d")]
 use crate::renderer::launch_scene_renderer::CloudState;
 
 pub static LAUNCH_SCENE_SCHEMA: GeneratorSchema = GeneratorSchema {
     #[cfg(debug_assertions)]
     name: "Launch Scene",
+    #[cfg(feature = "d3d")]
     instantiate_generator: |context| Box::new(LaunchScene::new(context)),
-    groups: &[
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "light map",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "position",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "x range",
-                    value_type: PropertyType::Vec2,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "y range",
-                    value_type: PropertyType::Vec2,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "z range",
-                    value_type: PropertyType::Vec2,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "rays",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "density",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "steps",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "step length",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "start dist",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "fluid",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "box pos",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "box size",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "march step length",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "density multiplier",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "fluid shadow",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "directional <-> point",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "pos",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "color",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "size (directional)",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "radius (point)",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "max radius (point)",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "rocket",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "height",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "enabled",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "clouds",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "enabled",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "y",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "height",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "map offset",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "color",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "scatter color",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "light direction",
-                    value_type: PropertyType::Rotation,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "opacity",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "gen once <-> live",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-    ],
+    groups: LaunchSceneProperties::GROUPS,
 };
 
+#[derive(Properties)]
+pub struct LaunchSceneProperties {
+    #[group = "light map"]
+    #[property(name = "position")]
+    pub light_map_pos: Vector3,
+    #[property(name = "x range")]
+    pub light_map_x_range: Vector2,
+    #[property(name = "y range")]
+    pub light_map_y_range: Vector2,
+    #[property(name = "z range")]
+    pub light_map_z_range: Vector2,
+
+    #[group = "rays"]
+    #[property(name = "density")]
+    pub rays_density: f32,
+    #[property(name = "steps", min = 0)]
+    pub rays_steps: i32,
+    #[property(name = "step length")]
+    pub rays_step_length: f32,
+    #[property(name = "start dist")]
+    pub rays_start_dist: f32,
+
+    #[group = "fluid"]
+    #[property(name = "box pos")]
+    pub fluid_box_pos: Vector3,
+    #[property(name = "box size")]
+    pub fluid_box_size: Vector3,
+    pub march_step_length: f32,
+    pub density_multiplier: f32,
+
+    #[group = "fluid shadow"]
+    /// Uses a point light above 0.5, or a directional light below it.
+    #[property(name = "directional <-> point", min = 0, max = 1)]
+    pub fluid_shadow_mode: f32,
+    #[property(name = "pos")]
+    pub fluid_shadow_pos: Vector3,
+    #[property(name = "color")]
+    pub fluid_shadow_color: RgbaColor,
+    #[property(name = "size (directional)")]
+    pub fluid_shadow_directional_size: Vector3,
+    #[property(name = "radius (point)")]
+    pub fluid_shadow_point_radius: f32,
+    #[property(name = "max radius (point)")]
+    pub fluid_shadow_point_max_radius: f32,
+
+    #[group = "rocket"]
+    #[property(name = "height")]
+    pub rocket_height: f32,
+    #[property(name = "enabled", default = 1)]
+    pub rocket_enabled: bool,
+
+    #[group = "clouds"]
+    #[property(name = "enabled", default = 1)]
+    pub clouds_enabled: bool,
+    #[property(name = "y")]
+    pub clouds_y: f32,
+    #[property(name = "height")]
+    pub clouds_height: f32,
+    #[property(name = "map offset")]
+    pub clouds_map_offset: Vector3,
+    #[property(name = "color")]
+    pub clouds_color: RgbaColor,
+    #[property(name = "scatter color")]
+    pub clouds_scatter_color: RgbaColor,
+    #[property(name = "light direction")]
+    pub clouds_light_direction: Quaternion,
+    #[property(name = "opacity")]
+    pub clouds_opacity: f32,
+    /// Generates the clouds every frame, rather than only the first time they're rendered.
+    #[property(name = "live")]
+    pub clouds_live: bool,
+}
+
+#[cfg(feature = "d3d")]
 pub struct LaunchScene {
     cloud_state: CloudState,
 }
 
+#[cfg(feature = "d3d")]
 impl LaunchScene {
     pub fn new(context: &mut CreationContext) -> Self {
         LaunchScene {
@@ -202,6 +98,7 @@ impl LaunchScene {
     }
 }
 
+#[cfg(feature = "d3d")]
 impl Generator for LaunchScene {
     fn update(
         &mut self,
@@ -220,7 +117,7 @@ impl Generator for LaunchScene {
             &mut renderers.clouds,
             &mut renderers.blit,
             &mut self.cloud_state,
-            properties,
+            &LaunchSceneProperties::from_properties(properties),
         );
     }
 }
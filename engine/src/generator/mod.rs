@@ -1,3 +1,6 @@
+// Only the schemas are built without D3D, so the generators' property structs go unread.
+#![cfg_attr(not(feature = "d3d"), allow(dead_code))]
+
 mod clear_fluid;
 mod clear_solid_scene;
 mod credits_scene;
@@ -12,14 +15,24 @@ mod simulate_fluid;
 mod skybox_scene;
 mod world_light;
 
+#[cfg(feature = "d3d")]
 use crate::animation::clip::ClipPropertyValue;
 use crate::animation::schema::GeneratorSchema;
+#[cfg(feature = "d3d")]
 use crate::binding::CameraBinding;
+#[cfg(feature = "d3d")]
 use crate::controller::{CloudController, PerspectiveCameraController, TransformController};
+#[cfg(feature = "d3d")]
 use crate::frame_context::FrameContext;
+#[cfg(feature = "d3d")]
 use crate::gbuffer::GBuffer;
+#[cfg(feature = "d3d")]
 use crate::renderer::RendererCollection;
 
+pub use self::hills_scene::HillsSceneProperties;
+pub use self::launch_scene::LaunchSceneProperties;
+pub use self::rocket_scene::RocketSceneProperties;
+
 pub static GENERATOR_SCHEMAS: &[GeneratorSchema] = &[
     self::clear_solid_scene::CLEAR_SOLID_SCENE_SCHEMA,
     self::perspective_camera::PERSPECTIVE_CAMERA_SCHEMA,
@@ -35,6 +48,7 @@ pub static GENERATOR_SCHEMAS: &[GeneratorSchema] = &[
     self::credits_scene::CREDITS_SCENE_SCHEMA,
 ];
 
+#[cfg(feature = "d3d")]
 pub trait Generator: 'static {
     fn update(
         &mut self,
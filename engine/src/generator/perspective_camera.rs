@@ -1,56 +1,51 @@
 use super::prelude::*;
+#[cfg(feature = "d3d")]
 use crate::binding::{CameraBinding, PropertyBinding};
+#[cfg(feature = "d3d")]
 use crate::camera;
+#[cfg(feature = "d3d")]
 use crate::camera::Camera;
+#[cfg(feature = "d3d")]
 use crate::controller::PerspectiveCameraController;
-use crate::math::Vector2;
+use crate::math::{Quaternion, Vector2, Vector3};
 
 pub static PERSPECTIVE_CAMERA_SCHEMA: GeneratorSchema = GeneratorSchema {
     #[cfg(debug_assertions)]
     name: "Perspective Camera",
+    #[cfg(feature = "d3d")]
     instantiate_generator: |_| Box::new(PerspectiveCamera::new()),
-    groups: &[SchemaGroup {
-        #[cfg(debug_assertions)]
-        name: "",
-        properties: &[
-            SchemaProperty {
-                #[cfg(debug_assertions)]
-                name: "base pos",
-                value_type: PropertyType::Vec3,
-            },
-            SchemaProperty {
-                #[cfg(debug_assertions)]
-                name: "gymbal dir",
-                value_type: PropertyType::Rotation,
-            },
-            SchemaProperty {
-                #[cfg(debug_assertions)]
-                name: "arm length",
-                value_type: PropertyType::Float,
-            },
-            SchemaProperty {
-                #[cfg(debug_assertions)]
-                name: "head dir",
-                value_type: PropertyType::Rotation,
-            },
-            SchemaProperty {
-                #[cfg(debug_assertions)]
-                name: "fov",
-                value_type: PropertyType::Float,
-            },
-            SchemaProperty {
-                #[cfg(debug_assertions)]
-                name: "z range",
-                value_type: PropertyType::Vec2,
-            },
-        ],
-    }],
+    groups: PerspectiveCameraProperties::GROUPS,
 };
 
+#[derive(Properties)]
+struct PerspectiveCameraProperties {
+    base_pos: Vector3,
+    gymbal_dir: Quaternion,
+    /// Distance from the base position to the camera, along the gymbal direction.
+    #[property(min = 0)]
+    arm_length: f32,
+    head_dir: Quaternion,
+    /// Vertical field of view.
+    #[property(
+        default = 60,
+        soft_min = 1,
+        soft_max = 120,
+        min = 0,
+        max = 179,
+        unit = "degrees"
+    )]
+    fov: f32,
+    /// Near and far clipping distances.
+    #[property(default = "0.1, 100")]
+    z_range: Vector2,
+}
+
+#[cfg(feature = "d3d")]
 pub struct PerspectiveCamera {
     camera: camera::PerspectiveCamera,
 }
 
+#[cfg(feature = "d3d")]
 impl PerspectiveCamera {
     pub fn new() -> Self {
         PerspectiveCamera {
@@ -59,6 +54,7 @@ impl PerspectiveCamera {
     }
 }
 
+#[cfg(feature = "d3d")]
 impl Generator for PerspectiveCamera {
     fn update(
         &mut self,
@@ -68,15 +64,14 @@ impl Generator for PerspectiveCamera {
         _local_frame: u32,
         properties: &[&[ClipPropertyValue]],
     ) {
-        self.camera.base_pos = prop(properties, 0, 0);
-        self.camera.gymbal_dir = prop(properties, 0, 1);
-        self.camera.arm_length = prop(properties, 0, 2);
-        self.camera.camera_dir = prop(properties, 0, 3);
-        self.camera.fov = prop::<f32>(properties, 0, 4).to_radians();
-
-        let z_range: Vector2 = prop(properties, 0, 5);
-        self.camera.near_z = z_range.x;
-        self.camera.far_z = z_range.y;
+        let properties = PerspectiveCameraProperties::from_properties(properties);
+        self.camera.base_pos = properties.base_pos;
+        self.camera.gymbal_dir = properties.gymbal_dir;
+        self.camera.arm_length = properties.arm_length;
+        self.camera.camera_dir = properties.head_dir;
+        self.camera.fov = properties.fov.to_radians();
+        self.camera.near_z = properties.z_range.x;
+        self.camera.far_z = properties.z_range.y;
 
         self.camera.update(context.viewport);
         self.camera.upload(context.devcon, &mut context.common);
@@ -87,16 +82,17 @@ impl Generator for PerspectiveCamera {
     }
 }
 
+#[cfg(feature = "d3d")]
 impl CameraBinding for PerspectiveCamera {
     fn camera_position_binding(&self) -> PropertyBinding {
-        PropertyBinding::new(0, 0)
+        PerspectiveCameraProperties::BASE_POS_BINDING
     }
 
     fn camera_direction_binding(&self) -> PropertyBinding {
-        PropertyBinding::new(0, 3)
+        PerspectiveCameraProperties::HEAD_DIR_BINDING
     }
 
     fn camera_fov_binding(&self) -> PropertyBinding {
-        PropertyBinding::new(0, 4)
+        PerspectiveCameraProperties::FOV_BINDING
     }
 }
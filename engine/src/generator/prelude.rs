@@ -1,14 +1,22 @@
+#[cfg(feature = "d3d")]
 pub use crate::animation::clip::ClipPropertyValue;
-pub use crate::animation::property::prop;
-pub use crate::animation::property::PropertyType;
-pub use crate::animation::schema::{GeneratorSchema, SchemaGroup, SchemaProperty};
+pub use crate::animation::schema::GeneratorSchema;
+#[cfg(feature = "d3d")]
 pub use crate::creation_context::CreationContext;
+#[cfg(feature = "d3d")]
 pub use crate::frame_context::FrameContext;
+#[cfg(feature = "d3d")]
 pub use crate::gbuffer::GBuffer;
+#[cfg(feature = "d3d")]
 pub use crate::generator::Generator;
+#[cfg(feature = "d3d")]
 pub use crate::renderer::RendererCollection;
+#[cfg(feature = "d3d")]
 pub use alloc::boxed::Box;
+pub use tool_derive::Properties;
 
-use crate::animation::clip::{ClipReference, GeneratorClipMap};
+use crate::animation::clip::ClipReference;
+#[cfg(feature = "d3d")]
+use crate::animation::clip::GeneratorClipMap;
 use crate::animation::property::PropertyValue;
 use crate::math::{Quaternion, RgbColor, RgbaColor, Vector2, Vector3, Vector4};
@@ -1,113 +1,58 @@
 use super::prelude::*;
+use crate::math::{Quaternion, RgbaColor, Vector3};
 
 pub static ROCKET_SCENE_SCHEMA: GeneratorSchema = GeneratorSchema {
     #[cfg(debug_assertions)]
     name: "Rocket Scene",
+    #[cfg(feature = "d3d")]
     instantiate_generator: |context| Box::new(RocketScene),
-    groups: &[
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "density vol",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "pos",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "size",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "light map blur",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "directional light",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "direction",
-                    value_type: PropertyType::Rotation,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "color",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "shadow vol pos",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "shadow vol size",
-                    value_type: PropertyType::Vec3,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "point light",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "pos",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "color",
-                    value_type: PropertyType::RgbaColor,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "radius",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "max radius",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "ambient light",
-            properties: &[SchemaProperty {
-                #[cfg(debug_assertions)]
-                name: "color",
-                value_type: PropertyType::RgbaColor,
-            }],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "rocket",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "base pos",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "enabled",
-                    value_type: PropertyType::Float,
-                }
-            ],
-        },
-    ],
+    groups: RocketSceneProperties::GROUPS,
 };
 
+#[derive(Properties)]
+pub struct RocketSceneProperties {
+    #[group = "density vol"]
+    #[property(name = "pos")]
+    pub density_vol_pos: Vector3,
+    #[property(name = "size")]
+    pub density_vol_size: Vector3,
+    pub light_map_blur: f32,
+
+    #[group = "directional light"]
+    #[property(name = "direction")]
+    pub directional_light_dir: Quaternion,
+    #[property(name = "color")]
+    pub directional_light_color: RgbaColor,
+    #[property(name = "shadow vol pos")]
+    pub directional_light_vol_pos: Vector3,
+    #[property(name = "shadow vol size")]
+    pub directional_light_vol_size: Vector3,
+
+    #[group = "point light"]
+    #[property(name = "pos")]
+    pub point_light_pos: Vector3,
+    #[property(name = "color")]
+    pub point_light_color: RgbaColor,
+    #[property(name = "radius")]
+    pub point_light_radius: f32,
+    #[property(name = "max radius")]
+    pub point_light_max_radius: f32,
+
+    #[group = "ambient light"]
+    #[property(name = "color")]
+    pub ambient_light_color: RgbaColor,
+
+    #[group = "rocket"]
+    #[property(name = "base pos")]
+    pub rocket_base_pos: Vector3,
+    #[property(name = "enabled", default = 1)]
+    pub rocket_enabled: bool,
+}
+
+#[cfg(feature = "d3d")]
 struct RocketScene;
 
+#[cfg(feature = "d3d")]
 impl Generator for RocketScene {
     fn update(
         &mut self,
@@ -117,6 +62,10 @@ impl Generator for RocketScene {
         _local_frame: u32,
         properties: &[&[ClipPropertyValue]],
     ) {
-        renderers.rocket_scene.update(io, context, properties);
+        renderers.rocket_scene.update(
+            io,
+            context,
+            &RocketSceneProperties::from_properties(properties),
+        );
     }
 }
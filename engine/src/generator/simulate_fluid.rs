@@ -1,92 +1,51 @@
 use super::prelude::*;
 use crate::math::Vector3;
+#[cfg(feature = "d3d")]
 use crate::renderer::fluid_sim_renderer::FluidProperties;
 
 pub static SIMULATE_FLUID_SCHEMA: GeneratorSchema = GeneratorSchema {
     #[cfg(debug_assertions)]
     name: "Simulate Fluid",
+    #[cfg(feature = "d3d")]
     instantiate_generator: |context| Box::new(SimulateFluid::new()),
-    groups: &[
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "vorticity strength",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "density dissipation",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "density buoyancy",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "density weight",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "temperature dissipation",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "velocity dissipation",
-                    value_type: PropertyType::Float,
-                },
-            ],
-        },
-        SchemaGroup {
-            #[cfg(debug_assertions)]
-            name: "input",
-            properties: &[
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "radius",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "pos",
-                    value_type: PropertyType::Vec3,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "density",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "temperature",
-                    value_type: PropertyType::Float,
-                },
-                SchemaProperty {
-                    #[cfg(debug_assertions)]
-                    name: "velocity",
-                    value_type: PropertyType::Vec3,
-                },
-            ],
-        },
-    ],
+    groups: SimulateFluidProperties::GROUPS,
 };
 
+#[derive(Properties)]
+struct SimulateFluidProperties {
+    vorticity_strength: f32,
+    density_dissipation: f32,
+    density_buoyancy: f32,
+    density_weight: f32,
+    temperature_dissipation: f32,
+    velocity_dissipation: f32,
+
+    #[group = "input"]
+    #[property(name = "radius")]
+    input_radius: Vector3,
+    #[property(name = "pos")]
+    input_pos: Vector3,
+    #[property(name = "density")]
+    density_amount: f32,
+    #[property(name = "temperature")]
+    temperature_amount: f32,
+    #[property(name = "velocity")]
+    velocity_amount: Vector3,
+}
+
+#[cfg(feature = "d3d")]
 pub struct SimulateFluid {
     last_frame: u32,
 }
 
+#[cfg(feature = "d3d")]
 impl SimulateFluid {
     fn new() -> Self {
         SimulateFluid { last_frame: 0 }
     }
 }
 
+#[cfg(feature = "d3d")]
 impl Generator for SimulateFluid {
     fn update(
         &mut self,
@@ -96,24 +55,25 @@ impl Generator for SimulateFluid {
         local_frame: u32,
         properties: &[&[ClipPropertyValue]],
     ) {
-        let radius: Vector3 = prop(properties, 1, 0);
+        let properties = SimulateFluidProperties::from_properties(properties);
+        let radius = properties.input_radius;
         let props = FluidProperties {
             input_radius: Vector3 {
                 x: radius.x.max(0.001),
                 y: radius.y.max(0.001),
                 z: radius.z.max(0.001),
             },
-            input_pos: prop(properties, 1, 1),
-            density_amount: prop(properties, 1, 2),
-            temperature_amount: prop(properties, 1, 3),
-            velocity_amount: prop(properties, 1, 4),
+            input_pos: properties.input_pos,
+            density_amount: properties.density_amount,
+            temperature_amount: properties.temperature_amount,
+            velocity_amount: properties.velocity_amount,
 
-            vorticity_strength: prop(properties, 0, 0),
-            density_dissipation: prop(properties, 0, 1),
-            density_buoyancy: prop(properties, 0, 2),
-            density_weight: prop(properties, 0, 3),
-            temperature_dissipation: prop(properties, 0, 4),
-            velocity_dissipation: prop(properties, 0, 5),
+            vorticity_strength: properties.vorticity_strength,
+            density_dissipation: properties.density_dissipation,
+            density_buoyancy: properties.density_buoyancy,
+            density_weight: properties.density_weight,
+            temperature_dissipation: properties.temperature_dissipation,
+            velocity_dissipation: properties.velocity_dissipation,
         };
 
         while self.last_frame / 2 < local_frame / 2 {
@@ -3,12 +3,15 @@ use super::prelude::*;
 pub static SKYBOX_SCENE_SCHEMA: GeneratorSchema = GeneratorSchema {
     #[cfg(debug_assertions)]
     name: "Skybox",
+    #[cfg(feature = "d3d")]
     instantiate_generator: |_| Box::new(SkyboxScene),
     groups: &[],
 };
 
+#[cfg(feature = "d3d")]
 pub struct SkyboxScene;
 
+#[cfg(feature = "d3d")]
 impl Generator for SkyboxScene {
     fn update(
         &mut self,
@@ -1,36 +1,29 @@
 use super::prelude::*;
+#[cfg(feature = "d3d")]
 use crate::frame_context::LightBuffer;
 use crate::math::{Quaternion, RgbaColor, Vector3, Vector4};
 
 pub static WORLD_LIGHT_SCHEMA: GeneratorSchema = GeneratorSchema {
     #[cfg(debug_assertions)]
     name: "World Light",
+    #[cfg(feature = "d3d")]
     instantiate_generator: |_| Box::new(WorldLight),
-    groups: &[SchemaGroup {
-        #[cfg(debug_assertions)]
-        name: "",
-        properties: &[
-            SchemaProperty {
-                #[cfg(debug_assertions)]
-                name: "direction",
-                value_type: PropertyType::Rotation,
-            },
-            SchemaProperty {
-                #[cfg(debug_assertions)]
-                name: "color",
-                value_type: PropertyType::RgbaColor,
-            },
-            SchemaProperty {
-                #[cfg(debug_assertions)]
-                name: "ambient",
-                value_type: PropertyType::Float,
-            },
-        ],
-    }],
+    groups: WorldLightProperties::GROUPS,
 };
 
+#[derive(Properties)]
+struct WorldLightProperties {
+    direction: Quaternion,
+    #[property(default = "1, 1, 1, 1")]
+    color: RgbaColor,
+    #[property(min = 0)]
+    ambient: f32,
+}
+
+#[cfg(feature = "d3d")]
 pub struct WorldLight;
 
+#[cfg(feature = "d3d")]
 impl Generator for WorldLight {
     fn update(
         &mut self,
@@ -40,11 +33,12 @@ impl Generator for WorldLight {
         _local_frame: u32,
         properties: &[&[ClipPropertyValue]],
     ) {
+        let properties = WorldLightProperties::from_properties(properties);
         let light_buffer = LightBuffer {
-            world_light_direction: prop::<Quaternion>(properties, 0, 0).as_right().as_vec4(0.),
-            world_light_color: prop::<RgbaColor>(properties, 0, 1).premult().0.as_vec4(0.),
-            world_light_ambient: prop(properties, 0, 2),
-            world_light_rotation: prop::<Quaternion>(properties, 0, 0),
+            world_light_direction: properties.direction.as_right().as_vec4(0.),
+            world_light_color: properties.color.premult().0.as_vec4(0.),
+            world_light_ambient: properties.ambient,
+            world_light_rotation: properties.direction,
         };
         context
             .common
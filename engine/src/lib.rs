@@ -64,28 +64,46 @@ macro_rules! offset_of {
 
 pub mod animation;
 pub mod binding;
+#[cfg(feature = "d3d")]
 pub mod blend_state;
+#[cfg(feature = "d3d")]
 pub mod buffer;
+#[cfg(feature = "d3d")]
 pub mod camera;
+#[cfg(feature = "d3d")]
 pub mod controller;
+#[cfg(feature = "d3d")]
 pub mod creation_context;
+#[cfg(feature = "d3d")]
 pub mod depth_state;
 pub mod field_offset;
+#[cfg(feature = "d3d")]
 pub mod frame_context;
+#[cfg(feature = "d3d")]
 pub mod gbuffer;
 pub mod generator;
+#[cfg(feature = "d3d")]
 pub mod material;
 pub mod math;
 pub mod mesh;
 //pub mod mesh_gen;
+#[cfg(feature = "d3d")]
 pub mod object;
+#[cfg(feature = "d3d")]
 pub mod raster_state;
+#[cfg(feature = "d3d")]
 pub mod renderer;
+#[cfg(feature = "d3d")]
 pub mod resources;
+#[cfg(feature = "d3d")]
 pub mod shader_view;
+#[cfg(feature = "d3d")]
 pub mod target_view;
+#[cfg(feature = "d3d")]
 pub mod texture;
+#[cfg(feature = "d3d")]
 pub mod unordered_view;
+#[cfg(feature = "d3d")]
 pub mod vertex_layout;
 pub mod viewport;
 //pub mod vec;
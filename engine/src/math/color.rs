@@ -1,4 +1,56 @@
-use super::{Vector3, Vector4};
+use super::{Float, Vector3, Vector4};
+use core::f32::consts::PI;
+
+/// Chroma or saturation below this is treated as grey, which doesn't have a meaningful hue.
+const ACHROMATIC_THRESHOLD: f32 = 1e-4;
+
+/// The space colours are converted to before being interpolated. Interpolating in linear RGB
+/// passes through greys between saturated hues, while the others keep colours vivid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    LinearRgb,
+    /// Perceptually uniform, so the brightness changes evenly.
+    Oklab,
+    /// OKLab in polar form, which goes around the hue wheel the short way.
+    Oklch,
+    /// Goes around the hue wheel the short way, keeping the saturation and value.
+    Hsv,
+}
+
+impl ColorSpace {
+    /// Every colour space, in the same order as their discriminants. The exporter and player use
+    /// a colour space's index in this list to identify it.
+    pub const ALL: [ColorSpace; 4] = [
+        ColorSpace::LinearRgb,
+        ColorSpace::Oklab,
+        ColorSpace::Oklch,
+        ColorSpace::Hsv,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_index(index: usize) -> ColorSpace {
+        ColorSpace::ALL[index]
+    }
+
+    #[cfg(debug_assertions)]
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorSpace::LinearRgb => "Linear RGB",
+            ColorSpace::Oklab => "OKLab",
+            ColorSpace::Oklch => "OKLCh",
+            ColorSpace::Hsv => "HSV",
+        }
+    }
+}
+
+impl Default for ColorSpace {
+    fn default() -> Self {
+        ColorSpace::LinearRgb
+    }
+}
 
 #[derive(Clone, Copy, PartialEq, PartialOrd, Default, Debug)]
 #[repr(C)]
@@ -28,6 +80,109 @@ impl RgbColor {
     pub fn lerp(self, b: RgbColor, t: f32) -> Self {
         RgbColor(self.0.lerp(b.0, t))
     }
+
+    /// Interpolates after converting both colours to another colour space.
+    pub fn lerp_in(self, b: RgbColor, t: f32, space: ColorSpace) -> Self {
+        match space {
+            ColorSpace::LinearRgb => self.lerp(b, t),
+            ColorSpace::Oklab => RgbColor::from_oklab(self.to_oklab().lerp(b.to_oklab(), t)),
+            ColorSpace::Oklch => {
+                let (a, b) = (self.to_oklch(), b.to_oklch());
+                let mut lch = a.lerp(b, t);
+                lch.z = lerp_hue(a.z, a.y, b.z, b.y, t, 2. * PI);
+                RgbColor::from_oklch(lch)
+            }
+            ColorSpace::Hsv => {
+                let (a, b) = (self.to_hsv(), b.to_hsv());
+                let mut hsv = a.lerp(b, t);
+                hsv.x = lerp_hue(a.x, a.y, b.x, b.y, t, 1.);
+                RgbColor::from_hsv(hsv)
+            }
+        }
+    }
+
+    /// Converts to OKLab lightness and a/b components.
+    pub fn to_oklab(self) -> Vector3 {
+        let l = 0.412_221_47 * self.r() + 0.536_332_55 * self.g() + 0.051_445_995 * self.b();
+        let m = 0.211_903_5 * self.r() + 0.680_699_5 * self.g() + 0.107_396_96 * self.b();
+        let s = 0.088_302_46 * self.r() + 0.281_718_84 * self.g() + 0.629_978_7 * self.b();
+
+        let l = cbrt(l);
+        let m = cbrt(m);
+        let s = cbrt(s);
+        Vector3 {
+            x: 0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+            y: 1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+            z: 0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+        }
+    }
+
+    pub fn from_oklab(lab: Vector3) -> Self {
+        let l = lab.x + 0.396_337_78 * lab.y + 0.215_803_76 * lab.z;
+        let m = lab.x - 0.105_561_346 * lab.y - 0.063_854_17 * lab.z;
+        let s = lab.x - 0.089_484_18 * lab.y - 1.291_485_5 * lab.z;
+
+        let l = l * l * l;
+        let m = m * m * m;
+        let s = s * s * s;
+        RgbColor::new(
+            4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+            -1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+            -0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+        )
+    }
+
+    /// Converts to OKLCh lightness, chroma and hue, with the hue in radians.
+    pub fn to_oklch(self) -> Vector3 {
+        let lab = self.to_oklab();
+        Vector3 {
+            x: lab.x,
+            y: (lab.y * lab.y + lab.z * lab.z).sqrt(),
+            z: lab.z.atan2(lab.y),
+        }
+    }
+
+    pub fn from_oklch(lch: Vector3) -> Self {
+        RgbColor::from_oklab(Vector3 {
+            x: lch.x,
+            y: lch.y * lch.z.cos(),
+            z: lch.y * lch.z.sin(),
+        })
+    }
+
+    /// Converts to hue, saturation and value, with the hue from 0 to 1.
+    pub fn to_hsv(self) -> Vector3 {
+        let max = self.r().max(self.g()).max(self.b());
+        let min = self.r().min(self.g()).min(self.b());
+        let range = max - min;
+
+        let hue = if range <= 0. {
+            0.
+        } else if max == self.r() {
+            (self.g() - self.b()) / range
+        } else if max == self.g() {
+            (self.b() - self.r()) / range + 2.
+        } else {
+            (self.r() - self.g()) / range + 4.
+        };
+        let hue = hue / 6.;
+        Vector3 {
+            x: hue - hue.floor(),
+            y: if max > 0. { range / max } else { 0. },
+            z: max,
+        }
+    }
+
+    pub fn from_hsv(hsv: Vector3) -> Self {
+        let hue = (hsv.x - hsv.x.floor()) * 6.;
+        let channel = |offset: f32| {
+            let k = offset + hue;
+            let k = k - (k / 6.).floor() * 6.;
+            let ramp = k.min(4. - k).max(0.).min(1.);
+            hsv.z - hsv.z * hsv.y * ramp
+        };
+        RgbColor::new(channel(5.), channel(3.), channel(1.))
+    }
 }
 
 impl From<[f32; 3]> for RgbColor {
@@ -92,6 +247,12 @@ impl RgbaColor {
         RgbaColor(self.0.lerp(b.0, t))
     }
 
+    /// Interpolates the colour in another colour space. Alpha is always interpolated linearly.
+    pub fn lerp_in(self, b: RgbaColor, t: f32, space: ColorSpace) -> Self {
+        let a = self.a() + (b.a() - self.a()) * t;
+        self.rgb().lerp_in(b.rgb(), t, space).with_a(a)
+    }
+
     pub fn premult(self) -> RgbColor {
         RgbColor::new(
             self.r() * self.a(),
@@ -124,3 +285,25 @@ impl Into<(f32, f32, f32, f32)> for RgbaColor {
         (self.r(), self.g(), self.b(), self.a())
     }
 }
+
+/// A cube root that works for negative numbers, which out-of-gamut colours can have.
+fn cbrt(val: f32) -> f32 {
+    val.abs().pow(1. / 3.).copysign(val)
+}
+
+/// Interpolates between two hues, going around the hue circle the short way. `full_turn` is the
+/// hue range. If one of the colours is grey, the other's hue is used for both so the hue doesn't
+/// sweep through unrelated colours.
+fn lerp_hue(a_hue: f32, a_chroma: f32, b_hue: f32, b_chroma: f32, t: f32, full_turn: f32) -> f32 {
+    let (a_hue, b_hue) = if a_chroma < ACHROMATIC_THRESHOLD {
+        (b_hue, b_hue)
+    } else if b_chroma < ACHROMATIC_THRESHOLD {
+        (a_hue, a_hue)
+    } else {
+        (a_hue, b_hue)
+    };
+
+    let hue_delta = b_hue - a_hue;
+    let hue_delta = hue_delta - (hue_delta / full_turn).round() * full_turn;
+    a_hue + hue_delta * t
+}
@@ -2,6 +2,7 @@
 mod macros;
 mod color;
 mod float;
+mod gradient;
 mod matrix4;
 mod quaternion;
 pub mod random;
@@ -10,8 +11,9 @@ mod vector2;
 mod vector3;
 mod vector4;
 
-pub use self::color::{RgbColor, RgbaColor};
+pub use self::color::{ColorSpace, RgbColor, RgbaColor};
 pub use self::float::Float;
+pub use self::gradient::{Gradient, GradientStop, MAX_GRADIENT_STOPS};
 pub use self::matrix4::Matrix4;
 pub use self::quaternion::Quaternion;
 pub use self::ray::Ray;
@@ -1,4 +1,5 @@
 use super::{Float, Matrix4, Vector3, Vector4};
+use core::f32::consts::PI;
 use core::ops;
 
 #[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
@@ -36,6 +37,13 @@ impl Quaternion {
         }
     }
 
+    /// A rotation without any roll that points the Z axis along a unit direction.
+    pub fn look_along(direction: Vector3) -> Self {
+        let yaw = direction.x.atan2(direction.z);
+        let pitch = -direction.y.max(-1.).min(1.).asin();
+        Quaternion::axis(Vector3::unit_y(), yaw) * Quaternion::axis(Vector3::unit_x(), pitch)
+    }
+
     pub fn slerp(self, target: Quaternion, amount: f32) -> Self {
         let lhs_vec: Vector4 = self.into();
         let rhs_vec: Vector4 = target.into();
@@ -59,6 +67,85 @@ impl Quaternion {
         (lhs_vec * theta.cos() + q2 * theta.sin()).into()
     }
 
+    pub fn dot(self, other: Quaternion) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    /// The inverse of a unit quaternion.
+    pub fn conjugate(self) -> Quaternion {
+        Quaternion {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+            w: self.w,
+        }
+    }
+
+    /// Negates the quaternion if needed so it's in the same hemisphere as `other`. Both represent
+    /// the same rotation, but interpolating between quaternions in the same hemisphere takes the
+    /// shortest arc.
+    pub fn align_to(self, other: Quaternion) -> Quaternion {
+        if self.dot(other) < 0. {
+            -self
+        } else {
+            self
+        }
+    }
+
+    /// The logarithm of a unit quaternion, which is the rotation axis scaled by half the angle.
+    pub fn log(self) -> Vector3 {
+        let axis = Vector3 {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+        };
+        let sin_half_angle = axis.length();
+        if sin_half_angle < 1e-6 {
+            return axis;
+        }
+        axis * (sin_half_angle.atan2(self.w) / sin_half_angle)
+    }
+
+    /// The inverse of `log`.
+    pub fn exp(log: Vector3) -> Quaternion {
+        let half_angle = log.length();
+        let axis = if half_angle < 1e-6 {
+            log
+        } else {
+            log * (half_angle.sin() / half_angle)
+        };
+        Quaternion {
+            x: axis.x,
+            y: axis.y,
+            z: axis.z,
+            w: half_angle.cos(),
+        }
+    }
+
+    /// Interpolates between `self` and `target` on a spline through the keyframes either side of
+    /// them, so the angular velocity doesn't jump at each keyframe like it does with `slerp`.
+    /// At the ends of a curve, the previous or next keyframe can be the same as the end keyframe.
+    pub fn squad(
+        self,
+        target: Quaternion,
+        previous: Quaternion,
+        next: Quaternion,
+        amount: f32,
+    ) -> Quaternion {
+        // Put every keyframe in the same hemisphere as the one before it, so each span takes the
+        // shortest arc
+        let previous = previous.align_to(self);
+        let target = target.align_to(self);
+        let next = next.align_to(target);
+
+        let start_control = squad_control(previous, self, target);
+        let end_control = squad_control(self, target, next);
+        self.slerp(target, amount).slerp(
+            start_control.slerp(end_control, amount),
+            2. * amount * (1. - amount),
+        )
+    }
+
     pub fn as_matrix(self) -> Matrix4 {
         let n = 2. / (self.x * self.x + self.y * self.y + self.z * self.z + self.w * self.w);
 
@@ -102,6 +189,34 @@ impl Quaternion {
         (pitch, roll, yaw)
     }
 
+    /// Like `as_euler`, but picks whichever of the equivalent sets of angles is closest to
+    /// `reference`. Angles can be outside of -180 to 180 degrees, and the pitch can go past 90
+    /// degrees, so values converted from a smoothly changing rotation also change smoothly.
+    pub fn as_euler_near(self, reference: (f32, f32, f32)) -> (f32, f32, f32) {
+        let (pitch, roll, yaw) = self.as_euler();
+        let unwrapped = (
+            unwrap_angle(pitch, reference.0),
+            unwrap_angle(roll, reference.1),
+            unwrap_angle(yaw, reference.2),
+        );
+        let flipped = (
+            unwrap_angle(PI - pitch, reference.0),
+            unwrap_angle(roll + PI, reference.1),
+            unwrap_angle(yaw + PI, reference.2),
+        );
+
+        let distance = |angles: (f32, f32, f32)| {
+            (angles.0 - reference.0).abs()
+                + (angles.1 - reference.1).abs()
+                + (angles.2 - reference.2).abs()
+        };
+        if distance(flipped) < distance(unwrapped) {
+            flipped
+        } else {
+            unwrapped
+        }
+    }
+
     pub fn as_vector(self) -> Vector3 {
         Vector3 {
             x: 0.,
@@ -123,7 +238,7 @@ impl Quaternion {
     }
 
     pub fn normalize(self) -> Quaternion {
-        let len = (self.x + self.y + self.z + self.w).sqrt();
+        let len = self.dot(self).sqrt();
         Quaternion {
             x: self.x / len,
             y: self.y / len,
@@ -133,6 +248,19 @@ impl Quaternion {
     }
 }
 
+/// Finds the control point for a keyframe in a SQUAD spline, from the keyframes either side of it.
+fn squad_control(previous: Quaternion, current: Quaternion, next: Quaternion) -> Quaternion {
+    let inverse = current.conjugate();
+    let next_log = (inverse * next).log();
+    let previous_log = (inverse * previous).log();
+    current * Quaternion::exp((next_log + previous_log) * -0.25)
+}
+
+/// Adds or removes whole turns from an angle to bring it as close as possible to `reference`.
+fn unwrap_angle(angle: f32, reference: f32) -> f32 {
+    angle - ((angle - reference) / (2. * PI)).round() * 2. * PI
+}
+
 impl Default for Quaternion {
     fn default() -> Self {
         Quaternion {
@@ -163,6 +291,19 @@ impl ops::Mul<Quaternion> for Quaternion {
     }
 }
 
+impl ops::Neg for Quaternion {
+    type Output = Quaternion;
+
+    fn neg(self) -> Quaternion {
+        Quaternion {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+            w: -self.w,
+        }
+    }
+}
+
 impl ops::MulAssign<Quaternion> for Vector3 {
     fn mul_assign(&mut self, rhs: Quaternion) {
         *self = *self * rhs;
@@ -204,3 +345,76 @@ impl Into<Vector4> for Quaternion {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vector3, b: Vector3) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn normalize_gives_unit_length() {
+        // The components sum to a negative number, which used to give NaN
+        let rotation = Quaternion {
+            x: -1.,
+            y: 2.,
+            z: -3.,
+            w: 0.5,
+        }
+        .normalize();
+        assert!((rotation.dot(rotation) - 1.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn as_matrix_ignores_scale() {
+        // Normalizing only rescales, and rotation matrices don't depend on the scale, so
+        // normalizing differently doesn't change the launch scene's pylons
+        let rotation = Quaternion::euler(0.3, -1.2, 2.);
+        let scaled = Quaternion {
+            x: rotation.x * 3.,
+            y: rotation.y * 3.,
+            z: rotation.z * 3.,
+            w: rotation.w * 3.,
+        };
+        let (a, b) = (rotation.as_matrix(), scaled.as_matrix());
+        for row in 0..4 {
+            assert!((a.row(row) - b.row(row)).length() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn rotation_to_rotates_onto_the_destination() {
+        let directions = [
+            Vector3 {
+                x: 1.,
+                y: 0.,
+                z: 0.,
+            },
+            Vector3 {
+                x: 0.4,
+                y: 13.,
+                z: -0.4,
+            },
+            Vector3 {
+                x: -0.2,
+                y: -1.,
+                z: 0.7,
+            },
+            Vector3 {
+                x: 0.,
+                y: -1.,
+                z: 0.,
+            },
+        ];
+        for &direction in &directions {
+            let rotation = Vector3::unit_y().get_rotation_to(direction, Vector3::unit_x());
+            assert!((rotation.dot(rotation) - 1.).abs() < 1e-5);
+            assert_close(
+                rotation.as_matrix().mul_norm(Vector3::unit_y()),
+                direction.unit(),
+            );
+        }
+    }
+}
@@ -24,3 +24,28 @@ pub fn noise(n: f32) -> f32 {
 
     min_noise + (max_noise - min_noise) * n.fract()
 }
+
+/// Hashes a seed and an integer position into a value between -1 and 1. This only uses integer
+/// operations, so unlike `pos_rand` it gives the same result on every machine.
+pub fn hash_rand(seed: u32, n: i32) -> f32 {
+    let mut x = (n as u32).wrapping_mul(0x9e37_79b1) ^ seed.wrapping_mul(0x85eb_ca77);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x2c1b_3c6d);
+    x ^= x >> 12;
+    x = x.wrapping_mul(0x297a_2d39);
+    x ^= x >> 15;
+
+    // Use the top 24 bits, since they can be represented exactly
+    (x >> 8) as f32 / (1 << 23) as f32 - 1.
+}
+
+/// Smoothly interpolated value noise between -1 and 1, where each seed gives a different curve.
+pub fn seeded_noise(seed: u32, n: f32) -> f32 {
+    let floor = n.floor();
+    let t = n - floor;
+    let t = t * t * (3. - 2. * t);
+    let min_noise = hash_rand(seed, floor as i32);
+    let max_noise = hash_rand(seed, floor as i32 + 1);
+
+    min_noise + (max_noise - min_noise) * t
+}
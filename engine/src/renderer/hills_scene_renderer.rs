@@ -28,8 +28,7 @@ use crate::renderer::godray_renderer::GodrayRenderer;
 use crate::gbuffer::GBuffer;
 use crate::frame_context::FrameContext;
 use crate::creation_context::CreationContext;
-use crate::animation::clip::ClipPropertyValue;
-use crate::animation::property::prop;
+use crate::generator::HillsSceneProperties;
 
 #[derive(Clone, Copy)]
 #[repr(C)]
@@ -67,26 +66,26 @@ impl HillsScene {
         clouds: &mut CloudsRenderer,
         shadow_map: &mut ShadowMapRenderer,
         godray: &mut GodrayRenderer,
-        properties: &[&[ClipPropertyValue]],
+        properties: &HillsSceneProperties,
     ) {
-        let terrain_center: Vector3 = prop(properties, 0, 0);
-        let terrain_size: Vector3 = prop(properties, 0, 1);
+        let terrain_center = properties.terrain_center;
+        let terrain_size = properties.terrain_size;
 
-        let light_map_pos: Vector3 = prop(properties, 1, 0);
-        let light_map_x_clip: Vector2 = prop(properties, 1, 1);
-        let light_map_y_clip: Vector2 = prop(properties, 1, 2);
-        let light_map_z_clip: Vector2 = prop(properties, 1, 3);
+        let light_map_pos = properties.light_map_pos;
+        let light_map_x_clip = properties.light_map_x_clip;
+        let light_map_y_clip = properties.light_map_y_clip;
+        let light_map_z_clip = properties.light_map_z_clip;
 
-        let fog_color = prop::<RgbaColor>(properties, 2, 0).premult();
-        let fog_exp: f32 = prop(properties, 2, 1);
-        let density: f32 = prop(properties, 2, 2);
+        let fog_color = properties.fog_color.premult();
+        let fog_exp = properties.fog_exp;
+        let density = properties.fog_density;
 
-        let cloud_y: f32 = prop(properties, 3, 0);
-        let cloud_height: f32 = prop(properties, 3, 1);
-        let map_offset: Vector3 = prop(properties, 3, 2);
+        let cloud_y = properties.cloud_y;
+        let cloud_height = properties.cloud_height;
+        let map_offset = properties.map_offset;
 
-        let sky_color = prop::<RgbaColor>(properties, 3, 3).premult();
-        let scatter_color = prop::<RgbaColor>(properties, 3, 4).premult();
+        let sky_color = properties.sky_color.premult();
+        let scatter_color = properties.scatter_color.premult();
 
         let terrain_size = Vector3 {
             x: terrain_size.x.max(0.01),
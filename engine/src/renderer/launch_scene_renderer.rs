@@ -1,10 +1,9 @@
-use crate::animation::clip::ClipPropertyValue;
-use crate::animation::property::prop;
 use crate::blend_state::{BlendRenderTargetConfig, BlendState};
 use crate::buffer::{Buffer, InitialData};
 use crate::creation_context::CreationContext;
 use crate::frame_context::FrameContext;
 use crate::gbuffer::GBuffer;
+use crate::generator::LaunchSceneProperties;
 use crate::math::{Matrix4, Quaternion, RgbColor, RgbaColor, Vector2, Vector3, Vector4};
 use crate::mesh::{primitives, Mesh, VertexInserter};
 use crate::object::MeshObject;
@@ -616,36 +615,36 @@ impl LaunchScene {
         clouds: &mut CloudsRenderer,
         blit: &mut BlitRenderer,
         cloud_state: &mut CloudState,
-        properties: &[&[ClipPropertyValue]],
+        properties: &LaunchSceneProperties,
     ) {
-        let light_map_pos: Vector3 = prop(properties, 0, 0);
-        let light_map_x_range: Vector2 = prop(properties, 0, 1);
-        let light_map_y_range: Vector2 = prop(properties, 0, 2);
-        let light_map_z_range: Vector2 = prop(properties, 0, 3);
-        let rays_density: f32 = prop(properties, 1, 0);
-
-        let fluid_box_pos: Vector3 = prop(properties, 2, 0);
-        let fluid_box_size: Vector3 = prop(properties, 2, 1);
-        let march_step_length: f32 = prop(properties, 2, 2);
-
-        let fluid_shadow_mode: f32 = prop(properties, 3, 0);
-        let fluid_shadow_pos: Vector3 = prop(properties, 3, 1);
-        let fluid_shadow_color = prop::<RgbaColor>(properties, 3, 2).premult();
-        let fluid_shadow_directional_size: Vector3 = prop(properties, 3, 3);
-        let fluid_shadow_point_radius: f32 = prop(properties, 3, 4);
-        let fluid_shadow_point_max_radius: f32 = prop(properties, 3, 5);
-
-        let render_sdf = prop::<f32>(properties, 4, 1) != 0.;
-
-        let render_clouds = prop::<f32>(properties, 5, 0) != 0.;
-        let clouds_y: f32 = prop(properties, 5, 1);
-        let clouds_height: f32 = prop(properties, 5, 2);
-        let clouds_map_offset: Vector3 = prop(properties, 5, 3);
-        let clouds_color = prop::<RgbaColor>(properties, 5, 4).premult();
-        let clouds_scatter_color = prop::<RgbaColor>(properties, 5, 5).premult();
-        let clouds_light_direction: Quaternion = prop(properties, 5, 6);
-        let clouds_opacity: f32 = prop(properties, 5, 7);
-        let clouds_are_live = prop::<f32>(properties, 5, 8) != 0.;
+        let light_map_pos = properties.light_map_pos;
+        let light_map_x_range = properties.light_map_x_range;
+        let light_map_y_range = properties.light_map_y_range;
+        let light_map_z_range = properties.light_map_z_range;
+        let rays_density = properties.rays_density;
+
+        let fluid_box_pos = properties.fluid_box_pos;
+        let fluid_box_size = properties.fluid_box_size;
+        let march_step_length = properties.march_step_length;
+
+        let fluid_shadow_mode = properties.fluid_shadow_mode;
+        let fluid_shadow_pos = properties.fluid_shadow_pos;
+        let fluid_shadow_color = properties.fluid_shadow_color.premult();
+        let fluid_shadow_directional_size = properties.fluid_shadow_directional_size;
+        let fluid_shadow_point_radius = properties.fluid_shadow_point_radius;
+        let fluid_shadow_point_max_radius = properties.fluid_shadow_point_max_radius;
+
+        let render_sdf = properties.rocket_enabled;
+
+        let render_clouds = properties.clouds_enabled;
+        let clouds_y = properties.clouds_y;
+        let clouds_height = properties.clouds_height;
+        let clouds_map_offset = properties.clouds_map_offset;
+        let clouds_color = properties.clouds_color.premult();
+        let clouds_scatter_color = properties.clouds_scatter_color.premult();
+        let clouds_light_direction = properties.clouds_light_direction;
+        let clouds_opacity = properties.clouds_opacity;
+        let clouds_are_live = properties.clouds_live;
 
         if (!cloud_state.has_rendered_clouds || clouds_are_live) && render_clouds {
             cloud_state.has_rendered_clouds = true;
@@ -735,8 +734,8 @@ impl LaunchScene {
                 use_point_light: (fluid_shadow_mode > 0.5) as u32,
                 light_color: fluid_shadow_color,
                 march_step_length,
-                density_multiplier: prop(properties, 2, 3),
-                rocket_height: prop(properties, 4, 0),
+                density_multiplier: properties.density_multiplier,
+                rocket_height: properties.rocket_height,
             },
         );
 
@@ -877,9 +876,9 @@ impl LaunchScene {
                 context,
                 world_to_shadow_transform,
                 rays_density,
-                prop::<f32>(properties, 1, 1) as u32,
-                prop(properties, 1, 2),
-                prop(properties, 1, 3),
+                properties.rays_steps.max(0) as u32,
+                properties.rays_step_length,
+                properties.rays_start_dist,
                 io.world_pos_map_write(),
                 shadow_map.shadow_map(),
                 Some((
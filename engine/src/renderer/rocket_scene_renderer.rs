@@ -1,10 +1,9 @@
-use crate::animation::clip::ClipPropertyValue;
-use crate::animation::property::prop;
 use crate::blend_state::{BlendRenderTargetConfig, BlendState};
 use crate::buffer::{Buffer, InitialData};
 use crate::creation_context::CreationContext;
 use crate::frame_context::FrameContext;
 use crate::gbuffer::GBuffer;
+use crate::generator::RocketSceneProperties;
 use crate::math::random::rand_float;
 use crate::math::{Matrix4, Quaternion, RgbColor, RgbaColor, Vector3, Vector4};
 use crate::renderer::common::PostRenderer;
@@ -632,32 +631,32 @@ impl RocketScene {
         &mut self,
         io: &mut GBuffer,
         context: &mut FrameContext,
-        properties: &[&[ClipPropertyValue]],
+        properties: &RocketSceneProperties,
     ) {
         // Read properties
-        let density_vol_pos: Vector3 = prop(properties, 0, 0);
-        let density_vol_size: Vector3 = prop(properties, 0, 1);
-        let light_map_blur: f32 = prop(properties, 0, 2);
+        let density_vol_pos = properties.density_vol_pos;
+        let density_vol_size = properties.density_vol_size;
+        let light_map_blur = properties.light_map_blur;
 
-        let directional_light_dir: Quaternion = prop(properties, 1, 0);
-        let directional_light_color = prop::<RgbaColor>(properties, 1, 1).premult();
-        let directional_light_vol_pos: Vector3 = prop(properties, 1, 2);
-        let directional_light_vol_size: Vector3 = prop(properties, 1, 3);
+        let directional_light_dir = properties.directional_light_dir;
+        let directional_light_color = properties.directional_light_color.premult();
+        let directional_light_vol_pos = properties.directional_light_vol_pos;
+        let directional_light_vol_size = properties.directional_light_vol_size;
 
-        let point_light_pos: Vector3 = prop(properties, 2, 0);
-        let point_light_color = prop::<RgbaColor>(properties, 2, 1).premult();
-        let point_light_radius: f32 = prop(properties, 2, 2);
-        let point_light_max_radius: f32 = prop(properties, 2, 3);
+        let point_light_pos = properties.point_light_pos;
+        let point_light_color = properties.point_light_color.premult();
+        let point_light_radius = properties.point_light_radius;
+        let point_light_max_radius = properties.point_light_max_radius;
 
         // adjust the point light color based on randomness
         self.last_flicker_rand = (rand_float(0., 1.) + self.last_flicker_rand) / 2.;
         let point_light_flicker = 1.; //0.8 + self.last_flicker_rand * 0.2;
         let point_light_color = point_light_color.with_a(point_light_flicker).premult();
 
-        let ambient_light_color = prop::<RgbaColor>(properties, 3, 0).premult();
+        let ambient_light_color = properties.ambient_light_color.premult();
 
-        let rocket_base_pos: Vector3 = prop(properties, 4, 0);
-        let rocket_enabled = prop::<f32>(properties, 4, 1) != 0.;
+        let rocket_base_pos = properties.rocket_base_pos;
+        let rocket_enabled = properties.rocket_enabled;
 
         let density_vol_scale = Vector3 {
             x: 1. / density_vol_size.x.max(0.01),
@@ -1,19 +1,51 @@
 use super::Stream;
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::cell::RefCell;
 use core::{intrinsics, mem};
 use engine::animation::animation_clip::{
-    AnimatedProperty, AnimatedPropertyField, AnimatedPropertyTarget, AnimationClip,
-    CurveInterpolation, CurveSegment,
+    AnimatedProperty, AnimatedPropertyField, AnimatedPropertyTarget, AnimationClip, BlendMode,
+    CurveInterpolation, CurveSegment, Extrapolation,
 };
 use engine::animation::clip::ClipReference;
+use engine::animation::constraint::{Constraint, ConstraintTarget};
 use engine::animation::cubic_bezier::CubicBezier;
+use engine::animation::driver::{DriverMapping, PropertyDriver};
+use engine::animation::easing::Easing;
+use engine::animation::expression::Expression;
+use engine::animation::modifier::Modifier;
 use engine::animation::property::{PropertyType, PropertyValue};
+use engine::animation::spline_path::{SplineKind, SplinePath};
+use engine::animation::tempo_map::{TempoChange, TempoMap};
 use engine::animation::timeline::{
     Clip, ClipSource, PropertyDefault, PropertyGroup, Timeline, Track,
 };
+use engine::binding::PropertyBinding;
 use engine::creation_context::CreationContext;
 use engine::generator::GENERATOR_SCHEMAS;
+use engine::math::{ColorSpace, Gradient, GradientStop, RgbaColor, MAX_GRADIENT_STOPS};
+
+struct TempoStream<'bytes> {
+    len: usize,
+    bars: Stream<'bytes>,
+    beats_per_minutes: Stream<'bytes>,
+    beats_per_bars: Stream<'bytes>,
+}
+
+impl<'bytes> TempoStream<'bytes> {
+    fn new(mut stream: Stream<'bytes>, len: usize) -> Self {
+        let bars = stream.substream(len * mem::size_of::<u32>());
+        let beats_per_minutes = stream.substream(len * mem::size_of::<f32>());
+        let beats_per_bars = stream;
+
+        TempoStream {
+            len,
+            bars,
+            beats_per_minutes,
+            beats_per_bars,
+        }
+    }
+}
 
 struct ClipStream<'bytes> {
     len: usize,
@@ -41,6 +73,9 @@ struct AnimationClipStream<'bytes> {
     len: usize,
     targets: Stream<'bytes>,
     schemas: Stream<'bytes>,
+    has_time_properties: Stream<'bytes>,
+    blend_modes: Stream<'bytes>,
+    has_weight_properties: Stream<'bytes>,
     num_props: Stream<'bytes>,
 }
 
@@ -48,12 +83,18 @@ impl<'bytes> AnimationClipStream<'bytes> {
     fn new(mut stream: Stream<'bytes>, len: usize) -> Self {
         let targets = stream.substream(len * mem::size_of::<u8>());
         let schemas = stream.substream(len * mem::size_of::<u8>());
+        let has_time_properties = stream.substream(len * mem::size_of::<u8>());
+        let blend_modes = stream.substream(len * mem::size_of::<u8>());
+        let has_weight_properties = stream.substream(len * mem::size_of::<u8>());
         let num_props = stream;
 
         AnimationClipStream {
             len,
             targets,
             schemas,
+            has_time_properties,
+            blend_modes,
+            has_weight_properties,
             num_props,
         }
     }
@@ -63,6 +104,7 @@ struct AnimationPropertyStream<'bytes> {
     len: usize,
     target_groups: Stream<'bytes>,
     target_props: Stream<'bytes>,
+    num_modifiers: Stream<'bytes>,
     num_fields: Stream<'bytes>,
 }
 
@@ -70,12 +112,14 @@ impl<'bytes> AnimationPropertyStream<'bytes> {
     fn new(mut stream: Stream<'bytes>, len: usize) -> Self {
         let target_groups = stream.substream(len * mem::size_of::<u8>());
         let target_props = stream.substream(len * mem::size_of::<u8>());
+        let num_modifiers = stream.substream(len * mem::size_of::<u8>());
         let num_fields = stream;
 
         AnimationPropertyStream {
             len,
             target_groups,
             target_props,
+            num_modifiers,
             num_fields,
         }
     }
@@ -84,17 +128,20 @@ impl<'bytes> AnimationPropertyStream<'bytes> {
 struct AnimationFieldStream<'bytes> {
     len: usize,
     local_offsets: Stream<'bytes>,
+    extrapolations: Stream<'bytes>,
     num_segments: Stream<'bytes>,
 }
 
 impl<'bytes> AnimationFieldStream<'bytes> {
     fn new(mut stream: Stream<'bytes>, len: usize) -> Self {
         let local_offsets = stream.substream(len * mem::size_of::<i32>());
+        let extrapolations = stream.substream(len * mem::size_of::<u8>());
         let num_segments = stream;
 
         AnimationFieldStream {
             len,
             local_offsets,
+            extrapolations,
             num_segments,
         }
     }
@@ -119,6 +166,128 @@ impl<'bytes> SegmentStream<'bytes> {
     }
 }
 
+struct ModifierStream<'bytes> {
+    len: usize,
+    types: Stream<'bytes>,
+    params: Stream<'bytes>,
+}
+
+impl<'bytes> ModifierStream<'bytes> {
+    fn new(mut stream: Stream<'bytes>, len: usize) -> Self {
+        let types = stream.substream(len * mem::size_of::<u8>());
+        let params = stream;
+
+        ModifierStream { len, types, params }
+    }
+}
+
+struct ExpressionStream<'bytes> {
+    len: usize,
+    lengths: Stream<'bytes>,
+    bytecode: Stream<'bytes>,
+}
+
+impl<'bytes> ExpressionStream<'bytes> {
+    fn new(mut stream: Stream<'bytes>, len: usize) -> Self {
+        let lengths = stream.substream(len * mem::size_of::<u8>());
+        let bytecode = stream;
+
+        ExpressionStream {
+            len,
+            lengths,
+            bytecode,
+        }
+    }
+}
+
+struct PathStream<'bytes> {
+    len: usize,
+    kinds: Stream<'bytes>,
+    num_points: Stream<'bytes>,
+    orient_groups: Stream<'bytes>,
+    orient_props: Stream<'bytes>,
+    points: Stream<'bytes>,
+}
+
+impl<'bytes> PathStream<'bytes> {
+    fn new(mut stream: Stream<'bytes>, len: usize) -> Self {
+        let kinds = stream.substream(len * mem::size_of::<u8>());
+        let num_points = stream.substream(len * mem::size_of::<u8>());
+        let orient_groups = stream.substream(len * mem::size_of::<u8>());
+        let orient_props = stream.substream(len * mem::size_of::<u8>());
+        let points = stream;
+
+        PathStream {
+            len,
+            kinds,
+            num_points,
+            orient_groups,
+            orient_props,
+            points,
+        }
+    }
+}
+
+struct DriverStream<'bytes> {
+    len: usize,
+    target_clips: Stream<'bytes>,
+    target_groups: Stream<'bytes>,
+    target_props: Stream<'bytes>,
+    source_clips: Stream<'bytes>,
+    source_groups: Stream<'bytes>,
+    source_props: Stream<'bytes>,
+    mappings: Stream<'bytes>,
+}
+
+impl<'bytes> DriverStream<'bytes> {
+    fn new(mut stream: Stream<'bytes>, len: usize) -> Self {
+        let target_clips = stream.substream(len * mem::size_of::<u8>());
+        let target_groups = stream.substream(len * mem::size_of::<u8>());
+        let target_props = stream.substream(len * mem::size_of::<u8>());
+        let source_clips = stream.substream(len * mem::size_of::<u8>());
+        let source_groups = stream.substream(len * mem::size_of::<u8>());
+        let source_props = stream.substream(len * mem::size_of::<u8>());
+        let mappings = stream;
+
+        DriverStream {
+            len,
+            target_clips,
+            target_groups,
+            target_props,
+            source_clips,
+            source_groups,
+            source_props,
+            mappings,
+        }
+    }
+}
+
+struct ConstraintStream<'bytes> {
+    kinds: Stream<'bytes>,
+    target_clips: Stream<'bytes>,
+    target_groups: Stream<'bytes>,
+    target_props: Stream<'bytes>,
+    data: Stream<'bytes>,
+}
+
+impl<'bytes> ConstraintStream<'bytes> {
+    fn new(mut stream: Stream<'bytes>, len: usize) -> Self {
+        let kinds = stream.substream(len * mem::size_of::<u8>());
+        let target_clips = stream.substream(len * mem::size_of::<u8>());
+        let target_groups = stream.substream(len * mem::size_of::<u8>());
+        let target_props = stream.substream(len * mem::size_of::<u8>());
+        let data = stream;
+
+        ConstraintStream {
+            kinds,
+            target_clips,
+            target_groups,
+            target_props,
+            data,
+        }
+    }
+}
+
 struct PropValStream<'bytes> {
     streams: [Stream<'bytes>; 4],
 }
@@ -136,6 +305,20 @@ fn deserialize_prop_val(
         };
 
         PropertyValue::ClipReference(clip_ref)
+    } else if val_type == PropertyType::Gradient {
+        let stop_count = prop_val_stream.streams[0].read_u8() as usize;
+        let mut stops = [GradientStop::default(); MAX_GRADIENT_STOPS];
+        for stop in &mut stops[..stop_count] {
+            stop.position = prop_val_stream.streams[0].read_f32();
+            stop.color = RgbaColor::new(
+                prop_val_stream.streams[0].read_f32(),
+                prop_val_stream.streams[1].read_f32(),
+                prop_val_stream.streams[2].read_f32(),
+                prop_val_stream.streams[3].read_f32(),
+            );
+        }
+
+        PropertyValue::Gradient(Gradient::new(&stops[..stop_count]))
     } else {
         PropertyValue::from_fields(
             val_type,
@@ -149,6 +332,91 @@ fn deserialize_prop_val(
     }
 }
 
+fn deserialize_interpolation(tag: u8, stream: &mut Stream) -> CurveInterpolation {
+    match tag & 0x1F {
+        0u8 => CurveInterpolation::Linear,
+        1u8 => {
+            let c1 = stream.read_vector2();
+            let c2 = stream.read_vector2();
+            CurveInterpolation::CubicBezier(CubicBezier::new(c1, c2))
+        }
+        2u8 => CurveInterpolation::Step,
+        3u8 => CurveInterpolation::StepNext,
+        tag => match Easing::from_index((tag - 4) as usize) {
+            Some(easing) => CurveInterpolation::Ease(easing),
+            // Easings this build doesn't know about are played linearly
+            None => CurveInterpolation::Linear,
+        },
+    }
+}
+
+fn deserialize_driver_mapping(driver_stream: &mut DriverStream) -> DriverMapping {
+    match driver_stream.mappings.read_u8() {
+        0u8 => DriverMapping::Linear {
+            scale: driver_stream.mappings.read_f32(),
+            offset: driver_stream.mappings.read_f32(),
+        },
+        1u8 => {
+            let input_min = driver_stream.mappings.read_f32();
+            let input_max = driver_stream.mappings.read_f32();
+            let output_min = driver_stream.mappings.read_f32();
+            let output_max = driver_stream.mappings.read_f32();
+            let curve_tag = driver_stream.mappings.read_u8();
+            DriverMapping::Remap {
+                input_min,
+                input_max,
+                output_min,
+                output_max,
+                curve: deserialize_interpolation(curve_tag, &mut driver_stream.mappings),
+            }
+        }
+        _ => unsafe { intrinsics::unreachable() },
+    }
+}
+
+fn deserialize_constraint(constraint_stream: &mut ConstraintStream) -> Constraint {
+    let kind = constraint_stream.kinds.read_u8();
+    let target = ConstraintTarget {
+        clip: ClipReference::new(constraint_stream.target_clips.read_u8() as u32),
+        property: PropertyBinding::new(
+            constraint_stream.target_groups.read_u8() as usize,
+            constraint_stream.target_props.read_u8() as usize,
+        ),
+    };
+
+    match kind {
+        0u8 => Constraint::LookAt {
+            eye: PropertyBinding::new(
+                constraint_stream.data.read_u8() as usize,
+                constraint_stream.data.read_u8() as usize,
+            ),
+            target,
+        },
+        1u8 => Constraint::Follow {
+            target,
+            offset: constraint_stream.data.read_vector3(),
+        },
+        2u8 => Constraint::DampedFollow {
+            target,
+            offset: constraint_stream.data.read_vector3(),
+            half_life: constraint_stream.data.read_f32(),
+            cache: RefCell::new(None),
+        },
+        _ => unsafe { intrinsics::unreachable() },
+    }
+}
+
+fn deserialize_extrapolation(tag: u8) -> Extrapolation {
+    match tag {
+        0u8 => Extrapolation::Constant,
+        1u8 => Extrapolation::Linear,
+        2u8 => Extrapolation::Cycle,
+        3u8 => Extrapolation::CycleWithOffset,
+        4u8 => Extrapolation::PingPong,
+        _ => unsafe { intrinsics::unreachable() },
+    }
+}
+
 fn deserialize_animation_field(
     val_type: PropertyType,
     prop_val_stream: &mut PropValStream,
@@ -156,6 +424,7 @@ fn deserialize_animation_field(
     segment_stream: &mut SegmentStream,
 ) -> AnimatedPropertyField {
     let local_offset_frames = field_stream.local_offsets.read_i32();
+    let extrapolations = field_stream.extrapolations.read_u8();
     let start_value = deserialize_prop_val(val_type, prop_val_stream);
     let num_segments = field_stream.num_segments.read_u8();
 
@@ -164,27 +433,57 @@ fn deserialize_animation_field(
     for _ in 0..num_segments {
         let duration_frames = segment_stream.durations.read_u32();
         let end_value = deserialize_prop_val(val_type, prop_val_stream);
-        let interpolation = match segment_stream.interpolations.read_u8() {
-            0u8 => CurveInterpolation::Linear,
-            1u8 => {
-                let c1 = segment_stream.interpolations.read_vector2();
-                let c2 = segment_stream.interpolations.read_vector2();
-                CurveInterpolation::CubicBezier(CubicBezier::new(c1, c2))
-            }
-            _ => unsafe { intrinsics::unreachable() },
-        };
+        let tag = segment_stream.interpolations.read_u8();
+        let interpolation = deserialize_interpolation(tag, &mut segment_stream.interpolations);
 
         segments.push(CurveSegment {
             duration_frames: duration_frames * 2,
+            #[cfg(debug_assertions)]
+            end_anchor: None,
             end_value,
             interpolation,
+            rotation_spline: tag & 0x80 != 0,
+            color_space: ColorSpace::from_index((tag >> 5 & 0x3) as usize),
         });
     }
 
     AnimatedPropertyField {
         local_offset_frames: local_offset_frames * 2,
+        #[cfg(debug_assertions)]
+        start_anchor: None,
         start_value,
         segments,
+        pre_extrapolation: deserialize_extrapolation(extrapolations & 0xF),
+        post_extrapolation: deserialize_extrapolation(extrapolations >> 4),
+    }
+}
+
+fn deserialize_modifier(modifier_stream: &mut ModifierStream) -> Modifier {
+    // Frequencies are per frame, and we run at double the tool's framerate
+    match modifier_stream.types.read_u8() {
+        0u8 => Modifier::Noise {
+            frequency: modifier_stream.params.read_f32() / 2.,
+            amplitude: modifier_stream.params.read_f32(),
+            seed: modifier_stream.params.read_u32(),
+            octaves: modifier_stream.params.read_u8() as u32,
+        },
+        1u8 => Modifier::Sine {
+            frequency: modifier_stream.params.read_f32() / 2.,
+            amplitude: modifier_stream.params.read_f32(),
+            phase: modifier_stream.params.read_f32(),
+        },
+        2u8 => Modifier::Quantize {
+            step: modifier_stream.params.read_f32(),
+        },
+        3u8 => Modifier::Clamp {
+            min: modifier_stream.params.read_f32(),
+            max: modifier_stream.params.read_f32(),
+        },
+        4u8 => Modifier::Spring {
+            frequency: modifier_stream.params.read_f32() / 2.,
+            damping: modifier_stream.params.read_f32(),
+        },
+        _ => unsafe { intrinsics::unreachable() },
     }
 }
 
@@ -196,6 +495,9 @@ pub fn deserialize_timeline(
     let project_duration = stream.read_u32();
 
     // Extract individual streams from the master one
+    let tempo_change_count = stream.read_u8();
+    let mut tempo_stream = TempoStream::new(stream.read_substream(), tempo_change_count as usize);
+
     let clip_count = stream.read_u8();
     let mut clip_stream = ClipStream::new(stream.read_substream(), clip_count as usize);
 
@@ -214,6 +516,23 @@ pub fn deserialize_timeline(
     let segment_count = stream.read_u8();
     let mut segment_stream = SegmentStream::new(stream.read_substream(), segment_count as usize);
 
+    let modifier_count = stream.read_u8();
+    let mut modifier_stream = ModifierStream::new(stream.read_substream(), modifier_count as usize);
+
+    let expression_count = stream.read_u8();
+    let mut expression_stream =
+        ExpressionStream::new(stream.read_substream(), expression_count as usize);
+
+    let path_count = stream.read_u8();
+    let mut path_stream = PathStream::new(stream.read_substream(), path_count as usize);
+
+    let driver_count = stream.read_u8();
+    let mut driver_stream = DriverStream::new(stream.read_substream(), driver_count as usize);
+
+    let constraint_count = stream.read_u8();
+    let mut constraint_stream =
+        ConstraintStream::new(stream.read_substream(), constraint_count as usize);
+
     let x_val_stream = stream.read_substream();
     let y_val_stream = stream.read_substream();
     let z_val_stream = stream.read_substream();
@@ -236,6 +555,32 @@ pub fn deserialize_timeline(
             let target_clip_id = animation_clip_stream.targets.read_u8();
             let target_schema =
                 &GENERATOR_SCHEMAS[animation_clip_stream.schemas.read_u8() as usize];
+            let time_property = if animation_clip_stream.has_time_properties.read_u8() != 0 {
+                Some(deserialize_animation_field(
+                    PropertyType::Float,
+                    &mut prop_val_stream,
+                    &mut animation_field_stream,
+                    &mut segment_stream,
+                ))
+            } else {
+                None
+            };
+            let blend_mode = match animation_clip_stream.blend_modes.read_u8() {
+                0u8 => BlendMode::Replace,
+                1u8 => BlendMode::Additive,
+                2u8 => BlendMode::Multiply,
+                _ => unsafe { intrinsics::unreachable() },
+            };
+            let weight_property = if animation_clip_stream.has_weight_properties.read_u8() != 0 {
+                Some(deserialize_animation_field(
+                    PropertyType::Float,
+                    &mut prop_val_stream,
+                    &mut animation_field_stream,
+                    &mut segment_stream,
+                ))
+            } else {
+                None
+            };
             let num_props = animation_clip_stream.num_props.read_u8();
 
             let mut animated_properties = Vec::new();
@@ -246,9 +591,63 @@ pub fn deserialize_timeline(
                 let target_type = target_schema.groups[target_group as usize].properties
                     [target_prop as usize]
                     .value_type;
+                let num_modifiers = animation_prop_stream.num_modifiers.read_u8();
+                let mut modifiers = Vec::new();
+                modifiers.reserve(num_modifiers as usize);
+                for _ in 0..num_modifiers {
+                    modifiers.push(deserialize_modifier(&mut modifier_stream));
+                }
                 let num_fields = animation_prop_stream.num_fields.read_u8();
 
-                let target = if num_fields == 0 {
+                let target = if num_fields == !0u8 {
+                    let bytecode_len = expression_stream.lengths.read_u8() as usize;
+                    let bytecode = expression_stream.bytecode.substream(bytecode_len);
+                    AnimatedPropertyTarget::Expression(Expression {
+                        #[cfg(debug_assertions)]
+                        source: String::new(),
+                        bytecode: bytecode.as_slice().to_vec(),
+                    })
+                } else if num_fields == !1u8 {
+                    let kind = match path_stream.kinds.read_u8() {
+                        0u8 => SplineKind::CatmullRom,
+                        1u8 => SplineKind::Centripetal,
+                        _ => unsafe { intrinsics::unreachable() },
+                    };
+                    let num_points = path_stream.num_points.read_u8();
+                    let orient_group = path_stream.orient_groups.read_u8();
+                    let orient_prop = path_stream.orient_props.read_u8();
+                    let orient_target = if orient_group == !0u8 {
+                        None
+                    } else {
+                        Some(PropertyBinding::new(
+                            orient_group as usize,
+                            orient_prop as usize,
+                        ))
+                    };
+
+                    let mut points = Vec::new();
+                    points.reserve(num_points as usize);
+                    for _ in 0..num_points {
+                        points.push(path_stream.points.read_vector3());
+                    }
+
+                    let progress = deserialize_animation_field(
+                        PropertyType::Float,
+                        &mut prop_val_stream,
+                        &mut animation_field_stream,
+                        &mut segment_stream,
+                    );
+                    AnimatedPropertyTarget::Path(SplinePath::new(
+                        kind,
+                        points,
+                        progress,
+                        orient_target,
+                    ))
+                } else if num_fields == !2u8 {
+                    AnimatedPropertyTarget::Constraint(deserialize_constraint(
+                        &mut constraint_stream,
+                    ))
+                } else if num_fields == 0 {
                     let field = deserialize_animation_field(
                         target_type,
                         &mut prop_val_stream,
@@ -275,13 +674,20 @@ pub fn deserialize_timeline(
                     group_index: target_group as usize,
                     property_index: target_prop as usize,
                     target,
+                    modifiers,
                     is_collapsed: false,
+                    spring_cache: RefCell::new(None),
                 });
             }
 
             let clip_source = ClipSource::Animation(AnimationClip {
                 target_clip: ClipReference::new(target_clip_id as u32),
+                time_property,
+                blend_mode,
+                weight_property,
                 properties: animated_properties,
+                is_time_collapsed: true,
+                is_weight_collapsed: true,
             });
             (target_schema, clip_source, Vec::new())
         } else {
@@ -299,6 +705,7 @@ pub fn deserialize_timeline(
                                 &mut prop_val_stream,
                             ),
                             is_override: false,
+                            driver: None,
                         })
                         .collect();
                     PropertyGroup {
@@ -324,6 +731,8 @@ pub fn deserialize_timeline(
 
             offset_frames: clip_start_time * 2,
             duration_frames: clip_duration * 2,
+            #[cfg(debug_assertions)]
+            anchor: None,
 
             property_groups: prop_groups,
             is_selected: false,
@@ -331,5 +740,44 @@ pub fn deserialize_timeline(
         tracks.push(Track { clips: vec![clip] });
     }
 
-    (project_duration, Timeline { tracks })
+    // Drivers are attached once every clip exists. Each clip is on its own track, so the clip ID
+    // is also the track index.
+    for _ in 0..driver_stream.len {
+        let target_clip = driver_stream.target_clips.read_u8() as usize;
+        let target_group = driver_stream.target_groups.read_u8() as usize;
+        let target_prop = driver_stream.target_props.read_u8() as usize;
+        let source_clip = ClipReference::new(driver_stream.source_clips.read_u8() as u32);
+        let source = PropertyBinding::new(
+            driver_stream.source_groups.read_u8() as usize,
+            driver_stream.source_props.read_u8() as usize,
+        );
+        let mapping = deserialize_driver_mapping(&mut driver_stream);
+
+        tracks[target_clip].clips[0].property_groups[target_group].defaults[target_prop].driver =
+            Some(PropertyDriver {
+                source_clip,
+                source,
+                mapping,
+            });
+    }
+
+    let mut tempo_changes = Vec::new();
+    tempo_changes.reserve(tempo_stream.len);
+    for _ in 0..tempo_stream.len {
+        tempo_changes.push(TempoChange {
+            bar: tempo_stream.bars.read_u32(),
+            beats_per_minute: tempo_stream.beats_per_minutes.read_f32(),
+            beats_per_bar: tempo_stream.beats_per_bars.read_u8() as u32,
+        });
+    }
+
+    (
+        project_duration,
+        Timeline {
+            tracks,
+            tempo_map: TempoMap {
+                changes: tempo_changes,
+            },
+        },
+    )
 }
@@ -34,7 +34,9 @@ use core::{mem, ptr};
 use engine::animation::clip::ActiveClipMap;
 use engine::animation::clip::{ActiveClip, ClipPropertyValue, ClipReference};
 use engine::animation::coallesce::coallesce_animations;
-use engine::animation::timeline::{ClipSource, Timeline};
+use engine::animation::constraint::apply_constraints;
+use engine::animation::driver::resolve_drivers;
+use engine::animation::timeline::{ClipSource, TimeBase, Timeline};
 use engine::creation_context::CreationContext;
 use engine::frame_context::{CommonData, FrameContext, FrameDataBuffer};
 use engine::gbuffer::GBuffer;
@@ -70,7 +72,7 @@ use winapi::um::winuser::{
     WS_SYSMENU, WS_VISIBLE,
 };
 
-// note: should match framerate in tool
+// note: should match framerate and tempo in tool
 const FRAMERATE: f64 = 60.;
 
 extern "C" {
@@ -127,7 +129,13 @@ fn render_frame(
     viewport: Viewport,
 ) {
     player_clip_map.update(&timeline, passed_frames);
-    coallesce_animations(&timeline, player_clip_map);
+    let time_base = TimeBase {
+        frames_per_second: FRAMERATE as f32 * 2.,
+        tempo_map: &timeline.tempo_map,
+    };
+    coallesce_animations(&timeline, player_clip_map, time_base);
+    resolve_drivers(&timeline, player_clip_map);
+    apply_constraints(&timeline, player_clip_map, time_base);
 
     let mut generator_map = Vec::new();
     generator_map.reserve(timeline.tracks.len());
@@ -42,7 +42,7 @@ impl PlayerClipMap {
                             .map(|default| ClipPropertyValue {
                                 value: default.value,
                                 is_overridden: false,
-                                targeted_by: None,
+                                targeted_by: Vec::new(),
                             })
                             .collect()
                     })
@@ -1,7 +1,9 @@
 mod audio_player;
+#[cfg(feature = "gui")]
 mod bass_player;
 mod no_audio_player;
 
 pub use self::audio_player::{AudioPlayer, ControllableAudioPlayer};
+#[cfg(feature = "gui")]
 pub use self::bass_player::BassPlayer;
 pub use self::no_audio_player::NoAudioPlayer;
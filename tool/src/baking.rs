@@ -0,0 +1,376 @@
+use crate::curve_fitting::fit_samples;
+use crate::editor_state::EditorState;
+use crate::timeline_interactions::insert_clip;
+use engine::animation::animation_clip::{
+    AnimatedProperty, AnimatedPropertyField, AnimatedPropertyTarget, AnimationClip, BlendMode,
+    CurveInterpolation, CurveSegment, Extrapolation,
+};
+use engine::animation::clip::ClipReference;
+use engine::animation::evaluate::evaluate;
+use engine::animation::property::{PropertyType, PropertyValue};
+use engine::animation::timeline::{Clip, ClipSource, TimeBase, Timeline, Track};
+use engine::math::{ColorSpace, MAX_GRADIENT_STOPS};
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::iter;
+use std::ops::Range;
+use std::path::Path;
+
+/// The most segments a baked field can have. The exporter writes each field's segment count as a
+/// `u8`.
+const MAX_BAKED_SEGMENTS: usize = u8::MAX as usize;
+
+#[derive(Clone, Copy)]
+pub enum BakeOutput {
+    /// Adds an animation clip on a new track that replaces the property with the baked values.
+    /// Properties with drivers or constraints can't be baked this way.
+    Keyframes,
+    /// Writes the baked values to a CSV file in the project's `bakes` folder.
+    Csv,
+}
+
+pub struct BakeRequest {
+    pub target_clip: ClipReference,
+    pub group_index: usize,
+    pub property_index: usize,
+    pub frames: Range<u32>,
+    pub output: BakeOutput,
+}
+
+/// A property's final value at each frame of a range, after every animation clip targeting it has
+/// been applied.
+pub struct BakedProperty {
+    pub start_frame: u32,
+    pub samples: Vec<PropertyValue>,
+}
+
+/// Finds the frames a clip is active for.
+fn clip_frame_range(timeline: &Timeline, reference: ClipReference) -> Option<Range<u32>> {
+    timeline.tracks.iter().find_map(|track| {
+        let clip_index = track
+            .clips
+            .iter()
+            .position(|clip| clip.id == reference.clip_id())?;
+        let start_frame = track.clip_start_frame(clip_index);
+        Some(start_frame..start_frame + track.clips[clip_index].duration_frames)
+    })
+}
+
+fn find_clip(timeline: &Timeline, reference: ClipReference) -> Option<&Clip> {
+    timeline
+        .tracks
+        .iter()
+        .flat_map(|track| track.clips.iter())
+        .find(|clip| clip.id == reference.clip_id())
+}
+
+/// Whether a property follows a driver, or an animation clip constrains it.
+fn is_driven_or_constrained(
+    timeline: &Timeline,
+    target_clip: ClipReference,
+    group_index: usize,
+    property_index: usize,
+) -> bool {
+    let is_driven = find_clip(timeline, target_clip)
+        .and_then(|clip| clip.property_groups.get(group_index))
+        .and_then(|group| group.defaults.get(property_index))
+        .map_or(false, |default| default.driver.is_some());
+
+    let is_constrained = timeline
+        .tracks
+        .iter()
+        .flat_map(|track| track.clips.iter())
+        .filter_map(|clip| match &clip.source {
+            ClipSource::Animation(animation) if animation.target_clip == target_clip => {
+                Some(animation)
+            }
+            _ => None,
+        })
+        .flat_map(|animation| animation.properties.iter())
+        .any(|animated_property| {
+            animated_property.group_index == group_index
+                && animated_property.property_index == property_index
+                && match animated_property.target {
+                    AnimatedPropertyTarget::Constraint(_) => true,
+                    _ => false,
+                }
+        });
+
+    is_driven || is_constrained
+}
+
+/// Evaluates a property of a clip at every frame in a range, the same way it's evaluated for the
+/// preview. The range is limited to the frames the clip is active for.
+pub fn bake_property(
+    timeline: &Timeline,
+    target_clip: ClipReference,
+    group_index: usize,
+    property_index: usize,
+    frames: Range<u32>,
+    frames_per_second: f32,
+) -> BakedProperty {
+    let frames = match clip_frame_range(timeline, target_clip) {
+        Some(clip_frames) => frames.start.max(clip_frames.start)..frames.end.min(clip_frames.end),
+        None => 0..0,
+    };
+
+    let samples = frames
+        .clone()
+        .filter_map(|frame| {
+            evaluate(timeline, frame, frames_per_second)
+                .into_iter()
+                .find(|active_clip| active_clip.reference == target_clip)
+                .map(|active_clip| active_clip.properties[group_index][property_index].value)
+        })
+        .collect();
+
+    BakedProperty {
+        start_frame: frames.start,
+        samples,
+    }
+}
+
+/// Builds a field with a linear segment between each pair of baked samples, starting at
+/// `local_offset_frames`.
+pub fn to_keyed_field(
+    baked: &BakedProperty,
+    local_offset_frames: i32,
+) -> Option<AnimatedPropertyField> {
+    let (&start_value, end_values) = baked.samples.split_first()?;
+    Some(AnimatedPropertyField {
+        local_offset_frames,
+        start_anchor: None,
+        start_value,
+        segments: end_values
+            .iter()
+            .map(|&end_value| CurveSegment {
+                duration_frames: 1,
+                end_anchor: None,
+                end_value,
+                interpolation: CurveInterpolation::Linear,
+                rotation_spline: false,
+                color_space: ColorSpace::LinearRgb,
+            })
+            .collect(),
+        pre_extrapolation: Extrapolation::Constant,
+        post_extrapolation: Extrapolation::Constant,
+    })
+}
+
+/// Builds a field from baked samples that can be exported. Bakes too long to have a segment per
+/// frame are reduced to fewer keyframes within `tolerance` of the samples. Returns `None` if there
+/// are no samples, or they can't be reduced to few enough keyframes.
+pub fn to_exportable_field(baked: &BakedProperty, tolerance: f32) -> Option<AnimatedPropertyField> {
+    let field = to_keyed_field(baked, 0)?;
+    if field.segments.len() <= MAX_BAKED_SEGMENTS {
+        return Some(field);
+    }
+
+    let fitted = fit_samples(&baked.samples, 0, tolerance)?;
+    if fitted.field.segments.len() <= MAX_BAKED_SEGMENTS {
+        Some(fitted.field)
+    } else {
+        None
+    }
+}
+
+fn field_names(value_type: PropertyType) -> Vec<String> {
+    let names: &[&str] = match value_type {
+        PropertyType::Float | PropertyType::Int | PropertyType::Bool => &["value"],
+        PropertyType::Vec2 => &["x", "y"],
+        PropertyType::Vec3 => &["x", "y", "z"],
+        PropertyType::Vec4 => &["x", "y", "z", "w"],
+        PropertyType::RgbColor => &["r", "g", "b"],
+        PropertyType::RgbaColor => &["r", "g", "b", "a"],
+        PropertyType::Rotation => &["pitch", "roll", "yaw"],
+        PropertyType::ClipReference => &["clip_id"],
+        PropertyType::Enum(_) => &["index"],
+        PropertyType::Gradient => {
+            let stop_names = (1..=MAX_GRADIENT_STOPS).flat_map(|stop| {
+                ["position", "r", "g", "b", "a"]
+                    .iter()
+                    .map(move |field| format!("stop{}_{}", stop, field))
+            });
+            return iter::once("stop_count".to_string())
+                .chain(stop_names)
+                .collect();
+        }
+    };
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// Writes one row per baked frame, with the frame's time in seconds and beats followed by each
+/// field of the value. Rotations are written as euler angles in degrees, kept continuous from one
+/// frame to the next.
+pub fn write_csv(
+    baked: &BakedProperty,
+    time_base: TimeBase,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let value_type = match baked.samples.first() {
+        Some(value) => value.get_type(),
+        None => return Ok(()),
+    };
+    writeln!(
+        writer,
+        "frame,seconds,beats,{}",
+        field_names(value_type).join(",")
+    )?;
+
+    let mut last_value = baked.samples[0];
+    for (sample_index, &value) in baked.samples.iter().enumerate() {
+        let frame = baked.start_frame + sample_index as u32;
+        write!(
+            writer,
+            "{},{},{}",
+            frame,
+            time_base.frame_to_seconds(frame as f32),
+            time_base.frame_to_beats(frame as f32)
+        )?;
+        for field in value.fields_near(last_value) {
+            write!(writer, ",{}", field)?;
+        }
+        writeln!(writer)?;
+        last_value = value;
+    }
+    Ok(())
+}
+
+/// Makes a string safe to use in a file name.
+fn file_name_part(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Processes `EditorState::bake_request`, if there is one. The result is described in
+/// `EditorState::last_bake`.
+pub fn handle_bake_request(
+    timeline: &mut Timeline,
+    editor_state: &mut EditorState,
+    bakes_path: &Path,
+) {
+    let request = match editor_state.bake_request.take() {
+        Some(request) => request,
+        None => return,
+    };
+    let (clip_name, schema) = match find_clip(timeline, request.target_clip) {
+        Some(clip) => (clip.name.clone(), clip.schema),
+        None => {
+            editor_state.last_bake = Some("Target clip doesn't exist".to_string());
+            return;
+        }
+    };
+    let property_name = schema.groups[request.group_index].properties[request.property_index].name;
+
+    // Drivers and constraints are applied after every animation clip, so they'd override the baked
+    // clip or be applied on top of it again
+    if let BakeOutput::Keyframes = request.output {
+        if is_driven_or_constrained(
+            timeline,
+            request.target_clip,
+            request.group_index,
+            request.property_index,
+        ) {
+            editor_state.last_bake = Some(format!(
+                "Can't bake {} to keyframes while it has a driver or constraint, remove them \
+                 first or bake to a CSV file",
+                property_name
+            ));
+            return;
+        }
+    }
+
+    let baked = bake_property(
+        timeline,
+        request.target_clip,
+        request.group_index,
+        request.property_index,
+        request.frames,
+        editor_state.fps,
+    );
+    if baked.samples.is_empty() {
+        editor_state.last_bake = Some("Target clip isn't active in the range".to_string());
+        return;
+    }
+
+    editor_state.last_bake = Some(match request.output {
+        BakeOutput::Keyframes => {
+            match to_exportable_field(&baked, editor_state.reduction_tolerance) {
+                Some(field) => {
+                    let keyframe_count = field.segments.len() + 1;
+
+                    // The clip goes on a new track at the end so it's blended last, replacing the
+                    // property with the baked values. The original animations are left in place.
+                    let clip = Clip {
+                        id: editor_state.next_clip_id,
+                        name: format!("{} (baked)", property_name),
+                        schema,
+                        source: ClipSource::Animation(AnimationClip {
+                            target_clip: request.target_clip,
+                            time_property: None,
+                            blend_mode: BlendMode::Replace,
+                            weight_property: None,
+                            is_time_collapsed: true,
+                            is_weight_collapsed: true,
+                            properties: vec![AnimatedProperty {
+                                group_index: request.group_index,
+                                property_index: request.property_index,
+                                is_collapsed: false,
+                                target: AnimatedPropertyTarget::Joined(field),
+                                modifiers: Vec::new(),
+                                spring_cache: RefCell::new(None),
+                            }],
+                        }),
+                        offset_frames: 0,
+                        duration_frames: baked.samples.len() as u32,
+                        anchor: None,
+                        property_groups: Vec::new(),
+                        is_selected: false,
+                    };
+                    let mut track = Track::default();
+                    insert_clip(&mut track, clip, baked.start_frame)
+                        .ok()
+                        .unwrap();
+                    timeline.tracks.push(track);
+                    editor_state.next_clip_id += 1;
+
+                    format!(
+                        "Baked {} frames of {} to {} keyframes",
+                        baked.samples.len(),
+                        property_name,
+                        keyframe_count
+                    )
+                }
+                None => format!(
+                    "Couldn't reduce {} frames of {} to {} keyframes or fewer, try a higher \
+                     tolerance or a shorter range",
+                    baked.samples.len(),
+                    property_name,
+                    MAX_BAKED_SEGMENTS + 1
+                ),
+            }
+        }
+        BakeOutput::Csv => {
+            let file_path = bakes_path.join(format!(
+                "{}-{}-{}.csv",
+                file_name_part(&clip_name),
+                file_name_part(property_name),
+                baked.start_frame
+            ));
+            let time_base = editor_state.time_base(&timeline.tempo_map);
+            let result = fs::create_dir_all(bakes_path)
+                .and_then(|_| File::create(&file_path))
+                .and_then(|file| {
+                    let mut writer = BufWriter::new(file);
+                    write_csv(&baked, time_base, &mut writer)?;
+                    writer.flush()
+                });
+            match result {
+                Ok(()) => format!("Wrote {}", file_path.display()),
+                Err(err) => format!("Couldn't write {}: {}", file_path.display(), err),
+            }
+        }
+    });
+}
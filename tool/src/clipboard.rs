@@ -0,0 +1,227 @@
+use crate::editor_state::EditorState;
+use crate::serialize::{
+    deserialize_clips, deserialize_keyframes, serialize_clips, serialize_keyframes,
+};
+use crate::timeline_interactions::{
+    deselect_all_clips, insert_remapped_clips, paste_keyframes, update_selected_clip_anchors,
+};
+use engine::animation::animation_clip::{AnimatedPropertyField, AnimatedPropertyTarget};
+use engine::animation::property::{PropertyType, PropertyValue};
+use engine::animation::timeline::{Clip, ClipSource, Timeline};
+use imgui_sys::{igGetClipboardText, igSetClipboardText};
+use ron::de::Deserializer;
+use ron::ser::{PrettyConfig, Serializer};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+
+/// A clip read from the clipboard, with the track and frame it was copied from.
+pub struct CopiedClip {
+    pub clip: Clip,
+    pub track_index: usize,
+    pub start_frame: u32,
+}
+
+/// Copies the selected clips to the clipboard, along with any animation clips targeting them.
+pub fn copy_selected_clips(timeline: &Timeline) {
+    let selected_ids: Vec<_> = timeline
+        .tracks
+        .iter()
+        .flat_map(|track| track.clips.iter())
+        .filter(|clip| clip.is_selected)
+        .map(|clip| clip.id)
+        .collect();
+    if selected_ids.is_empty() {
+        return;
+    }
+
+    let copied_ids: Vec<_> = timeline
+        .tracks
+        .iter()
+        .flat_map(|track| track.clips.iter())
+        .filter(|clip| match &clip.source {
+            ClipSource::Animation(animation) => {
+                clip.is_selected || selected_ids.contains(&animation.target_clip.clip_id())
+            }
+            _ => clip.is_selected,
+        })
+        .map(|clip| clip.id)
+        .collect();
+
+    let mut serializer = Serializer::new(Some(fragment_config()), false);
+    serialize_clips(timeline, &copied_ids, &mut serializer).unwrap();
+    set_clipboard_text(&serializer.into_output_string());
+}
+
+/// Pastes clips from the clipboard, moved so the first one starts at the playhead. The clips are
+/// given new IDs, and references between them are updated to match. References to clips that
+/// weren't copied are kept if the timeline has a clip with that ID. The pasted clips are selected,
+/// and their generators need to be instantiated.
+pub fn paste_clips(timeline: &mut Timeline, editor_state: &mut EditorState) {
+    let copied_clips = match get_clipboard_text().and_then(|text| {
+        let mut deserializer = Deserializer::from_str(&text).ok()?;
+        deserialize_clips(&mut deserializer, timeline).ok()
+    }) {
+        Some(copied_clips) => copied_clips,
+        None => {
+            eprintln!("The clipboard doesn't contain any clips.");
+            return;
+        }
+    };
+
+    let existing_ids: Vec<_> = timeline
+        .tracks
+        .iter()
+        .flat_map(|track| track.clips.iter())
+        .chain(copied_clips.iter().map(|copied| &copied.clip))
+        .map(|clip| clip.id)
+        .collect();
+    let mut id_map = HashMap::new();
+    for copied in &copied_clips {
+        id_map.insert(copied.clip.id, editor_state.next_clip_id);
+        editor_state.next_clip_id += 1;
+    }
+
+    let first_start_frame = copied_clips
+        .iter()
+        .map(|copied| copied.start_frame)
+        .min()
+        .unwrap_or(0);
+    let frame_offset = editor_state.current_frame() as i64 - first_start_frame as i64;
+    let mut clips = Vec::new();
+    for copied in copied_clips {
+        let mut clip = copied.clip;
+        if let ClipSource::Animation(animation) = &clip.source {
+            if !existing_ids.contains(&animation.target_clip.clip_id()) {
+                eprintln!(
+                    "Couldn't find the target of animation clip \"{}\", so it won't be pasted.",
+                    clip.name
+                );
+                continue;
+            }
+        }
+        clear_missing_clip_references(&mut clip, &existing_ids);
+
+        clip.is_selected = true;
+        let start_frame = (copied.start_frame as i64 + frame_offset).max(0) as u32;
+        clips.push((clip, copied.track_index, start_frame));
+    }
+
+    deselect_all_clips(timeline);
+    insert_remapped_clips(timeline, clips, &id_map);
+    update_selected_clip_anchors(timeline, editor_state.fps);
+}
+
+/// Keyframes read from the clipboard, with the option names of the enum they were copied from.
+pub struct CopiedKeyframes {
+    pub enum_options: Vec<String>,
+    pub fields: Vec<AnimatedPropertyField>,
+}
+
+/// Copies every keyframe of a property's fields to the clipboard. The motion editor doesn't
+/// select keyframes, so the whole property is copied.
+pub fn copy_property_keyframes(fields: &[AnimatedPropertyField], value_type: PropertyType) {
+    let mut serializer = Serializer::new(Some(fragment_config()), false);
+    serialize_keyframes(fields, enum_options(value_type), &mut serializer).unwrap();
+    set_clipboard_text(&serializer.into_output_string());
+}
+
+/// Pastes keyframes from the clipboard into a property's fields, with the first keyframe at
+/// `position_frames` relative to the clip. Keyframes already in the pasted range are replaced.
+/// Returns false if the clipboard doesn't have keyframes of the same type for every field. Enum
+/// keyframes are only pasted into enums with the same options, since their values are indices.
+pub fn paste_copied_keyframes(
+    fields: &mut [AnimatedPropertyField],
+    value_type: PropertyType,
+    position_frames: i32,
+) -> bool {
+    let copied = match get_clipboard_text().and_then(|text| {
+        let mut deserializer = Deserializer::from_str(&text).ok()?;
+        deserialize_keyframes(&mut deserializer).ok()
+    }) {
+        Some(copied) => copied,
+        None => return false,
+    };
+
+    let is_matching_type = copied.fields.len() == fields.len()
+        && copied
+            .fields
+            .iter()
+            .zip(fields.iter())
+            .all(|(pasted, field)| pasted.start_value.get_type() == field.start_value.get_type())
+        && copied
+            .enum_options
+            .iter()
+            .map(|option| option as &str)
+            .eq(enum_options(value_type).iter().cloned());
+    if !is_matching_type {
+        return false;
+    }
+
+    for (field, pasted) in fields.iter_mut().zip(copied.fields) {
+        paste_keyframes(field, pasted, position_frames);
+    }
+    true
+}
+
+/// The option names of an enum property. Other types don't have any.
+fn enum_options(value_type: PropertyType) -> &'static [&'static str] {
+    match value_type {
+        PropertyType::Enum(options) => options,
+        _ => &[],
+    }
+}
+
+/// Clears clip reference properties and keyframes pointing at clips that don't exist. Drivers and
+/// constraints following them have already been removed when the clip was read.
+fn clear_missing_clip_references(clip: &mut Clip, existing_ids: &[u32]) {
+    let clear_value = |value: &mut PropertyValue| {
+        if let PropertyValue::ClipReference(Some(reference)) = value {
+            if !existing_ids.contains(&reference.clip_id()) {
+                *value = PropertyValue::ClipReference(None);
+            }
+        }
+    };
+
+    for default in clip
+        .property_groups
+        .iter_mut()
+        .flat_map(|group| group.defaults.iter_mut())
+    {
+        clear_value(&mut default.value);
+    }
+    if let ClipSource::Animation(animation) = &mut clip.source {
+        for property in &mut animation.properties {
+            if let AnimatedPropertyTarget::Joined(field) = &mut property.target {
+                clear_value(&mut field.start_value);
+                for segment in &mut field.segments {
+                    clear_value(&mut segment.end_value);
+                }
+            }
+        }
+    }
+}
+
+/// Copied text is formatted like a save, so it's readable when pasted into a bug report.
+fn fragment_config() -> PrettyConfig {
+    PrettyConfig {
+        depth_limit: 20,
+        new_line: "\n".to_string(),
+        indentor: "  ".to_string(),
+        separate_tuple_members: false,
+        enumerate_arrays: false,
+    }
+}
+
+fn get_clipboard_text() -> Option<String> {
+    let text = unsafe { igGetClipboardText() };
+    if text.is_null() {
+        return None;
+    }
+    let text = unsafe { CStr::from_ptr(text) };
+    text.to_str().ok().map(|text| text.to_string())
+}
+
+fn set_clipboard_text(text: &str) {
+    let text = CString::new(text).unwrap();
+    unsafe { igSetClipboardText(text.as_ptr()) };
+}
@@ -0,0 +1,268 @@
+use engine::animation::animation_clip::{
+    AnimatedPropertyField, CurveInterpolation, CurveSegment, Extrapolation,
+};
+use engine::animation::coallesce::get_animation_field_value;
+use engine::animation::cubic_bezier::CubicBezier;
+use engine::animation::property::PropertyValue;
+use engine::math::{ColorSpace, Vector2};
+
+/// X positions tried for each of the bezier control points. With both at a third the curve's
+/// x is linear in its parameter, the others allow sharper eases.
+const CONTROL_X_CANDIDATES: [f32; 7] = [0., 0.15, 1. / 3., 0.5, 2. / 3., 0.85, 1.];
+
+/// How many bisection steps are used to find the bezier parameter for an x position.
+const BEZIER_SEARCH_STEPS: usize = 24;
+
+/// A field rebuilt from samples, along with the largest difference between it and the samples.
+pub struct FittedField {
+    pub field: AnimatedPropertyField,
+    pub max_error: f32,
+}
+
+/// Samples a field at every frame from its first keyframe to its last.
+pub fn sample_field(field: &AnimatedPropertyField) -> Vec<PropertyValue> {
+    (0..=field.duration_frames())
+        .map(|frame| {
+            get_animation_field_value(field, (field.local_offset_frames + frame as i32) as f32)
+        })
+        .collect()
+}
+
+/// Replaces a field's keyframes with as few linear and cubic bezier segments as possible, while
+/// staying within `tolerance` of its value at every frame. Extrapolation is kept as-is.
+pub fn reduce_keyframes(field: &AnimatedPropertyField, tolerance: f32) -> Option<FittedField> {
+    let mut fitted = fit_samples(&sample_field(field), field.local_offset_frames, tolerance)?;
+    fitted.field.pre_extrapolation = field.pre_extrapolation;
+    fitted.field.post_extrapolation = field.post_extrapolation;
+    Some(fitted)
+}
+
+/// Fits segments to one sample per frame, with the first sample at `local_offset_frames`. Each
+/// segment is made as long as possible before starting the next one. The error is measured per
+/// field in the units shown in the motion editor, so rotations are compared in degrees.
+///
+/// Returns `None` if there are no samples or they're clip references, which can't be
+/// interpolated.
+pub fn fit_samples(
+    samples: &[PropertyValue],
+    local_offset_frames: i32,
+    tolerance: f32,
+) -> Option<FittedField> {
+    let start_value = *samples.first()?;
+    if let PropertyValue::ClipReference(_) = start_value {
+        return None;
+    }
+
+    let last_index = samples.len() - 1;
+    let mut segments = Vec::new();
+    let mut max_error = 0f32;
+    let mut start_index = 0;
+    while start_index < last_index {
+        let fits = |len: usize| fit_span(&samples[start_index..=start_index + len], tolerance);
+
+        // A span of one frame has nothing in between to fit, so it's always linear, though
+        // rounding can still put it slightly off the end value. Double the length until a span
+        // doesn't fit, then binary search for the longest one that does.
+        let mut good_len = 1;
+        let mut good_fit = (
+            CurveInterpolation::Linear,
+            span_error(&samples[start_index..=start_index + 1], |x| x),
+        );
+        let max_len = last_index - start_index;
+        let mut bad_len = max_len + 1;
+        let mut probe_len = 2;
+        while probe_len <= max_len {
+            match fits(probe_len) {
+                Some(fit) => {
+                    good_len = probe_len;
+                    good_fit = fit;
+                    probe_len *= 2;
+                }
+                None => {
+                    bad_len = probe_len;
+                    break;
+                }
+            }
+        }
+        if probe_len > max_len && good_len < max_len {
+            match fits(max_len) {
+                Some(fit) => {
+                    good_len = max_len;
+                    good_fit = fit;
+                }
+                None => bad_len = max_len,
+            }
+        }
+        while bad_len - good_len > 1 {
+            let mid_len = (good_len + bad_len) / 2;
+            match fits(mid_len) {
+                Some(fit) => {
+                    good_len = mid_len;
+                    good_fit = fit;
+                }
+                None => bad_len = mid_len,
+            }
+        }
+
+        let (interpolation, error) = good_fit;
+        max_error = max_error.max(error);
+        segments.push(CurveSegment {
+            duration_frames: good_len as u32,
+            end_anchor: None,
+            end_value: samples[start_index + good_len],
+            interpolation,
+            rotation_spline: false,
+            color_space: ColorSpace::LinearRgb,
+        });
+        start_index += good_len;
+    }
+
+    Some(FittedField {
+        field: AnimatedPropertyField {
+            local_offset_frames,
+            start_anchor: None,
+            start_value,
+            segments,
+            pre_extrapolation: Extrapolation::Constant,
+            post_extrapolation: Extrapolation::Constant,
+        },
+        max_error,
+    })
+}
+
+/// Finds an interpolation from the first sample to the last that passes within `tolerance` of
+/// every sample in between, preferring linear interpolation.
+fn fit_span(samples: &[PropertyValue], tolerance: f32) -> Option<(CurveInterpolation, f32)> {
+    let linear_error = span_error(samples, |x| x);
+    if linear_error <= tolerance {
+        return Some((CurveInterpolation::Linear, linear_error));
+    }
+
+    let start_fields: Vec<f32> = samples[0].fields().collect();
+    let end_fields: Vec<f32> = samples[samples.len() - 1].fields_near(samples[0]).collect();
+    let deltas: Vec<f32> = start_fields
+        .iter()
+        .zip(&end_fields)
+        .map(|(start, end)| end - start)
+        .collect();
+    let delta_length_sq: f32 = deltas.iter().map(|delta| delta * delta).sum();
+    if delta_length_sq <= 0. {
+        // The curve scales the change from the start to the end value, so there's nothing to
+        // shape if they're the same
+        return None;
+    }
+
+    let mut best_fit: Option<(CubicBezier, f32)> = None;
+    for &x1 in CONTROL_X_CANDIDATES.iter() {
+        for &x2 in CONTROL_X_CANDIDATES.iter() {
+            let (y1, y2) = match solve_control_ys(samples, &start_fields, &deltas, x1, x2) {
+                Some(ys) => ys,
+                None => continue,
+            };
+            let bezier = CubicBezier::new(Vector2 { x: x1, y: y1 }, Vector2 { x: x2, y: y2 });
+            let error = span_error(samples, |x| bezier.get_y_at(x));
+            if best_fit
+                .as_ref()
+                .map_or(true, |(_, best_error)| error < *best_error)
+            {
+                best_fit = Some((bezier, error));
+            }
+        }
+    }
+
+    match best_fit {
+        Some((bezier, error)) if error <= tolerance => {
+            Some((CurveInterpolation::CubicBezier(bezier), error))
+        }
+        _ => None,
+    }
+}
+
+/// Finds the y positions of the control points that best fit the samples in a least squares
+/// sense, given their x positions. The curve is linear in the y positions, so this is solved
+/// directly.
+fn solve_control_ys(
+    samples: &[PropertyValue],
+    start_fields: &[f32],
+    deltas: &[f32],
+    x1: f32,
+    x2: f32,
+) -> Option<(f32, f32)> {
+    let span_frames = (samples.len() - 1) as f32;
+    let mut aa = 0.;
+    let mut ab = 0.;
+    let mut bb = 0.;
+    let mut ar = 0.;
+    let mut br = 0.;
+    for (sample_index, sample) in samples.iter().enumerate() {
+        let s = bezier_param_at(x1, x2, sample_index as f32 / span_frames);
+        let basis_a = 3. * (1. - s) * (1. - s) * s;
+        let basis_b = 3. * (1. - s) * s * s;
+        let basis_end = s * s * s;
+
+        // How far each field is from where the end control point alone would put it, projected
+        // onto the direction of the change
+        let residual: f32 = sample
+            .fields_near(samples[0])
+            .zip(start_fields.iter().zip(deltas))
+            .map(|(field, (start, delta))| (field - start - delta * basis_end) * delta)
+            .sum();
+
+        aa += basis_a * basis_a;
+        ab += basis_a * basis_b;
+        bb += basis_b * basis_b;
+        ar += basis_a * residual;
+        br += basis_b * residual;
+    }
+
+    let delta_length_sq: f32 = deltas.iter().map(|delta| delta * delta).sum();
+    let determinant = (aa * bb - ab * ab) * delta_length_sq;
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+    Some((
+        (ar * bb - br * ab) / determinant,
+        (br * aa - ar * ab) / determinant,
+    ))
+}
+
+/// Finds the parameter of a bezier curve with the given control point x positions, at which the
+/// curve reaches an x position.
+fn bezier_param_at(x1: f32, x2: f32, x: f32) -> f32 {
+    let mut low = 0.;
+    let mut high = 1.;
+    for _ in 0..BEZIER_SEARCH_STEPS {
+        let mid = (low + high) / 2.;
+        let mid_x = 3. * (1. - mid) * (1. - mid) * mid * x1
+            + 3. * (1. - mid) * mid * mid * x2
+            + mid * mid * mid;
+        if mid_x < x {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low + high) / 2.
+}
+
+/// The largest difference between a field of a sample and the interpolated value, when
+/// interpolating between the first and last samples with a curve.
+fn span_error(samples: &[PropertyValue], curve: impl Fn(f32) -> f32) -> f32 {
+    let start_value = samples[0];
+    let end_value = samples[samples.len() - 1];
+    let span_frames = (samples.len() - 1) as f32;
+
+    let mut max_error = 0f32;
+    for (sample_index, sample) in samples.iter().enumerate() {
+        let value = start_value
+            .lerp(end_value, curve(sample_index as f32 / span_frames))
+            .unwrap();
+        for (value_field, sample_field) in value
+            .fields_near(start_value)
+            .zip(sample.fields_near(start_value))
+        {
+            max_error = max_error.max((value_field - sample_field).abs());
+        }
+    }
+    max_error
+}
@@ -1,4 +1,5 @@
-use engine::animation::clip::{ActiveClip, ActiveClipMap, ClipPropertyValue, ClipReference};
+use engine::animation::clip::{ActiveClip, ActiveClipMap, ClipReference};
+use engine::animation::evaluate::active_clips_at;
 use engine::animation::timeline::Timeline;
 use std::collections::HashMap;
 use std::iter::FromIterator;
@@ -10,55 +11,7 @@ pub struct EditorClipMap {
 
 impl EditorClipMap {
     pub fn from_timeline(timeline: &Timeline, current_frame: u32) -> Self {
-        // Build a list of clips that are currently active
-        let active_clips: Vec<_> = timeline
-            .tracks
-            .iter()
-            .enumerate()
-            .filter_map(|(track_index, track)| {
-                track
-                    .clips
-                    .iter()
-                    .enumerate()
-                    .scan(0, |last_end_time, (clip_index, clip)| {
-                        let clip_start_time = *last_end_time + clip.offset_frames;
-                        *last_end_time = clip_start_time + clip.duration_frames;
-
-                        Some((clip_start_time, clip_index, clip))
-                    })
-                    .filter(|&(clip_start_time, _, clip)| {
-                        current_frame >= clip_start_time
-                            && current_frame < clip_start_time + clip.duration_frames
-                    })
-                    .map(|(clip_start_time, clip_index, clip)| {
-                        let properties = clip
-                            .property_groups
-                            .iter()
-                            .map(|group| {
-                                group
-                                    .defaults
-                                    .iter()
-                                    .map(|default| ClipPropertyValue {
-                                        value: default.value,
-                                        is_overridden: default.is_override,
-                                        targeted_by: None,
-                                    })
-                                    .collect()
-                            })
-                            .collect();
-
-                        ActiveClip {
-                            name: clip.name.clone(),
-                            reference: ClipReference::new(clip.id),
-                            track_index,
-                            clip_index,
-                            local_time: current_frame - clip_start_time,
-                            properties,
-                        }
-                    })
-                    .next()
-            })
-            .collect();
+        let active_clips = active_clips_at(timeline, current_frame);
         let map = HashMap::from_iter(
             active_clips
                 .iter()
@@ -1,13 +1,15 @@
 use crate::audio::ControllableAudioPlayer;
+use crate::baking::BakeRequest;
+use crate::validation::ValidationReport;
 use engine::animation::clip::ClipReference;
 use engine::animation::property::PropertyValue;
 use engine::animation::schema::GeneratorSchema;
+use engine::animation::tempo_map::TempoMap;
+use engine::animation::timeline::TimeBase;
 use imgui_sys::{ImGuiID, ImVec2};
 
 pub struct EditorState<'player> {
     pub fps: f32,
-    pub bpm: f32,
-    pub beats_per_bar: u32,
     pub timeline_zoom: f32,
     pub motion_editor_zoom: f32,
     pub motion_editor_pan: ImVec2,
@@ -42,22 +44,28 @@ pub struct EditorState<'player> {
 
     pub cam_locked: Option<ImVec2>,
 
+    pub reduction_tolerance: f32,
+    /// The keyframe count before and after the last keyframe reduction, and its maximum error.
+    pub last_reduction: Option<(usize, usize, f32)>,
+
+    pub bake_request: Option<BakeRequest>,
+    /// A description of the last bake's result, or why it failed.
+    pub last_bake: Option<String>,
+    /// The result of the last time the timeline was validated from the validation panel.
+    pub last_validation: Option<ValidationReport>,
+    /// Clips changed by a drag or other live edit since the animation was last evaluated, whose
+    /// cached animation state needs to be cleared.
+    pub edited_clips: Vec<ClipReference>,
+
     current_frame: u32,
     is_playing: bool,
     audio_player: &'player mut ControllableAudioPlayer,
 }
 
 impl<'player> EditorState<'player> {
-    pub fn new(
-        fps: f32,
-        bpm: f32,
-        beats_per_bar: u32,
-        audio_player: &'player mut ControllableAudioPlayer,
-    ) -> Self {
+    pub fn new(fps: f32, audio_player: &'player mut ControllableAudioPlayer) -> Self {
         EditorState {
             fps,
-            bpm,
-            beats_per_bar,
             drag_offset: 0,
             track_pixel_offset: 0.,
             track_offset: 0,
@@ -77,12 +85,25 @@ impl<'player> EditorState<'player> {
             current_frame: 0,
             is_playing: false,
             cam_locked: None,
+            reduction_tolerance: 0.01,
+            last_reduction: None,
+            bake_request: None,
+            last_bake: None,
+            last_validation: None,
+            edited_clips: Vec::new(),
             retarget_clip_request: None,
             retarget_clip_response: None,
             audio_player,
         }
     }
 
+    pub fn time_base<'map>(&self, tempo_map: &'map TempoMap) -> TimeBase<'map> {
+        TimeBase {
+            frames_per_second: self.fps,
+            tempo_map,
+        }
+    }
+
     pub fn frame_to_seconds(&self, frame: u32) -> f32 {
         frame as f32 / self.fps
     }
@@ -2,12 +2,23 @@ use super::binary_writer::write;
 use engine::animation::animation_clip::{
     AnimatedPropertyField, AnimatedPropertyTarget, CurveInterpolation,
 };
+use engine::animation::constraint::Constraint;
+use engine::animation::driver::DriverMapping;
+use engine::animation::modifier::Modifier;
 use engine::animation::property::PropertyValue;
 use engine::animation::timeline::{ClipSource, Timeline};
 use engine::generator::GENERATOR_SCHEMAS;
 use std::collections::HashMap;
 use std::iter::FromIterator;
 
+#[derive(Default)]
+struct TempoStream {
+    len: usize,
+    bars: Vec<u8>,
+    beats_per_minutes: Vec<u8>,
+    beats_per_bars: Vec<u8>,
+}
+
 #[derive(Default)]
 struct ClipStream {
     len: usize,
@@ -21,6 +32,9 @@ struct AnimationClipStream {
     len: usize,
     targets: Vec<u8>,
     schemas: Vec<u8>,
+    has_time_properties: Vec<u8>,
+    blend_modes: Vec<u8>,
+    has_weight_properties: Vec<u8>,
     num_props: Vec<u8>,
 }
 
@@ -29,6 +43,7 @@ struct AnimationPropertyStream {
     len: usize,
     target_groups: Vec<u8>,
     target_props: Vec<u8>,
+    num_modifiers: Vec<u8>,
     num_fields: Vec<u8>,
 }
 
@@ -36,6 +51,7 @@ struct AnimationPropertyStream {
 struct AnimationFieldStream {
     len: usize,
     local_offsets: Vec<u8>,
+    extrapolations: Vec<u8>,
     num_segments: Vec<u8>,
 }
 
@@ -46,6 +62,52 @@ struct SegmentStream {
     interpolations: Vec<u8>,
 }
 
+#[derive(Default)]
+struct ModifierStream {
+    len: usize,
+    types: Vec<u8>,
+    params: Vec<u8>,
+}
+
+#[derive(Default)]
+struct ExpressionStream {
+    len: usize,
+    lengths: Vec<u8>,
+    bytecode: Vec<u8>,
+}
+
+#[derive(Default)]
+struct PathStream {
+    len: usize,
+    kinds: Vec<u8>,
+    num_points: Vec<u8>,
+    orient_groups: Vec<u8>,
+    orient_props: Vec<u8>,
+    points: Vec<u8>,
+}
+
+#[derive(Default)]
+struct DriverStream {
+    len: usize,
+    target_clips: Vec<u8>,
+    target_groups: Vec<u8>,
+    target_props: Vec<u8>,
+    source_clips: Vec<u8>,
+    source_groups: Vec<u8>,
+    source_props: Vec<u8>,
+    mappings: Vec<u8>,
+}
+
+#[derive(Default)]
+struct ConstraintStream {
+    len: usize,
+    kinds: Vec<u8>,
+    target_clips: Vec<u8>,
+    target_groups: Vec<u8>,
+    target_props: Vec<u8>,
+    data: Vec<u8>,
+}
+
 #[derive(Default)]
 struct PropValStream {
     streams: [Vec<u8>; 4],
@@ -66,6 +128,16 @@ fn export_property_value(
                 write(&mut stream.streams[0], !0u8);
             }
         }
+    } else if let PropertyValue::Gradient(gradient) = value {
+        // Only the stops that are used are written, so the player reads the count first
+        write(&mut stream.streams[0], gradient.stops().len() as u8);
+        for stop in gradient.stops() {
+            write(&mut stream.streams[0], stop.position);
+            let color: [f32; 4] = stop.color.into();
+            for (field_index, &field) in color.iter().enumerate() {
+                write(&mut stream.streams[field_index], field);
+            }
+        }
     } else {
         for (field_index, field) in value.fields().enumerate() {
             write(&mut stream.streams[field_index], field);
@@ -82,6 +154,11 @@ fn export_animated_field(
 ) {
     field_stream.len += 1;
     write(&mut field_stream.local_offsets, field.local_offset_frames);
+    // pre-extrapolation is in the low nibble, post-extrapolation in the high nibble
+    write(
+        &mut field_stream.extrapolations,
+        field.pre_extrapolation as u8 | (field.post_extrapolation as u8) << 4,
+    );
     export_property_value(field.start_value, id_map, prop_val_stream);
     write(&mut field_stream.num_segments, field.segments.len() as u8);
 
@@ -89,13 +166,136 @@ fn export_animated_field(
         segment_stream.len += 1;
         write(&mut segment_stream.durations, segment.duration_frames);
         export_property_value(segment.end_value, id_map, prop_val_stream);
-        match &segment.interpolation {
-            CurveInterpolation::Linear => write(&mut segment_stream.interpolations, 0u8),
-            CurveInterpolation::CubicBezier(bezier) => {
-                write(&mut segment_stream.interpolations, 1u8);
-                write(&mut segment_stream.interpolations, bezier.c1());
-                write(&mut segment_stream.interpolations, bezier.c2());
-            }
+
+        // the interpolation type is in the low 5 bits of the tag, followed by 2 bits of colour
+        // space and the rotation spline flag in the high bit
+        let spline_flag = if segment.rotation_spline { 0x80u8 } else { 0 };
+        let flags = (segment.color_space.index() as u8) << 5 | spline_flag;
+        export_interpolation(
+            &segment.interpolation,
+            flags,
+            &mut segment_stream.interpolations,
+        );
+    }
+}
+
+fn export_interpolation(interpolation: &CurveInterpolation, flags: u8, stream: &mut Vec<u8>) {
+    match interpolation {
+        CurveInterpolation::Linear => write(stream, flags),
+        CurveInterpolation::CubicBezier(bezier) => {
+            write(stream, 1u8 | flags);
+            write(stream, bezier.c1());
+            write(stream, bezier.c2());
+        }
+        CurveInterpolation::Step => write(stream, 2u8 | flags),
+        CurveInterpolation::StepNext => write(stream, 3u8 | flags),
+        CurveInterpolation::Ease(easing) => {
+            // easings are packed into the tag, after the fixed interpolation types
+            write(stream, (4u8 + easing.index() as u8) | flags);
+        }
+    }
+}
+
+fn export_driver_mapping(mapping: &DriverMapping, driver_stream: &mut DriverStream) {
+    match mapping {
+        DriverMapping::Linear { scale, offset } => {
+            write(&mut driver_stream.mappings, 0u8);
+            write(&mut driver_stream.mappings, *scale);
+            write(&mut driver_stream.mappings, *offset);
+        }
+        DriverMapping::Remap {
+            input_min,
+            input_max,
+            output_min,
+            output_max,
+            curve,
+        } => {
+            write(&mut driver_stream.mappings, 1u8);
+            write(&mut driver_stream.mappings, *input_min);
+            write(&mut driver_stream.mappings, *input_max);
+            write(&mut driver_stream.mappings, *output_min);
+            write(&mut driver_stream.mappings, *output_max);
+            export_interpolation(curve, 0, &mut driver_stream.mappings);
+        }
+    }
+}
+
+fn export_constraint(
+    constraint: &Constraint,
+    id_map: &HashMap<u32, (u32, usize, usize)>,
+    constraint_stream: &mut ConstraintStream,
+) {
+    let target = constraint.target();
+    let (remapped_target, _, _) = id_map[&target.clip.clip_id()];
+    constraint_stream.len += 1;
+    write(&mut constraint_stream.target_clips, remapped_target as u8);
+    write(
+        &mut constraint_stream.target_groups,
+        target.property.group as u8,
+    );
+    write(
+        &mut constraint_stream.target_props,
+        target.property.prop as u8,
+    );
+
+    match constraint {
+        Constraint::LookAt { eye, .. } => {
+            write(&mut constraint_stream.kinds, 0u8);
+            write(&mut constraint_stream.data, eye.group as u8);
+            write(&mut constraint_stream.data, eye.prop as u8);
+        }
+        Constraint::Follow { offset, .. } => {
+            write(&mut constraint_stream.kinds, 1u8);
+            write(&mut constraint_stream.data, *offset);
+        }
+        Constraint::DampedFollow {
+            offset, half_life, ..
+        } => {
+            write(&mut constraint_stream.kinds, 2u8);
+            write(&mut constraint_stream.data, *offset);
+            write(&mut constraint_stream.data, *half_life);
+        }
+    }
+}
+
+fn export_modifier(modifier: &Modifier, modifier_stream: &mut ModifierStream) {
+    modifier_stream.len += 1;
+    match *modifier {
+        Modifier::Noise {
+            frequency,
+            amplitude,
+            seed,
+            octaves,
+        } => {
+            write(&mut modifier_stream.types, 0u8);
+            write(&mut modifier_stream.params, frequency);
+            write(&mut modifier_stream.params, amplitude);
+            write(&mut modifier_stream.params, seed);
+            write(&mut modifier_stream.params, octaves as u8);
+        }
+        Modifier::Sine {
+            frequency,
+            amplitude,
+            phase,
+        } => {
+            write(&mut modifier_stream.types, 1u8);
+            write(&mut modifier_stream.params, frequency);
+            write(&mut modifier_stream.params, amplitude);
+            write(&mut modifier_stream.params, phase);
+        }
+        Modifier::Quantize { step } => {
+            write(&mut modifier_stream.types, 2u8);
+            write(&mut modifier_stream.params, step);
+        }
+        Modifier::Clamp { min, max } => {
+            write(&mut modifier_stream.types, 3u8);
+            write(&mut modifier_stream.params, min);
+            write(&mut modifier_stream.params, max);
+        }
+        Modifier::Spring { frequency, damping } => {
+            write(&mut modifier_stream.types, 4u8);
+            write(&mut modifier_stream.params, frequency);
+            write(&mut modifier_stream.params, damping);
         }
     }
 }
@@ -133,11 +333,17 @@ pub fn export_timeline(timeline: &Timeline, buffer: &mut Vec<u8>) {
         },
     ));
 
+    let mut tempo_stream = TempoStream::default();
     let mut clip_stream = ClipStream::default();
     let mut animation_clip_stream = AnimationClipStream::default();
     let mut animation_prop_stream = AnimationPropertyStream::default();
     let mut animation_field_stream = AnimationFieldStream::default();
     let mut segment_stream = SegmentStream::default();
+    let mut modifier_stream = ModifierStream::default();
+    let mut expression_stream = ExpressionStream::default();
+    let mut path_stream = PathStream::default();
+    let mut driver_stream = DriverStream::default();
+    let mut constraint_stream = ConstraintStream::default();
     let mut prop_val_stream = PropValStream::default();
 
     let project_duration = clip_refs
@@ -147,7 +353,15 @@ pub fn export_timeline(timeline: &Timeline, buffer: &mut Vec<u8>) {
         .cloned()
         .unwrap_or(0);
 
-    for (track_index, clip_index, clip_start_time, _) in &clip_refs {
+    for change in &timeline.tempo_map.changes {
+        tempo_stream.len += 1;
+        write(&mut tempo_stream.bars, change.bar);
+        write(&mut tempo_stream.beats_per_minutes, change.beats_per_minute);
+        write(&mut tempo_stream.beats_per_bars, change.beats_per_bar as u8);
+    }
+
+    for (new_clip_id, (track_index, clip_index, clip_start_time, _)) in clip_refs.iter().enumerate()
+    {
         let clip = &timeline.tracks[*track_index].clips[*clip_index];
 
         clip_stream.len += 1;
@@ -160,7 +374,7 @@ pub fn export_timeline(timeline: &Timeline, buffer: &mut Vec<u8>) {
             .unwrap();
 
         match &clip.source {
-            ClipSource::Generator(_) => {
+            ClipSource::Generator(_) | ClipSource::Unloaded => {
                 // clip type = index of the schema in the schema list
                 write(&mut clip_stream.types, schema_index as u8);
 
@@ -170,6 +384,30 @@ pub fn export_timeline(timeline: &Timeline, buffer: &mut Vec<u8>) {
                         export_property_value(default.value, &id_map, &mut prop_val_stream);
                     }
                 }
+
+                // overridden properties aren't driven, just like they aren't animated
+                for (group_index, group) in clip.property_groups.iter().enumerate() {
+                    for (prop_index, default) in group.defaults.iter().enumerate() {
+                        let driver = match &default.driver {
+                            Some(driver) if !default.is_override => driver,
+                            _ => continue,
+                        };
+                        let (remapped_source, _, _) =
+                            match id_map.get(&driver.source_clip.clip_id()) {
+                                Some(source) => *source,
+                                None => continue, // the source clip has been deleted
+                            };
+
+                        driver_stream.len += 1;
+                        write(&mut driver_stream.target_clips, new_clip_id as u8);
+                        write(&mut driver_stream.target_groups, group_index as u8);
+                        write(&mut driver_stream.target_props, prop_index as u8);
+                        write(&mut driver_stream.source_clips, remapped_source as u8);
+                        write(&mut driver_stream.source_groups, driver.source.group as u8);
+                        write(&mut driver_stream.source_props, driver.source.prop as u8);
+                        export_driver_mapping(&driver.mapping, &mut driver_stream);
+                    }
+                }
             }
             ClipSource::Animation(animation_clip) => {
                 // clip type = !0
@@ -181,7 +419,8 @@ pub fn export_timeline(timeline: &Timeline, buffer: &mut Vec<u8>) {
                 write(&mut animation_clip_stream.targets, remapped_ref as u8);
                 write(&mut animation_clip_stream.schemas, schema_index as u8);
 
-                // Build a list of properties that aren't overridden by the target clip
+                // Build a list of properties that aren't overridden by the target clip, leaving out
+                // constraints that follow a deleted clip
                 let target_clip = &timeline.tracks[target_track_index].clips[target_clip_index];
                 let active_animated_properties: Vec<_> = animation_clip
                     .properties
@@ -189,7 +428,13 @@ pub fn export_timeline(timeline: &Timeline, buffer: &mut Vec<u8>) {
                     .filter(|&prop| {
                         let targeted_default = &target_clip.property_groups[prop.group_index]
                             .defaults[prop.property_index];
-                        !targeted_default.is_override
+                        let has_target = match &prop.target {
+                            AnimatedPropertyTarget::Constraint(constraint) => {
+                                id_map.contains_key(&constraint.target().clip.clip_id())
+                            }
+                            _ => true,
+                        };
+                        !targeted_default.is_override && has_target
                     })
                     .collect();
 
@@ -200,13 +445,37 @@ pub fn export_timeline(timeline: &Timeline, buffer: &mut Vec<u8>) {
 
                 // todo: skip this clip entirely if there aren't any active animated properties
 
-                /*export_animated_field(
-                    &animation_clip.time_property,
-                    &id_map,
-                    &mut prop_val_stream,
-                    &mut animation_field_stream,
-                    &mut segment_stream,
-                );*/
+                match &animation_clip.time_property {
+                    Some(time_property) => {
+                        write(&mut animation_clip_stream.has_time_properties, 1u8);
+                        export_animated_field(
+                            time_property,
+                            &id_map,
+                            &mut prop_val_stream,
+                            &mut animation_field_stream,
+                            &mut segment_stream,
+                        );
+                    }
+                    None => write(&mut animation_clip_stream.has_time_properties, 0u8),
+                }
+
+                write(
+                    &mut animation_clip_stream.blend_modes,
+                    animation_clip.blend_mode as u8,
+                );
+                match &animation_clip.weight_property {
+                    Some(weight_property) => {
+                        write(&mut animation_clip_stream.has_weight_properties, 1u8);
+                        export_animated_field(
+                            weight_property,
+                            &id_map,
+                            &mut prop_val_stream,
+                            &mut animation_field_stream,
+                            &mut segment_stream,
+                        );
+                    }
+                    None => write(&mut animation_clip_stream.has_weight_properties, 0u8),
+                }
 
                 for &animated_property in &active_animated_properties {
                     animation_prop_stream.len += 1;
@@ -218,6 +487,13 @@ pub fn export_timeline(timeline: &Timeline, buffer: &mut Vec<u8>) {
                         &mut animation_prop_stream.target_props,
                         animated_property.property_index as u8,
                     );
+                    write(
+                        &mut animation_prop_stream.num_modifiers,
+                        animated_property.modifiers.len() as u8,
+                    );
+                    for modifier in &animated_property.modifiers {
+                        export_modifier(modifier, &mut modifier_stream);
+                    }
 
                     match &animated_property.target {
                         AnimatedPropertyTarget::Joined(field) => {
@@ -242,6 +518,54 @@ pub fn export_timeline(timeline: &Timeline, buffer: &mut Vec<u8>) {
                                 );
                             }
                         }
+                        AnimatedPropertyTarget::Expression(expression) => {
+                            // expressions are marked by having !0 fields
+                            write(&mut animation_prop_stream.num_fields, !0u8);
+                            expression_stream.len += 1;
+                            write(
+                                &mut expression_stream.lengths,
+                                expression.bytecode.len() as u8,
+                            );
+                            expression_stream
+                                .bytecode
+                                .extend_from_slice(&expression.bytecode);
+                        }
+                        AnimatedPropertyTarget::Path(path) => {
+                            // paths are marked by having !1 fields, and their progress is
+                            // exported like a joined field
+                            write(&mut animation_prop_stream.num_fields, !1u8);
+                            path_stream.len += 1;
+                            write(&mut path_stream.kinds, path.kind as u8);
+                            write(&mut path_stream.num_points, path.points.len() as u8);
+                            match path.orient_target {
+                                Some(orient_target) => {
+                                    write(
+                                        &mut path_stream.orient_groups,
+                                        orient_target.group as u8,
+                                    );
+                                    write(&mut path_stream.orient_props, orient_target.prop as u8);
+                                }
+                                None => {
+                                    write(&mut path_stream.orient_groups, !0u8);
+                                    write(&mut path_stream.orient_props, !0u8);
+                                }
+                            }
+                            for &point in &path.points {
+                                write(&mut path_stream.points, point);
+                            }
+                            export_animated_field(
+                                &path.progress,
+                                &id_map,
+                                &mut prop_val_stream,
+                                &mut animation_field_stream,
+                                &mut segment_stream,
+                            );
+                        }
+                        AnimatedPropertyTarget::Constraint(constraint) => {
+                            // constraints are marked by having !2 fields
+                            write(&mut animation_prop_stream.num_fields, !2u8);
+                            export_constraint(constraint, &id_map, &mut constraint_stream);
+                        }
                     }
                 }
             }
@@ -251,6 +575,17 @@ pub fn export_timeline(timeline: &Timeline, buffer: &mut Vec<u8>) {
     // Composite everything into the output buffer
     write(buffer, project_duration as u32);
 
+    write(buffer, tempo_stream.len as u8);
+    write(
+        buffer,
+        (tempo_stream.bars.len()
+            + tempo_stream.beats_per_minutes.len()
+            + tempo_stream.beats_per_bars.len()) as u32,
+    );
+    buffer.extend_from_slice(&tempo_stream.bars);
+    buffer.extend_from_slice(&tempo_stream.beats_per_minutes);
+    buffer.extend_from_slice(&tempo_stream.beats_per_bars);
+
     write(buffer, clip_refs.len() as u8);
     write(
         buffer,
@@ -266,10 +601,16 @@ pub fn export_timeline(timeline: &Timeline, buffer: &mut Vec<u8>) {
         buffer,
         (animation_clip_stream.targets.len()
             + animation_clip_stream.schemas.len()
+            + animation_clip_stream.has_time_properties.len()
+            + animation_clip_stream.blend_modes.len()
+            + animation_clip_stream.has_weight_properties.len()
             + animation_clip_stream.num_props.len()) as u32,
     );
     buffer.extend_from_slice(&animation_clip_stream.targets);
     buffer.extend_from_slice(&animation_clip_stream.schemas);
+    buffer.extend_from_slice(&animation_clip_stream.has_time_properties);
+    buffer.extend_from_slice(&animation_clip_stream.blend_modes);
+    buffer.extend_from_slice(&animation_clip_stream.has_weight_properties);
     buffer.extend_from_slice(&animation_clip_stream.num_props);
 
     write(buffer, animation_prop_stream.len as u8);
@@ -277,19 +618,23 @@ pub fn export_timeline(timeline: &Timeline, buffer: &mut Vec<u8>) {
         buffer,
         (animation_prop_stream.target_groups.len()
             + animation_prop_stream.target_props.len()
+            + animation_prop_stream.num_modifiers.len()
             + animation_prop_stream.num_fields.len()) as u32,
     );
     buffer.extend_from_slice(&animation_prop_stream.target_groups);
     buffer.extend_from_slice(&animation_prop_stream.target_props);
+    buffer.extend_from_slice(&animation_prop_stream.num_modifiers);
     buffer.extend_from_slice(&animation_prop_stream.num_fields);
 
     write(buffer, animation_field_stream.len as u8);
     write(
         buffer,
-        (animation_field_stream.local_offsets.len() + animation_field_stream.num_segments.len())
-            as u32,
+        (animation_field_stream.local_offsets.len()
+            + animation_field_stream.extrapolations.len()
+            + animation_field_stream.num_segments.len()) as u32,
     );
     buffer.extend_from_slice(&animation_field_stream.local_offsets);
+    buffer.extend_from_slice(&animation_field_stream.extrapolations);
     buffer.extend_from_slice(&animation_field_stream.num_segments);
 
     write(buffer, segment_stream.len as u8);
@@ -300,6 +645,71 @@ pub fn export_timeline(timeline: &Timeline, buffer: &mut Vec<u8>) {
     buffer.extend_from_slice(&segment_stream.durations);
     buffer.extend_from_slice(&segment_stream.interpolations);
 
+    write(buffer, modifier_stream.len as u8);
+    write(
+        buffer,
+        (modifier_stream.types.len() + modifier_stream.params.len()) as u32,
+    );
+    buffer.extend_from_slice(&modifier_stream.types);
+    buffer.extend_from_slice(&modifier_stream.params);
+
+    write(buffer, expression_stream.len as u8);
+    write(
+        buffer,
+        (expression_stream.lengths.len() + expression_stream.bytecode.len()) as u32,
+    );
+    buffer.extend_from_slice(&expression_stream.lengths);
+    buffer.extend_from_slice(&expression_stream.bytecode);
+
+    write(buffer, path_stream.len as u8);
+    write(
+        buffer,
+        (path_stream.kinds.len()
+            + path_stream.num_points.len()
+            + path_stream.orient_groups.len()
+            + path_stream.orient_props.len()
+            + path_stream.points.len()) as u32,
+    );
+    buffer.extend_from_slice(&path_stream.kinds);
+    buffer.extend_from_slice(&path_stream.num_points);
+    buffer.extend_from_slice(&path_stream.orient_groups);
+    buffer.extend_from_slice(&path_stream.orient_props);
+    buffer.extend_from_slice(&path_stream.points);
+
+    write(buffer, driver_stream.len as u8);
+    write(
+        buffer,
+        (driver_stream.target_clips.len()
+            + driver_stream.target_groups.len()
+            + driver_stream.target_props.len()
+            + driver_stream.source_clips.len()
+            + driver_stream.source_groups.len()
+            + driver_stream.source_props.len()
+            + driver_stream.mappings.len()) as u32,
+    );
+    buffer.extend_from_slice(&driver_stream.target_clips);
+    buffer.extend_from_slice(&driver_stream.target_groups);
+    buffer.extend_from_slice(&driver_stream.target_props);
+    buffer.extend_from_slice(&driver_stream.source_clips);
+    buffer.extend_from_slice(&driver_stream.source_groups);
+    buffer.extend_from_slice(&driver_stream.source_props);
+    buffer.extend_from_slice(&driver_stream.mappings);
+
+    write(buffer, constraint_stream.len as u8);
+    write(
+        buffer,
+        (constraint_stream.kinds.len()
+            + constraint_stream.target_clips.len()
+            + constraint_stream.target_groups.len()
+            + constraint_stream.target_props.len()
+            + constraint_stream.data.len()) as u32,
+    );
+    buffer.extend_from_slice(&constraint_stream.kinds);
+    buffer.extend_from_slice(&constraint_stream.target_clips);
+    buffer.extend_from_slice(&constraint_stream.target_groups);
+    buffer.extend_from_slice(&constraint_stream.target_props);
+    buffer.extend_from_slice(&constraint_stream.data);
+
     write(buffer, prop_val_stream.streams[0].len() as u32);
     buffer.extend_from_slice(&prop_val_stream.streams[0]);
 
@@ -0,0 +1,337 @@
+use engine::animation::expression::{
+    MAX_BYTECODE_LEN, MAX_STACK_DEPTH, OP_ABS, OP_ADD, OP_CLAMP, OP_CONST, OP_COS, OP_DIV,
+    OP_FLOOR, OP_FRACT, OP_MAX, OP_MIN, OP_MIX, OP_MOD, OP_MUL, OP_NEG, OP_NOISE, OP_POW, OP_SIN,
+    OP_SMOOTHSTEP, OP_SQRT, OP_SUB, OP_TAN, OP_VAR, VAR_BEAT, VAR_CLIP_T, VAR_FRAME, VAR_T,
+};
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+// name, opcode, argument count
+const FUNCTIONS: [(&str, u8, usize); 13] = [
+    ("sin", OP_SIN, 1),
+    ("cos", OP_COS, 1),
+    ("tan", OP_TAN, 1),
+    ("abs", OP_ABS, 1),
+    ("floor", OP_FLOOR, 1),
+    ("fract", OP_FRACT, 1),
+    ("sqrt", OP_SQRT, 1),
+    ("min", OP_MIN, 2),
+    ("max", OP_MAX, 2),
+    ("pow", OP_POW, 2),
+    ("smoothstep", OP_SMOOTHSTEP, 3),
+    ("clamp", OP_CLAMP, 3),
+    ("mix", OP_MIX, 3),
+];
+
+const VARIABLES: [(&str, u8); 4] = [
+    ("t", VAR_T),
+    ("frame", VAR_FRAME),
+    ("beat", VAR_BEAT),
+    ("clip_t", VAR_CLIP_T),
+];
+
+#[derive(Debug)]
+pub struct CompileError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (at character {})", self.message, self.position + 1)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Identifier(String),
+    Symbol(char),
+    End,
+}
+
+struct Compiler<'source> {
+    chars: Peekable<CharIndices<'source>>,
+    token: Token,
+    token_position: usize,
+    source_len: usize,
+    bytecode: Vec<u8>,
+    stack_depth: usize,
+}
+
+/// Compiles an expression into bytecode for `Expression::evaluate`. The whole expression can be a
+/// `vec2`, `vec3` or `vec4` call to produce each component separately.
+pub fn compile_expression(source: &str) -> Result<Vec<u8>, CompileError> {
+    let mut compiler = Compiler {
+        chars: source.char_indices().peekable(),
+        token: Token::End,
+        token_position: 0,
+        source_len: source.len(),
+        bytecode: Vec::new(),
+        stack_depth: 0,
+    };
+    compiler.next_token()?;
+    compiler.compile_root()?;
+
+    if compiler.bytecode.len() > MAX_BYTECODE_LEN {
+        return Err(CompileError {
+            position: 0,
+            message: "Expression is too long".to_string(),
+        });
+    }
+    Ok(compiler.bytecode)
+}
+
+impl<'source> Compiler<'source> {
+    fn error<T>(&self, message: &str) -> Result<T, CompileError> {
+        Err(CompileError {
+            position: self.token_position,
+            message: message.to_string(),
+        })
+    }
+
+    fn next_token(&mut self) -> Result<(), CompileError> {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.chars.next();
+        }
+
+        let (position, c) = match self.chars.next() {
+            Some(next) => next,
+            None => {
+                self.token_position = self.source_len;
+                self.token = Token::End;
+                return Ok(());
+            }
+        };
+        self.token_position = position;
+
+        self.token = if c.is_ascii_digit() || c == '.' {
+            let mut number = c.to_string();
+            while let Some(&(_, c)) = self.chars.peek() {
+                if !c.is_ascii_digit() && c != '.' {
+                    break;
+                }
+                number.push(c);
+                self.chars.next();
+            }
+            match number.parse() {
+                Ok(value) => Token::Number(value),
+                Err(_) => return self.error("Invalid number"),
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let mut identifier = c.to_string();
+            while let Some(&(_, c)) = self.chars.peek() {
+                if !c.is_alphanumeric() && c != '_' {
+                    break;
+                }
+                identifier.push(c);
+                self.chars.next();
+            }
+            Token::Identifier(identifier)
+        } else if "+-*/%^(),".contains(c) {
+            Token::Symbol(c)
+        } else {
+            return self.error("Unexpected character");
+        };
+        Ok(())
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), CompileError> {
+        if self.token != Token::Symbol(symbol) {
+            return self.error(&format!("Expected '{}'", symbol));
+        }
+        self.next_token()
+    }
+
+    fn emit(&mut self, opcode: u8, arg_count: usize) {
+        self.bytecode.push(opcode);
+        self.stack_depth = self.stack_depth + 1 - arg_count;
+    }
+
+    fn push(&mut self) -> Result<(), CompileError> {
+        if self.stack_depth == MAX_STACK_DEPTH {
+            return self.error("Expression is too complex");
+        }
+        self.stack_depth += 1;
+        Ok(())
+    }
+
+    fn emit_constant(&mut self, value: f32) -> Result<(), CompileError> {
+        self.push()?;
+        self.bytecode.push(OP_CONST);
+        self.bytecode
+            .extend_from_slice(&value.to_bits().to_le_bytes());
+        Ok(())
+    }
+
+    fn compile_root(&mut self) -> Result<(), CompileError> {
+        let component_count = match &self.token {
+            Token::Identifier(name) if name == "vec2" => 2,
+            Token::Identifier(name) if name == "vec3" => 3,
+            Token::Identifier(name) if name == "vec4" => 4,
+            _ => 1,
+        };
+
+        if component_count == 1 {
+            self.compile_sum()?;
+        } else {
+            self.next_token()?;
+            self.expect_symbol('(')?;
+            for component in 0..component_count {
+                if component > 0 {
+                    self.expect_symbol(',')?;
+                }
+                self.compile_sum()?;
+            }
+            self.expect_symbol(')')?;
+        }
+
+        if self.token != Token::End {
+            return self.error("Unexpected input after the expression");
+        }
+        Ok(())
+    }
+
+    fn compile_sum(&mut self) -> Result<(), CompileError> {
+        self.compile_product()?;
+        loop {
+            let opcode = match self.token {
+                Token::Symbol('+') => OP_ADD,
+                Token::Symbol('-') => OP_SUB,
+                _ => return Ok(()),
+            };
+            self.next_token()?;
+            self.compile_product()?;
+            self.emit(opcode, 2);
+        }
+    }
+
+    fn compile_product(&mut self) -> Result<(), CompileError> {
+        self.compile_unary()?;
+        loop {
+            let opcode = match self.token {
+                Token::Symbol('*') => OP_MUL,
+                Token::Symbol('/') => OP_DIV,
+                Token::Symbol('%') => OP_MOD,
+                _ => return Ok(()),
+            };
+            self.next_token()?;
+            self.compile_unary()?;
+            self.emit(opcode, 2);
+        }
+    }
+
+    fn compile_unary(&mut self) -> Result<(), CompileError> {
+        if self.token == Token::Symbol('-') {
+            self.next_token()?;
+            self.compile_unary()?;
+            self.emit(OP_NEG, 1);
+            Ok(())
+        } else {
+            self.compile_power()
+        }
+    }
+
+    fn compile_power(&mut self) -> Result<(), CompileError> {
+        self.compile_atom()?;
+        if self.token == Token::Symbol('^') {
+            self.next_token()?;
+            self.compile_unary()?;
+            self.emit(OP_POW, 2);
+        }
+        Ok(())
+    }
+
+    fn compile_atom(&mut self) -> Result<(), CompileError> {
+        let token = self.token.clone();
+        let position = self.token_position;
+        self.next_token()?;
+
+        match token {
+            Token::Number(value) => self.emit_constant(value),
+            Token::Symbol('(') => {
+                self.compile_sum()?;
+                self.expect_symbol(')')
+            }
+            Token::Identifier(name) => {
+                if self.token == Token::Symbol('(') {
+                    self.next_token()?;
+                    self.compile_call(&name, position)
+                } else if name == "pi" {
+                    self.emit_constant(std::f32::consts::PI)
+                } else if let Some(&(_, index)) = VARIABLES.iter().find(|var| var.0 == name) {
+                    self.push()?;
+                    self.bytecode.push(OP_VAR);
+                    self.bytecode.push(index);
+                    Ok(())
+                } else {
+                    Err(CompileError {
+                        position,
+                        message: format!("Unknown variable '{}'", name),
+                    })
+                }
+            }
+            _ => Err(CompileError {
+                position,
+                message: "Expected a value".to_string(),
+            }),
+        }
+    }
+
+    fn compile_call(&mut self, name: &str, position: usize) -> Result<(), CompileError> {
+        let mut arg_count = 0;
+        if self.token != Token::Symbol(')') {
+            loop {
+                self.compile_sum()?;
+                arg_count += 1;
+                if self.token != Token::Symbol(',') {
+                    break;
+                }
+                self.next_token()?;
+            }
+        }
+        self.expect_symbol(')')?;
+
+        // Noise takes an optional seed, which defaults to 0
+        if name == "noise" {
+            return match arg_count {
+                1 => {
+                    self.emit_constant(0.)?;
+                    self.emit(OP_NOISE, 2);
+                    Ok(())
+                }
+                2 => {
+                    self.emit(OP_NOISE, 2);
+                    Ok(())
+                }
+                _ => Err(CompileError {
+                    position,
+                    message: "noise takes 1 or 2 arguments".to_string(),
+                }),
+            };
+        }
+
+        match FUNCTIONS.iter().find(|function| function.0 == name) {
+            Some(&(_, opcode, expected_count)) if expected_count == arg_count => {
+                self.emit(opcode, arg_count);
+                Ok(())
+            }
+            Some(&(_, _, expected_count)) => Err(CompileError {
+                position,
+                message: format!("{} takes {} arguments", name, expected_count),
+            }),
+            None if name.starts_with("vec") => Err(CompileError {
+                position,
+                message: "Vectors can only be used for the whole expression".to_string(),
+            }),
+            None => Err(CompileError {
+                position,
+                message: format!("Unknown function '{}'", name),
+            }),
+        }
+    }
+}
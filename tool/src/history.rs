@@ -0,0 +1,377 @@
+use crate::recycle_bin::RecycleBin;
+use crate::serialize::{deserialize_timeline_headless, serialize_timeline};
+use engine::animation::timeline::{Clip, ClipSource, Timeline};
+use ron::de::Deserializer;
+use ron::ser::Serializer;
+use std::collections::{HashMap, VecDeque};
+use std::mem;
+
+/// How many edits can be undone before the oldest ones are forgotten.
+pub const DEFAULT_HISTORY_DEPTH: usize = 200;
+
+/// The undo and redo history of a timeline and its recycle bin.
+///
+/// Rather than each edit knowing how to reverse itself, the history keeps the serialized timeline
+/// as of the last recorded edit, and the text that changed in each edit before that. Edits are
+/// recorded by calling `record` once the user has finished making them, so a whole drag is one
+/// entry however many frames it took.
+pub struct History {
+    max_depth: usize,
+    current: String,
+    undo_edits: VecDeque<TextEdit>,
+    redo_edits: Vec<TextEdit>,
+}
+
+impl History {
+    pub fn new(timeline: &Timeline, recycle_bin: &RecycleBin, max_depth: usize) -> Self {
+        History {
+            max_depth,
+            current: timeline_to_compact_string(timeline, recycle_bin),
+            undo_edits: VecDeque::new(),
+            redo_edits: Vec::new(),
+        }
+    }
+
+    /// Adds an entry for any changes made to the timeline since the last one, and returns true if
+    /// there were any. Making a change clears the redo history.
+    pub fn record(&mut self, timeline: &Timeline, recycle_bin: &RecycleBin) -> bool {
+        let new_state = timeline_to_compact_string(timeline, recycle_bin);
+        if new_state == self.current {
+            return false;
+        }
+
+        self.undo_edits
+            .push_back(TextEdit::between(&self.current, &new_state));
+        while self.undo_edits.len() > self.max_depth {
+            self.undo_edits.pop_front();
+        }
+        self.redo_edits.clear();
+        self.current = new_state;
+        true
+    }
+
+    /// Reverts the timeline to before the last entry, recording any changes that haven't been yet
+    /// so they're what gets undone. Returns true if the timeline was changed, in which case any
+    /// new generator clips are unloaded and need to be instantiated. If the entry can't be loaded,
+    /// the timeline and the undo and redo entries are left as they were.
+    pub fn undo(
+        &mut self,
+        timeline: &mut Timeline,
+        recycle_bin: &mut RecycleBin,
+    ) -> Result<bool, ron::de::Error> {
+        self.record(timeline, recycle_bin);
+        let mut state = self.current.clone();
+        match self.undo_edits.back() {
+            Some(edit) => edit.revert(&mut state),
+            None => return Ok(false),
+        }
+
+        restore(&state, timeline, recycle_bin)?;
+        self.current = state;
+        self.redo_edits.extend(self.undo_edits.pop_back());
+        Ok(true)
+    }
+
+    /// Reapplies the last undone entry. Returns true if the timeline was changed, in which case
+    /// any new generator clips are unloaded and need to be instantiated. If the entry can't be
+    /// loaded, the timeline and the undo and redo entries are left as they were.
+    pub fn redo(
+        &mut self,
+        timeline: &mut Timeline,
+        recycle_bin: &mut RecycleBin,
+    ) -> Result<bool, ron::de::Error> {
+        // Changes since the last undo replace the redo history
+        if self.record(timeline, recycle_bin) {
+            return Ok(false);
+        }
+        let mut state = self.current.clone();
+        match self.redo_edits.last() {
+            Some(edit) => edit.apply(&mut state),
+            None => return Ok(false),
+        }
+
+        restore(&state, timeline, recycle_bin)?;
+        self.current = state;
+        self.undo_edits.extend(self.redo_edits.pop());
+        Ok(true)
+    }
+}
+
+/// Replaces the timeline with a serialized state, or leaves it alone if the state can't be loaded.
+/// Generators and selections are kept for clips that are in both, so undoing doesn't recreate
+/// every generator.
+fn restore(
+    state: &str,
+    timeline: &mut Timeline,
+    recycle_bin: &mut RecycleBin,
+) -> Result<(), ron::de::Error> {
+    let mut deserializer = Deserializer::from_str(state)?;
+    let (mut restored_timeline, restored_bin) = deserialize_timeline_headless(&mut deserializer)?;
+
+    let mut old_clips: HashMap<u32, Clip> = mem::replace(&mut timeline.tracks, Vec::new())
+        .into_iter()
+        .flat_map(|track| track.clips.into_iter())
+        .map(|clip| (clip.id, clip))
+        .collect();
+    for clip in restored_timeline
+        .tracks
+        .iter_mut()
+        .flat_map(|track| track.clips.iter_mut())
+    {
+        let old_clip = match old_clips.remove(&clip.id) {
+            Some(old_clip) => old_clip,
+            None => continue,
+        };
+        clip.is_selected = old_clip.is_selected;
+
+        let is_same_schema = clip.schema.name == old_clip.schema.name;
+        if let ClipSource::Unloaded = clip.source {
+            if is_same_schema && old_clip.source.is_generator() {
+                clip.source = old_clip.source;
+            }
+        }
+    }
+
+    *timeline = restored_timeline;
+    *recycle_bin = restored_bin;
+    Ok(())
+}
+
+/// The text that differs between two serialized timelines. Edits usually only touch a small part
+/// of the timeline, so only the range between the first and last changed characters is kept.
+struct TextEdit {
+    start: usize,
+    before: String,
+    after: String,
+}
+
+impl TextEdit {
+    fn between(before: &str, after: &str) -> Self {
+        let mut prefix_len = before
+            .bytes()
+            .zip(after.bytes())
+            .take_while(|(before_byte, after_byte)| before_byte == after_byte)
+            .count();
+        while !before.is_char_boundary(prefix_len) {
+            prefix_len -= 1;
+        }
+
+        // The suffix can't overlap the prefix in either string
+        let max_suffix_len = before.len().min(after.len()) - prefix_len;
+        let mut suffix_len = before
+            .bytes()
+            .rev()
+            .zip(after.bytes().rev())
+            .take(max_suffix_len)
+            .take_while(|(before_byte, after_byte)| before_byte == after_byte)
+            .count();
+        while !before.is_char_boundary(before.len() - suffix_len) {
+            suffix_len -= 1;
+        }
+
+        TextEdit {
+            start: prefix_len,
+            before: before[prefix_len..before.len() - suffix_len].to_string(),
+            after: after[prefix_len..after.len() - suffix_len].to_string(),
+        }
+    }
+
+    fn apply(&self, text: &mut String) {
+        text.replace_range(self.start..self.start + self.before.len(), &self.after);
+    }
+
+    fn revert(&self, text: &mut String) {
+        text.replace_range(self.start..self.start + self.after.len(), &self.before);
+    }
+}
+
+fn timeline_to_compact_string(timeline: &Timeline, recycle_bin: &RecycleBin) -> String {
+    let mut serializer = Serializer::new(None, false);
+    serialize_timeline(timeline, recycle_bin, &mut serializer).unwrap();
+    serializer.into_output_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use engine::animation::tempo_map::TempoMap;
+    use engine::animation::timeline::Track;
+    use engine::generator::GENERATOR_SCHEMAS;
+
+    /// Checks the edit turns `before` into `after` and back again.
+    fn check_edit(before: &str, after: &str) -> TextEdit {
+        let edit = TextEdit::between(before, after);
+        let mut text = before.to_string();
+        edit.apply(&mut text);
+        assert_eq!(text, after);
+        edit.revert(&mut text);
+        assert_eq!(text, before);
+        edit
+    }
+
+    #[test]
+    fn text_edit_keeps_only_changed_text() {
+        let edit = check_edit("offset: 10, duration: 20", "offset: 15, duration: 20");
+        assert_eq!(edit.start, 9);
+        assert_eq!(edit.before, "0");
+        assert_eq!(edit.after, "5");
+
+        let edit = check_edit("same", "same");
+        assert_eq!(edit.before, "");
+        assert_eq!(edit.after, "");
+
+        check_edit("abcdef", "abcXYZdef");
+        check_edit("abcXYZdef", "abcdef");
+        check_edit("aaa", "aaaa");
+        check_edit("", "abc");
+        check_edit("abc", "");
+    }
+
+    #[test]
+    fn text_edit_splits_on_char_boundaries() {
+        // 'é' and 'è' share their first byte, so the prefix has to back off to the start of 'é'
+        let edit = check_edit("name: \"Café\"", "name: \"Cafè\"");
+        assert_eq!(edit.start, 10);
+        assert_eq!(edit.before, "é");
+        assert_eq!(edit.after, "è");
+
+        // 'ä' and 'Ĥ' share their last byte, so the suffix has to back off to the end of 'ä'
+        let edit = check_edit("xäy", "xĤy");
+        assert_eq!(edit.before, "ä");
+        assert_eq!(edit.after, "Ĥ");
+
+        check_edit("é", "è");
+        check_edit("ä", "Ĥ");
+        check_edit("日本", "日本語");
+        check_edit("語日本", "日本");
+        check_edit("日本", "");
+    }
+
+    fn clear_solid_clip(id: u32, name: &str) -> Clip {
+        let schema = GENERATOR_SCHEMAS
+            .iter()
+            .find(|schema| schema.name == "Clear Solid")
+            .unwrap();
+        Clip {
+            id,
+            name: name.to_string(),
+            schema,
+            source: ClipSource::Unloaded,
+            offset_frames: 0,
+            duration_frames: 60,
+            anchor: None,
+            property_groups: schema
+                .groups
+                .iter()
+                .map(|group| group.instantiate())
+                .collect(),
+            is_selected: false,
+        }
+    }
+
+    /// The parts of the timeline the tests change, to compare before and after undoing.
+    fn summarize(timeline: &Timeline) -> (Vec<Vec<(u32, String, u32)>>, TempoMap) {
+        let tracks = timeline
+            .tracks
+            .iter()
+            .map(|track| {
+                track
+                    .clips
+                    .iter()
+                    .map(|clip| (clip.id, clip.name.clone(), clip.offset_frames))
+                    .collect()
+            })
+            .collect();
+        (tracks, timeline.tempo_map.clone())
+    }
+
+    #[test]
+    fn undo_and_redo_restore_recorded_timelines() {
+        let mut timeline = Timeline {
+            tracks: vec![Track {
+                clips: vec![clear_solid_clip(1, "Café")],
+            }],
+            ..Timeline::default()
+        };
+        let mut recycle_bin = RecycleBin::default();
+        let mut history = History::new(&timeline, &recycle_bin, DEFAULT_HISTORY_DEPTH);
+        assert!(!history.record(&timeline, &recycle_bin));
+        let first = summarize(&timeline);
+
+        timeline.tracks[0].clips[0].name = "Cafè".to_string();
+        timeline.tracks[0].clips[0].offset_frames = 30;
+        timeline.tracks[0].clips[0].is_selected = true;
+        timeline.tempo_map.changes[0].beats_per_minute = 140.;
+        assert!(history.record(&timeline, &recycle_bin));
+        let second = summarize(&timeline);
+
+        // Changes that haven't been recorded yet are recorded by undoing, so they're what's undone
+        timeline.tracks.push(Track {
+            clips: vec![clear_solid_clip(2, "日本")],
+        });
+        let third = summarize(&timeline);
+
+        assert!(history.undo(&mut timeline, &mut recycle_bin).unwrap());
+        assert_eq!(summarize(&timeline), second);
+        assert!(timeline.tracks[0].clips[0].is_selected);
+        assert!(history.undo(&mut timeline, &mut recycle_bin).unwrap());
+        assert_eq!(summarize(&timeline), first);
+        assert!(!history.undo(&mut timeline, &mut recycle_bin).unwrap());
+
+        assert!(history.redo(&mut timeline, &mut recycle_bin).unwrap());
+        assert_eq!(summarize(&timeline), second);
+        assert!(history.redo(&mut timeline, &mut recycle_bin).unwrap());
+        assert_eq!(summarize(&timeline), third);
+        assert!(!history.redo(&mut timeline, &mut recycle_bin).unwrap());
+
+        // Making a change after undoing replaces the redo history
+        assert!(history.undo(&mut timeline, &mut recycle_bin).unwrap());
+        timeline.tracks[0].clips[0].offset_frames = 45;
+        assert!(!history.redo(&mut timeline, &mut recycle_bin).unwrap());
+        assert_eq!(timeline.tracks[0].clips[0].offset_frames, 45);
+        assert_eq!(timeline.tracks.len(), 1);
+        assert!(history.undo(&mut timeline, &mut recycle_bin).unwrap());
+        assert_eq!(summarize(&timeline), second);
+    }
+
+    #[test]
+    fn failed_undo_and_redo_leave_the_history_alone() {
+        let mut timeline = Timeline {
+            tracks: vec![Track {
+                clips: vec![clear_solid_clip(1, "Clip")],
+            }],
+            ..Timeline::default()
+        };
+        let mut recycle_bin = RecycleBin::default();
+        let mut history = History::new(&timeline, &recycle_bin, DEFAULT_HISTORY_DEPTH);
+        timeline.tracks[0].clips[0].offset_frames = 30;
+        assert!(history.record(&timeline, &recycle_bin));
+        let current = history.current.clone();
+
+        // An entry whose text doesn't load, e.g. because it was recorded by a broken serializer
+        let broken_edit = || TextEdit {
+            start: 0,
+            before: "(".to_string(),
+            after: String::new(),
+        };
+        history.undo_edits.push_back(broken_edit());
+        assert!(history.undo(&mut timeline, &mut recycle_bin).is_err());
+        assert_eq!(history.current, current);
+        assert_eq!(history.undo_edits.len(), 2);
+        assert!(history.redo_edits.is_empty());
+        assert_eq!(timeline.tracks[0].clips[0].offset_frames, 30);
+
+        history.undo_edits.pop_back();
+        history.redo_edits.push(broken_edit());
+        assert!(history.redo(&mut timeline, &mut recycle_bin).is_err());
+        assert_eq!(history.current, current);
+        assert_eq!(history.undo_edits.len(), 1);
+        assert_eq!(history.redo_edits.len(), 1);
+        assert_eq!(timeline.tracks[0].clips[0].offset_frames, 30);
+
+        // The entries before the broken one still work
+        history.redo_edits.clear();
+        assert!(history.undo(&mut timeline, &mut recycle_bin).unwrap());
+        assert_eq!(timeline.tracks[0].clips[0].offset_frames, 0);
+    }
+}
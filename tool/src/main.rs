@@ -1,17 +1,27 @@
-#![windows_subsystem = "windows"]
+#![cfg_attr(feature = "gui", windows_subsystem = "windows")]
+// Without the editor, most of the editing code is only used by tests
+#![cfg_attr(not(feature = "gui"), allow(dead_code))]
 
+#[cfg(feature = "gui")]
 use chrono::Utc;
+#[cfg(feature = "gui")]
 use imgui_sys::{
-    igBegin, igDockSpace, igEnd, igGetIDStr, igGetMainViewport, igPopStyleVar, igPushStyleVarFloat,
-    igPushStyleVarVec2, igSetNextWindowPos, igSetNextWindowSize, igSetNextWindowViewport,
-    ImGuiCond, ImGuiDockNodeFlags, ImGuiStyleVar, ImGuiWindowFlags, ImVec2,
+    igBegin, igDockSpace, igEnd, igGetIDStr, igGetIO, igGetMainViewport, igIsAnyItemActive,
+    igIsKeyPressed, igPopStyleVar, igPushStyleVarFloat, igPushStyleVarVec2, igSetNextWindowPos,
+    igSetNextWindowSize, igSetNextWindowViewport, ImGuiCond, ImGuiDockNodeFlags, ImGuiStyleVar,
+    ImGuiWindowFlags, ImVec2,
 };
 use ron::de::Deserializer;
+#[cfg(feature = "gui")]
 use ron::ser::{PrettyConfig, Serializer};
 use std::env::current_exe;
+#[cfg(feature = "gui")]
 use std::time::Instant;
+#[cfg(feature = "gui")]
 use std::{mem, ptr};
+#[cfg(feature = "gui")]
 use winapi::um::libloaderapi::GetModuleHandleA;
+#[cfg(feature = "gui")]
 use winapi::um::winuser::{
     CreateWindowExA, RegisterClassA, CS_OWNDC, WNDCLASSA, WS_EX_OVERLAPPEDWINDOW,
     WS_OVERLAPPEDWINDOW, WS_VISIBLE,
@@ -21,35 +31,99 @@ use winapi::um::winuser::{
 mod cstr;
 
 mod audio;
+mod baking;
+mod clipboard;
+mod curve_fitting;
 mod editor_clip_map;
 mod editor_state;
+#[cfg(feature = "gui")]
 mod exporter;
+mod expression_compiler;
+mod history;
+#[cfg(feature = "gui")]
 mod imgui;
+#[cfg(feature = "gui")]
 mod imgui_window;
+mod migration;
+#[cfg(feature = "gui")]
 mod panels;
-//mod recycle_bin;
+mod recycle_bin;
 //mod mesh_list;
 mod serialize;
 mod timeline_interactions;
+mod validation;
 
+#[cfg(feature = "gui")]
 use crate::editor_state::EditorState;
+#[cfg(feature = "gui")]
+use crate::history::{History, DEFAULT_HISTORY_DEPTH};
+#[cfg(feature = "gui")]
 use crate::imgui_window::ImGuiWindow;
+#[cfg(feature = "gui")]
+use crate::recycle_bin::RecycleBin;
+use crate::serialize::deserialize_timeline_headless;
+#[cfg(feature = "gui")]
 use crate::serialize::{deserialize_timeline, serialize_timeline};
+use crate::validation::validate_timeline;
+#[cfg(feature = "gui")]
+use engine::animation::tempo_map::TempoMap;
+#[cfg(feature = "gui")]
 use engine::animation::timeline::{Timeline, Track};
+#[cfg(feature = "gui")]
 use engine::creation_context::CreationContext;
+#[cfg(feature = "gui")]
 use engine::frame_context::CommonData;
+#[cfg(feature = "gui")]
 use engine::gbuffer::GBuffer;
+#[cfg(feature = "gui")]
 use engine::renderer::RendererCollection;
+#[cfg(feature = "gui")]
 use engine::resources::perf_table::PerfTable;
+#[cfg(feature = "gui")]
 use engine::resources::shader_manager::ShaderManager;
+#[cfg(feature = "gui")]
 use engine::viewport::Viewport;
+#[cfg(feature = "gui")]
 use path_abs::PathDir;
-use std::fs;
 use std::path::Path;
+use std::{env, fs, process};
 
 fn main() {
     engine::math::random::seed_rand(0x1337b012);
 
+    let mut project_path = current_exe().unwrap(); // "re19/target/debug/tool.exe"
+    project_path.pop(); // "re19/target/debug/"
+    project_path.pop(); // "re19/target/"
+    project_path.pop(); // "re19/"
+    project_path.push("project"); // "re19/project/
+    let head_save_path = project_path.join("saves").join("000000-000000-head.save");
+
+    // `tool --validate [save]` checks a save (the head save by default) without opening the
+    // editor, exiting with 1 if there are any problems
+    if env::args().nth(1).as_ref().map(String::as_str) == Some("--validate") {
+        let save_path = env::args()
+            .nth(2)
+            .map_or(head_save_path, |path| path.into());
+        process::exit(validate_save(&save_path));
+    }
+
+    #[cfg(feature = "gui")]
+    run_editor(&project_path);
+    #[cfg(not(feature = "gui"))]
+    {
+        eprintln!("Only `tool --validate [save]` is available without the gui feature");
+        process::exit(2);
+    }
+}
+
+/// Opens the editor window on the head save, saving and exporting the project when it's closed.
+#[cfg(feature = "gui")]
+fn run_editor(project_path: &Path) {
+    let shader_path = project_path.join("shaders");
+    let saves_path = project_path.join("saves");
+    let bakes_path = project_path.join("bakes");
+    let head_save_path = saves_path.join("000000-000000-head.save");
+
     let class_name = cstr!("You lost the game");
     let inst = unsafe { GetModuleHandleA(ptr::null()) };
 
@@ -83,14 +157,6 @@ fn main() {
         )
     };
 
-    let mut project_path = current_exe().unwrap(); // "re19/target/debug/tool.exe"
-    project_path.pop(); // "re19/target/debug/"
-    project_path.pop(); // "re19/target/"
-    project_path.pop(); // "re19/"
-    project_path.push("project"); // "re19/project/
-    let shader_path = project_path.join("shaders");
-    let saves_path = project_path.join("saves");
-
     let mut window = ImGuiWindow::new(hwnd);
 
     // note: should match viewport in player
@@ -113,8 +179,7 @@ fn main() {
     let mut perf_table = PerfTable::new(creation_context.device, creation_context.devcon);
 
     // Try to load the timeline from a file
-    let head_save_path = saves_path.join("000000-000000-head.save");
-    let mut timeline = match fs::read_to_string(&head_save_path) {
+    let (mut timeline, mut recycle_bin) = match fs::read_to_string(&head_save_path) {
         Ok(file_content) => {
             // save a backup in case deserialization causes problems
             save_backup(&saves_path, &head_save_path, &file_content);
@@ -122,9 +187,13 @@ fn main() {
             let mut deserializer = Deserializer::from_str(&file_content).unwrap();
             deserialize_timeline(&mut deserializer, &mut creation_context).unwrap()
         }
-        Err(_) => Timeline {
-            tracks: vec![Track::default()],
-        },
+        Err(_) => (
+            Timeline {
+                tracks: vec![Track::default()],
+                tempo_map: TempoMap::new(112., 4),
+            },
+            RecycleBin::default(),
+        ),
     };
 
     /*let mut mesh_list = mesh_list::MeshList {
@@ -141,15 +210,25 @@ fn main() {
     let mut audio_player = audio::BassPlayer::new(audio_path.to_str().unwrap()).unwrap();
 
     // note: should match framerate in player
-    let mut editor_state = EditorState::new(60., 112., 4, &mut audio_player);
+    let mut editor_state = EditorState::new(60., &mut audio_player);
 
-    // Set the editor's next ID to the next highest one
+    // Set the editor's next ID to the next highest one, including clips in the recycle bin so
+    // they can be restored with the same ID
+    let bin_clips = recycle_bin
+        .entries
+        .iter()
+        .flat_map(|entry| entry.clips.iter())
+        .map(|recycled| &recycled.clip);
     editor_state.next_clip_id = timeline
         .tracks
         .iter()
         .flat_map(|track| track.clips.iter())
+        .chain(bin_clips)
         .fold(0, |next_id, clip| next_id.max(clip.id + 1));
 
+    let mut history = History::new(&timeline, &recycle_bin, DEFAULT_HISTORY_DEPTH);
+    let mut was_input_down = false;
+
     let mut last_save_time = Instant::now();
     let mut last_frame_time = Instant::now();
 
@@ -203,9 +282,45 @@ fn main() {
         //    and builds any other info needed by the property editor)
         //  - Render the current clips
 
+        // Ctrl+Z undoes, and Ctrl+Y or Ctrl+Shift+Z redoes, unless a text box is using the keys
+        let io = unsafe { &*igGetIO() };
+        if io.key_ctrl && !io.want_text_input {
+            let is_z_pressed = unsafe { igIsKeyPressed('Z' as i32, true) };
+            let is_y_pressed = unsafe { igIsKeyPressed('Y' as i32, true) };
+            let did_restore = if is_z_pressed && !io.key_shift {
+                history.undo(&mut timeline, &mut recycle_bin)
+            } else if is_y_pressed || (is_z_pressed && io.key_shift) {
+                history.redo(&mut timeline, &mut recycle_bin)
+            } else {
+                Ok(false)
+            };
+            match did_restore {
+                Ok(true) => timeline.instantiate_generators(&mut CreationContext {
+                    device: window.resources.device(),
+                    devcon: window.resources.devcon(),
+                    shader_manager: &mut shader_manager,
+                    viewport: project_viewport,
+                }),
+                Ok(false) => {}
+                Err(err) => eprintln!("Couldn't load the timeline from the history: {}", err),
+            }
+        }
+
         let cpu_ui_query = perf_table.start_cpu_str("ui (cpu)");
         panels::draw_profiler(&mut perf_table);
         panels::draw_timeline(
+            &mut timeline,
+            &mut recycle_bin,
+            &mut editor_state,
+            &mut CreationContext {
+                device: window.resources.device(),
+                devcon: window.resources.devcon(),
+                shader_manager: &mut shader_manager,
+                viewport: project_viewport,
+            },
+        );
+        panels::draw_recycle_bin(
+            &mut recycle_bin,
             &mut timeline,
             &mut editor_state,
             &mut CreationContext {
@@ -220,8 +335,25 @@ fn main() {
             editor_clip_map::EditorClipMap::from_timeline(&timeline, editor_state.current_frame());
         perf_table.end(clip_map_query);
         panels::draw_motion_editor(&mut timeline, &mut editor_state, &clip_map);
+        panels::draw_validation(&mut timeline, &mut editor_state);
+        baking::handle_bake_request(&mut timeline, &mut editor_state, &bakes_path);
         let animation_query = perf_table.start_cpu_str("animation");
-        engine::animation::coallesce::coallesce_animations(&timeline, &mut clip_map);
+        // Clips being edited by a drag are previewed before the edit is recorded, so the state
+        // their animations cached from earlier frames has to be forgotten now
+        for edited_clip in editor_state.edited_clips.drain(..) {
+            timeline.clear_clip_animation_caches(edited_clip);
+        }
+        engine::animation::coallesce::coallesce_animations(
+            &timeline,
+            &mut clip_map,
+            editor_state.time_base(&timeline.tempo_map),
+        );
+        engine::animation::driver::resolve_drivers(&timeline, &mut clip_map);
+        engine::animation::constraint::apply_constraints(
+            &timeline,
+            &mut clip_map,
+            editor_state.time_base(&timeline.tempo_map),
+        );
         perf_table.end(animation_query);
         panels::draw_property_editor(&mut timeline, &clip_map, &mut editor_state);
         perf_table.end(cpu_ui_query);
@@ -244,6 +376,20 @@ fn main() {
         perf_table.end(frame_query);
         perf_table.end(cpu_frame_query);
 
+        // Edits are recorded once every mouse button and key has been released, so a drag or a
+        // held key is undone in one step
+        let io = unsafe { &*igGetIO() };
+        let is_input_down =
+            io.mouse_down.iter().any(|&down| down) || io.keys_down.iter().any(|&down| down);
+        if was_input_down
+            && !is_input_down
+            && !unsafe { igIsAnyItemActive() }
+            && history.record(&timeline, &recycle_bin)
+        {
+            timeline.clear_animation_caches();
+        }
+        was_input_down = is_input_down;
+
         let gpu_ui_query = perf_table.start_gpu_str("ui (gpu)");
         window.end_frame();
         perf_table.end(gpu_ui_query);
@@ -261,20 +407,54 @@ fn main() {
         // delete the oldest one
         if last_save_time.elapsed().as_secs() >= 5 * 60 {
             last_save_time = Instant::now();
-            save_backup(&saves_path, &head_save_path, &timeline_to_string(&timeline));
+            save_backup(
+                &saves_path,
+                &head_save_path,
+                &timeline_to_string(&timeline, &recycle_bin),
+            );
         }
     }
 
     // Save the timeline to disk
-    fs::write(&head_save_path, timeline_to_string(&timeline)).unwrap();
+    fs::write(&head_save_path, timeline_to_string(&timeline, &recycle_bin)).unwrap();
 
-    // Export the data and save that to disk
+    // Export the data and save that to disk, warning about anything that won't export correctly
+    validate_timeline(&timeline).print();
     let mut export = Vec::new();
     exporter::export_shaders(&shader_manager, &mut export);
     exporter::export_timeline(&timeline, &mut export);
     fs::write(project_path.join("data.blob"), &export).unwrap();
 }
 
+/// Prints any problems in a save, returning the exit code for the `--validate` command.
+fn validate_save(save_path: &Path) -> i32 {
+    let file_content = match fs::read_to_string(save_path) {
+        Ok(file_content) => file_content,
+        Err(err) => {
+            eprintln!("Couldn't read {}: {}", save_path.display(), err);
+            return 2;
+        }
+    };
+    let loaded = Deserializer::from_str(&file_content)
+        .and_then(|mut deserializer| deserialize_timeline_headless(&mut deserializer));
+    let (timeline, _) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("Couldn't load {}: {}", save_path.display(), err);
+            return 2;
+        }
+    };
+
+    let report = validate_timeline(&timeline);
+    report.print();
+    if report.is_empty() {
+        0
+    } else {
+        1
+    }
+}
+
+#[cfg(feature = "gui")]
 fn save_backup(saves_path: &Path, head_save_path: &Path, timeline_str: &str) {
     let current_date_time = Utc::now();
     let save_file_name = saves_path.join(
@@ -315,7 +495,8 @@ fn save_backup(saves_path: &Path, head_save_path: &Path, timeline_str: &str) {
     }
 }
 
-fn timeline_to_string(timeline: &Timeline) -> String {
+#[cfg(feature = "gui")]
+fn timeline_to_string(timeline: &Timeline, recycle_bin: &RecycleBin) -> String {
     let mut serializer = Serializer::new(
         Some(PrettyConfig {
             depth_limit: 20,
@@ -326,6 +507,6 @@ fn timeline_to_string(timeline: &Timeline) -> String {
         }),
         false,
     );
-    serialize_timeline(&timeline, &mut serializer).unwrap();
+    serialize_timeline(&timeline, recycle_bin, &mut serializer).unwrap();
     serializer.into_output_string()
 }
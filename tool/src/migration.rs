@@ -0,0 +1,183 @@
+use engine::animation::property::{PropertyType, PropertyValue};
+
+/// The version of the save format written by this build of the tool. Saves from before versions
+/// were recorded are version 0.
+///
+/// To rename, move, convert or remove a schema or property without losing work in existing saves,
+/// increment this and add a migration with the new version to `MIGRATIONS`.
+pub const SAVE_VERSION: u32 = 2;
+
+/// The migrations applied when loading old saves, in the order they were added. Each one applies
+/// to saves from before its version, and names things as they were after the migrations before it.
+pub static MIGRATIONS: &[SchemaMigration] = &[
+    // Flags and counts were floats before there were integer and boolean properties
+    SchemaMigration {
+        version: 2,
+        migration: Migration::ConvertProperty {
+            schema: "Launch Scene",
+            group: "rays",
+            property: "steps",
+            to: PropertyType::Int,
+        },
+    },
+    SchemaMigration {
+        version: 2,
+        migration: Migration::ConvertProperty {
+            schema: "Launch Scene",
+            group: "rocket",
+            property: "enabled",
+            to: PropertyType::Bool,
+        },
+    },
+    SchemaMigration {
+        version: 2,
+        migration: Migration::ConvertProperty {
+            schema: "Launch Scene",
+            group: "clouds",
+            property: "enabled",
+            to: PropertyType::Bool,
+        },
+    },
+    SchemaMigration {
+        version: 2,
+        migration: Migration::ConvertProperty {
+            schema: "Launch Scene",
+            group: "clouds",
+            property: "gen once <-> live",
+            to: PropertyType::Bool,
+        },
+    },
+    SchemaMigration {
+        version: 2,
+        migration: Migration::RenameProperty {
+            schema: "Launch Scene",
+            group: "clouds",
+            from: "gen once <-> live",
+            to: "live",
+        },
+    },
+    SchemaMigration {
+        version: 2,
+        migration: Migration::ConvertProperty {
+            schema: "Rocket Scene",
+            group: "rocket",
+            property: "enabled",
+            to: PropertyType::Bool,
+        },
+    },
+];
+
+pub struct SchemaMigration {
+    pub version: u32,
+    pub migration: Migration,
+}
+
+// Variants are only constructed once there's something to migrate
+#[allow(dead_code)]
+pub enum Migration {
+    /// Changes the schema of every clip using `from` to `to`.
+    RenameSchema {
+        from: &'static str,
+        to: &'static str,
+    },
+    RenameGroup {
+        schema: &'static str,
+        from: &'static str,
+        to: &'static str,
+    },
+    RenameProperty {
+        schema: &'static str,
+        group: &'static str,
+        from: &'static str,
+        to: &'static str,
+    },
+    /// Moves a property to another group, keeping its name.
+    MoveProperty {
+        schema: &'static str,
+        property: &'static str,
+        from_group: &'static str,
+        to_group: &'static str,
+    },
+    /// Converts the values and keyframes of a property to a new type with `convert_value`.
+    /// Animations that can't be converted, like paths and constraints, are removed.
+    ConvertProperty {
+        schema: &'static str,
+        group: &'static str,
+        property: &'static str,
+        to: PropertyType,
+    },
+    /// Removes a property along with its animations, and the drivers and constraints following
+    /// it.
+    RemoveProperty {
+        schema: &'static str,
+        group: &'static str,
+        property: &'static str,
+    },
+}
+
+/// What loading an old save changed, so renamed or removed properties aren't lost silently.
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub migrated: Vec<String>,
+    pub dropped: Vec<String>,
+}
+
+impl MigrationReport {
+    pub fn new(from_version: u32) -> Self {
+        MigrationReport {
+            from_version,
+            migrated: Vec::new(),
+            dropped: Vec::new(),
+        }
+    }
+
+    pub fn print(&self) {
+        if self.migrated.is_empty() && self.dropped.is_empty() {
+            return;
+        }
+
+        eprintln!(
+            "Migrated save from version {} to version {}:",
+            self.from_version, SAVE_VERSION
+        );
+        for migrated in &self.migrated {
+            eprintln!("  {}", migrated);
+        }
+        for dropped in &self.dropped {
+            eprintln!("  Dropped: {}", dropped);
+        }
+    }
+}
+
+/// Converts a value to another type through its fields. A single field is copied to every field
+/// of the new type, so a `Float` scale becomes a uniform `Vec2` scale. Otherwise extra fields are
+/// dropped and missing ones are taken from the new type's default value, so an `RgbColor` becomes
+/// an opaque `RgbaColor`. Returns `None` for clip references and gradients, which can only be
+/// converted to themselves.
+pub fn convert_value(value: PropertyValue, to: PropertyType) -> Option<PropertyValue> {
+    let from = value.get_type();
+    if from == to {
+        return Some(value);
+    }
+    match (from, to) {
+        (PropertyType::ClipReference, _)
+        | (PropertyType::Gradient, _)
+        | (_, PropertyType::ClipReference)
+        | (_, PropertyType::Gradient) => return None,
+        _ => {}
+    }
+
+    let fields: Vec<_> = value.fields().collect();
+    let from_count = fields.len();
+    let to_count = to.num_fields();
+    let converted_fields: Vec<_> = if from_count == 1 {
+        vec![fields[0]; to_count]
+    } else {
+        fields
+            .into_iter()
+            .chain(to.default_value().fields().skip(from_count))
+            .take(to_count)
+            .collect()
+    };
+    PropertyValue::from_fields(to, &mut converted_fields.into_iter())
+}
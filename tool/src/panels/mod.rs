@@ -1,27 +1,32 @@
+use engine::animation::timeline::TimeBase;
+
 mod motion_editor;
 mod preview;
 mod profiler;
 mod property_editor;
-//mod recycle_bin;
+mod recycle_bin;
 //mod mesh_editor;
 mod time_bar;
 mod timeline;
+mod validation;
 
 pub use self::motion_editor::draw_motion_editor;
 pub use self::preview::draw_preview;
 pub use self::profiler::draw_profiler;
 pub use self::property_editor::draw_property_editor;
-//pub use self::recycle_bin::draw_recycle_bin;
+pub use self::recycle_bin::draw_recycle_bin;
 //pub use self::mesh_editor::draw_mesh_editor;
 pub use self::time_bar::{draw_time_bar, SCRUBBER_HEIGHT};
 pub use self::timeline::draw_timeline;
+pub use self::validation::draw_validation;
 
 const ZOOM_CURVE_AMOUNT: f32 = 1000.;
 const ZOOM_MIN_FACTOR: f32 = 1000. / 600.;
 const ZOOM_MAX_FACTOR: f32 = 1000.;
 
-pub fn get_fpb(fps: f32, bpm: f32) -> f32 {
-    (fps * 60.) / bpm
+/// Gets the number of frames in the first beat, which zoom levels are relative to.
+pub fn get_fpb(time_base: TimeBase) -> f32 {
+    time_base.beats_to_frame(1.)
 }
 
 pub fn zoom_to_time_scale(zoom: f32, fpb: f32) -> f32 {
@@ -1,29 +1,46 @@
-use super::{draw_time_bar, get_fpb, SCRUBBER_HEIGHT};
+use super::{draw_time_bar, SCRUBBER_HEIGHT};
+use crate::baking::{BakeOutput, BakeRequest};
+use crate::clipboard::{copy_property_keyframes, paste_copied_keyframes};
 use crate::cstr;
+use crate::curve_fitting::reduce_keyframes;
 use crate::editor_state::EditorState;
+use crate::expression_compiler::compile_expression;
 use crate::imgui::DrawList;
-use crate::timeline_interactions::{delete_keyframe, remove_clip};
+use crate::timeline_interactions::{
+    convert_to_path, delete_keyframe, frame_to_anchor, remove_clip, update_field_anchors,
+};
 use engine::animation::animation_clip::{
-    AnimatedPropertyField, AnimatedPropertyTarget, AnimationClip, CurveInterpolation, CurveSegment,
+    AnimatedPropertyField, AnimatedPropertyTarget, AnimationClip, CurveInterpolation, Extrapolation,
 };
-use engine::animation::clip::ActiveClipMap;
+use engine::animation::clip::{ActiveClipMap, ClipReference};
+use engine::animation::constraint::{Constraint, ConstraintTarget};
 use engine::animation::cubic_bezier::CubicBezier;
-use engine::animation::property::PropertyValue;
+use engine::animation::easing::Easing;
+use engine::animation::expression::Expression;
+use engine::animation::modifier::Modifier;
+use engine::animation::property::{PropertyType, PropertyValue};
 use engine::animation::schema::GeneratorSchema;
-use engine::animation::timeline::{ClipSource, Timeline};
-use engine::math::Vector2;
+use engine::animation::spline_path::{SplineKind, SplinePath};
+use engine::animation::timeline::{ClipSource, TimeBase, Timeline};
+use engine::binding::PropertyBinding;
+use engine::math::{ColorSpace, Vector2, Vector3};
 use imgui_sys::{
-    igArrowButton, igBegin, igBeginChild, igBeginPopupContextItem, igButton,
-    igCalcTextSize_nonUDT2, igEnd, igEndChild, igEndPopup, igGetContentRegionAvail_nonUDT2,
-    igGetCursorPosX, igGetCursorPosY, igGetCursorPos_nonUDT2, igGetCursorScreenPos_nonUDT2,
-    igGetIO, igGetMouseDragDelta_nonUDT2, igGetMousePos_nonUDT2, igGetWindowPos_nonUDT2,
-    igInvisibleButton, igIsItemActive, igIsItemClicked, igIsItemHovered, igIsMouseDragging,
-    igIsRectVisibleVec2, igIsWindowHovered, igMenuItemBool, igPopClipRect, igPopID, igPopStyleVar,
-    igPushClipRect, igPushIDInt, igPushStyleVarVec2, igResetMouseDragDelta, igSameLine,
-    igSeparator, igSetCursorPos, igSetCursorPosX, igSetCursorPosY, igSetCursorScreenPos,
-    igSetNextWindowPos, igSetTooltip, ImGuiCond, ImGuiDir, ImGuiHoveredFlags, ImGuiStyleVar,
+    igArrowButton, igBegin, igBeginChild, igBeginMenu, igBeginPopup, igBeginPopupContextItem,
+    igButton, igCalcTextSize_nonUDT2, igDragFloat, igDragFloat3, igDragInt, igEnd, igEndChild,
+    igEndMenu, igEndPopup, igGetContentRegionAvail_nonUDT2, igGetCursorPosX, igGetCursorPosY,
+    igGetCursorPos_nonUDT2, igGetCursorScreenPos_nonUDT2, igGetIO, igGetMouseDragDelta_nonUDT2,
+    igGetMousePos_nonUDT2, igGetWindowPos_nonUDT2, igInputText, igInvisibleButton,
+    igIsAnyItemActive, igIsItemActive, igIsItemClicked, igIsItemHovered, igIsMouseDragging,
+    igIsRectVisibleVec2, igIsWindowFocused, igIsWindowHovered, igMenuItemBool, igOpenPopup,
+    igPopClipRect, igPopID, igPopStyleVar, igPushClipRect, igPushIDInt, igPushStyleVarVec2,
+    igResetMouseDragDelta, igSameLine, igSeparator, igSetCursorPos, igSetCursorPosX,
+    igSetCursorPosY, igSetCursorScreenPos, igSetNextWindowPos, igSetTooltip, igText, ImGuiCond,
+    ImGuiDir, ImGuiFocusedFlags, ImGuiHoveredFlags, ImGuiInputTextFlags, ImGuiStyleVar,
     ImGuiWindowFlags, ImVec2, ImVec4,
 };
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::c_char;
 use std::{f32, iter, ptr, slice, u32};
 
 const KEYFRAME_BAR_VIRTUAL_HEIGHT: f32 = 40.;
@@ -50,7 +67,7 @@ pub fn draw_motion_editor(
     editor_state: &mut EditorState,
     clip_map: &ActiveClipMap,
 ) {
-    let fpb = get_fpb(editor_state.fps, editor_state.bpm);
+    let time_base = editor_state.time_base(&timeline.tempo_map);
 
     unsafe {
         igPushStyleVarVec2(ImGuiStyleVar::WindowPadding, ImVec2::new(0., 0.));
@@ -67,6 +84,23 @@ pub fn draw_motion_editor(
     };
 
     if show_window {
+        // Constraints can follow any generator clip with a position, so find those before the
+        // animation clips are borrowed for editing
+        let target_clips: Vec<_> = timeline
+            .tracks
+            .iter()
+            .flat_map(|track| track.clips.iter())
+            .filter(|clip| match &clip.source {
+                ClipSource::Animation(_) => false,
+                _ => first_vec3_binding(clip.schema).is_some(),
+            })
+            .map(|clip| TargetClip {
+                reference: ClipReference::new(clip.id),
+                name: clip.name.clone(),
+                schema: clip.schema,
+            })
+            .collect();
+
         // Build a list of animation clips to display
         // If there aren't any selected, we just show all of them
         let are_selected_animations = timeline
@@ -92,6 +126,7 @@ pub fn draw_motion_editor(
             .filter(|(_, clip)| !are_selected_animations || clip.is_selected)
             .filter_map(|(clip_start_time, clip)| match &mut clip.source {
                 ClipSource::Animation(animation) => Some((
+                    ClipReference::new(clip.id),
                     clip_start_time,
                     clip.duration_frames,
                     &clip.name as &str,
@@ -101,7 +136,13 @@ pub fn draw_motion_editor(
                 _ => None,
             })
             .collect();
-        edit_clips(selected_clips, editor_state, clip_map, fpb);
+        edit_clips(
+            selected_clips,
+            &target_clips,
+            editor_state,
+            clip_map,
+            time_base,
+        );
 
         // Delete any animation clips that are now empty
         // todo: this currently breaks if the clip is active
@@ -136,10 +177,18 @@ fn zoom_to_pixel_scale(zoom: f32) -> f32 {
 }
 
 fn edit_clips(
-    clips: Vec<(u32, u32, &str, &GeneratorSchema, &mut AnimationClip)>,
+    clips: Vec<(
+        ClipReference,
+        u32,
+        u32,
+        &str,
+        &GeneratorSchema,
+        &mut AnimationClip,
+    )>,
+    target_clips: &[TargetClip],
     editor_state: &mut EditorState,
     clip_map: &ActiveClipMap,
-    fpb: f32,
+    time_base: TimeBase,
 ) {
     let screen_cursor = unsafe { igGetCursorScreenPos_nonUDT2() };
     let available_size = unsafe { igGetContentRegionAvail_nonUDT2() };
@@ -202,7 +251,7 @@ fn edit_clips(
     // Calculate the time range of visible clips
     let (min_frames, max_frames) = clips.iter().fold(
         (u32::MAX, u32::MIN),
-        |(min_frames, max_frames), (start_frames, duration_frames, _, _, _)| {
+        |(min_frames, max_frames), (_, start_frames, duration_frames, _, _, _)| {
             (
                 min_frames.min(*start_frames),
                 max_frames.max(*start_frames + *duration_frames),
@@ -215,7 +264,7 @@ fn edit_clips(
     // Calculate the height of the virtual canvas (the height of all of the properties)
     let virtual_canvas_height = clips
         .iter()
-        .map(|(_, _, _, _, clip)| get_clip_virtual_height(clip))
+        .map(|(_, _, _, _, _, clip)| get_clip_virtual_height(clip))
         .sum::<f32>();
 
     // Determine our current rectangle in the virtual canvases coordinate space
@@ -312,12 +361,11 @@ fn edit_clips(
         );
     };
     draw_time_bar(
-        fpb,
-        start_frames / fpb,
-        end_frames / fpb,
+        start_frames,
+        end_frames,
         available_size.x - SIDEBAR_WIDTH,
         available_size.y - SCRUBBER_HEIGHT,
-        editor_state,
+        time_base,
     );
     if unsafe { igIsItemActive() } {
         let mouse_screen_pos = unsafe { igGetMousePos_nonUDT2() };
@@ -379,18 +427,8 @@ fn edit_clips(
     );
 
     // Draw vertical lines for all keyframe positions
-    for (clip_start_frame, _, _, _, clip_animation) in clips.iter() {
-        let fields = /*iter::once(&clip_animation.time_property).chain(*/
-            clip_animation
-                .properties
-                .iter()
-                .flat_map(|property| match &property.target {
-                    AnimatedPropertyTarget::Joined(field) => slice::from_ref(field),
-                    AnimatedPropertyTarget::Separate(fields) => fields,
-                });
-        //);
-
-        for field in fields {
+    for (_, clip_start_frame, _, _, _, clip_animation) in clips.iter() {
+        for field in clip_animation.fields() {
             let mut current_pos = *clip_start_frame as i32 + field.local_offset_frames;
             draw_keyframe_line(
                 &mut draw_list,
@@ -424,13 +462,16 @@ fn edit_clips(
     }
 
     // Start drawing properties!
-    for (index, (clip_start_frame, clip_duration, clip_name, schema, clip_animation)) in
-        clips.into_iter().enumerate()
+    for (
+        index,
+        (clip_reference, clip_start_frame, clip_duration, clip_name, schema, clip_animation),
+    ) in clips.into_iter().enumerate()
     {
         unsafe {
             igPushIDInt(index as i32);
         }
         draw_clip(
+            clip_reference,
             clip_start_frame,
             clip_duration,
             current_frame >= clip_start_frame && current_frame < clip_start_frame + clip_duration,
@@ -442,7 +483,9 @@ fn edit_clips(
             time_scale,
             pixel_scale,
             available_size.x,
+            target_clips,
             clip_map,
+            time_base,
             editor_state,
         );
         unsafe {
@@ -486,14 +529,23 @@ fn get_clip_virtual_height(clip: &AnimationClip) -> f32 {
             }
         })
         .sum::<f32>()
-    /*+ if clip.is_time_collapsed {
-        KEYFRAME_BAR_VIRTUAL_HEIGHT
-    } else {
-        KEYFRAME_BAR_VIRTUAL_HEIGHT + MOTION_BAR_VIRTUAL_HEIGHT
-    }*/
+        + get_optional_field_virtual_height(&clip.time_property, clip.is_time_collapsed)
+        + get_optional_field_virtual_height(&clip.weight_property, clip.is_weight_collapsed)
+}
+
+fn get_optional_field_virtual_height(
+    field: &Option<AnimatedPropertyField>,
+    is_collapsed: bool,
+) -> f32 {
+    match (field, is_collapsed) {
+        (None, _) => 0.,
+        (Some(_), true) => KEYFRAME_BAR_VIRTUAL_HEIGHT,
+        (Some(_), false) => KEYFRAME_BAR_VIRTUAL_HEIGHT + MOTION_BAR_VIRTUAL_HEIGHT,
+    }
 }
 
 fn draw_clip(
+    clip_reference: ClipReference,
     clip_start_frame: u32,
     clip_duration: u32,
     is_clip_active: bool,
@@ -505,7 +557,9 @@ fn draw_clip(
     time_scale: f32,
     pixel_scale: f32,
     available_pixel_width: f32,
+    target_clips: &[TargetClip],
     clip_map: &ActiveClipMap,
+    time_base: TimeBase,
     editor_state: &mut EditorState,
 ) {
     let clip_props_height = get_clip_virtual_height(clip_animation);
@@ -570,51 +624,139 @@ fn draw_clip(
         }
 
         // draw each property
-        /*unsafe {
-            igPushIDInt(0);
+        if let Some(time_property) = &mut clip_animation.time_property {
+            unsafe {
+                igPushIDInt(0);
+            }
+            let time_interaction = draw_clip_property(
+                0,
+                clip_start_frame,
+                "Time",
+                &mut clip_animation.is_time_collapsed,
+                slice::from_mut(time_property),
+                None,
+                Some((0., 1.)),
+                min_frames,
+                virtual_viewport,
+                time_scale,
+                pixel_scale,
+                available_pixel_width,
+                time_base,
+                editor_state,
+            );
+            unsafe {
+                igPopID();
+            };
+
+            if let PropertyInteraction::Delete(_) = time_interaction {
+                clip_animation.time_property = None;
+            }
+        }
+        if let Some(weight_property) = &mut clip_animation.weight_property {
+            unsafe {
+                igPushIDInt(-1);
+            }
+            let weight_interaction = draw_clip_property(
+                0,
+                clip_start_frame,
+                "Weight",
+                &mut clip_animation.is_weight_collapsed,
+                slice::from_mut(weight_property),
+                None,
+                None,
+                min_frames,
+                virtual_viewport,
+                time_scale,
+                pixel_scale,
+                available_pixel_width,
+                time_base,
+                editor_state,
+            );
+            unsafe {
+                igPopID();
+            };
+
+            if let PropertyInteraction::Delete(_) = weight_interaction {
+                clip_animation.weight_property = None;
+            }
         }
-        draw_clip_property(
-            clip_start_frame,
-            "Time",
-            &mut clip_animation.is_time_collapsed,
-            slice::from_mut(&mut clip_animation.time_property),
-            Some((0., 1.)),
-            min_frames,
-            virtual_viewport,
-            time_scale,
-            pixel_scale,
-            available_pixel_width,
-            editor_state,
-        );
-        unsafe {
-            igPopID();
-        };*/
         let mut interaction = PropertyInteraction::None;
         for (prop_index, animated_property) in clip_animation.properties.iter_mut().enumerate() {
-            let prop_fields = match &mut animated_property.target {
-                AnimatedPropertyTarget::Joined(field) => slice::from_mut(field),
-                AnimatedPropertyTarget::Separate(fields) => fields,
+            let schema_prop = &clip_schema.groups[animated_property.group_index].properties
+                [animated_property.property_index];
+            let value_type = schema_prop.value_type;
+            let value_range = schema_prop.display_range();
+
+            let (prop_fields, expression, path, constraint) = match &mut animated_property.target {
+                AnimatedPropertyTarget::Expression(expression) => {
+                    (Default::default(), Some(expression), None, None)
+                }
+                AnimatedPropertyTarget::Path(SplinePath {
+                    kind,
+                    points,
+                    progress,
+                    orient_target,
+                    ..
+                }) => (
+                    slice::from_mut(progress),
+                    None,
+                    Some(PathMenu {
+                        kind,
+                        points,
+                        orient_target,
+                        schema: clip_schema,
+                    }),
+                    None,
+                ),
+                AnimatedPropertyTarget::Constraint(constraint) => (
+                    Default::default(),
+                    None,
+                    None,
+                    Some(ConstraintMenu {
+                        constraint,
+                        schema: clip_schema,
+                        target_clips,
+                    }),
+                ),
+                target => (target.fields_mut(), None, None, None),
+            };
+
+            // Expressions and constraints don't have any keyframes to get a range from, and path
+            // progress always goes from 0 to 1
+            let prop_min_max = if path.is_some() {
+                Some((0., 1.))
+            } else if expression.is_some() || constraint.is_some() {
+                Some(value_range.unwrap_or((0., 1.)))
+            } else {
+                value_range
             };
-            let prop_min_max = clip_schema.groups[animated_property.group_index].properties
-                [animated_property.property_index]
-                .value_type
-                .value_range();
 
             unsafe { igPushIDInt(prop_index as i32 + 1) };
             interaction = interaction.union(draw_clip_property(
                 prop_index,
                 clip_start_frame,
-                &clip_schema.groups[animated_property.group_index].properties
-                    [animated_property.property_index]
-                    .name,
+                &schema_prop.name,
                 &mut animated_property.is_collapsed,
                 prop_fields,
+                Some(PropertyMenu {
+                    value_type,
+                    modifiers: &mut animated_property.modifiers,
+                    can_use_path: value_type == PropertyType::Vec3,
+                    can_use_constraint: value_type == PropertyType::Vec3
+                        || (value_type == PropertyType::Rotation
+                            && first_vec3_binding(clip_schema).is_some()),
+                    target_clips,
+                    expression,
+                    path,
+                    constraint,
+                }),
                 prop_min_max,
                 min_frames,
                 virtual_viewport,
                 time_scale,
                 pixel_scale,
                 available_pixel_width,
+                time_base,
                 editor_state,
             ));
             unsafe {
@@ -627,6 +769,86 @@ fn draw_clip(
             PropertyInteraction::Delete(index) => {
                 clip_animation.properties.remove(index);
             }
+            PropertyInteraction::SetTarget(index, target_kind) => {
+                let property = &mut clip_animation.properties[index];
+                property.target = match target_kind {
+                    TargetKind::Keyframes => {
+                        let schema_prop = &clip_schema.groups[property.group_index].properties
+                            [property.property_index];
+                        AnimatedPropertyTarget::Joined(AnimatedPropertyField {
+                            local_offset_frames: 0,
+                            start_anchor: None,
+                            start_value: schema_prop.default_value(),
+                            segments: Vec::new(),
+                            pre_extrapolation: Extrapolation::Constant,
+                            post_extrapolation: Extrapolation::Constant,
+                        })
+                    }
+                    TargetKind::Expression => AnimatedPropertyTarget::Expression(Expression {
+                        source: "0".to_string(),
+                        bytecode: compile_expression("0").unwrap(),
+                    }),
+                    TargetKind::Path => {
+                        AnimatedPropertyTarget::Path(convert_to_path(&property.target))
+                    }
+                    TargetKind::Constraint(target) => {
+                        let schema_prop = &clip_schema.groups[property.group_index].properties
+                            [property.property_index];
+                        let constraint = if schema_prop.value_type == PropertyType::Rotation {
+                            // Look from the clip's first position, which is usually what moves it
+                            Constraint::LookAt {
+                                eye: first_vec3_binding(clip_schema).unwrap(),
+                                target,
+                            }
+                        } else {
+                            Constraint::Follow {
+                                target,
+                                offset: Vector3::default(),
+                            }
+                        };
+                        AnimatedPropertyTarget::Constraint(constraint)
+                    }
+                };
+            }
+            PropertyInteraction::PathChanged(index) => {
+                if let AnimatedPropertyTarget::Path(path) =
+                    &mut clip_animation.properties[index].target
+                {
+                    path.update_arc_lengths();
+                }
+            }
+            PropertyInteraction::ReduceKeyframes(index) => {
+                let mut keyframes_before = 0;
+                let mut keyframes_after = 0;
+                let mut max_error = 0f32;
+                for field in clip_animation.properties[index].target.fields_mut() {
+                    keyframes_before += field.segments.len() + 1;
+                    match reduce_keyframes(field, editor_state.reduction_tolerance) {
+                        Some(fitted) if fitted.field.segments.len() < field.segments.len() => {
+                            max_error = max_error.max(fitted.max_error);
+                            *field = fitted.field;
+                        }
+                        _ => {}
+                    }
+                    keyframes_after += field.segments.len() + 1;
+                }
+                editor_state.last_reduction = Some((keyframes_before, keyframes_after, max_error));
+            }
+            PropertyInteraction::Bake(index, output) => {
+                let property = &clip_animation.properties[index];
+                editor_state.bake_request = Some(BakeRequest {
+                    target_clip: clip_animation.target_clip,
+                    group_index: property.group_index,
+                    property_index: property.property_index,
+                    frames: clip_start_frame..clip_start_frame + clip_duration,
+                    output,
+                });
+            }
+        }
+
+        // Dragging or typing in any of the clip's properties or their menus edits it live
+        if unsafe { igIsAnyItemActive() && igIsWindowFocused(ImGuiFocusedFlags::ChildWindows) } {
+            editor_state.edited_clips.push(clip_reference);
         }
     }
     unsafe {
@@ -635,6 +857,489 @@ fn draw_clip(
     unsafe { igSetCursorPos(ImVec2::new(cursor_pos.x, cursor_pos.y + pixel_height)) };
 }
 
+/// Draws the keyframe reduction settings, returning true if the reduction should be done.
+fn draw_reduction_menu(editor_state: &mut EditorState) -> bool {
+    unsafe {
+        igDragFloat(
+            cstr!("Tolerance"),
+            &mut editor_state.reduction_tolerance,
+            0.001,
+            0.,
+            f32::MAX,
+            cstr!("%.3f"),
+            1.,
+        );
+    }
+    let should_reduce =
+        unsafe { igMenuItemBool(cstr!("Reduce keyframes"), ptr::null(), false, true) };
+
+    if let Some((keyframes_before, keyframes_after, max_error)) = editor_state.last_reduction {
+        let text = CString::new(format!(
+            "Last reduction: {} to {} keyframes, max error {:.4}",
+            keyframes_before, keyframes_after, max_error
+        ))
+        .unwrap();
+        unsafe { igText(cstr!("%s"), text.as_ptr()) };
+    }
+
+    should_reduce
+}
+
+/// Draws the bake options, returning where the property should be baked to if one was picked.
+fn draw_bake_menu(editor_state: &EditorState) -> Option<BakeOutput> {
+    let mut output = None;
+    if unsafe { igBeginMenu(cstr!("Bake"), true) } {
+        if unsafe { igMenuItemBool(cstr!("To keyframes"), ptr::null(), false, true) } {
+            output = Some(BakeOutput::Keyframes);
+        }
+        if unsafe { igMenuItemBool(cstr!("To CSV"), ptr::null(), false, true) } {
+            output = Some(BakeOutput::Csv);
+        }
+        unsafe { igEndMenu() };
+    }
+
+    if let Some(last_bake) = &editor_state.last_bake {
+        let text = CString::new(format!("Last bake: {}", last_bake)).unwrap();
+        unsafe { igText(cstr!("%s"), text.as_ptr()) };
+    }
+
+    output
+}
+
+fn draw_expression_menu(expression: &mut Expression) {
+    let mut data_bytes = expression.source.clone().into_bytes();
+    data_bytes.push(0);
+    data_bytes.resize(data_bytes.len() + 64, 0); // reserve space for 64 more chars
+
+    let was_changed = unsafe {
+        igText(cstr!("Expression"));
+        igInputText(
+            cstr!("##expression"),
+            &mut data_bytes[0] as *mut u8 as *mut i8,
+            data_bytes.capacity(),
+            ImGuiInputTextFlags::empty(),
+            None,
+            ptr::null_mut(),
+        )
+    };
+
+    if was_changed {
+        // Remove the first null char and everything after it
+        if let Some(char_index) = data_bytes.iter().position(|&byte| byte == 0) {
+            data_bytes.truncate(char_index);
+        }
+        expression.source = String::from_utf8(data_bytes).unwrap();
+    }
+
+    // Keep the last working bytecode around while the expression is being edited
+    match compile_expression(&expression.source) {
+        Ok(bytecode) => expression.bytecode = bytecode,
+        Err(err) => {
+            let err_str = CString::new(err.to_string()).unwrap();
+            unsafe { igText(err_str.as_ptr()) };
+        }
+    }
+}
+
+/// Returns true if the shape of the path was changed.
+fn draw_path_menu(path: PathMenu) -> bool {
+    let mut was_changed = false;
+
+    unsafe { igText(cstr!("Path")) };
+    let kinds = [
+        (cstr!("Catmull-Rom"), SplineKind::CatmullRom),
+        (cstr!("Centripetal"), SplineKind::Centripetal),
+    ];
+    for &(kind_label, kind) in kinds.iter() {
+        if unsafe { igMenuItemBool(kind_label, ptr::null(), *path.kind == kind, true) } {
+            *path.kind = kind;
+            was_changed = true;
+        }
+    }
+
+    let mut remove_index = None;
+    for (point_index, point) in path.points.iter_mut().enumerate() {
+        unsafe {
+            igPushIDInt(point_index as i32);
+            was_changed |= igDragFloat3(
+                cstr!("##point"),
+                &mut point.x,
+                0.01,
+                0.,
+                0.,
+                cstr!("%.3f"),
+                1.,
+            );
+            igSameLine(0., 5.);
+        }
+        if unsafe { igButton(cstr!("X"), ImVec2::new(15., 0.)) } {
+            remove_index = Some(point_index);
+        }
+        unsafe { igPopID() };
+    }
+    if let Some(remove_index) = remove_index {
+        path.points.remove(remove_index);
+        was_changed = true;
+    }
+
+    if unsafe { igButton(cstr!("Add point"), ImVec2::new(0., 0.)) } {
+        // Continue in the direction of the last span
+        let point_count = path.points.len();
+        let new_point = match point_count {
+            0 => Vector3::default(),
+            1 => path.points[0] + Vector3::unit_z(),
+            _ => path.points[point_count - 1] * 2. - path.points[point_count - 2],
+        };
+        path.points.push(new_point);
+        was_changed = true;
+    }
+
+    if unsafe { igBeginMenu(cstr!("Orient along path"), true) } {
+        if unsafe {
+            igMenuItemBool(
+                cstr!("None"),
+                ptr::null(),
+                path.orient_target.is_none(),
+                true,
+            )
+        } {
+            *path.orient_target = None;
+        }
+
+        for (group_index, group) in path.schema.groups.iter().enumerate() {
+            for (prop_index, prop) in group.properties.iter().enumerate() {
+                if prop.value_type != PropertyType::Rotation {
+                    continue;
+                }
+
+                let is_selected = match path.orient_target {
+                    Some(binding) => binding.group == group_index && binding.prop == prop_index,
+                    None => false,
+                };
+                let label = if group.name.is_empty() {
+                    CString::new(prop.name).unwrap()
+                } else {
+                    CString::new(format!("{} / {}", group.name, prop.name)).unwrap()
+                };
+                if unsafe { igMenuItemBool(label.as_ptr(), ptr::null(), is_selected, true) } {
+                    *path.orient_target = Some(PropertyBinding::new(group_index, prop_index));
+                }
+            }
+        }
+
+        unsafe { igEndMenu() };
+    }
+
+    was_changed
+}
+
+/// Finds the first `Vec3` property of a schema.
+fn first_vec3_binding(schema: &GeneratorSchema) -> Option<PropertyBinding> {
+    schema
+        .groups
+        .iter()
+        .enumerate()
+        .flat_map(|(group_index, group)| {
+            group
+                .properties
+                .iter()
+                .enumerate()
+                .map(move |(prop_index, prop)| (group_index, prop_index, prop))
+        })
+        .find(|(_, _, prop)| prop.value_type == PropertyType::Vec3)
+        .map(|(group_index, prop_index, _)| PropertyBinding::new(group_index, prop_index))
+}
+
+/// Draws a menu item for each `Vec3` property of a schema, returning the one that was clicked.
+fn draw_vec3_menu_items(
+    schema: &GeneratorSchema,
+    selected: Option<PropertyBinding>,
+) -> Option<PropertyBinding> {
+    let mut clicked = None;
+    for (group_index, group) in schema.groups.iter().enumerate() {
+        for (prop_index, prop) in group.properties.iter().enumerate() {
+            if prop.value_type != PropertyType::Vec3 {
+                continue;
+            }
+
+            let is_selected = match selected {
+                Some(binding) => binding.group == group_index && binding.prop == prop_index,
+                None => false,
+            };
+            let label = if group.name.is_empty() {
+                CString::new(prop.name).unwrap()
+            } else {
+                CString::new(format!("{} / {}", group.name, prop.name)).unwrap()
+            };
+            if unsafe { igMenuItemBool(label.as_ptr(), ptr::null(), is_selected, true) } {
+                clicked = Some(PropertyBinding::new(group_index, prop_index));
+            }
+        }
+    }
+    clicked
+}
+
+/// Draws a menu of clips and their positions, returning the position that was clicked.
+fn draw_constraint_target_menu(
+    label: *const c_char,
+    target_clips: &[TargetClip],
+    selected: Option<ConstraintTarget>,
+) -> Option<ConstraintTarget> {
+    let mut clicked = None;
+    if unsafe { igBeginMenu(label, !target_clips.is_empty()) } {
+        for (clip_index, target_clip) in target_clips.iter().enumerate() {
+            let selected_binding = selected
+                .filter(|target| target.clip == target_clip.reference)
+                .map(|target| target.property);
+            let clip_label = CString::new(target_clip.name.as_str()).unwrap();
+            unsafe { igPushIDInt(clip_index as i32) };
+            if unsafe { igBeginMenu(clip_label.as_ptr(), true) } {
+                if let Some(binding) = draw_vec3_menu_items(target_clip.schema, selected_binding) {
+                    clicked = Some(ConstraintTarget {
+                        clip: target_clip.reference,
+                        property: binding,
+                    });
+                }
+                unsafe { igEndMenu() };
+            }
+            unsafe { igPopID() };
+        }
+        unsafe { igEndMenu() };
+    }
+    clicked
+}
+
+fn draw_constraint_menu(menu: ConstraintMenu) {
+    unsafe { igText(cstr!("Constraint")) };
+
+    let target = menu.constraint.target();
+    let target_name = match menu
+        .target_clips
+        .iter()
+        .find(|target_clip| target_clip.reference == target.clip)
+    {
+        Some(target_clip) => &target_clip.name as &str,
+        None => "BAD REFERENCE",
+    };
+    let target_text = CString::new(format!("Target: {}", target_name)).unwrap();
+    unsafe { igText(cstr!("%s"), target_text.as_ptr()) };
+    if let Some(new_target) =
+        draw_constraint_target_menu(cstr!("Change target"), menu.target_clips, Some(target))
+    {
+        *menu.constraint.target_mut() = new_target;
+    }
+
+    match menu.constraint {
+        Constraint::LookAt { eye, .. } => {
+            if unsafe { igBeginMenu(cstr!("Look from"), true) } {
+                if let Some(binding) = draw_vec3_menu_items(menu.schema, Some(*eye)) {
+                    *eye = binding;
+                }
+                unsafe { igEndMenu() };
+            }
+        }
+        Constraint::Follow { target, offset } => {
+            if unsafe { igMenuItemBool(cstr!("Damped"), ptr::null(), false, true) } {
+                *menu.constraint = Constraint::DampedFollow {
+                    target: *target,
+                    offset: *offset,
+                    half_life: 0.25,
+                    cache: RefCell::new(None),
+                };
+                return;
+            }
+            draw_offset_drag(offset);
+        }
+        Constraint::DampedFollow {
+            target,
+            offset,
+            half_life,
+            ..
+        } => {
+            if unsafe { igMenuItemBool(cstr!("Damped"), ptr::null(), true, true) } {
+                *menu.constraint = Constraint::Follow {
+                    target: *target,
+                    offset: *offset,
+                };
+                return;
+            }
+            draw_offset_drag(offset);
+            unsafe {
+                igDragFloat(
+                    cstr!("Half-life"),
+                    half_life,
+                    0.01,
+                    0.,
+                    f32::MAX,
+                    cstr!("%.2f s"),
+                    1.,
+                );
+            }
+        }
+    }
+}
+
+fn draw_offset_drag(offset: &mut Vector3) {
+    unsafe {
+        igDragFloat3(
+            cstr!("Offset"),
+            &mut offset.x,
+            0.01,
+            0.,
+            0.,
+            cstr!("%.3f"),
+            1.,
+        );
+    }
+}
+
+fn draw_modifier_menu(modifiers: &mut Vec<Modifier>) {
+    let mut remove_index = None;
+    for (modifier_index, modifier) in modifiers.iter_mut().enumerate() {
+        unsafe { igPushIDInt(modifier_index as i32) };
+        match modifier {
+            Modifier::Noise {
+                frequency,
+                amplitude,
+                seed,
+                octaves,
+            } => {
+                unsafe { igText(cstr!("Noise")) };
+                let mut signed_seed = *seed as i32;
+                let mut signed_octaves = *octaves as i32;
+                unsafe {
+                    igDragFloat(
+                        cstr!("Frequency"),
+                        frequency,
+                        0.001,
+                        0.,
+                        0.,
+                        cstr!("%.3f"),
+                        1.,
+                    );
+                    igDragFloat(
+                        cstr!("Amplitude"),
+                        amplitude,
+                        0.01,
+                        0.,
+                        0.,
+                        cstr!("%.3f"),
+                        1.,
+                    );
+                    igDragInt(cstr!("Seed"), &mut signed_seed, 1., 0, 0, cstr!("%d"));
+                    igDragInt(
+                        cstr!("Octaves"),
+                        &mut signed_octaves,
+                        0.1,
+                        1,
+                        8,
+                        cstr!("%d"),
+                    );
+                }
+                *seed = signed_seed as u32;
+                *octaves = signed_octaves.max(1) as u32;
+            }
+            Modifier::Sine {
+                frequency,
+                amplitude,
+                phase,
+            } => unsafe {
+                igText(cstr!("Sine"));
+                igDragFloat(
+                    cstr!("Frequency"),
+                    frequency,
+                    0.001,
+                    0.,
+                    0.,
+                    cstr!("%.3f"),
+                    1.,
+                );
+                igDragFloat(
+                    cstr!("Amplitude"),
+                    amplitude,
+                    0.01,
+                    0.,
+                    0.,
+                    cstr!("%.3f"),
+                    1.,
+                );
+                igDragFloat(cstr!("Phase"), phase, 0.01, 0., 1., cstr!("%.3f"), 1.);
+            },
+            Modifier::Quantize { step } => unsafe {
+                igText(cstr!("Quantize"));
+                igDragFloat(cstr!("Step"), step, 0.01, 0., 0., cstr!("%.3f"), 1.);
+            },
+            Modifier::Clamp { min, max } => unsafe {
+                igText(cstr!("Clamp"));
+                igDragFloat(cstr!("Min"), min, 0.01, 0., 0., cstr!("%.3f"), 1.);
+                igDragFloat(cstr!("Max"), max, 0.01, 0., 0., cstr!("%.3f"), 1.);
+            },
+            Modifier::Spring { frequency, damping } => unsafe {
+                igText(cstr!("Spring"));
+                igDragFloat(
+                    cstr!("Frequency"),
+                    frequency,
+                    0.001,
+                    0.,
+                    0.,
+                    cstr!("%.3f"),
+                    1.,
+                );
+                igDragFloat(cstr!("Damping"), damping, 0.01, 0., 0., cstr!("%.3f"), 1.);
+                *frequency = frequency.max(0.);
+                *damping = damping.max(0.);
+            },
+        }
+        if unsafe { igButton(cstr!("Remove"), ImVec2::new(0., 0.)) } {
+            remove_index = Some(modifier_index);
+        }
+        unsafe {
+            igSeparator();
+            igPopID();
+        }
+    }
+    if let Some(remove_index) = remove_index {
+        modifiers.remove(remove_index);
+    }
+
+    // Only the first spring on a property has an effect
+    let has_spring = modifiers.iter().any(|modifier| match modifier {
+        Modifier::Spring { .. } => true,
+        _ => false,
+    });
+    if unsafe { igBeginMenu(cstr!("Add modifier"), true) } {
+        let new_modifier = if unsafe { igMenuItemBool(cstr!("Noise"), ptr::null(), false, true) } {
+            Some(Modifier::Noise {
+                frequency: 0.1,
+                amplitude: 1.,
+                seed: 0,
+                octaves: 1,
+            })
+        } else if unsafe { igMenuItemBool(cstr!("Sine"), ptr::null(), false, true) } {
+            Some(Modifier::Sine {
+                frequency: 0.1,
+                amplitude: 1.,
+                phase: 0.,
+            })
+        } else if unsafe { igMenuItemBool(cstr!("Quantize"), ptr::null(), false, true) } {
+            Some(Modifier::Quantize { step: 1. })
+        } else if unsafe { igMenuItemBool(cstr!("Clamp"), ptr::null(), false, true) } {
+            Some(Modifier::Clamp { min: 0., max: 1. })
+        } else if unsafe { igMenuItemBool(cstr!("Spring"), ptr::null(), false, !has_spring) } {
+            Some(Modifier::Spring {
+                frequency: 0.05,
+                damping: 0.5,
+            })
+        } else {
+            None
+        };
+        if let Some(new_modifier) = new_modifier {
+            modifiers.push(new_modifier);
+        }
+        unsafe { igEndMenu() };
+    }
+}
+
 fn get_all_keyframe_positions<'fields>(
     fields: &'fields [AnimatedPropertyField],
     clip_start_frame: i32,
@@ -658,6 +1363,53 @@ fn get_all_keyframe_positions<'fields>(
 enum PropertyInteraction {
     None,
     Delete(usize),
+    SetTarget(usize, TargetKind),
+    PathChanged(usize),
+    ReduceKeyframes(usize),
+    Bake(usize, BakeOutput),
+}
+
+#[derive(Clone, Copy)]
+enum TargetKind {
+    Keyframes,
+    Expression,
+    Path,
+    Constraint(ConstraintTarget),
+}
+
+/// The extra settings shown in the popup menu of a property.
+struct PropertyMenu<'prop> {
+    value_type: PropertyType,
+    modifiers: &'prop mut Vec<Modifier>,
+    can_use_path: bool,
+    can_use_constraint: bool,
+    target_clips: &'prop [TargetClip],
+    expression: Option<&'prop mut Expression>,
+    path: Option<PathMenu<'prop>>,
+    constraint: Option<ConstraintMenu<'prop>>,
+}
+
+/// The parts of a path that are edited from its property's menu. Its progress is keyframed like
+/// any other field.
+struct PathMenu<'prop> {
+    kind: &'prop mut SplineKind,
+    points: &'prop mut Vec<Vector3>,
+    orient_target: &'prop mut Option<PropertyBinding>,
+    schema: &'prop GeneratorSchema,
+}
+
+/// The parts of a constraint that are edited from its property's menu.
+struct ConstraintMenu<'prop> {
+    constraint: &'prop mut Constraint,
+    schema: &'prop GeneratorSchema,
+    target_clips: &'prop [TargetClip],
+}
+
+/// A clip that constraints can follow the position of.
+struct TargetClip {
+    reference: ClipReference,
+    name: String,
+    schema: &'static GeneratorSchema,
 }
 
 impl PropertyInteraction {
@@ -675,12 +1427,14 @@ fn draw_clip_property(
     prop_name: &str,
     is_collapsed: &mut bool,
     fields: &mut [AnimatedPropertyField],
+    menu: Option<PropertyMenu>,
     min_max: Option<(f32, f32)>,
     min_frames: u32,
     virtual_viewport: ImVec4,
     time_scale: f32,
     pixel_scale: f32,
     available_pixel_width: f32,
+    time_base: TimeBase,
     editor_state: &mut EditorState,
 ) -> PropertyInteraction {
     let mut interaction = PropertyInteraction::None;
@@ -742,7 +1496,8 @@ fn draw_clip_property(
                             max_val = max_val.max(component);
                         }
                         for curve_segment in &field.segments {
-                            for component in curve_segment.end_value.fields() {
+                            for component in curve_segment.end_value.fields_near(field.start_value)
+                            {
                                 min_val = min_val.min(component);
                                 max_val = max_val.max(component);
                             }
@@ -803,7 +1558,104 @@ fn draw_clip_property(
             *is_collapsed = !*is_collapsed;
         }
 
-        // Draw the delete and seek left/right buttons
+        // Draw the modifier, delete and seek left/right buttons
+        unsafe {
+            igSetCursorScreenPos(ImVec2::new(
+                screen_cursor.x + SIDEBAR_WIDTH - 10. - 20. * 4.,
+                screen_cursor.y + 6.,
+            ));
+        }
+        if let Some(menu) = menu {
+            if unsafe { igButton(cstr!("M"), ImVec2::new(15., 0.)) } {
+                unsafe { igOpenPopup(cstr!("modifiers")) };
+            }
+            if unsafe { igBeginPopup(cstr!("modifiers"), ImGuiWindowFlags::empty()) } {
+                let is_expression = menu.expression.is_some();
+                let is_path = menu.path.is_some();
+                let is_constraint = menu.constraint.is_some();
+                let is_keyframed = !is_expression && !is_path && !is_constraint;
+                if unsafe {
+                    igMenuItemBool(cstr!("Use keyframes"), ptr::null(), is_keyframed, true)
+                } && !is_keyframed
+                {
+                    interaction = PropertyInteraction::SetTarget(index, TargetKind::Keyframes);
+                }
+                if unsafe {
+                    igMenuItemBool(cstr!("Use expression"), ptr::null(), is_expression, true)
+                } && !is_expression
+                {
+                    interaction = PropertyInteraction::SetTarget(index, TargetKind::Expression);
+                }
+                if menu.can_use_path
+                    && unsafe { igMenuItemBool(cstr!("Use path"), ptr::null(), is_path, true) }
+                    && !is_path
+                {
+                    interaction = PropertyInteraction::SetTarget(index, TargetKind::Path);
+                }
+                if menu.can_use_constraint {
+                    if let Some(target) = draw_constraint_target_menu(
+                        cstr!("Use constraint"),
+                        menu.target_clips,
+                        None,
+                    ) {
+                        interaction =
+                            PropertyInteraction::SetTarget(index, TargetKind::Constraint(target));
+                    }
+                }
+                unsafe { igSeparator() };
+
+                if let Some(expression) = menu.expression {
+                    draw_expression_menu(expression);
+                    unsafe { igSeparator() };
+                }
+                if let Some(path) = menu.path {
+                    if draw_path_menu(path) {
+                        interaction = PropertyInteraction::PathChanged(index);
+                    }
+                    unsafe { igSeparator() };
+                }
+                if let Some(constraint) = menu.constraint {
+                    draw_constraint_menu(constraint);
+                    unsafe { igSeparator() };
+                }
+                if !is_expression && !is_constraint {
+                    if unsafe {
+                        igMenuItemBool(cstr!("Copy all keyframes"), ptr::null(), false, true)
+                    } {
+                        copy_property_keyframes(fields, menu.value_type);
+                    }
+                    if unsafe {
+                        igMenuItemBool(
+                            cstr!("Paste keyframes at playhead"),
+                            ptr::null(),
+                            false,
+                            true,
+                        )
+                    } {
+                        let position_frames =
+                            editor_state.current_frame() as i32 - clip_start_frame as i32;
+                        if paste_copied_keyframes(fields, menu.value_type, position_frames) {
+                            for field in fields.iter_mut() {
+                                update_field_anchors(field, clip_start_frame, time_base);
+                            }
+                        } else {
+                            eprintln!("The clipboard doesn't contain keyframes for this property.");
+                        }
+                    }
+                    unsafe { igSeparator() };
+                    if draw_reduction_menu(editor_state) {
+                        interaction = PropertyInteraction::ReduceKeyframes(index);
+                    }
+                    unsafe { igSeparator() };
+                }
+                if let Some(output) = draw_bake_menu(editor_state) {
+                    interaction = PropertyInteraction::Bake(index, output);
+                }
+                unsafe { igSeparator() };
+                draw_modifier_menu(menu.modifiers);
+                unsafe { igEndPopup() };
+            }
+        }
         unsafe {
             igSetCursorScreenPos(ImVec2::new(
                 screen_cursor.x + SIDEBAR_WIDTH - 10. - 20. * 3.,
@@ -900,8 +1752,10 @@ fn draw_clip_property(
             unsafe { igPushIDInt(field_index as i32) };
             draw_clip_keyframe_bar(
                 field,
+                clip_start_frame,
                 time_scale * pixel_scale,
                 ImVec2::new(field_pos.x, field_pos.y + keyframe_bar_height / 2.),
+                time_base,
                 editor_state,
             );
             unsafe { igPopID() };
@@ -924,14 +1778,17 @@ fn draw_clip_property(
 
 fn draw_clip_keyframe_bar(
     field: &mut AnimatedPropertyField,
+    clip_start_frame: u32,
     scale: f32,
     screen_pos: ImVec2,
+    time_base: TimeBase,
     editor_state: &mut EditorState,
 ) {
     let mut interaction = KeyframeInteraction::None;
 
     // Draw each of the controllers
     let mut current_x = screen_pos.x + field.local_offset_frames as f32 * scale;
+    let has_segments = !field.segments.is_empty();
     interaction = interaction.union(draw_keyframe_controller(
         None,
         &mut field.local_offset_frames,
@@ -940,12 +1797,22 @@ fn draw_clip_keyframe_bar(
             .first_mut()
             .map(|segment| &mut segment.duration_frames),
         None,
+        None,
+        None,
+        Some(&mut field.pre_extrapolation),
+        if has_segments {
+            None
+        } else {
+            Some(&mut field.post_extrapolation)
+        },
+        field.start_anchor.is_some(),
         false,
         scale,
         ImVec2::new(current_x, screen_pos.y),
         editor_state,
     ));
-    for segment_index in 0..field.segments.len() {
+    let segment_count = field.segments.len();
+    for segment_index in 0..segment_count {
         unsafe { igPushIDInt(segment_index as i32) };
 
         let (before_segments, after_segments) = field.segments.split_at_mut(segment_index + 1);
@@ -958,6 +1825,24 @@ fn draw_clip_keyframe_bar(
             &mut last_duration_int,
             next_segment.map(|segment| &mut segment.duration_frames),
             Some(&mut last_segment.interpolation),
+            if let PropertyValue::Rotation(_) = last_segment.end_value {
+                Some(&mut last_segment.rotation_spline)
+            } else {
+                None
+            },
+            match last_segment.end_value {
+                PropertyValue::RgbColor(_) | PropertyValue::RgbaColor(_) => {
+                    Some(&mut last_segment.color_space)
+                }
+                _ => None,
+            },
+            None,
+            if segment_index + 1 == segment_count {
+                Some(&mut field.post_extrapolation)
+            } else {
+                None
+            },
+            last_segment.end_anchor.is_some(),
             true,
             scale,
             ImVec2::new(
@@ -977,6 +1862,27 @@ fn draw_clip_keyframe_bar(
         KeyframeInteraction::Deleted(delete_index) => {
             delete_keyframe(field, delete_index);
         }
+        KeyframeInteraction::ToggleAnchor(toggle_index) => {
+            let keyframe_frame = clip_start_frame as i32
+                + field.local_offset_frames
+                + field
+                    .segments
+                    .iter()
+                    .take(toggle_index.map(|index| index + 1).unwrap_or(0))
+                    .map(|segment| segment.duration_frames as i32)
+                    .sum::<i32>();
+            let anchor = match toggle_index {
+                Some(index) => &mut field.segments[index].end_anchor,
+                None => &mut field.start_anchor,
+            };
+            *anchor = match anchor {
+                Some(_) => None,
+                None => Some(frame_to_anchor(keyframe_frame, time_base)),
+            };
+        }
+        KeyframeInteraction::Moved => {
+            update_field_anchors(field, clip_start_frame, time_base);
+        }
     }
 }
 
@@ -984,6 +1890,8 @@ fn draw_clip_keyframe_bar(
 enum KeyframeInteraction {
     None,
     Deleted(Option<usize>),
+    ToggleAnchor(Option<usize>),
+    Moved,
 }
 
 impl KeyframeInteraction {
@@ -1000,6 +1908,11 @@ fn draw_keyframe_controller(
     last_duration: &mut i32,
     mut next_duration: Option<&mut u32>,
     interpolation: Option<&mut CurveInterpolation>,
+    rotation_spline: Option<&mut bool>,
+    color_space: Option<&mut ColorSpace>,
+    pre_extrapolation: Option<&mut Extrapolation>,
+    post_extrapolation: Option<&mut Extrapolation>,
+    is_anchored: bool,
     limit_last: bool,
     scale: f32,
     screen_pos: ImVec2,
@@ -1065,9 +1978,86 @@ fn draw_keyframe_controller(
                 ));
             }
 
+            let is_step = interpolation.is_step();
+            if unsafe { igMenuItemBool(cstr!("Step"), ptr::null(), is_step, true) } {
+                *interpolation = CurveInterpolation::Step;
+            }
+
+            if unsafe {
+                igMenuItemBool(
+                    cstr!("Step next"),
+                    ptr::null(),
+                    interpolation.is_step_next(),
+                    true,
+                )
+            } {
+                *interpolation = CurveInterpolation::StepNext;
+            }
+
+            if unsafe { igBeginMenu(cstr!("Ease"), true) } {
+                for &easing in Easing::ALL.iter() {
+                    let label = CString::new(easing.name()).unwrap();
+                    if unsafe {
+                        igMenuItemBool(
+                            label.as_ptr(),
+                            ptr::null(),
+                            interpolation.is_ease(easing),
+                            true,
+                        )
+                    } {
+                        *interpolation = CurveInterpolation::Ease(easing);
+                    }
+                }
+
+                unsafe { igEndMenu() };
+            }
+
+            unsafe { igSeparator() };
+        }
+
+        if let Some(rotation_spline) = rotation_spline {
+            if unsafe {
+                igMenuItemBool(
+                    cstr!("Smooth rotation"),
+                    ptr::null(),
+                    *rotation_spline,
+                    true,
+                )
+            } {
+                *rotation_spline = !*rotation_spline;
+            }
+
+            unsafe { igSeparator() };
+        }
+
+        if let Some(color_space) = color_space {
+            if unsafe { igBeginMenu(cstr!("Colour space"), true) } {
+                for &space in ColorSpace::ALL.iter() {
+                    let label = CString::new(space.name()).unwrap();
+                    if unsafe {
+                        igMenuItemBool(label.as_ptr(), ptr::null(), *color_space == space, true)
+                    } {
+                        *color_space = space;
+                    }
+                }
+
+                unsafe { igEndMenu() };
+            }
+
             unsafe { igSeparator() };
         }
 
+        if let Some(pre_extrapolation) = pre_extrapolation {
+            extrapolation_menu(cstr!("Extrapolate before"), pre_extrapolation);
+        }
+        if let Some(post_extrapolation) = post_extrapolation {
+            extrapolation_menu(cstr!("Extrapolate after"), post_extrapolation);
+        }
+
+        if unsafe { igMenuItemBool(cstr!("Anchor to beat"), ptr::null(), is_anchored, true) } {
+            interaction = KeyframeInteraction::ToggleAnchor(index);
+        }
+
         if unsafe { igMenuItemBool(cstr!("Delete"), ptr::null(), false, true) } {
             interaction = KeyframeInteraction::Deleted(index);
         }
@@ -1099,11 +2089,35 @@ fn draw_keyframe_controller(
         if let Some(next_duration) = &mut next_duration {
             **next_duration = (**next_duration as i32 - duration_delta) as u32;
         }
+
+        if duration_delta != 0 {
+            interaction = KeyframeInteraction::Moved;
+        }
     }
 
     return interaction;
 }
 
+fn extrapolation_menu(label: *const c_char, extrapolation: &mut Extrapolation) {
+    if unsafe { igBeginMenu(label, true) } {
+        let options = [
+            (cstr!("Constant"), Extrapolation::Constant),
+            (cstr!("Linear"), Extrapolation::Linear),
+            (cstr!("Cycle"), Extrapolation::Cycle),
+            (cstr!("Cycle with offset"), Extrapolation::CycleWithOffset),
+            (cstr!("Ping-pong"), Extrapolation::PingPong),
+        ];
+        for &(option_label, option) in options.iter() {
+            let is_selected = *extrapolation == option;
+            if unsafe { igMenuItemBool(option_label, ptr::null(), is_selected, true) } {
+                *extrapolation = option;
+            }
+        }
+
+        unsafe { igEndMenu() };
+    }
+}
+
 fn draw_field(
     field: &mut AnimatedPropertyField,
     screen_pos: ImVec2,
@@ -1119,13 +2133,16 @@ fn draw_field(
     let start_x_pos = screen_pos.x + local_offset_pixels;
     let mut current_screen_pos = ImVec2::new(start_x_pos, screen_pos.y);
 
+    // Rotations are shown as the euler angles closest to the first keyframe's, so the curves
+    // don't jump between equivalent angles
+    let euler_reference = field.start_value;
+
     // Draw each of the curve segments
-    let mut start_val = field.start_value;
-    for (segment_index, segment) in field.segments.iter_mut().enumerate() {
+    for segment_index in 0..field.segments.len() {
         let segment_rect = ImVec4::new(
             current_screen_pos.x,
             current_screen_pos.y,
-            current_screen_pos.x + segment.duration_frames as f32 * scale,
+            current_screen_pos.x + field.segments[segment_index].duration_frames as f32 * scale,
             current_screen_pos.y + screen_height,
         );
 
@@ -1140,8 +2157,9 @@ fn draw_field(
                 igPushIDInt(segment_index as i32);
             }
             draw_curve_segment(
-                start_val,
-                segment,
+                field,
+                segment_index,
+                euler_reference,
                 segment_rect,
                 min_val,
                 max_val,
@@ -1152,13 +2170,13 @@ fn draw_field(
             };
         }
 
-        start_val = segment.end_value;
         current_screen_pos.x = segment_rect.z;
     }
 
     // Draw the curve controllers
     draw_curve_controller(
         &mut field.start_value,
+        euler_reference,
         ImVec2::new(start_x_pos, screen_pos.y),
         screen_height,
         min_val,
@@ -1174,6 +2192,7 @@ fn draw_field(
         current_x += segment.duration_frames as f32 * scale;
         draw_curve_controller(
             &mut segment.end_value,
+            euler_reference,
             ImVec2::new(current_x, screen_pos.y),
             screen_height,
             min_val,
@@ -1195,6 +2214,7 @@ fn get_colors_iter(field_index: usize) -> impl Iterator<Item = (f32, f32, f32)>
 
 fn draw_curve_controller(
     value: &mut PropertyValue,
+    euler_reference: PropertyValue,
     screen_pos: ImVec2,
     screen_height: f32,
     min_val: f32,
@@ -1206,7 +2226,7 @@ fn draw_curve_controller(
 
     let mut did_change_value = false;
     let mut new_value_fields = value
-        .fields()
+        .fields_near(euler_reference)
         .zip(get_colors_iter(field_index))
         .enumerate()
         .map(|(field_index, (field_val, field_color))| {
@@ -1267,8 +2287,9 @@ fn draw_curve_controller(
 }
 
 fn draw_curve_segment(
-    start_value: PropertyValue,
-    segment: &mut CurveSegment,
+    field: &mut AnimatedPropertyField,
+    segment_index: usize,
+    euler_reference: PropertyValue,
     target_rect: ImVec4,
     min_val: f32,
     max_val: f32,
@@ -1285,17 +2306,24 @@ fn draw_curve_segment(
         .into_iter()
         .map(|point_index| point_index as f32 * CURVE_PIXEL_SPACE / rect_width)
         .chain(iter::once(1.))
-        .map(|point_x| (point_x, segment.interpolation.eval(point_x)));
+        .map(|point_x| {
+            (
+                point_x,
+                field.segments[segment_index].interpolation.eval(point_x),
+            )
+        });
 
     // Draw lines for each field in the PropertyValue
     let mut last_interp_x = 0.;
-    let mut last_val = start_value;
+    let mut last_val = field.key_value(segment_index);
     for (interp_x, interp_y) in curve_interp_points {
-        let new_val = start_value.lerp(segment.end_value, interp_y).unwrap();
+        let new_val = field.interpolate_segment(segment_index, interp_y);
         let colors_iter = get_colors_iter(field_index);
 
-        for ((this_field_val, last_field_val), line_color) in
-            new_val.fields().zip(last_val.fields()).zip(colors_iter)
+        for ((this_field_val, last_field_val), line_color) in new_val
+            .fields_near(euler_reference)
+            .zip(last_val.fields_near(euler_reference))
+            .zip(colors_iter)
         {
             let last_normal_y = (last_field_val - min_val) / (max_val - min_val);
             let this_normal_y = (this_field_val - min_val) / (max_val - min_val);
@@ -1319,8 +2347,11 @@ fn draw_curve_segment(
     }
 
     // Draw interpolation-specific controls
-    match &mut segment.interpolation {
-        CurveInterpolation::Linear => {}
+    match &mut field.segments[segment_index].interpolation {
+        CurveInterpolation::Linear
+        | CurveInterpolation::Step
+        | CurveInterpolation::StepNext
+        | CurveInterpolation::Ease(_) => {}
         CurveInterpolation::CubicBezier(bezier) => {
             let c1 = bezier.c1();
             let c2 = bezier.c2();
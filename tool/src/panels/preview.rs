@@ -185,7 +185,7 @@ pub fn draw_preview(
                 let new_direction = roll_rotation * new_direction;
 
                 direction_default.value = PropertyValue::Rotation(new_direction);
-                if direction_active.targeted_by.is_some() {
+                if !direction_active.targeted_by.is_empty() {
                     direction_default.is_override = true;
                 }
 
@@ -198,7 +198,7 @@ pub fn draw_preview(
                 let new_position =
                     position_active.value.into_vec3().unwrap() + position_delta * new_direction;
                 position_default.value = PropertyValue::Vec3(new_position);
-                if position_active.targeted_by.is_some() {
+                if !position_active.targeted_by.is_empty() {
                     position_default.is_override = true;
                 }
 
@@ -211,7 +211,7 @@ pub fn draw_preview(
                     .max(1.)
                     .min(90.);
                 fov_default.value = PropertyValue::Float(new_fov);
-                if fov_active.targeted_by.is_some() {
+                if !fov_active.targeted_by.is_empty() {
                     fov_default.is_override = true;
                 }
             }
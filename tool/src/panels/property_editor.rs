@@ -5,23 +5,31 @@ use crate::cstr;
 use crate::editor_state::EditorState;
 use crate::imgui::DrawList;
 use crate::timeline_interactions::{deselect_all_clips, insert_keyframe, select_clip};
-use engine::animation::animation_clip::AnimatedPropertyTarget;
+use engine::animation::animation_clip::{AnimatedPropertyTarget, CurveInterpolation};
 use engine::animation::clip::{ActiveClipMap, ClipPropertyValue, ClipReference};
-use engine::animation::property::PropertyValue;
+use engine::animation::driver::{DriverMapping, PropertyDriver};
+use engine::animation::easing::Easing;
+use engine::animation::property::{PropertyType, PropertyValue};
 use engine::animation::schema::GeneratorSchema;
 use engine::animation::timeline::{Clip, ClipSource, PropertyDefault, Timeline};
-use engine::math::Quaternion;
+use engine::binding::PropertyBinding;
+use engine::math::{GradientStop, Quaternion, MAX_GRADIENT_STOPS};
 use imgui_sys::{
-    igArrowButton, igBegin, igButton, igCalcTextSize_nonUDT2, igCheckbox, igColorPicker3,
-    igColorPicker4, igDragFloat, igDragFloat2, igDragFloat3, igDragFloat4, igEnd,
+    igArrowButton, igBegin, igBeginCombo, igBeginPopupContextItem, igButton,
+    igCalcTextSize_nonUDT2, igCheckbox, igColorEdit4, igColorPicker3, igColorPicker4, igDragFloat,
+    igDragFloat2, igDragFloat3, igDragFloat4, igDragInt, igEnd, igEndCombo, igEndPopup,
     igGetContentRegionAvailWidth, igGetCursorPosX, igGetCursorScreenPos_nonUDT2, igGetIDStr,
-    igGetMousePos_nonUDT2, igInvisibleButton, igIsItemClicked, igIsItemHovered, igIsKeyDown,
-    igPopID, igPopItemWidth, igPopStyleColor, igPushIDInt, igPushItemWidth, igPushStyleColor,
-    igSameLine, igSetCursorPosX, igText, igTreeNodeExPtr, igTreePop, ImGuiCol, ImGuiColorEditFlags,
-    ImGuiDir, ImGuiHoveredFlags, ImGuiTreeNodeFlags, ImGuiWindowFlags, ImVec2, ImVec4,
+    igGetMousePos_nonUDT2, igIndent, igInvisibleButton, igIsItemClicked, igIsItemHovered,
+    igIsKeyDown, igMenuItemBool, igPopID, igPopItemWidth, igPopStyleColor, igPushIDInt,
+    igPushItemWidth, igPushStyleColor, igSameLine, igSelectable, igSetCursorPosX, igSetTooltip,
+    igSmallButton, igText, igTreeNodeExPtr, igTreePop, igUnindent, ImGuiCol, ImGuiColorEditFlags,
+    ImGuiComboFlags, ImGuiDir, ImGuiHoveredFlags, ImGuiSelectableFlags, ImGuiTreeNodeFlags,
+    ImGuiWindowFlags, ImVec2, ImVec4,
 };
+use std::collections::HashMap;
 use std::ffi::CString;
-use std::{iter, ptr};
+use std::iter::{self, FromIterator};
+use std::ptr;
 use winapi::um::winuser::VK_SHIFT;
 
 pub fn draw_property_editor(
@@ -55,6 +63,14 @@ pub fn draw_property_editor(
     }
 
     if show_window {
+        // Drivers can follow any clip, so they need to look up clips that aren't being edited
+        let clip_infos: ClipInfos = HashMap::from_iter(
+            timeline
+                .tracks
+                .iter()
+                .flat_map(|track| track.clips.iter())
+                .map(|clip| (clip.id, (clip.schema, clip.name.clone()))),
+        );
         let mut clip_rects = Vec::new();
         let mut new_keyframes = Vec::new();
         let mut selected_clips = timeline
@@ -76,6 +92,7 @@ pub fn draw_property_editor(
                 draw_clip_editor(
                     selected_clip,
                     clip_map,
+                    &clip_infos,
                     &mut clip_rects,
                     &mut new_keyframes,
                     editor_state,
@@ -94,6 +111,7 @@ pub fn draw_property_editor(
                 draw_clip_editor(
                     clip,
                     clip_map,
+                    &clip_infos,
                     &mut clip_rects,
                     &mut new_keyframes,
                     editor_state,
@@ -189,6 +207,9 @@ pub fn draw_property_editor(
                 AnimatedPropertyTarget::Separate(fields) => fields
                     .iter_mut()
                     .all(|field| insert_keyframe(field, active_clip.local_time as i32, value)),
+                AnimatedPropertyTarget::Expression(_)
+                | AnimatedPropertyTarget::Path(_)
+                | AnimatedPropertyTarget::Constraint(_) => false,
             };
 
             if could_insert_keyframe {
@@ -204,9 +225,13 @@ pub fn draw_property_editor(
     unsafe { igEnd() };
 }
 
+/// The schema and name of each clip, by clip ID.
+type ClipInfos = HashMap<u32, (&'static GeneratorSchema, String)>;
+
 fn draw_clip_editor(
     clip: &mut Clip,
     clip_map: &ActiveClipMap,
+    clip_infos: &ClipInfos,
     clip_rects: &mut Vec<(Option<ClipReference>, ImVec2, ImVec2, bool)>,
     new_keyframes: &mut Vec<(ClipReference, ClipReference, usize, usize, PropertyValue)>,
     editor_state: &mut EditorState,
@@ -262,6 +287,7 @@ fn draw_clip_editor(
                         prop,
                         active_prop,
                         clip_schema,
+                        clip_infos,
                         clip_rects,
                         new_keyframes,
                         editor_state,
@@ -290,6 +316,7 @@ fn draw_property(
     prop: &mut PropertyDefault,
     active_prop: Option<&ClipPropertyValue>,
     schema: &'static GeneratorSchema,
+    clip_infos: &ClipInfos,
     clip_rects: &mut Vec<(Option<ClipReference>, ImVec2, ImVec2, bool)>,
     new_keyframes: &mut Vec<(ClipReference, ClipReference, usize, usize, PropertyValue)>,
     editor_state: &mut EditorState,
@@ -301,7 +328,7 @@ fn draw_property(
 
     // display/allow changing override state and keyframe button
     let is_targeted = match active_prop {
-        Some(active_prop) => active_prop.targeted_by.is_some(),
+        Some(active_prop) => !active_prop.targeted_by.is_empty(),
         None => false,
     };
     let is_overridden = match active_prop {
@@ -330,7 +357,10 @@ fn draw_property(
         igSameLine(0., -1.);
         if igArrowButton(cstr!("##keyframe"), ImGuiDir::Down) {
             //let target_animation_clip = active_prop.and_then(|prop| prop.targeted_by);
-            if let Some(target_animation_clip) = active_prop.and_then(|prop| prop.targeted_by) {
+            // Keyframes go into the clip that was blended last, since it has the final say
+            let target_animation_clip =
+                active_prop.and_then(|prop| prop.targeted_by.last().cloned());
+            if let Some(target_animation_clip) = target_animation_clip {
                 new_keyframes.push((
                     target_animation_clip,
                     current_clip,
@@ -355,6 +385,7 @@ fn draw_property(
     // display name
     let schema_prop = &schema.groups[group_index].properties[prop_index];
     let name_cstr = CString::new(&schema_prop.name as &str).unwrap();
+    let driver_button_id = unsafe { igGetIDStr(cstr!("driver button")) };
     unsafe {
         let current_x = igGetCursorPosX();
 
@@ -363,22 +394,98 @@ fn draw_property(
 
         igSetCursorPosX(current_x + reserve_width - text_width.x - 10.);
         igText(cstr!("%s"), name_cstr.as_ptr());
+        if let Some(description) = schema_prop.description {
+            if igIsItemHovered(ImGuiHoveredFlags::empty()) {
+                let description_cstr = CString::new(description).unwrap();
+                igSetTooltip(cstr!("%s"), description_cstr.as_ptr());
+            }
+        }
+
+        // Right clicking the name allows driving the property from another clip, which is picked
+        // by clicking it in the timeline
+        if igBeginPopupContextItem(cstr!("driver menu"), 1) {
+            if prop.driver.is_none() {
+                if igMenuItemBool(cstr!("Drive from clip..."), ptr::null(), false, true) {
+                    editor_state.select_clip_request = Some(driver_button_id);
+                    editor_state.select_clip_response = None;
+                }
+            } else if igMenuItemBool(cstr!("Remove driver"), ptr::null(), false, true) {
+                prop.driver = None;
+            }
+            igEndPopup();
+        }
+
         igSameLine(current_x + reserve_width, -1.);
         igPushItemWidth(igGetContentRegionAvailWidth());
     };
 
+    if editor_state.select_clip_request == Some(driver_button_id) {
+        if let Some(clip_response) = editor_state.select_clip_response {
+            prop.driver = Some(PropertyDriver::new(
+                clip_response,
+                PropertyBinding::new(0, 0),
+            ));
+            editor_state.select_clip_request = None;
+            editor_state.select_clip_response = None;
+        }
+    }
+
+    // Dragging is limited to the soft range, falling back to the hard range. A range of 0 to 0
+    // doesn't limit it at all.
+    let step = schema_prop.drag_step();
+    let (drag_min, drag_max) = schema_prop
+        .soft_range
+        .or(schema_prop.hard_range)
+        .unwrap_or((0., 0.));
+    let format_cstr = match schema_prop.unit {
+        Some(unit) => CString::new(format!("%.3f {}", unit.replace('%', "%%"))).unwrap(),
+        None => CString::new("%.3f").unwrap(),
+    };
+
     let did_change = match &mut current_val {
         PropertyValue::Float(val) => unsafe {
-            igDragFloat(cstr!(""), val, 0.01, 0., 0., cstr!("%.3f"), 2.)
+            igDragFloat(
+                cstr!(""),
+                val,
+                step,
+                drag_min,
+                drag_max,
+                format_cstr.as_ptr(),
+                2.,
+            )
         },
         PropertyValue::Vec2(val) => unsafe {
-            igDragFloat2(cstr!(""), &mut val.x, 0.01, 0., 0., cstr!("%.3f"), 2.)
+            igDragFloat2(
+                cstr!(""),
+                &mut val.x,
+                step,
+                drag_min,
+                drag_max,
+                format_cstr.as_ptr(),
+                2.,
+            )
         },
         PropertyValue::Vec3(val) => unsafe {
-            igDragFloat3(cstr!(""), &mut val.x, 0.01, 0., 0., cstr!("%.3f"), 2.)
+            igDragFloat3(
+                cstr!(""),
+                &mut val.x,
+                step,
+                drag_min,
+                drag_max,
+                format_cstr.as_ptr(),
+                2.,
+            )
         },
         PropertyValue::Vec4(val) => unsafe {
-            igDragFloat4(cstr!(""), &mut val.x, 0.01, 0., 0., cstr!("%.3f"), 2.)
+            igDragFloat4(
+                cstr!(""),
+                &mut val.x,
+                step,
+                drag_min,
+                drag_max,
+                format_cstr.as_ptr(),
+                2.,
+            )
         },
         PropertyValue::RgbColor(val) => unsafe {
             igColorPicker3(
@@ -403,6 +510,105 @@ fn draw_property(
                 ptr::null(),
             )
         },
+        PropertyValue::Int(val) => unsafe {
+            igDragInt(
+                cstr!(""),
+                val,
+                step,
+                drag_min as i32,
+                drag_max as i32,
+                cstr!("%d"),
+            )
+        },
+        PropertyValue::Bool(val) => unsafe { igCheckbox(cstr!(""), val) },
+        PropertyValue::Enum(val) => {
+            let options = match schema_prop.value_type {
+                PropertyType::Enum(options) => options,
+                _ => &[],
+            };
+            let current_name =
+                CString::new(options.get(*val as usize).cloned().unwrap_or("?")).unwrap();
+
+            let mut did_select = false;
+            if unsafe { igBeginCombo(cstr!(""), current_name.as_ptr(), ImGuiComboFlags::empty()) } {
+                for (option_index, &option) in options.iter().enumerate() {
+                    let option_cstr = CString::new(option).unwrap();
+                    let is_selected = option_index == *val as usize;
+                    if unsafe {
+                        igSelectable(
+                            option_cstr.as_ptr(),
+                            is_selected,
+                            ImGuiSelectableFlags::empty(),
+                            ImVec2::new(0., 0.),
+                        )
+                    } && !is_selected
+                    {
+                        *val = option_index as u32;
+                        did_select = true;
+                    }
+                }
+                unsafe { igEndCombo() };
+            }
+            did_select
+        }
+        PropertyValue::Gradient(val) => {
+            let mut did_edit = false;
+            let mut remove_stop = None;
+            for (stop_index, stop) in val.stops_mut().iter_mut().enumerate() {
+                unsafe {
+                    igPushIDInt(stop_index as i32);
+                    igPushItemWidth(60.);
+                    did_edit |= igDragFloat(
+                        cstr!("##position"),
+                        &mut stop.position,
+                        0.005,
+                        0.,
+                        1.,
+                        cstr!("%.3f"),
+                        1.,
+                    );
+                    igPopItemWidth();
+                    igSameLine(0., -1.);
+                    did_edit |= igColorEdit4(
+                        cstr!("##color"),
+                        &mut stop.color.0.x,
+                        ImGuiColorEditFlags::RGB
+                            | ImGuiColorEditFlags::Float
+                            | ImGuiColorEditFlags::AlphaBar
+                            | ImGuiColorEditFlags::AlphaPreview
+                            | ImGuiColorEditFlags::NoInputs
+                            | ImGuiColorEditFlags::HDR,
+                    );
+                    igSameLine(0., -1.);
+                    if igSmallButton(cstr!("x")) {
+                        remove_stop = Some(stop_index);
+                    }
+                    igPopID();
+                }
+            }
+
+            if let Some(stop_index) = remove_stop {
+                val.remove(stop_index);
+                did_edit = true;
+            }
+            if val.stops().len() < MAX_GRADIENT_STOPS && unsafe { igSmallButton(cstr!("+ stop")) } {
+                // New stops go halfway between the last two, so they don't change the gradient
+                let last_stop = val.stop(val.stops().len() - 1);
+                let previous_position = val.stop(val.stops().len().max(2) - 2).position;
+                let position = if val.stops().len() > 1 {
+                    (previous_position + last_stop.position) / 2.
+                } else {
+                    last_stop.position
+                };
+                val.insert(GradientStop::new(position, val.eval(position)));
+                did_edit = true;
+            }
+
+            if did_edit {
+                val.sort();
+            }
+            did_edit
+        }
         PropertyValue::Rotation(val) => {
             // todo: there's probably something nicer we can do to display this
             let euler = val.as_euler();
@@ -416,10 +622,10 @@ fn draw_property(
                 igDragFloat3(
                     cstr!(""),
                     &mut euler_degrees.0,
-                    1.,
-                    0.,
-                    0.,
-                    cstr!("%.3f"),
+                    step,
+                    drag_min,
+                    drag_max,
+                    format_cstr.as_ptr(),
                     1.,
                 )
             } {
@@ -496,14 +702,217 @@ fn draw_property(
 
     unsafe { igPopItemWidth() };
 
+    if let Some(driver) = &mut prop.driver {
+        if !draw_driver(driver, clip_infos) {
+            prop.driver = None;
+        }
+    }
+
     if did_change {
-        prop.value = current_val;
+        prop.value = schema_prop.clamp(current_val);
+        editor_state.edited_clips.push(current_clip);
 
         // Update the override mode: if currently animating, enable override
         if let Some(active_prop) = active_prop {
-            if active_prop.targeted_by.is_some() {
+            if !active_prop.targeted_by.is_empty() {
                 prop.is_override = true;
             }
         }
     }
 }
+
+/// Draws the source and mapping of a property's driver. Returns false if the driver was removed.
+fn draw_driver(driver: &mut PropertyDriver, clip_infos: &ClipInfos) -> bool {
+    let mut keep_driver = true;
+    unsafe {
+        igIndent(90.);
+        igPushItemWidth(igGetContentRegionAvailWidth() - 30.);
+    }
+
+    match clip_infos.get(&driver.source_clip.clip_id()) {
+        Some((source_schema, source_name)) => {
+            let source_prop = source_schema
+                .groups
+                .get(driver.source.group)
+                .and_then(|group| {
+                    group
+                        .properties
+                        .get(driver.source.prop)
+                        .map(|prop| (group, prop))
+                });
+            let preview = match source_prop {
+                Some((group, prop)) => format!("{}: {} / {}", source_name, group.name, prop.name),
+                None => format!("{}: ?", source_name),
+            };
+            let preview_cstr = CString::new(preview).unwrap();
+
+            if unsafe {
+                igBeginCombo(
+                    cstr!("##source"),
+                    preview_cstr.as_ptr(),
+                    ImGuiComboFlags::empty(),
+                )
+            } {
+                for (group_index, group) in source_schema.groups.iter().enumerate() {
+                    for (prop_index, prop) in group.properties.iter().enumerate() {
+                        let label = CString::new(format!(
+                            "{} / {}##{}_{}",
+                            group.name, prop.name, group_index, prop_index
+                        ))
+                        .unwrap();
+                        let is_selected =
+                            driver.source.group == group_index && driver.source.prop == prop_index;
+                        if unsafe {
+                            igSelectable(
+                                label.as_ptr(),
+                                is_selected,
+                                ImGuiSelectableFlags::empty(),
+                                ImVec2::new(0., 0.),
+                            )
+                        } {
+                            driver.source = PropertyBinding::new(group_index, prop_index);
+                        }
+                    }
+                }
+                unsafe { igEndCombo() };
+            }
+        }
+        None => unsafe { igText(cstr!("The source clip has been deleted")) },
+    }
+    unsafe {
+        igPopItemWidth();
+        igSameLine(0., -1.);
+        if igSmallButton(cstr!("x##driver")) {
+            keep_driver = false;
+        }
+    }
+
+    // Clicking the mapping's name switches between the two kinds
+    let mapping_label = match driver.mapping {
+        DriverMapping::Linear { .. } => cstr!("linear"),
+        DriverMapping::Remap { .. } => cstr!("remap"),
+    };
+    if unsafe { igSmallButton(mapping_label) } {
+        driver.mapping = match driver.mapping {
+            DriverMapping::Linear { .. } => DriverMapping::Remap {
+                input_min: 0.,
+                input_max: 1.,
+                output_min: 0.,
+                output_max: 1.,
+                curve: CurveInterpolation::Linear,
+            },
+            DriverMapping::Remap { .. } => DriverMapping::default(),
+        };
+    }
+
+    unsafe {
+        igSameLine(0., -1.);
+        igPushItemWidth(60.);
+    }
+    match &mut driver.mapping {
+        DriverMapping::Linear { scale, offset } => unsafe {
+            igDragFloat(cstr!("##scale"), scale, 0.01, 0., 0., cstr!("x %.3f"), 1.);
+            igSameLine(0., -1.);
+            igDragFloat(cstr!("##offset"), offset, 0.01, 0., 0., cstr!("+ %.3f"), 1.);
+        },
+        DriverMapping::Remap {
+            input_min,
+            input_max,
+            output_min,
+            output_max,
+            curve,
+        } => {
+            unsafe {
+                igDragFloat(
+                    cstr!("##input min"),
+                    input_min,
+                    0.01,
+                    0.,
+                    0.,
+                    cstr!("%.3f"),
+                    1.,
+                );
+                igSameLine(0., -1.);
+                igDragFloat(
+                    cstr!("##input max"),
+                    input_max,
+                    0.01,
+                    0.,
+                    0.,
+                    cstr!("%.3f"),
+                    1.,
+                );
+                igSameLine(0., -1.);
+                igText(cstr!("->"));
+                igSameLine(0., -1.);
+                igDragFloat(
+                    cstr!("##output min"),
+                    output_min,
+                    0.01,
+                    0.,
+                    0.,
+                    cstr!("%.3f"),
+                    1.,
+                );
+                igSameLine(0., -1.);
+                igDragFloat(
+                    cstr!("##output max"),
+                    output_max,
+                    0.01,
+                    0.,
+                    0.,
+                    cstr!("%.3f"),
+                    1.,
+                );
+                igSameLine(0., -1.);
+            }
+
+            // Remapping curves can be linear or use any of the easings
+            let curve_name = match *curve {
+                CurveInterpolation::Ease(easing) => easing.name(),
+                _ => "Linear",
+            };
+            let curve_name_cstr = CString::new(curve_name).unwrap();
+            unsafe { igPushItemWidth(100.) };
+            if unsafe {
+                igBeginCombo(
+                    cstr!("##curve"),
+                    curve_name_cstr.as_ptr(),
+                    ImGuiComboFlags::empty(),
+                )
+            } {
+                if unsafe {
+                    igSelectable(
+                        cstr!("Linear"),
+                        curve.is_linear(),
+                        ImGuiSelectableFlags::empty(),
+                        ImVec2::new(0., 0.),
+                    )
+                } {
+                    *curve = CurveInterpolation::Linear;
+                }
+                for &easing in Easing::ALL.iter() {
+                    let label = CString::new(easing.name()).unwrap();
+                    if unsafe {
+                        igSelectable(
+                            label.as_ptr(),
+                            curve.is_ease(easing),
+                            ImGuiSelectableFlags::empty(),
+                            ImVec2::new(0., 0.),
+                        )
+                    } {
+                        *curve = CurveInterpolation::Ease(easing);
+                    }
+                }
+                unsafe { igEndCombo() };
+            }
+            unsafe { igPopItemWidth() };
+        }
+    }
+
+    unsafe {
+        igPopItemWidth();
+        igUnindent(90.);
+    }
+    keep_driver
+}
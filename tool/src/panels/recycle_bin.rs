@@ -1,15 +1,27 @@
 use crate::cstr;
 use crate::editor_state::EditorState;
-use crate::recycle_bin::RecycleBin;
+use crate::recycle_bin::{RecycleBin, RestorePosition};
+use engine::animation::timeline::Timeline;
+use engine::creation_context::CreationContext;
 use imgui_sys::{
-    igBegin, igEnd, igPopStyleVar, igPushStyleVarVec2, ImGuiStyleVar, ImGuiWindowFlags, ImVec2,
+    igBegin, igButton, igEnd, igPopID, igPushIDInt, igSameLine, igSeparator, igText,
+    ImGuiWindowFlags, ImVec2,
 };
+use std::ffi::CString;
 use std::ptr;
 
-pub fn draw_recycle_bin(bin: &mut RecycleBin, editor_state: &mut EditorState) {
-    unsafe {
-        igPushStyleVarVec2(ImGuiStyleVar::WindowPadding, ImVec2::new(0., 0.));
-    }
+enum BinAction {
+    Restore(usize, RestorePosition),
+    Purge(usize),
+    PurgeAll,
+}
+
+pub fn draw_recycle_bin(
+    bin: &mut RecycleBin,
+    timeline: &mut Timeline,
+    editor_state: &mut EditorState,
+    creation_context: &mut CreationContext,
+) {
     let show_window = unsafe {
         igBegin(
             cstr!("Recycle Bin"),
@@ -17,12 +29,65 @@ pub fn draw_recycle_bin(bin: &mut RecycleBin, editor_state: &mut EditorState) {
             ImGuiWindowFlags::empty(),
         )
     };
-    unsafe {
-        igPopStyleVar(1);
-    }
 
     if show_window {
-        // todo
+        let mut action = None;
+
+        if bin.entries.is_empty() {
+            unsafe {
+                igText(cstr!("Deleted clips will appear here"));
+            }
+        } else if unsafe { igButton(cstr!("Purge All"), ImVec2::new(0., 0.)) } {
+            action = Some(BinAction::PurgeAll);
+        }
+
+        // Most recently deleted first
+        for (entry_index, entry) in bin.entries.iter().enumerate().rev() {
+            let deleted_clip = &entry.clips[0].clip;
+            let label = match entry.clips.len() - 1 {
+                0 => deleted_clip.name.clone(),
+                1 => format!("{} (1 animation)", deleted_clip.name),
+                animation_count => {
+                    format!("{} ({} animations)", deleted_clip.name, animation_count)
+                }
+            };
+            let label_cstr = CString::new(label).unwrap();
+
+            unsafe {
+                igPushIDInt(entry_index as i32);
+                igSeparator();
+                igText(cstr!("%s"), label_cstr.as_ptr());
+
+                if igButton(cstr!("Restore"), ImVec2::new(0., 0.)) {
+                    action = Some(BinAction::Restore(entry_index, RestorePosition::Original));
+                }
+                igSameLine(0., -1.);
+                if igButton(cstr!("Restore at Playhead"), ImVec2::new(0., 0.)) {
+                    let position = RestorePosition::Frame(editor_state.current_frame());
+                    action = Some(BinAction::Restore(entry_index, position));
+                }
+                igSameLine(0., -1.);
+                if igButton(cstr!("Purge"), ImVec2::new(0., 0.)) {
+                    action = Some(BinAction::Purge(entry_index));
+                }
+                igPopID();
+            }
+        }
+
+        match action {
+            Some(BinAction::Restore(entry_index, position)) => {
+                bin.restore(
+                    entry_index,
+                    position,
+                    timeline,
+                    &mut editor_state.next_clip_id,
+                );
+                timeline.instantiate_generators(creation_context);
+            }
+            Some(BinAction::Purge(entry_index)) => bin.purge(entry_index),
+            Some(BinAction::PurgeAll) => bin.purge_all(),
+            None => {}
+        }
     }
 
     unsafe {
@@ -1,6 +1,6 @@
 use crate::cstr;
-use crate::editor_state::EditorState;
 use crate::imgui::DrawList;
+use engine::animation::timeline::TimeBase;
 use imgui_sys::{
     igGetCursorPosY, igGetCursorScreenPos_nonUDT2, igInvisibleButton, igSetCursorPosY, ImVec2,
 };
@@ -9,18 +9,20 @@ pub const SCRUBBER_HEIGHT: f32 = 20.;
 const MARKER_DIVIDER: f32 = 2.;
 
 pub fn draw_time_bar(
-    fpb: f32,
-    start_beats: f32,
-    end_beats: f32,
+    start_frames: f32,
+    end_frames: f32,
     pixel_width: f32,
     extend_lines_height: f32,
-    editor_state: &EditorState,
+    time_base: TimeBase,
 ) {
     if pixel_width == 0. {
         return;
     }
 
-    let time_scale = pixel_width / ((end_beats - start_beats) * fpb);
+    let time_scale = pixel_width / (end_frames - start_frames);
+    let start_beats = time_base.frame_to_beats(start_frames);
+    let end_beats = time_base.frame_to_beats(end_frames);
+    let beat_scale = pixel_width / (end_beats - start_beats);
 
     let screen_top_left = unsafe { igGetCursorScreenPos_nonUDT2() };
     let cursor_start_y = unsafe { igGetCursorPosY() };
@@ -40,15 +42,15 @@ pub fn draw_time_bar(
     );
 
     let mark_beats =
-        MARKER_DIVIDER.powf(((80. / (time_scale * fpb)).log2() / MARKER_DIVIDER.log2()).ceil());
+        MARKER_DIVIDER.powf(((80. / beat_scale).log2() / MARKER_DIVIDER.log2()).ceil());
     let first_marker_index = (start_beats / mark_beats).floor();
     let last_marker_index = (end_beats / mark_beats).ceil();
 
     for marker_index in (first_marker_index as i32)..(last_marker_index as i32) {
         let marker_beats = marker_index as f32 * mark_beats;
-        let marker_pixels = marker_beats * time_scale * fpb;
+        let marker_frames = time_base.beats_to_frame(marker_beats);
         let global_marker_x =
-            (screen_top_left.x - start_beats * time_scale * fpb + marker_pixels).floor();
+            (screen_top_left.x + (marker_frames - start_frames) * time_scale).floor();
 
         draw_list.draw_line(
             (global_marker_x, screen_top_left.y),
@@ -69,30 +71,22 @@ pub fn draw_time_bar(
             );
         }
 
-        let current_bar = marker_beats as i32 / editor_state.beats_per_bar as i32;
-        let current_beat = marker_beats as i32 % editor_state.beats_per_bar as i32;
-        let marker_label = if marker_beats >= 0. {
-            if marker_beats.fract() > 0.001 {
-                format!(
-                    "{:02}:{:02}.{:02}",
-                    current_bar,
-                    current_beat,
-                    (marker_beats.fract() * 100.) as i32
-                )
-            } else {
-                format!("{:02}:{:02}", current_bar, current_beat)
-            }
+        // Times before the start are labelled by how far before the start they are
+        let (sign, position) = if marker_beats >= 0. {
+            ("", time_base.tempo_map.beats_to_position(marker_beats))
         } else {
-            if marker_beats.fract() < -0.001 {
-                format!(
-                    "-{:02}:{:02}.{:02}",
-                    -current_bar,
-                    -current_beat,
-                    -(marker_beats.fract() * 100.) as i32
-                )
-            } else {
-                format!("-{:02}:{:02}", -current_bar, -current_beat)
-            }
+            ("-", time_base.tempo_map.beats_to_position(-marker_beats))
+        };
+        let marker_label = if position.beat.fract() > 0.001 {
+            format!(
+                "{}{:02}:{:02}.{:02}",
+                sign,
+                position.bar,
+                position.beat as i32,
+                (position.beat.fract() * 100.) as i32
+            )
+        } else {
+            format!("{}{:02}:{:02}", sign, position.bar, position.beat as i32)
         };
 
         draw_list.draw_text(
@@ -1,37 +1,47 @@
 use super::{draw_time_bar, get_fpb, zoom_to_time_scale};
+use crate::clipboard::{copy_selected_clips, paste_clips};
 use crate::cstr;
 use crate::editor_state::EditorState;
 use crate::imgui::{DrawList, ImColor};
+use crate::recycle_bin::RecycleBin;
 use crate::timeline_interactions::{
-    can_fit_clip, change_selected_clip_tracks, deselect_all_clips, get_snapping_points,
-    insert_clip, move_selected_clips, remove_clip, resize_selected_clips_left,
-    resize_selected_clips_right, select_clip, snap_offset, trim_empty_tracks,
+    can_fit_clip, change_selected_clip_tracks, clip_anchor_at, deselect_all_clips,
+    get_snapping_points, insert_clip, insert_keyframe, move_selected_clips,
+    resize_selected_clips_left, resize_selected_clips_right, retime_anchored_items, select_clip,
+    snap_offset, trim_empty_tracks, update_selected_clip_anchors,
 };
 use engine::animation::animation_clip::{
-    AnimatedProperty, AnimatedPropertyField, AnimatedPropertyTarget, AnimationClip,
+    AnimatedProperty, AnimatedPropertyField, AnimatedPropertyTarget, AnimationClip, BlendMode,
+    CurveInterpolation, CurveSegment, Extrapolation,
 };
 use engine::animation::clip::ClipReference;
+use engine::animation::coallesce::get_animation_field_value;
+use engine::animation::property::PropertyValue;
 use engine::animation::schema::GeneratorSchema;
+use engine::animation::tempo_map::{TempoChange, TempoMap};
 use engine::animation::timeline::{Clip, ClipSource, Timeline, Track};
 use engine::creation_context::CreationContext;
 use engine::generator::GENERATOR_SCHEMAS;
+use engine::math::ColorSpace;
 use imgui_sys::{
-    igBegin, igBeginChild, igBeginPopupContextItem, igButton, igCalcTextSize_nonUDT2, igDummy,
-    igEnd, igEndChild, igEndPopup, igGetContentRegionAvail_nonUDT2, igGetCursorPosX,
-    igGetCursorPosY, igGetCursorPos_nonUDT2, igGetCursorScreenPos_nonUDT2, igGetIO,
-    igGetMouseDragDelta_nonUDT2, igGetMousePos_nonUDT2, igGetScrollX, igGetScrollY,
-    igGetWindowContentRegionMax_nonUDT2, igGetWindowContentRegionWidth, igGetWindowPos_nonUDT2,
-    igIndent, igInputText, igInvisibleButton, igIsItemActive, igIsItemClicked, igIsItemHovered,
-    igIsKeyDown, igIsKeyPressed, igIsMouseClicked, igIsMouseDragging, igIsMouseReleased,
-    igIsWindowFocused, igIsWindowHovered, igMenuItemBool, igPopID, igPopItemWidth, igPopStyleColor,
-    igPopStyleVar, igPushIDInt, igPushItemWidth, igPushStyleColor, igPushStyleVarVec2,
-    igResetMouseDragDelta, igSameLine, igSetCursorPosX, igSetCursorPosY, igSetKeyboardFocusHere,
-    igSetMouseCursor, igSetScrollX, igSetScrollY, igSliderFloat, igText, ImGuiCol,
-    ImGuiFocusedFlags, ImGuiHoveredFlags, ImGuiInputTextFlags, ImGuiMouseCursor, ImGuiStyleVar,
-    ImGuiWindowFlags, ImVec2,
+    igBegin, igBeginChild, igBeginMenu, igBeginPopup, igBeginPopupContextItem, igButton,
+    igCalcTextSize_nonUDT2, igDragFloat, igDragInt, igDummy, igEnd, igEndChild, igEndMenu,
+    igEndPopup, igGetContentRegionAvail_nonUDT2, igGetCursorPosX, igGetCursorPosY,
+    igGetCursorPos_nonUDT2, igGetCursorScreenPos_nonUDT2, igGetIO, igGetMouseDragDelta_nonUDT2,
+    igGetMousePos_nonUDT2, igGetScrollX, igGetScrollY, igGetWindowContentRegionMax_nonUDT2,
+    igGetWindowContentRegionWidth, igGetWindowPos_nonUDT2, igIndent, igInputText,
+    igInvisibleButton, igIsItemActive, igIsItemClicked, igIsItemDeactivatedAfterEdit,
+    igIsItemHovered, igIsKeyDown, igIsKeyPressed, igIsMouseClicked, igIsMouseDragging,
+    igIsMouseReleased, igIsWindowFocused, igIsWindowHovered, igMenuItemBool, igOpenPopup, igPopID,
+    igPopItemWidth, igPopStyleColor, igPopStyleVar, igPushIDInt, igPushItemWidth, igPushStyleColor,
+    igPushStyleVarVec2, igResetMouseDragDelta, igSameLine, igSetCursorPosX, igSetCursorPosY,
+    igSetKeyboardFocusHere, igSetMouseCursor, igSetScrollX, igSetScrollY, igSliderFloat, igText,
+    ImGuiCol, ImGuiFocusedFlags, ImGuiHoveredFlags, ImGuiInputTextFlags, ImGuiMouseCursor,
+    ImGuiStyleVar, ImGuiWindowFlags, ImVec2,
 };
+use std::cell::RefCell;
 use std::ffi::CString;
-use std::{mem, ptr};
+use std::{mem, ptr, u32};
 use winapi::um::winuser::{VK_DELETE, VK_LEFT, VK_RIGHT, VK_SHIFT, VK_SPACE};
 
 pub const TRACK_HEIGHT: f32 = 20.;
@@ -39,27 +49,28 @@ const TOOL_AREA_HEIGHT: f32 = 30.;
 
 pub fn get_clip_border_color(clip: &Clip) -> ImColor {
     match clip.source {
-        ClipSource::Generator(_) => (0.067, 0.298, 0.165).into(),
+        ClipSource::Generator(_) | ClipSource::Unloaded => (0.067, 0.298, 0.165).into(),
         ClipSource::Animation(_) => (0.3, 0.3, 0.3).into(),
     }
 }
 
 pub fn get_clip_selected_color(clip: &Clip) -> ImColor {
     match clip.source {
-        ClipSource::Generator(_) => (0.227, 1.000, 0.549).into(),
+        ClipSource::Generator(_) | ClipSource::Unloaded => (0.227, 1.000, 0.549).into(),
         ClipSource::Animation(_) => (0.8, 0.8, 0.8).into(),
     }
 }
 
 pub fn get_clip_nonselected_color(clip: &Clip) -> ImColor {
     match clip.source {
-        ClipSource::Generator(_) => (0.180, 0.800, 0.443).into(),
+        ClipSource::Generator(_) | ClipSource::Unloaded => (0.180, 0.800, 0.443).into(),
         ClipSource::Animation(_) => (0.6, 0.6, 0.6).into(),
     }
 }
 
 pub fn draw_timeline(
     timeline: &mut Timeline,
+    recycle_bin: &mut RecycleBin,
     editor_state: &mut EditorState,
     creation_context: &mut CreationContext,
 ) {
@@ -68,12 +79,15 @@ pub fn draw_timeline(
         editor_state.play_pause();
     }
 
+    let time_base = editor_state.time_base(&timeline.tempo_map);
+    let current_beats = time_base.frame_to_beats(editor_state.current_frame() as f32);
     let seek_beats = if unsafe { igIsKeyDown(VK_SHIFT) } {
-        editor_state.beats_per_bar
+        timeline.tempo_map.beats_per_bar_at(current_beats)
     } else {
         1
     };
-    let seek_frames = editor_state.seconds_to_frame(seek_beats as f32 / editor_state.bpm * 60.);
+    let seek_frames = time_base.beats_to_frame(current_beats + seek_beats as f32)
+        - editor_state.current_frame() as f32;
 
     if unsafe { igIsKeyPressed(VK_LEFT, true) } {
         editor_state.seek_relative(-(seek_frames as i32));
@@ -130,17 +144,27 @@ pub fn draw_timeline(
                 igSameLine(0., 10.);
 
                 let current_seconds = editor_state.frame_to_seconds(editor_state.current_frame());
-                let current_beats = current_seconds * editor_state.bpm / 60.;
+                let current_position =
+                    time_base.frame_to_position(editor_state.current_frame() as f32);
                 igText(
                     cstr!("Time: %02u:%02u.%02u (%02u:%02u.%02us)"),
-                    current_beats as u32 / editor_state.beats_per_bar,
-                    current_beats as u32 % editor_state.beats_per_bar,
-                    (current_beats.fract() * 100.) as u32,
+                    current_position.bar as u32,
+                    current_position.beat as u32,
+                    (current_position.beat.fract() * 100.) as u32,
                     current_seconds as u32 / 60,
                     current_seconds as u32 % 60,
                     (current_seconds.fract() * 100.) as u32,
                 );
 
+                igSameLine(0., 10.);
+                if igButton(cstr!("Tempo"), ImVec2::new(0., 0.)) {
+                    igOpenPopup(cstr!("tempo map"));
+                }
+                if igBeginPopup(cstr!("tempo map"), ImGuiWindowFlags::empty()) {
+                    draw_tempo_map_menu(timeline, editor_state);
+                    igEndPopup();
+                }
+
                 igSameLine(0., 0.);
                 igSetCursorPosX(window_size.x - 315.);
                 clip_selector(timeline, editor_state);
@@ -174,7 +198,7 @@ pub fn draw_timeline(
                     | ImGuiWindowFlags::NoScrollWithMouse,
             )
         } {
-            let fpb = get_fpb(editor_state.fps, editor_state.bpm);
+            let fpb = get_fpb(editor_state.time_base(&timeline.tempo_map));
             let time_scale = zoom_to_time_scale(last_zoom, fpb);
             let current_time_pixels = editor_state.current_frame() as f32 * time_scale;
             let screen_cursor_pos = unsafe { igGetCursorScreenPos_nonUDT2() };
@@ -228,7 +252,7 @@ pub fn draw_timeline(
                 unsafe { igResetMouseDragDelta(2) };
             }
 
-            draw_scrubber_bar(time_scale, editor_state);
+            draw_scrubber_bar(time_scale, &timeline.tempo_map, editor_state);
 
             let mut draw_list = DrawList::for_current_window();
             let window_pos = unsafe { igGetWindowPos_nonUDT2() };
@@ -327,6 +351,18 @@ pub fn draw_timeline(
                         }
                         ClipInteraction::None => unreachable!(),
                     }
+
+                    update_selected_clip_anchors(timeline, editor_state.fps);
+
+                    // Moving or resizing a clip changes the frames its animations are at
+                    editor_state.edited_clips.extend(
+                        timeline
+                            .tracks
+                            .iter()
+                            .flat_map(|track| track.clips.iter())
+                            .filter(|clip| clip.is_selected)
+                            .map(|clip| ClipReference::new(clip.id)),
+                    );
                 }
             }
 
@@ -350,19 +386,42 @@ pub fn draw_timeline(
             igEndChild();
         };
 
-        // Delete any selected clips
+        // Move any selected clips to the recycle bin. Generator clips go first, so animation
+        // clips targeting them are binned with them.
         if unsafe {
             igIsWindowFocused(ImGuiFocusedFlags::ChildWindows) && igIsKeyPressed(VK_DELETE, false)
         } {
-            for track in &mut timeline.tracks {
-                let mut clip_index = 0;
-                while clip_index < track.clips.len() {
-                    if track.clips[clip_index].is_selected {
-                        remove_clip(track, clip_index);
-                    } else {
-                        clip_index += 1;
-                    }
-                }
+            let mut selected_clips: Vec<_> = timeline
+                .tracks
+                .iter()
+                .flat_map(|track| track.clips.iter())
+                .filter(|clip| clip.is_selected)
+                .map(|clip| {
+                    let is_animation = match clip.source {
+                        ClipSource::Animation(_) => true,
+                        _ => false,
+                    };
+                    (is_animation, clip.id)
+                })
+                .collect();
+            selected_clips.sort_by_key(|&(is_animation, _)| is_animation);
+            for (_, clip_id) in selected_clips {
+                recycle_bin.delete_clip(timeline, clip_id);
+            }
+        }
+
+        // Ctrl+C copies the selected clips, and Ctrl+V pastes clips at the playhead
+        let io = unsafe { &*igGetIO() };
+        if io.key_ctrl
+            && !io.want_text_input
+            && unsafe { igIsWindowFocused(ImGuiFocusedFlags::ChildWindows) }
+        {
+            if unsafe { igIsKeyPressed('C' as i32, false) } {
+                copy_selected_clips(timeline);
+            }
+            if unsafe { igIsKeyPressed('V' as i32, false) } {
+                paste_clips(timeline, editor_state);
+                timeline.instantiate_generators(creation_context);
             }
         }
     }
@@ -441,29 +500,30 @@ fn try_create_clip(
                 schema: target_schema,
                 source: ClipSource::Animation(AnimationClip {
                     target_clip: target_ref,
-                    /*is_time_collapsed: true,
-                    time_property: AnimatedPropertyField {
-                        local_offset_frames: 0,
-                        start_value: PropertyValue::Float(0.),
-                        segments: vec![CurveSegment {
-                            duration_frames: 1,
-                            end_value: PropertyValue::Float(1.),
-                            interpolation: CurveInterpolation::Linear,
-                        }],
-                    },*/
+                    time_property: None,
+                    blend_mode: BlendMode::Replace,
+                    weight_property: None,
+                    is_time_collapsed: true,
+                    is_weight_collapsed: true,
                     properties: vec![AnimatedProperty {
                         group_index,
                         property_index: prop_index,
                         is_collapsed: false,
                         target: AnimatedPropertyTarget::Joined(AnimatedPropertyField {
                             local_offset_frames: global_frame as i32 - start_frame_u32 as i32,
+                            start_anchor: None,
                             start_value: val,
                             segments: Vec::new(),
+                            pre_extrapolation: Extrapolation::Constant,
+                            post_extrapolation: Extrapolation::Constant,
                         }),
+                        modifiers: Vec::new(),
+                        spring_cache: RefCell::new(None),
                     }],
                 }),
                 offset_frames: 0,
                 duration_frames: 1,
+                anchor: None,
                 property_groups: Vec::new(),
                 is_selected: true,
             },
@@ -550,20 +610,25 @@ fn schema_menu_item(
     }
 }
 
-fn draw_scrubber_bar(time_scale: f32, editor_state: &mut EditorState) {
+fn draw_scrubber_bar(time_scale: f32, tempo_map: &TempoMap, editor_state: &mut EditorState) {
     let window_x = unsafe { igGetWindowPos_nonUDT2().x };
     let start_cursor_pos = unsafe { igGetCursorScreenPos_nonUDT2() };
     let start_pixel = window_x - start_cursor_pos.x;
     let pixel_width = unsafe { igGetWindowContentRegionWidth() };
 
-    let fpb = get_fpb(editor_state.fps, editor_state.bpm);
-    let start_beats = start_pixel / (time_scale * fpb);
-    let end_beats = (start_pixel + pixel_width) / (time_scale * fpb);
+    let start_frames = start_pixel / time_scale;
+    let end_frames = (start_pixel + pixel_width) / time_scale;
 
     // Move the cursor to the top left of the visible area
     unsafe { igSetCursorPosX(start_pixel) };
 
-    draw_time_bar(fpb, start_beats, end_beats, pixel_width, 0., editor_state);
+    draw_time_bar(
+        start_frames,
+        end_frames,
+        pixel_width,
+        0.,
+        editor_state.time_base(tempo_map),
+    );
 
     if unsafe { igIsItemActive() } {
         let mouse_screen_pos = unsafe { igGetMousePos_nonUDT2() };
@@ -643,7 +708,8 @@ fn start_interaction(timeline: &Timeline, editor_state: &mut EditorState) {
     editor_state.track_offset = 0;
     editor_state.track_pixel_offset =
         unsafe { igGetMousePos_nonUDT2().y - igGetCursorScreenPos_nonUDT2().y };
-    editor_state.snapping_points = get_snapping_points(timeline);
+    editor_state.snapping_points =
+        get_snapping_points(timeline, editor_state.time_base(&timeline.tempo_map));
 }
 
 fn try_start_interaction(
@@ -686,7 +752,7 @@ fn try_receive_target(editor_state: &mut EditorState, clip: &mut Clip) {
     };
     let animation = match &mut clip.source {
         ClipSource::Animation(a) => a,
-        ClipSource::Generator(_) => return,
+        ClipSource::Generator(_) | ClipSource::Unloaded => return,
     };
 
     animation.target_clip = new_target;
@@ -694,6 +760,187 @@ fn try_receive_target(editor_state: &mut EditorState, clip: &mut Clip) {
     editor_state.retarget_clip_response = None;
 }
 
+fn draw_tempo_map_menu(timeline: &mut Timeline, editor_state: &EditorState) {
+    // Retiming only ever pushes items later, so it waits until a drag is released rather than
+    // running on every step of it
+    let mut was_changed = false;
+    let mut remove_index = None;
+    let change_count = timeline.tempo_map.changes.len();
+    for change_index in 0..change_count {
+        // Keep changes in order by only letting them move as far as their neighbours
+        let min_bar = match change_index {
+            0 => 0,
+            _ => timeline.tempo_map.changes[change_index - 1].bar,
+        };
+        let max_bar = timeline
+            .tempo_map
+            .changes
+            .get(change_index + 1)
+            .map(|next_change| next_change.bar)
+            .unwrap_or(u32::MAX);
+
+        let change = &mut timeline.tempo_map.changes[change_index];
+        let mut bar = change.bar as i32;
+        let mut beats_per_bar = change.beats_per_bar as i32;
+        unsafe {
+            igPushIDInt(change_index as i32);
+            igPushItemWidth(80.);
+
+            // The first change always starts at the first bar
+            if change_index == 0 {
+                igText(cstr!("Bar %5d"), 0);
+            } else {
+                igDragInt(cstr!("Bar"), &mut bar, 0.1, 0, 0, cstr!("%d"));
+                was_changed |= igIsItemDeactivatedAfterEdit();
+            }
+            igSameLine(0., 10.);
+            igDragFloat(
+                cstr!("BPM"),
+                &mut change.beats_per_minute,
+                0.1,
+                1.,
+                1000.,
+                cstr!("%.2f"),
+                1.,
+            );
+            was_changed |= igIsItemDeactivatedAfterEdit();
+            igSameLine(0., 10.);
+            igDragInt(
+                cstr!("Beats per bar"),
+                &mut beats_per_bar,
+                0.1,
+                1,
+                32,
+                cstr!("%d"),
+            );
+            was_changed |= igIsItemDeactivatedAfterEdit();
+            igPopItemWidth();
+        }
+        change.bar = (bar.max(0) as u32).max(min_bar).min(max_bar);
+        change.beats_per_minute = change.beats_per_minute.max(1.);
+        change.beats_per_bar = beats_per_bar.max(1) as u32;
+
+        if change_index > 0 {
+            unsafe { igSameLine(0., 10.) };
+            if unsafe { igButton(cstr!("Remove"), ImVec2::new(0., 0.)) } {
+                remove_index = Some(change_index);
+            }
+        }
+        unsafe { igPopID() };
+    }
+
+    if let Some(remove_index) = remove_index {
+        timeline.tempo_map.changes.remove(remove_index);
+        was_changed = true;
+    }
+
+    if unsafe { igButton(cstr!("Add tempo change"), ImVec2::new(0., 0.)) } {
+        let last_change = *timeline.tempo_map.changes.last().unwrap();
+        timeline.tempo_map.changes.push(TempoChange {
+            bar: last_change.bar + 1,
+            ..last_change
+        });
+        was_changed = true;
+    }
+
+    if was_changed {
+        retime_anchored_items(timeline, editor_state.fps);
+    }
+}
+
+fn draw_animation_clip_menu(
+    animation_clip: &mut AnimationClip,
+    clip_start_frame: u32,
+    clip_duration: u32,
+    editor_state: &EditorState,
+) {
+    if unsafe { igBeginMenu(cstr!("Blend mode"), true) } {
+        for &(label, blend_mode) in &[
+            (cstr!("Replace"), BlendMode::Replace),
+            (cstr!("Additive"), BlendMode::Additive),
+            (cstr!("Multiply"), BlendMode::Multiply),
+        ] {
+            let is_selected = animation_clip.blend_mode == blend_mode;
+            if unsafe { igMenuItemBool(label, ptr::null(), is_selected, true) } {
+                animation_clip.blend_mode = blend_mode;
+            }
+        }
+        unsafe { igEndMenu() };
+    }
+
+    // Start the time curve off mapping the clip's time onto itself, and the weight at 1
+    draw_optional_field_menu(
+        "time remap",
+        &mut animation_clip.time_property,
+        &mut animation_clip.is_time_collapsed,
+        clip_start_frame,
+        editor_state,
+        || AnimatedPropertyField {
+            local_offset_frames: 0,
+            start_anchor: None,
+            start_value: PropertyValue::Float(0.),
+            segments: vec![CurveSegment {
+                duration_frames: clip_duration,
+                end_anchor: None,
+                end_value: PropertyValue::Float(1.),
+                interpolation: CurveInterpolation::Linear,
+                rotation_spline: false,
+                color_space: ColorSpace::LinearRgb,
+            }],
+            pre_extrapolation: Extrapolation::Constant,
+            post_extrapolation: Extrapolation::Constant,
+        },
+    );
+    draw_optional_field_menu(
+        "weight",
+        &mut animation_clip.weight_property,
+        &mut animation_clip.is_weight_collapsed,
+        clip_start_frame,
+        editor_state,
+        || AnimatedPropertyField {
+            local_offset_frames: 0,
+            start_anchor: None,
+            start_value: PropertyValue::Float(1.),
+            segments: Vec::new(),
+            pre_extrapolation: Extrapolation::Constant,
+            post_extrapolation: Extrapolation::Constant,
+        },
+    );
+}
+
+fn draw_optional_field_menu(
+    name: &str,
+    field: &mut Option<AnimatedPropertyField>,
+    is_collapsed: &mut bool,
+    clip_start_frame: u32,
+    editor_state: &EditorState,
+    create_field: impl FnOnce() -> AnimatedPropertyField,
+) {
+    let add_label = CString::new(format!("Add {}", name)).unwrap();
+    let key_label = CString::new(format!("Key {}", name)).unwrap();
+    let remove_label = CString::new(format!("Remove {}", name)).unwrap();
+
+    let existing_field = match field {
+        Some(existing_field) => existing_field,
+        None => {
+            if unsafe { igMenuItemBool(add_label.as_ptr(), ptr::null(), false, true) } {
+                *field = Some(create_field());
+                *is_collapsed = false;
+            }
+            return;
+        }
+    };
+
+    if unsafe { igMenuItemBool(key_label.as_ptr(), ptr::null(), false, true) } {
+        let local_frame = editor_state.current_frame() as i32 - clip_start_frame as i32;
+        let value = get_animation_field_value(existing_field, local_frame as f32);
+        insert_keyframe(existing_field, local_frame, value);
+    }
+    if unsafe { igMenuItemBool(remove_label.as_ptr(), ptr::null(), false, true) } {
+        *field = None;
+    }
+}
+
 fn draw_timeline_clip(
     timeline: &mut Timeline,
     track_index: usize,
@@ -791,6 +1038,18 @@ fn draw_timeline_clip(
                 editor_state.renaming_clip = Some(ClipReference::new(clip_id));
             }
 
+            let clip = &mut timeline.tracks[track_index].clips[clip_index];
+            let is_anchored = clip.anchor.is_some();
+            if unsafe { igMenuItemBool(cstr!("Anchor to beats"), ptr::null(), is_anchored, true) } {
+                clip.anchor = if is_anchored {
+                    None
+                } else {
+                    let clip_start_frame = last_clip_end_frame + clip.offset_frames;
+                    let time_base = editor_state.time_base(&timeline.tempo_map);
+                    Some(clip_anchor_at(clip_start_frame, clip, time_base))
+                };
+            }
+
             let clip = &timeline.tracks[track_index].clips[clip_index];
             if clip.source.is_generator() {
                 if unsafe { igMenuItemBool(cstr!("Copy"), ptr::null(), false, true) } {
@@ -807,6 +1066,18 @@ fn draw_timeline_clip(
                     editor_state.retarget_clip_request =
                         Some((clip.schema, ClipReference::new(clip.id)));
                 }
+
+                let clip_start_frame = last_clip_end_frame + clip.offset_frames;
+                let clip = &mut timeline.tracks[track_index].clips[clip_index];
+                let clip_duration = clip.duration_frames;
+                if let ClipSource::Animation(animation_clip) = &mut clip.source {
+                    draw_animation_clip_menu(
+                        animation_clip,
+                        clip_start_frame,
+                        clip_duration,
+                        editor_state,
+                    );
+                }
             }
 
             unsafe { igEndPopup() };
@@ -837,9 +1108,14 @@ fn draw_timeline_clip(
                     is_collapsed: false,
                     target: AnimatedPropertyTarget::Joined(AnimatedPropertyField {
                         local_offset_frames: global_time as i32 - clip_start_time as i32,
+                        start_anchor: None,
                         start_value: val,
                         segments: Vec::new(),
+                        pre_extrapolation: Extrapolation::Constant,
+                        post_extrapolation: Extrapolation::Constant,
                     }),
+                    modifiers: Vec::new(),
+                    spring_cache: RefCell::new(None),
                 });
                 editor_state.insert_animation = None;
             }
@@ -0,0 +1,73 @@
+use crate::cstr;
+use crate::editor_state::EditorState;
+use crate::timeline_interactions::deselect_all_clips;
+use crate::validation::validate_timeline;
+use engine::animation::timeline::Timeline;
+use imgui_sys::{
+    igBegin, igButton, igEnd, igPopID, igPushIDInt, igSameLine, igSeparator, igText, igTextWrapped,
+    ImGuiWindowFlags, ImVec2,
+};
+use std::ffi::CString;
+use std::ptr;
+
+pub fn draw_validation(timeline: &mut Timeline, editor_state: &mut EditorState) {
+    let show_window = unsafe {
+        igBegin(
+            cstr!("Validation"),
+            ptr::null_mut(),
+            ImGuiWindowFlags::empty(),
+        )
+    };
+
+    if show_window {
+        if unsafe { igButton(cstr!("Validate"), ImVec2::new(0., 0.)) } {
+            editor_state.last_validation = Some(validate_timeline(timeline));
+        }
+
+        let mut select_clip_id = None;
+        match &editor_state.last_validation {
+            None => unsafe {
+                igText(cstr!(
+                    "Validate to check the timeline for problems before exporting"
+                ));
+            },
+            Some(report) if report.is_empty() => unsafe {
+                igText(cstr!("No problems found"));
+            },
+            Some(report) => {
+                for (issue_index, issue) in report.issues.iter().enumerate() {
+                    let message = CString::new(issue.message.as_str()).unwrap();
+
+                    unsafe {
+                        igPushIDInt(issue_index as i32);
+                        igSeparator();
+                        if let Some(clip_id) = issue.clip_id {
+                            if igButton(cstr!("Select"), ImVec2::new(0., 0.)) {
+                                select_clip_id = Some(clip_id);
+                            }
+                            igSameLine(0., -1.);
+                        }
+                        igTextWrapped(cstr!("%s"), message.as_ptr());
+                        igPopID();
+                    }
+                }
+            }
+        }
+
+        if let Some(clip_id) = select_clip_id {
+            deselect_all_clips(timeline);
+            let clip = timeline
+                .tracks
+                .iter_mut()
+                .flat_map(|track| track.clips.iter_mut())
+                .find(|clip| clip.id == clip_id);
+            if let Some(clip) = clip {
+                clip.is_selected = true;
+            }
+        }
+    }
+
+    unsafe {
+        igEnd();
+    }
+}
@@ -1,5 +1,393 @@
-use engine::animation::timeline::Clip;
+use crate::timeline_interactions::{insert_remapped_clips, remove_clip};
+use engine::animation::animation_clip::{
+    AnimatedProperty, AnimatedPropertyField, AnimatedPropertyTarget,
+};
+use engine::animation::clip::ClipReference;
+use engine::animation::driver::PropertyDriver;
+use engine::animation::property::PropertyValue;
+use engine::animation::timeline::{Clip, ClipSource, Timeline};
+use engine::binding::PropertyBinding;
+use std::collections::HashMap;
+use std::mem;
 
+/// Holds deleted clips so they can be restored. The bin is saved with the timeline.
+#[derive(Default)]
 pub struct RecycleBin {
-    clips: Vec<Clip>,
+    pub entries: Vec<BinEntry>,
+}
+
+/// A deleted clip, the animation clips that targeted it, and the references to them from other
+/// clips. The deleted clip is always first.
+pub struct BinEntry {
+    pub clips: Vec<RecycledClip>,
+    pub references: Vec<RecycledReference>,
+}
+
+pub struct RecycledClip {
+    pub clip: Clip,
+    pub track_index: usize,
+    pub start_frame: u32,
+}
+
+/// A reference from a clip that's still in the timeline to a deleted one, which was removed when
+/// the clip was deleted.
+pub enum RecycledReference {
+    /// A clip reference property that was pointing at `target`.
+    Property {
+        clip: ClipReference,
+        binding: PropertyBinding,
+        target: ClipReference,
+    },
+    /// A driver following a property of a deleted clip.
+    Driver {
+        clip: ClipReference,
+        binding: PropertyBinding,
+        driver: PropertyDriver,
+    },
+    /// A constraint in an animation clip following a property of a deleted clip.
+    Constraint {
+        clip: ClipReference,
+        property: AnimatedProperty,
+    },
+    /// A clip reference keyframe in an animation clip that was pointing at `target`. The key is
+    /// the start value when `key_index` is 0, or the end value of the segment before it.
+    Keyframe {
+        clip: ClipReference,
+        binding: PropertyBinding,
+        key_index: usize,
+        target: ClipReference,
+    },
+}
+
+#[derive(Clone, Copy)]
+pub enum RestorePosition {
+    /// Puts the clips back on the tracks and frames they were deleted from.
+    Original,
+    /// Puts the deleted clip at a frame on its original track, with its animation clips moved
+    /// along with it.
+    Frame(u32),
+}
+
+impl RecycleBin {
+    /// Moves a clip to the bin along with any animation clips targeting it. References to them
+    /// from other clips are removed, and kept in the bin to be restored with them.
+    pub fn delete_clip(&mut self, timeline: &mut Timeline, clip_id: u32) {
+        let animation_ids: Vec<_> = timeline
+            .tracks
+            .iter()
+            .flat_map(|track| track.clips.iter())
+            .filter(|clip| match &clip.source {
+                ClipSource::Animation(animation) => animation.target_clip.clip_id() == clip_id,
+                _ => false,
+            })
+            .map(|clip| clip.id)
+            .collect();
+
+        let mut clips = Vec::new();
+        for id in Some(clip_id).into_iter().chain(animation_ids) {
+            let position = timeline
+                .tracks
+                .iter()
+                .enumerate()
+                .flat_map(|(track_index, track)| {
+                    track
+                        .clips
+                        .iter()
+                        .position(|clip| clip.id == id)
+                        .map(|clip_index| (track_index, clip_index))
+                })
+                .next();
+            let (track_index, clip_index) = match position {
+                Some(position) => position,
+                None => continue,
+            };
+
+            let track = &mut timeline.tracks[track_index];
+            let start_frame = track.clip_start_frame(clip_index);
+            let mut clip = remove_clip(track, clip_index);
+            clip.is_selected = false;
+
+            // Generators are recreated when the clip is restored, rather than kept around
+            if clip.source.is_generator() {
+                clip.source = ClipSource::Unloaded;
+            }
+
+            clips.push(RecycledClip {
+                clip,
+                track_index,
+                start_frame,
+            });
+        }
+        if clips.is_empty() {
+            return;
+        }
+
+        let deleted_ids: Vec<_> = clips.iter().map(|recycled| recycled.clip.id).collect();
+        let references = take_references(timeline, &deleted_ids);
+        self.entries.push(BinEntry { clips, references });
+    }
+
+    /// Puts the clips in an entry back in the timeline, with the references to them that were
+    /// removed when they were deleted. Clips that don't fit where they were are put on a new
+    /// track, and clips whose IDs have been taken since they were deleted are given new ones. The
+    /// restored generator clips are unloaded and need to be instantiated.
+    pub fn restore(
+        &mut self,
+        entry_index: usize,
+        position: RestorePosition,
+        timeline: &mut Timeline,
+        next_clip_id: &mut u32,
+    ) {
+        let entry = self.entries.remove(entry_index);
+
+        let used_ids: Vec<_> = timeline
+            .tracks
+            .iter()
+            .flat_map(|track| track.clips.iter())
+            .map(|clip| clip.id)
+            .collect();
+        let mut id_map = HashMap::new();
+        for recycled in &entry.clips {
+            if used_ids.contains(&recycled.clip.id) {
+                id_map.insert(recycled.clip.id, *next_clip_id);
+                *next_clip_id += 1;
+            } else {
+                *next_clip_id = (*next_clip_id).max(recycled.clip.id + 1);
+            }
+        }
+
+        let frame_offset = match position {
+            RestorePosition::Original => 0,
+            RestorePosition::Frame(frame) => frame as i64 - entry.clips[0].start_frame as i64,
+        };
+        let clips = entry
+            .clips
+            .into_iter()
+            .map(|recycled| {
+                let start_frame = (recycled.start_frame as i64 + frame_offset).max(0) as u32;
+                (recycled.clip, recycled.track_index, start_frame)
+            })
+            .collect();
+        insert_remapped_clips(timeline, clips, &id_map);
+
+        for reference in entry.references {
+            restore_reference(timeline, reference, &id_map);
+        }
+    }
+
+    /// Permanently deletes an entry.
+    pub fn purge(&mut self, entry_index: usize) {
+        self.entries.remove(entry_index);
+    }
+
+    /// Permanently deletes every entry.
+    pub fn purge_all(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Removes every reference to the deleted clips from the clips left in the timeline.
+fn take_references(timeline: &mut Timeline, deleted_ids: &[u32]) -> Vec<RecycledReference> {
+    let mut references = Vec::new();
+    for clip in timeline
+        .tracks
+        .iter_mut()
+        .flat_map(|track| track.clips.iter_mut())
+    {
+        let clip_reference = ClipReference::new(clip.id);
+        for (group_index, group) in clip.property_groups.iter_mut().enumerate() {
+            for (prop_index, default) in group.defaults.iter_mut().enumerate() {
+                let binding = PropertyBinding::new(group_index, prop_index);
+                if let PropertyValue::ClipReference(Some(target)) = default.value {
+                    if deleted_ids.contains(&target.clip_id()) {
+                        default.value = PropertyValue::ClipReference(None);
+                        references.push(RecycledReference::Property {
+                            clip: clip_reference,
+                            binding,
+                            target,
+                        });
+                    }
+                }
+
+                let is_driven_by_deleted = match &default.driver {
+                    Some(driver) => deleted_ids.contains(&driver.source_clip.clip_id()),
+                    None => false,
+                };
+                if is_driven_by_deleted {
+                    references.push(RecycledReference::Driver {
+                        clip: clip_reference,
+                        binding,
+                        driver: default.driver.take().unwrap(),
+                    });
+                }
+            }
+        }
+
+        if let ClipSource::Animation(animation) = &mut clip.source {
+            for property in &mut animation.properties {
+                let binding = PropertyBinding::new(property.group_index, property.property_index);
+                let field = match &mut property.target {
+                    AnimatedPropertyTarget::Joined(field) => field,
+                    _ => continue,
+                };
+                for key_index in 0..=field.segments.len() {
+                    let value = key_value_mut(field, key_index).unwrap();
+                    if let PropertyValue::ClipReference(Some(target)) = *value {
+                        if deleted_ids.contains(&target.clip_id()) {
+                            *value = PropertyValue::ClipReference(None);
+                            references.push(RecycledReference::Keyframe {
+                                clip: clip_reference,
+                                binding,
+                                key_index,
+                                target,
+                            });
+                        }
+                    }
+                }
+            }
+
+            let properties = mem::replace(&mut animation.properties, Vec::new());
+            for property in properties {
+                let is_following_deleted = match &property.target {
+                    AnimatedPropertyTarget::Constraint(constraint) => {
+                        deleted_ids.contains(&constraint.target().clip.clip_id())
+                    }
+                    _ => false,
+                };
+                if is_following_deleted {
+                    references.push(RecycledReference::Constraint {
+                        clip: clip_reference,
+                        property,
+                    });
+                } else {
+                    animation.properties.push(property);
+                }
+            }
+        }
+    }
+    references
+}
+
+/// Puts a reference back if the clip it's from is still in the timeline, and nothing has taken
+/// its place since.
+fn restore_reference(
+    timeline: &mut Timeline,
+    reference: RecycledReference,
+    id_map: &HashMap<u32, u32>,
+) {
+    let remap = |reference: ClipReference| match id_map.get(&reference.clip_id()) {
+        Some(&new_id) => ClipReference::new(new_id),
+        None => reference,
+    };
+    let clip_id = match &reference {
+        RecycledReference::Property { clip, .. }
+        | RecycledReference::Driver { clip, .. }
+        | RecycledReference::Constraint { clip, .. }
+        | RecycledReference::Keyframe { clip, .. } => clip.clip_id(),
+    };
+    let clip = match timeline
+        .tracks
+        .iter_mut()
+        .flat_map(|track| track.clips.iter_mut())
+        .find(|clip| clip.id == clip_id)
+    {
+        Some(clip) => clip,
+        None => return,
+    };
+
+    match reference {
+        RecycledReference::Property {
+            binding, target, ..
+        } => {
+            let default = match clip
+                .property_groups
+                .get_mut(binding.group)
+                .and_then(|group| group.defaults.get_mut(binding.prop))
+            {
+                Some(default) => default,
+                None => return,
+            };
+            if let PropertyValue::ClipReference(None) = default.value {
+                default.value = PropertyValue::ClipReference(Some(remap(target)));
+            }
+        }
+        RecycledReference::Driver {
+            binding,
+            mut driver,
+            ..
+        } => {
+            let default = match clip
+                .property_groups
+                .get_mut(binding.group)
+                .and_then(|group| group.defaults.get_mut(binding.prop))
+            {
+                Some(default) => default,
+                None => return,
+            };
+            if default.driver.is_none() {
+                driver.source_clip = remap(driver.source_clip);
+                default.driver = Some(driver);
+            }
+        }
+        RecycledReference::Constraint { mut property, .. } => {
+            let animation = match &mut clip.source {
+                ClipSource::Animation(animation) => animation,
+                _ => return,
+            };
+            let is_animated = animation.properties.iter().any(|animated| {
+                animated.group_index == property.group_index
+                    && animated.property_index == property.property_index
+            });
+            if is_animated {
+                return;
+            }
+
+            if let AnimatedPropertyTarget::Constraint(constraint) = &mut property.target {
+                let target = constraint.target_mut();
+                target.clip = remap(target.clip);
+            }
+            animation.properties.push(property);
+        }
+        RecycledReference::Keyframe {
+            binding,
+            key_index,
+            target,
+            ..
+        } => {
+            let animation = match &mut clip.source {
+                ClipSource::Animation(animation) => animation,
+                _ => return,
+            };
+            let field = match animation
+                .properties
+                .iter_mut()
+                .find(|animated| {
+                    animated.group_index == binding.group && animated.property_index == binding.prop
+                })
+                .map(|animated| &mut animated.target)
+            {
+                Some(AnimatedPropertyTarget::Joined(field)) => field,
+                _ => return,
+            };
+            if let Some(value) = key_value_mut(field, key_index) {
+                if let PropertyValue::ClipReference(None) = *value {
+                    *value = PropertyValue::ClipReference(Some(remap(target)));
+                }
+            }
+        }
+    }
+}
+
+/// The value of a keyframe, where key 0 is the field's start value.
+fn key_value_mut(
+    field: &mut AnimatedPropertyField,
+    key_index: usize,
+) -> Option<&mut PropertyValue> {
+    match key_index {
+        0 => Some(&mut field.start_value),
+        _ => field
+            .segments
+            .get_mut(key_index - 1)
+            .map(|segment| &mut segment.end_value),
+    }
 }
@@ -1,75 +1,665 @@
+use crate::clipboard;
+use crate::expression_compiler::compile_expression;
+use crate::migration::{convert_value, Migration, MigrationReport, MIGRATIONS, SAVE_VERSION};
+use crate::recycle_bin;
 use engine::animation::schema::GeneratorSchema;
-use engine::animation::{animation_clip, clip, cubic_bezier, property, schema, timeline};
+use engine::animation::{
+    animation_clip, clip, constraint, cubic_bezier, driver, easing, expression, modifier, property,
+    schema, spline_path, tempo_map, timeline,
+};
+use engine::binding;
+#[cfg(feature = "gui")]
 use engine::creation_context::CreationContext;
 use engine::generator::GENERATOR_SCHEMAS;
 use engine::math;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::iter::FromIterator;
+use std::iter::{self, FromIterator};
+use std::mem;
 
 pub fn serialize_timeline<S: Serializer>(
     timeline: &timeline::Timeline,
+    recycle_bin: &recycle_bin::RecycleBin,
     serializer: S,
 ) -> Result<S::Ok, S::Error> {
-    let timeline = Timeline::from(timeline);
+    let timeline = Timeline::from(timeline, recycle_bin);
     timeline.serialize(serializer)
 }
 
+#[cfg(feature = "gui")]
 pub fn deserialize_timeline<'de, D: Deserializer<'de>>(
     deserializer: D,
     context: &mut CreationContext,
-) -> Result<timeline::Timeline, D::Error> {
-    Ok(Timeline::deserialize(deserializer)?.into(context))
+) -> Result<(timeline::Timeline, recycle_bin::RecycleBin), D::Error> {
+    let (mut timeline, recycle_bin) = deserialize_timeline_headless(deserializer)?;
+    timeline.instantiate_generators(context);
+    Ok((timeline, recycle_bin))
+}
+
+/// Loads a timeline without creating any generators, so it can be evaluated without a device.
+pub fn deserialize_timeline_headless<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<(timeline::Timeline, recycle_bin::RecycleBin), D::Error> {
+    Ok(Timeline::deserialize(deserializer)?.into())
+}
+
+/// Writes the clips with the given IDs, with the tracks and frames they're at, so they can be
+/// pasted into this timeline or another one.
+pub fn serialize_clips<S: Serializer>(
+    timeline: &timeline::Timeline,
+    clip_ids: &[u32],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let fragment = ClipFragment::from(timeline, clip_ids);
+    fragment.serialize(serializer)
+}
+
+/// Reads copied clips to paste into a timeline. The clips keep the IDs they were copied with.
+/// Drivers and constraints following clips that weren't copied are kept if the timeline has a
+/// clip with the same ID.
+pub fn deserialize_clips<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    timeline: &timeline::Timeline,
+) -> Result<Vec<clipboard::CopiedClip>, D::Error> {
+    Ok(ClipFragment::deserialize(deserializer)?.into(timeline))
+}
+
+/// Writes the keyframed fields of a property, so they can be pasted into another property. Enum
+/// properties also write their option names, so they're only pasted into the same kind of enum.
+pub fn serialize_keyframes<S: Serializer>(
+    fields: &[animation_clip::AnimatedPropertyField],
+    enum_options: &[&str],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let fragment = KeyframeFragment {
+        enum_options: enum_options
+            .iter()
+            .map(|option| option.to_string())
+            .collect(),
+        fields: fields.iter().map(AnimatedPropertyField::from).collect(),
+    };
+    fragment.serialize(serializer)
+}
+
+pub fn deserialize_keyframes<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<clipboard::CopiedKeyframes, D::Error> {
+    let fragment = KeyframeFragment::deserialize(deserializer)?;
+    Ok(clipboard::CopiedKeyframes {
+        enum_options: fragment.enum_options,
+        fields: fragment
+            .fields
+            .into_iter()
+            .map(|field| field.into())
+            .collect(),
+    })
 }
 
 #[derive(Serialize, Deserialize)]
 struct Timeline {
+    #[serde(default)]
+    pub version: u32,
     pub tracks: Vec<Track>,
+    #[serde(default = "default_tempo_map")]
+    pub tempo_map: TempoMap,
+    #[serde(default)]
+    pub recycle_bin: Vec<BinEntry>,
 }
 
-impl From<&timeline::Timeline> for Timeline {
-    fn from(timeline: &timeline::Timeline) -> Self {
+impl Timeline {
+    fn from(timeline: &timeline::Timeline, recycle_bin: &recycle_bin::RecycleBin) -> Self {
+        // Clips in the bin can be referenced by other clips in the bin
+        let bin_clips = recycle_bin
+            .entries
+            .iter()
+            .flat_map(|entry| entry.clips.iter())
+            .map(|recycled| &recycled.clip);
+        let clip_schemas = HashMap::from_iter(
+            timeline
+                .tracks
+                .iter()
+                .flat_map(|track| track.clips.iter())
+                .chain(bin_clips)
+                .map(|clip| (clip.id, clip.schema)),
+        );
+
         Timeline {
+            version: SAVE_VERSION,
             tracks: timeline
                 .tracks
                 .iter()
-                .map(|track| Track::from(track))
+                .map(|track| Track::from(track, &clip_schemas))
+                .collect(),
+            tempo_map: TempoMap::from(&timeline.tempo_map),
+            recycle_bin: recycle_bin
+                .entries
+                .iter()
+                .map(|entry| BinEntry::from(entry, &clip_schemas))
                 .collect(),
         }
     }
-}
 
-impl Timeline {
-    fn into(self, context: &mut CreationContext) -> timeline::Timeline {
-        timeline::Timeline {
+    fn into(mut self) -> (timeline::Timeline, recycle_bin::RecycleBin) {
+        if self.version > SAVE_VERSION {
+            eprintln!(
+                "The save is from version {} of the tool, but this is version {}, so some of it might not load.",
+                self.version, SAVE_VERSION
+            );
+        }
+        self.migrate().print();
+
+        // Drivers can reference clips on any track, so every clip's schema is found up front
+        let bin_clips = self
+            .recycle_bin
+            .iter()
+            .flat_map(|entry| entry.clips.iter())
+            .map(|recycled| &recycled.clip);
+        let clip_schemas = HashMap::from_iter(
+            self.tracks
+                .iter()
+                .flat_map(|track| track.clips.iter())
+                .chain(bin_clips)
+                .filter_map(|clip| {
+                    GENERATOR_SCHEMAS
+                        .iter()
+                        .find(|schema| schema.name == clip.schema)
+                        .map(|schema| (clip.id, schema))
+                }),
+        );
+
+        let timeline = timeline::Timeline {
             tracks: self
                 .tracks
                 .into_iter()
-                .map(|track| track.into(context))
+                .map(|track| track.into(&clip_schemas))
+                .collect(),
+            tempo_map: (&self.tempo_map).into(),
+        };
+
+        for (track_index, clip_index, binding) in driver::find_driver_cycles(&timeline) {
+            let clip = &timeline.tracks[track_index].clips[clip_index];
+            eprintln!(
+                "Property {} of clip \"{}\" has a driver that depends on itself, so it won't be driven.",
+                clip.schema.groups[binding.group].properties[binding.prop].name, clip.name
+            );
+        }
+
+        let recycle_bin = recycle_bin::RecycleBin {
+            entries: self
+                .recycle_bin
+                .into_iter()
+                .filter_map(|entry| entry.into(&clip_schemas))
+                .collect(),
+        };
+
+        (timeline, recycle_bin)
+    }
+
+    /// Applies the migrations added since the timeline was saved, so clips and properties that
+    /// have been renamed, moved or converted since still load.
+    fn migrate(&mut self) -> MigrationReport {
+        let saved_version = self.version;
+        let mut report = MigrationReport::new(saved_version);
+        for schema_migration in MIGRATIONS
+            .iter()
+            .filter(|schema_migration| schema_migration.version > saved_version)
+        {
+            self.apply_migration(&schema_migration.migration, &mut report);
+        }
+        self.version = SAVE_VERSION;
+        report
+    }
+
+    fn apply_migration(&mut self, migration: &Migration, report: &mut MigrationReport) {
+        match *migration {
+            Migration::RenameSchema { from, to } => {
+                let mut clip_count = 0;
+                for clip in self.clips_mut().filter(|clip| clip.schema == from) {
+                    clip.schema = to.to_string();
+                    clip_count += 1;
+                }
+                if clip_count > 0 {
+                    report.migrated.push(format!(
+                        "Renamed schema {} to {} on {} clips.",
+                        from, to, clip_count
+                    ));
+                }
+            }
+            Migration::RenameGroup { schema, from, to } => {
+                let mut rename_count = 0;
+                for clip in self.clips_mut().filter(|clip| clip.schema == schema) {
+                    for group in &mut clip.property_groups {
+                        if group.name == from {
+                            group.name = to.to_string();
+                            rename_count += 1;
+                        }
+                    }
+                }
+                self.retain_bindings(schema, &mut |_, _, group_name, _| {
+                    if group_name == from {
+                        *group_name = to.to_string();
+                        rename_count += 1;
+                    }
+                    true
+                });
+                if rename_count > 0 {
+                    report.migrated.push(format!(
+                        "Renamed group {} to {} on schema {} in {} places.",
+                        from, to, schema, rename_count
+                    ));
+                }
+            }
+            Migration::RenameProperty {
+                schema,
+                group,
+                from,
+                to,
+            } => {
+                let mut rename_count = 0;
+                for default in self.defaults_mut(schema, group) {
+                    if default.name == from {
+                        default.name = to.to_string();
+                        rename_count += 1;
+                    }
+                }
+                self.retain_bindings(schema, &mut |_, _, group_name, property_name| {
+                    if group_name == group && property_name == from {
+                        *property_name = to.to_string();
+                        rename_count += 1;
+                    }
+                    true
+                });
+                if rename_count > 0 {
+                    report.migrated.push(format!(
+                        "Renamed property {} to {} in group {} of schema {} in {} places.",
+                        from, to, group, schema, rename_count
+                    ));
+                }
+            }
+            Migration::MoveProperty {
+                schema,
+                property,
+                from_group,
+                to_group,
+            } => {
+                let mut move_count = 0;
+                for clip in self.clips_mut().filter(|clip| clip.schema == schema) {
+                    let moved_default = clip
+                        .property_groups
+                        .iter_mut()
+                        .filter(|group| group.name == from_group)
+                        .filter_map(|group| {
+                            let default_index = group
+                                .defaults
+                                .iter()
+                                .position(|default| default.name == property)?;
+                            Some(group.defaults.remove(default_index))
+                        })
+                        .next();
+                    let moved_default = match moved_default {
+                        Some(default) => default,
+                        None => continue,
+                    };
+
+                    match clip
+                        .property_groups
+                        .iter_mut()
+                        .find(|group| group.name == to_group)
+                    {
+                        Some(group) => group.defaults.push(moved_default),
+                        None => clip.property_groups.push(PropertyGroup {
+                            name: to_group.to_string(),
+                            defaults: vec![moved_default],
+                        }),
+                    }
+                    move_count += 1;
+                }
+                self.retain_bindings(schema, &mut |_, _, group_name, property_name| {
+                    if group_name == from_group && property_name == property {
+                        *group_name = to_group.to_string();
+                        move_count += 1;
+                    }
+                    true
+                });
+                if move_count > 0 {
+                    report.migrated.push(format!(
+                        "Moved property {} of schema {} from group {} to {} in {} places.",
+                        property, schema, from_group, to_group, move_count
+                    ));
+                }
+            }
+            Migration::ConvertProperty {
+                schema,
+                group,
+                property,
+                to,
+            } => {
+                let mut convert_count = 0;
+                for clip in self.clips_mut().filter(|clip| clip.schema == schema) {
+                    let clip_name = &clip.name;
+                    for property_group in clip
+                        .property_groups
+                        .iter_mut()
+                        .filter(|property_group| property_group.name == group)
+                    {
+                        property_group.defaults.retain(|default| {
+                            if default.name != property {
+                                return true;
+                            }
+                            convert_count += 1;
+                            if convert_value((&default.value).into(), to).is_some() {
+                                return true;
+                            }
+                            report.dropped.push(format!(
+                                "Couldn't convert property {} of clip \"{}\", so it will be reset.",
+                                property, clip_name
+                            ));
+                            false
+                        });
+                        for default in &mut property_group.defaults {
+                            if default.name == property {
+                                default.value = PropertyValue::from(
+                                    convert_value((&default.value).into(), to).unwrap(),
+                                );
+                            }
+                        }
+                    }
+
+                    let animation = match &mut clip.animation {
+                        Some(animation) => animation,
+                        None => continue,
+                    };
+                    let properties = mem::replace(&mut animation.properties, Vec::new());
+                    for mut animated_property in properties {
+                        if animated_property.group_name == group
+                            && animated_property.property_name == property
+                        {
+                            convert_count += 1;
+                            if !animated_property.target.convert(to) {
+                                report.dropped.push(format!(
+                                    "Couldn't convert the animation of property {} on clip \"{}\", so it will be deleted.",
+                                    property, clip_name
+                                ));
+                                continue;
+                            }
+                        }
+                        animation.properties.push(animated_property);
+                    }
+                }
+                if convert_count > 0 {
+                    report.migrated.push(format!(
+                        "Converted property {} in group {} of schema {} in {} places.",
+                        property, group, schema, convert_count
+                    ));
+                }
+            }
+            Migration::RemoveProperty {
+                schema,
+                group,
+                property,
+            } => {
+                let mut remove_count = 0;
+                for default in self.defaults_mut(schema, group) {
+                    if default.name == property {
+                        remove_count += 1;
+                    }
+                }
+                for clip in self.clips_mut().filter(|clip| clip.schema == schema) {
+                    for property_group in &mut clip.property_groups {
+                        if property_group.name == group {
+                            property_group
+                                .defaults
+                                .retain(|default| default.name != property);
+                        }
+                    }
+                }
+                if remove_count > 0 {
+                    report.dropped.push(format!(
+                        "Property {} in group {} of schema {} was removed from {} clips.",
+                        property, group, schema, remove_count
+                    ));
+                }
+
+                let dropped = &mut report.dropped;
+                self.retain_bindings(
+                    schema,
+                    &mut |clip_name, reference, group_name, property_name| {
+                        if group_name != group || property_name != property {
+                            return true;
+                        }
+                        dropped.push(format!(
+                            "The {} on clip \"{}\" used property {} in group {} of schema {}, which was removed.",
+                            reference, clip_name, property, group, schema
+                        ));
+                        false
+                    },
+                );
+            }
+        }
+    }
+
+    /// Iterates over every clip, including those in the recycle bin.
+    fn clips_mut(&mut self) -> impl Iterator<Item = &mut Clip> {
+        let bin_clips = self
+            .recycle_bin
+            .iter_mut()
+            .flat_map(|entry| entry.clips.iter_mut())
+            .map(|recycled| &mut recycled.clip);
+        self.tracks
+            .iter_mut()
+            .flat_map(|track| track.clips.iter_mut())
+            .chain(bin_clips)
+    }
+
+    fn defaults_mut<'timeline>(
+        &'timeline mut self,
+        schema: &'timeline str,
+        group: &'timeline str,
+    ) -> impl Iterator<Item = &'timeline mut PropertyDefault> {
+        self.clips_mut()
+            .filter(move |clip| clip.schema == schema)
+            .flat_map(|clip| clip.property_groups.iter_mut())
+            .filter(move |property_group| property_group.name == group)
+            .flat_map(|property_group| property_group.defaults.iter_mut())
+    }
+
+    /// Calls `visit` with the clip name, kind of reference, group name and property name of every
+    /// reference to a property of `schema` from animations, drivers, constraints and paths.
+    /// References that `visit` returns false for are removed, along with the animation they're
+    /// part of if they can't be removed on their own.
+    fn retain_bindings(
+        &mut self,
+        schema: &str,
+        visit: &mut FnMut(&str, &str, &mut String, &mut String) -> bool,
+    ) {
+        let clip_schemas: HashMap<u32, String> =
+            HashMap::from_iter(self.clips_mut().map(|clip| (clip.id, clip.schema.clone())));
+        let is_schema_clip =
+            |clip_id: u32| clip_schemas.get(&clip_id).map(|name| name as &str) == Some(schema);
+
+        for clip in self.clips_mut() {
+            let clip_name = &clip.name;
+            for default in clip
+                .property_groups
+                .iter_mut()
+                .flat_map(|property_group| property_group.defaults.iter_mut())
+            {
+                let keep_driver = match &mut default.driver {
+                    Some(driver) if is_schema_clip(driver.source_clip) => visit(
+                        clip_name,
+                        "driver",
+                        &mut driver.source.group_name,
+                        &mut driver.source.property_name,
+                    ),
+                    _ => true,
+                };
+                if !keep_driver {
+                    default.driver = None;
+                }
+            }
+
+            let animation = match &mut clip.animation {
+                Some(animation) => animation,
+                None => continue,
+            };
+            let is_schema = clip.schema == schema;
+            let properties = mem::replace(&mut animation.properties, Vec::new());
+            for mut animated_property in properties {
+                let mut keep = !is_schema
+                    || visit(
+                        clip_name,
+                        "animation",
+                        &mut animated_property.group_name,
+                        &mut animated_property.property_name,
+                    );
+
+                match &mut animated_property.target {
+                    AnimatedPropertyTarget::Path(path) if is_schema => {
+                        let keep_orient_target = match &mut path.orient_target {
+                            Some(binding) => visit(
+                                clip_name,
+                                "path",
+                                &mut binding.group_name,
+                                &mut binding.property_name,
+                            ),
+                            None => true,
+                        };
+                        if !keep_orient_target {
+                            path.orient_target = None;
+                        }
+                    }
+                    AnimatedPropertyTarget::Constraint(constraint) => {
+                        if let Constraint::LookAt { eye, .. } = constraint {
+                            if is_schema {
+                                keep &= visit(
+                                    clip_name,
+                                    "constraint",
+                                    &mut eye.group_name,
+                                    &mut eye.property_name,
+                                );
+                            }
+                        }
+                        let target = constraint.target_mut();
+                        if is_schema_clip(target.clip) {
+                            keep &= visit(
+                                clip_name,
+                                "constraint",
+                                &mut target.property.group_name,
+                                &mut target.property.property_name,
+                            );
+                        }
+                    }
+                    _ => {}
+                }
+
+                if keep {
+                    animation.properties.push(animated_property);
+                }
+            }
+        }
+    }
+}
+
+/// The schema of each clip, by clip ID.
+type ClipSchemas = HashMap<u32, &'static GeneratorSchema>;
+
+// Projects saved before the tempo map existed used a fixed tempo
+fn default_tempo_map() -> TempoMap {
+    TempoMap::from(&tempo_map::TempoMap::new(112., 4))
+}
+
+#[derive(Serialize, Deserialize)]
+struct TempoMap {
+    changes: Vec<TempoChange>,
+}
+
+impl From<&tempo_map::TempoMap> for TempoMap {
+    fn from(tempo_map: &tempo_map::TempoMap) -> Self {
+        TempoMap {
+            changes: tempo_map
+                .changes
+                .iter()
+                .map(|change| TempoChange {
+                    bar: change.bar,
+                    beats_per_minute: change.beats_per_minute,
+                    beats_per_bar: change.beats_per_bar,
+                })
                 .collect(),
         }
     }
 }
 
+impl Into<tempo_map::TempoMap> for &TempoMap {
+    fn into(self) -> tempo_map::TempoMap {
+        let mut tempo_map = tempo_map::TempoMap {
+            changes: self
+                .changes
+                .iter()
+                .map(|change| tempo_map::TempoChange {
+                    bar: change.bar,
+                    beats_per_minute: change.beats_per_minute,
+                    beats_per_bar: change.beats_per_bar,
+                })
+                .collect(),
+        };
+        tempo_map.normalize();
+        tempo_map
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TempoChange {
+    bar: u32,
+    beats_per_minute: f32,
+    beats_per_bar: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BeatPosition {
+    bar: i32,
+    beat: f32,
+}
+
+impl From<tempo_map::BeatPosition> for BeatPosition {
+    fn from(position: tempo_map::BeatPosition) -> Self {
+        BeatPosition {
+            bar: position.bar,
+            beat: position.beat,
+        }
+    }
+}
+
+impl Into<tempo_map::BeatPosition> for &BeatPosition {
+    fn into(self) -> tempo_map::BeatPosition {
+        tempo_map::BeatPosition {
+            bar: self.bar,
+            beat: self.beat,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct Track {
     pub clips: Vec<Clip>,
 }
 
-impl From<&timeline::Track> for Track {
-    fn from(track: &timeline::Track) -> Self {
+impl Track {
+    fn from(track: &timeline::Track, clip_schemas: &ClipSchemas) -> Self {
         Track {
-            clips: track.clips.iter().map(|clip| Clip::from(clip)).collect(),
+            clips: track
+                .clips
+                .iter()
+                .map(|clip| Clip::from(clip, clip_schemas))
+                .collect(),
         }
     }
-}
 
-impl Track {
-    fn into(self, context: &mut CreationContext) -> timeline::Track {
+    fn into(self, clip_schemas: &ClipSchemas) -> timeline::Track {
         let mut clips = Vec::new();
         let mut next_offset = 0;
         for clip in self.clips {
-            match clip.into(context) {
+            match clip.into(clip_schemas) {
                 ConvertedClip::Clip(mut converted_clip) => {
                     converted_clip.offset_frames += next_offset;
                     next_offset = 0;
@@ -91,40 +681,40 @@ struct Clip {
     pub animation: Option<AnimationClip>,
     pub offset_frames: u32,
     pub duration_frames: u32,
+    #[serde(default)]
+    pub anchor: Option<ClipAnchor>,
     pub property_groups: Vec<PropertyGroup>,
 }
 
-impl From<&timeline::Clip> for Clip {
-    fn from(clip: &timeline::Clip) -> Self {
+impl Clip {
+    fn from(clip: &timeline::Clip, clip_schemas: &ClipSchemas) -> Self {
         Clip {
             id: clip.id,
             name: clip.name.clone(),
             schema: clip.schema.name.to_string(),
             animation: match &clip.source {
-                timeline::ClipSource::Animation(animation_clip) => {
-                    Some(AnimationClip::from(animation_clip, clip.schema))
-                }
+                timeline::ClipSource::Animation(animation_clip) => Some(AnimationClip::from(
+                    animation_clip,
+                    clip.schema,
+                    clip_schemas,
+                )),
                 _ => None,
             },
             offset_frames: clip.offset_frames,
             duration_frames: clip.duration_frames,
+            anchor: clip.anchor.map(ClipAnchor::from),
             property_groups: clip
                 .property_groups
                 .iter()
                 .zip(clip.schema.groups.iter())
-                .map(|(property_group, schema)| PropertyGroup::from(property_group, schema))
+                .map(|(property_group, schema)| {
+                    PropertyGroup::from(property_group, schema, clip_schemas)
+                })
                 .collect(),
         }
     }
-}
-
-enum ConvertedClip {
-    Clip(timeline::Clip),
-    NoClip(u32),
-}
 
-impl Clip {
-    fn into(self, context: &mut CreationContext) -> ConvertedClip {
+    fn into(self, clip_schemas: &ClipSchemas) -> ConvertedClip {
         // Figure out which schema we're referencing by searching the available schemas
         let named_schema = GENERATOR_SCHEMAS
             .iter()
@@ -140,14 +730,12 @@ impl Clip {
             }
         };
 
-        let (generator, property_groups) = match self.animation {
+        let (source, property_groups) = match self.animation {
             Some(animation_clip) => (
-                timeline::ClipSource::Animation(animation_clip.into(named_schema)),
+                timeline::ClipSource::Animation(animation_clip.into(named_schema, clip_schemas)),
                 Vec::new(),
             ),
             None => {
-                let generator =
-                    timeline::ClipSource::Generator((named_schema.instantiate_generator)(context));
                 let available_groups: HashMap<&str, &PropertyGroup> = HashMap::from_iter(
                     self.property_groups
                         .iter()
@@ -155,12 +743,12 @@ impl Clip {
                 );
                 let property_groups = named_schema.groups.iter().map(|schema_group| {
                     match available_groups.get(&schema_group.name) {
-                        Some(group) => (*group).into(schema_group),
+                        Some(group) => (*group).into(schema_group, &self.name, clip_schemas),
                         None => schema_group.instantiate(),
                     }
                 });
 
-                (generator, property_groups.collect())
+                (timeline::ClipSource::Unloaded, property_groups.collect())
             }
         };
 
@@ -168,15 +756,333 @@ impl Clip {
             id: self.id,
             name: self.name,
             schema: named_schema,
-            source: generator,
+            source,
             offset_frames: self.offset_frames,
             duration_frames: self.duration_frames,
+            anchor: self.anchor.as_ref().map(|anchor| anchor.into()),
             property_groups,
             is_selected: false,
         })
     }
 }
 
+enum ConvertedClip {
+    Clip(timeline::Clip),
+    NoClip(u32),
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinEntry {
+    clips: Vec<RecycledClip>,
+    references: Vec<RecycledReference>,
+}
+
+impl BinEntry {
+    fn from(entry: &recycle_bin::BinEntry, clip_schemas: &ClipSchemas) -> Self {
+        BinEntry {
+            clips: entry
+                .clips
+                .iter()
+                .map(|recycled| RecycledClip {
+                    clip: Clip::from(&recycled.clip, clip_schemas),
+                    track_index: recycled.track_index,
+                    start_frame: recycled.start_frame,
+                })
+                .collect(),
+            references: entry
+                .references
+                .iter()
+                .filter_map(|reference| RecycledReference::from(reference, clip_schemas))
+                .collect(),
+        }
+    }
+
+    /// Converts a bin entry, or returns `None` if its deleted clip couldn't be loaded.
+    fn into(self, clip_schemas: &ClipSchemas) -> Option<recycle_bin::BinEntry> {
+        let mut clips = Vec::new();
+        for recycled in self.clips {
+            match recycled.clip.into(clip_schemas) {
+                ConvertedClip::Clip(clip) => clips.push(recycle_bin::RecycledClip {
+                    clip,
+                    track_index: recycled.track_index,
+                    start_frame: recycled.start_frame,
+                }),
+                ConvertedClip::NoClip(_) if clips.is_empty() => return None,
+                ConvertedClip::NoClip(_) => {}
+            }
+        }
+        if clips.is_empty() {
+            return None;
+        }
+
+        Some(recycle_bin::BinEntry {
+            clips,
+            references: self
+                .references
+                .into_iter()
+                .filter_map(|reference| reference.into(clip_schemas))
+                .collect(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecycledClip {
+    clip: Clip,
+    track_index: usize,
+    start_frame: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+enum RecycledReference {
+    Property {
+        clip: u32,
+        property: PropertyBinding,
+        target: u32,
+    },
+    Driver {
+        clip: u32,
+        property: PropertyBinding,
+        driver: PropertyDriver,
+    },
+    Constraint {
+        clip: u32,
+        property: AnimatedProperty,
+    },
+    Keyframe {
+        clip: u32,
+        property: PropertyBinding,
+        key_index: usize,
+        target: u32,
+    },
+}
+
+impl RecycledReference {
+    /// Converts a reference, or returns `None` if the clip it's from has been deleted for good.
+    fn from(
+        reference: &recycle_bin::RecycledReference,
+        clip_schemas: &ClipSchemas,
+    ) -> Option<Self> {
+        Some(match reference {
+            recycle_bin::RecycledReference::Property {
+                clip,
+                binding,
+                target,
+            } => RecycledReference::Property {
+                clip: clip.clip_id(),
+                property: PropertyBinding::from(*binding, clip_schemas.get(&clip.clip_id())?),
+                target: target.clip_id(),
+            },
+            recycle_bin::RecycledReference::Driver {
+                clip,
+                binding,
+                driver,
+            } => RecycledReference::Driver {
+                clip: clip.clip_id(),
+                property: PropertyBinding::from(*binding, clip_schemas.get(&clip.clip_id())?),
+                driver: PropertyDriver::from(driver, clip_schemas)?,
+            },
+            recycle_bin::RecycledReference::Constraint { clip, property } => {
+                RecycledReference::Constraint {
+                    clip: clip.clip_id(),
+                    property: AnimatedProperty::from(
+                        property,
+                        clip_schemas.get(&clip.clip_id())?,
+                        clip_schemas,
+                    )?,
+                }
+            }
+            recycle_bin::RecycledReference::Keyframe {
+                clip,
+                binding,
+                key_index,
+                target,
+            } => RecycledReference::Keyframe {
+                clip: clip.clip_id(),
+                property: PropertyBinding::from(*binding, clip_schemas.get(&clip.clip_id())?),
+                key_index: *key_index,
+                target: target.clip_id(),
+            },
+        })
+    }
+
+    fn into(self, clip_schemas: &ClipSchemas) -> Option<recycle_bin::RecycledReference> {
+        Some(match self {
+            RecycledReference::Property {
+                clip,
+                property,
+                target,
+            } => recycle_bin::RecycledReference::Property {
+                clip: clip::ClipReference::new(clip),
+                binding: property.into(clip_schemas.get(&clip)?)?,
+                target: clip::ClipReference::new(target),
+            },
+            RecycledReference::Driver {
+                clip,
+                property,
+                driver,
+            } => recycle_bin::RecycledReference::Driver {
+                clip: clip::ClipReference::new(clip),
+                binding: property.into(clip_schemas.get(&clip)?)?,
+                driver: PropertyDriver::into(&driver, clip_schemas)?,
+            },
+            RecycledReference::Constraint { clip, property } => {
+                recycle_bin::RecycledReference::Constraint {
+                    clip: clip::ClipReference::new(clip),
+                    property: property.into(clip_schemas.get(&clip)?, clip_schemas)?,
+                }
+            }
+            RecycledReference::Keyframe {
+                clip,
+                property,
+                key_index,
+                target,
+            } => recycle_bin::RecycledReference::Keyframe {
+                clip: clip::ClipReference::new(clip),
+                binding: property.into(clip_schemas.get(&clip)?)?,
+                key_index,
+                target: clip::ClipReference::new(target),
+            },
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ClipFragment {
+    #[serde(default)]
+    version: u32,
+    clips: Vec<CopiedClip>,
+}
+
+impl ClipFragment {
+    fn from(timeline: &timeline::Timeline, clip_ids: &[u32]) -> Self {
+        // The copied clips can reference clips that weren't copied, so every clip's schema is
+        // needed
+        let clip_schemas = HashMap::from_iter(
+            timeline
+                .tracks
+                .iter()
+                .flat_map(|track| track.clips.iter())
+                .map(|clip| (clip.id, clip.schema)),
+        );
+
+        let mut clips = Vec::new();
+        for (track_index, track) in timeline.tracks.iter().enumerate() {
+            let mut last_clip_end = 0;
+            for clip in &track.clips {
+                let clip_start_time = last_clip_end + clip.offset_frames;
+                last_clip_end = clip_start_time + clip.duration_frames;
+
+                if clip_ids.contains(&clip.id) {
+                    clips.push(CopiedClip {
+                        clip: Clip::from(clip, &clip_schemas),
+                        track_index,
+                        start_frame: clip_start_time,
+                    });
+                }
+            }
+        }
+
+        ClipFragment {
+            version: SAVE_VERSION,
+            clips,
+        }
+    }
+
+    fn into(self, timeline: &timeline::Timeline) -> Vec<clipboard::CopiedClip> {
+        if self.version > SAVE_VERSION {
+            eprintln!(
+                "The clips are from version {} of the tool, but this is version {}, so some of them might not paste.",
+                self.version, SAVE_VERSION
+            );
+        }
+
+        // Copied clips are migrated like a save, by putting them in a timeline
+        let (positions, clips): (Vec<_>, Vec<_>) = self
+            .clips
+            .into_iter()
+            .map(|copied| ((copied.track_index, copied.start_frame), copied.clip))
+            .unzip();
+        let mut migrated = Timeline {
+            version: self.version,
+            tracks: vec![Track { clips }],
+            tempo_map: default_tempo_map(),
+            recycle_bin: Vec::new(),
+        };
+        migrated.migrate().print();
+        let clips = migrated.tracks.pop().unwrap().clips;
+
+        // Copied clips take the place of any clips in the timeline with the same ID
+        let copied_clip_schemas = clips.iter().filter_map(|clip| {
+            GENERATOR_SCHEMAS
+                .iter()
+                .find(|schema| schema.name == clip.schema)
+                .map(|schema| (clip.id, schema))
+        });
+        let clip_schemas = HashMap::from_iter(
+            timeline
+                .tracks
+                .iter()
+                .flat_map(|track| track.clips.iter())
+                .map(|clip| (clip.id, clip.schema))
+                .chain(copied_clip_schemas),
+        );
+
+        clips
+            .into_iter()
+            .zip(positions)
+            .filter_map(
+                |(clip, (track_index, start_frame))| match clip.into(&clip_schemas) {
+                    ConvertedClip::Clip(clip) => Some(clipboard::CopiedClip {
+                        clip,
+                        track_index,
+                        start_frame,
+                    }),
+                    ConvertedClip::NoClip(_) => None,
+                },
+            )
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CopiedClip {
+    clip: Clip,
+    track_index: usize,
+    start_frame: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeyframeFragment {
+    #[serde(default)]
+    enum_options: Vec<String>,
+    fields: Vec<AnimatedPropertyField>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ClipAnchor {
+    start: BeatPosition,
+    end: BeatPosition,
+}
+
+impl From<timeline::ClipAnchor> for ClipAnchor {
+    fn from(anchor: timeline::ClipAnchor) -> Self {
+        ClipAnchor {
+            start: BeatPosition::from(anchor.start),
+            end: BeatPosition::from(anchor.end),
+        }
+    }
+}
+
+impl Into<timeline::ClipAnchor> for &ClipAnchor {
+    fn into(self) -> timeline::ClipAnchor {
+        timeline::ClipAnchor {
+            start: (&self.start).into(),
+            end: (&self.end).into(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct PropertyGroup {
     pub name: String,
@@ -184,19 +1090,30 @@ struct PropertyGroup {
 }
 
 impl PropertyGroup {
-    fn from(property_group: &timeline::PropertyGroup, schema: &schema::SchemaGroup) -> Self {
+    fn from(
+        property_group: &timeline::PropertyGroup,
+        schema: &schema::SchemaGroup,
+        clip_schemas: &ClipSchemas,
+    ) -> Self {
         PropertyGroup {
             name: schema.name.to_string(),
             defaults: property_group
                 .defaults
                 .iter()
                 .zip(schema.properties.iter())
-                .map(|(property_default, schema)| PropertyDefault::from(property_default, schema))
+                .map(|(property_default, schema)| {
+                    PropertyDefault::from(property_default, schema, clip_schemas)
+                })
                 .collect(),
         }
     }
 
-    fn into(&self, schema: &schema::SchemaGroup) -> timeline::PropertyGroup {
+    fn into(
+        &self,
+        schema: &schema::SchemaGroup,
+        clip_name: &str,
+        clip_schemas: &ClipSchemas,
+    ) -> timeline::PropertyGroup {
         let available_defaults: HashMap<&str, &PropertyDefault> = HashMap::from_iter(
             self.defaults
                 .iter()
@@ -204,7 +1121,19 @@ impl PropertyGroup {
         );
         let defaults = schema.properties.iter().map(|schema_prop| {
             match available_defaults.get(&schema_prop.name) {
-                Some(default) => (*default).into(),
+                Some(default) => {
+                    let mut default = (*default).into(schema_prop, clip_name, clip_schemas);
+                    let clamped_value = schema_prop.clamp(default.value);
+                    if clamped_value != default.value {
+                        let (min, max) = schema_prop.hard_range.unwrap();
+                        eprintln!(
+                            "Property {} of clip \"{}\" is outside of {} to {}, so it will be clamped.",
+                            schema_prop.name, clip_name, min, max
+                        );
+                        default.value = clamped_value;
+                    }
+                    default
+                }
                 None => schema_prop.instantiate(),
             }
         });
@@ -220,23 +1149,144 @@ struct PropertyDefault {
     pub name: String,
     pub value: PropertyValue,
     pub is_override: bool,
+    #[serde(default)]
+    pub driver: Option<PropertyDriver>,
 }
 
 impl PropertyDefault {
-    fn from(property_default: &timeline::PropertyDefault, schema: &schema::SchemaProperty) -> Self {
+    fn from(
+        property_default: &timeline::PropertyDefault,
+        schema: &schema::SchemaProperty,
+        clip_schemas: &ClipSchemas,
+    ) -> Self {
         PropertyDefault {
             name: schema.name.to_string(),
             value: PropertyValue::from(property_default.value),
             is_override: property_default.is_override,
+            driver: property_default
+                .driver
+                .as_ref()
+                .and_then(|driver| PropertyDriver::from(driver, clip_schemas)),
         }
     }
-}
 
-impl Into<timeline::PropertyDefault> for &PropertyDefault {
-    fn into(self) -> timeline::PropertyDefault {
+    fn into(
+        &self,
+        schema: &schema::SchemaProperty,
+        clip_name: &str,
+        clip_schemas: &ClipSchemas,
+    ) -> timeline::PropertyDefault {
         timeline::PropertyDefault {
             value: (&self.value).into(),
             is_override: self.is_override,
+            driver: self.driver.as_ref().and_then(|driver| {
+                let driver = driver.into(clip_schemas);
+                if driver.is_none() {
+                    eprintln!(
+                        "Couldn't find the source of property {} of clip \"{}\", so it will be undriven.",
+                        schema.name, clip_name
+                    );
+                }
+                driver
+            }),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PropertyDriver {
+    source_clip: u32,
+    source: PropertyBinding,
+    #[serde(default)]
+    mapping: DriverMapping,
+}
+
+impl PropertyDriver {
+    fn from(driver: &driver::PropertyDriver, clip_schemas: &ClipSchemas) -> Option<Self> {
+        // Drivers following a deleted clip aren't saved
+        let source_schema = clip_schemas.get(&driver.source_clip.clip_id())?;
+        Some(PropertyDriver {
+            source_clip: driver.source_clip.clip_id(),
+            source: PropertyBinding::from(driver.source, source_schema),
+            mapping: DriverMapping::from(&driver.mapping),
+        })
+    }
+
+    fn into(&self, clip_schemas: &ClipSchemas) -> Option<driver::PropertyDriver> {
+        let source_schema = clip_schemas.get(&self.source_clip)?;
+        Some(driver::PropertyDriver {
+            source_clip: clip::ClipReference::new(self.source_clip),
+            source: self.source.clone().into(source_schema)?,
+            mapping: (&self.mapping).into(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum DriverMapping {
+    Linear {
+        scale: f32,
+        offset: f32,
+    },
+    Remap {
+        input_min: f32,
+        input_max: f32,
+        output_min: f32,
+        output_max: f32,
+        curve: CurveInterpolation,
+    },
+}
+
+impl Default for DriverMapping {
+    fn default() -> Self {
+        DriverMapping::from(&driver::DriverMapping::default())
+    }
+}
+
+impl From<&driver::DriverMapping> for DriverMapping {
+    fn from(mapping: &driver::DriverMapping) -> Self {
+        match mapping {
+            driver::DriverMapping::Linear { scale, offset } => DriverMapping::Linear {
+                scale: *scale,
+                offset: *offset,
+            },
+            driver::DriverMapping::Remap {
+                input_min,
+                input_max,
+                output_min,
+                output_max,
+                curve,
+            } => DriverMapping::Remap {
+                input_min: *input_min,
+                input_max: *input_max,
+                output_min: *output_min,
+                output_max: *output_max,
+                curve: CurveInterpolation::from(curve),
+            },
+        }
+    }
+}
+
+impl Into<driver::DriverMapping> for &DriverMapping {
+    fn into(self) -> driver::DriverMapping {
+        match self {
+            DriverMapping::Linear { scale, offset } => driver::DriverMapping::Linear {
+                scale: *scale,
+                offset: *offset,
+            },
+            DriverMapping::Remap {
+                input_min,
+                input_max,
+                output_min,
+                output_max,
+                curve,
+            } => driver::DriverMapping::Remap {
+                input_min: *input_min,
+                input_max: *input_max,
+                output_min: *output_min,
+                output_max: *output_max,
+                curve: curve.into(),
+            },
         }
     }
 }
@@ -251,6 +1301,40 @@ enum PropertyValue {
     RgbaColor { r: f32, g: f32, b: f32, a: f32 },
     Rotation { x: f32, y: f32, z: f32, w: f32 },
     ClipReference(Option<u32>),
+    Int(i32),
+    Bool(bool),
+    Enum(u32),
+    Gradient(Vec<GradientStop>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct GradientStop {
+    position: f32,
+    r: f32,
+    g: f32,
+    b: f32,
+    a: f32,
+}
+
+impl From<&math::GradientStop> for GradientStop {
+    fn from(stop: &math::GradientStop) -> Self {
+        GradientStop {
+            position: stop.position,
+            r: stop.color.r(),
+            g: stop.color.g(),
+            b: stop.color.b(),
+            a: stop.color.a(),
+        }
+    }
+}
+
+impl Into<math::GradientStop> for &GradientStop {
+    fn into(self) -> math::GradientStop {
+        math::GradientStop::new(
+            self.position,
+            math::RgbaColor::new(self.r, self.g, self.b, self.a),
+        )
+    }
 }
 
 impl From<property::PropertyValue> for PropertyValue {
@@ -289,6 +1373,12 @@ impl From<property::PropertyValue> for PropertyValue {
             property::PropertyValue::ClipReference(val) => {
                 PropertyValue::ClipReference(val.map(|clip_ref| clip_ref.clip_id()))
             }
+            property::PropertyValue::Int(val) => PropertyValue::Int(val),
+            property::PropertyValue::Bool(val) => PropertyValue::Bool(val),
+            property::PropertyValue::Enum(val) => PropertyValue::Enum(val),
+            property::PropertyValue::Gradient(val) => {
+                PropertyValue::Gradient(val.stops().iter().map(GradientStop::from).collect())
+            }
         }
     }
 }
@@ -328,6 +1418,13 @@ impl Into<property::PropertyValue> for &PropertyValue {
             PropertyValue::ClipReference(val) => property::PropertyValue::ClipReference(
                 val.map(|clip_ref| clip::ClipReference::new(clip_ref)),
             ),
+            PropertyValue::Int(val) => property::PropertyValue::Int(*val),
+            PropertyValue::Bool(val) => property::PropertyValue::Bool(*val),
+            PropertyValue::Enum(val) => property::PropertyValue::Enum(*val),
+            PropertyValue::Gradient(stops) => {
+                let stops: Vec<math::GradientStop> = stops.iter().map(|stop| stop.into()).collect();
+                property::PropertyValue::Gradient(math::Gradient::new(&stops))
+            }
         }
     }
 }
@@ -335,56 +1432,124 @@ impl Into<property::PropertyValue> for &PropertyValue {
 #[derive(Serialize, Deserialize)]
 struct AnimationClip {
     target_clip: u32,
-    //time_property: AnimatedPropertyField,
+    #[serde(default)]
+    time_property: Option<AnimatedPropertyField>,
+    #[serde(default)]
+    blend_mode: BlendMode,
+    #[serde(default)]
+    weight_property: Option<AnimatedPropertyField>,
     properties: Vec<AnimatedProperty>,
 }
 
 impl AnimationClip {
-    pub fn from(clip: &animation_clip::AnimationClip, schema: &GeneratorSchema) -> Self {
+    pub fn from(
+        clip: &animation_clip::AnimationClip,
+        schema: &GeneratorSchema,
+        clip_schemas: &ClipSchemas,
+    ) -> Self {
         AnimationClip {
             target_clip: clip.target_clip.clip_id(),
+            time_property: clip.time_property.as_ref().map(AnimatedPropertyField::from),
+            blend_mode: BlendMode::from(clip.blend_mode),
+            weight_property: clip
+                .weight_property
+                .as_ref()
+                .map(AnimatedPropertyField::from),
             properties: clip
                 .properties
                 .iter()
-                .map(|prop| AnimatedProperty::from(prop, schema))
+                .filter_map(|prop| AnimatedProperty::from(prop, schema, clip_schemas))
                 .collect(),
         }
     }
 
-    pub fn into(self, schema: &GeneratorSchema) -> animation_clip::AnimationClip {
+    pub fn into(
+        self,
+        schema: &GeneratorSchema,
+        clip_schemas: &ClipSchemas,
+    ) -> animation_clip::AnimationClip {
         animation_clip::AnimationClip {
             target_clip: clip::ClipReference::new(self.target_clip),
-            //time_property: self.time_property.into(),
-            //is_time_collapsed: true,
+            time_property: self.time_property.map(|field| field.into()),
+            blend_mode: (&self.blend_mode).into(),
+            weight_property: self.weight_property.map(|field| field.into()),
+            is_time_collapsed: true,
+            is_weight_collapsed: true,
             properties: self
                 .properties
                 .into_iter()
-                .map(|prop| prop.into(schema))
+                .map(|prop| prop.into(schema, clip_schemas))
                 .filter_map(|prop| prop)
                 .collect(),
         }
     }
 }
 
+#[derive(Serialize, Deserialize)]
+enum BlendMode {
+    Replace,
+    Additive,
+    Multiply,
+}
+
+impl Default for BlendMode {
+    fn default() -> Self {
+        BlendMode::Replace
+    }
+}
+
+impl From<animation_clip::BlendMode> for BlendMode {
+    fn from(blend_mode: animation_clip::BlendMode) -> Self {
+        match blend_mode {
+            animation_clip::BlendMode::Replace => BlendMode::Replace,
+            animation_clip::BlendMode::Additive => BlendMode::Additive,
+            animation_clip::BlendMode::Multiply => BlendMode::Multiply,
+        }
+    }
+}
+
+impl Into<animation_clip::BlendMode> for &BlendMode {
+    fn into(self) -> animation_clip::BlendMode {
+        match self {
+            BlendMode::Replace => animation_clip::BlendMode::Replace,
+            BlendMode::Additive => animation_clip::BlendMode::Additive,
+            BlendMode::Multiply => animation_clip::BlendMode::Multiply,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct AnimatedProperty {
     group_name: String,
     property_name: String,
     target: AnimatedPropertyTarget,
+    #[serde(default)]
+    modifiers: Vec<Modifier>,
 }
 
 impl AnimatedProperty {
-    pub fn from(property: &animation_clip::AnimatedProperty, schema: &GeneratorSchema) -> Self {
-        AnimatedProperty {
+    /// Converts an animated property, or returns `None` if it's a constraint following a deleted
+    /// clip.
+    pub fn from(
+        property: &animation_clip::AnimatedProperty,
+        schema: &GeneratorSchema,
+        clip_schemas: &ClipSchemas,
+    ) -> Option<Self> {
+        Some(AnimatedProperty {
             group_name: schema.groups[property.group_index].name.to_string(),
             property_name: schema.groups[property.group_index].properties[property.property_index]
                 .name
                 .to_string(),
-            target: AnimatedPropertyTarget::from(&property.target),
-        }
+            target: AnimatedPropertyTarget::from(&property.target, schema, clip_schemas)?,
+            modifiers: property.modifiers.iter().map(Modifier::from).collect(),
+        })
     }
 
-    pub fn into(self, schema: &GeneratorSchema) -> Option<animation_clip::AnimatedProperty> {
+    pub fn into(
+        self,
+        schema: &GeneratorSchema,
+        clip_schemas: &ClipSchemas,
+    ) -> Option<animation_clip::AnimatedProperty> {
         let group_index = match schema
             .groups
             .iter()
@@ -411,24 +1576,202 @@ impl AnimatedProperty {
             }
         };
 
+        // Keyframes outside of the property's hard range are clamped, like property defaults
+        let schema_prop = &schema.groups[group_index].properties[prop_index];
+        let mut target = match self.target.into(schema, clip_schemas) {
+            Some(target) => target,
+            None => {
+                eprintln!("Couldn't find the target of the constraint on property {} of schema {}'s group {}, so animation will be deleted.", self.property_name, schema.name, self.group_name);
+                return None;
+            }
+        };
+        let keyframed_fields = match target {
+            animation_clip::AnimatedPropertyTarget::Joined(_)
+            | animation_clip::AnimatedPropertyTarget::Separate(_) => target.fields_mut(),
+            _ => &mut [],
+        };
+        let mut clamped_count = 0;
+        for field in keyframed_fields {
+            let keyframe_values = iter::once(&mut field.start_value).chain(
+                field
+                    .segments
+                    .iter_mut()
+                    .map(|segment| &mut segment.end_value),
+            );
+            for value in keyframe_values {
+                let clamped_value = schema_prop.clamp(*value);
+                if clamped_value != *value {
+                    *value = clamped_value;
+                    clamped_count += 1;
+                }
+            }
+        }
+        if clamped_count > 0 {
+            let (min, max) = schema_prop.hard_range.unwrap();
+            eprintln!(
+                "{} keyframes of property {} on schema {} are outside of {} to {}, so they will be clamped.",
+                clamped_count, self.property_name, schema.name, min, max
+            );
+        }
+
         Some(animation_clip::AnimatedProperty {
             group_index,
             property_index: prop_index,
-            target: self.target.into(),
+            target,
+            modifiers: self
+                .modifiers
+                .iter()
+                .map(|modifier| modifier.into())
+                .collect(),
             is_collapsed: false,
+            spring_cache: RefCell::new(None),
         })
     }
 }
 
+#[derive(Serialize, Deserialize)]
+enum Modifier {
+    Noise {
+        frequency: f32,
+        amplitude: f32,
+        seed: u32,
+        octaves: u32,
+    },
+    Sine {
+        frequency: f32,
+        amplitude: f32,
+        phase: f32,
+    },
+    Quantize {
+        step: f32,
+    },
+    Clamp {
+        min: f32,
+        max: f32,
+    },
+    Spring {
+        frequency: f32,
+        damping: f32,
+    },
+}
+
+impl From<&modifier::Modifier> for Modifier {
+    fn from(modifier: &modifier::Modifier) -> Self {
+        match *modifier {
+            modifier::Modifier::Noise {
+                frequency,
+                amplitude,
+                seed,
+                octaves,
+            } => Modifier::Noise {
+                frequency,
+                amplitude,
+                seed,
+                octaves,
+            },
+            modifier::Modifier::Sine {
+                frequency,
+                amplitude,
+                phase,
+            } => Modifier::Sine {
+                frequency,
+                amplitude,
+                phase,
+            },
+            modifier::Modifier::Quantize { step } => Modifier::Quantize { step },
+            modifier::Modifier::Clamp { min, max } => Modifier::Clamp { min, max },
+            modifier::Modifier::Spring { frequency, damping } => {
+                Modifier::Spring { frequency, damping }
+            }
+        }
+    }
+}
+
+impl Into<modifier::Modifier> for &Modifier {
+    fn into(self) -> modifier::Modifier {
+        match *self {
+            Modifier::Noise {
+                frequency,
+                amplitude,
+                seed,
+                octaves,
+            } => modifier::Modifier::Noise {
+                frequency,
+                amplitude,
+                seed,
+                octaves,
+            },
+            Modifier::Sine {
+                frequency,
+                amplitude,
+                phase,
+            } => modifier::Modifier::Sine {
+                frequency,
+                amplitude,
+                phase,
+            },
+            Modifier::Quantize { step } => modifier::Modifier::Quantize { step },
+            Modifier::Clamp { min, max } => modifier::Modifier::Clamp { min, max },
+            Modifier::Spring { frequency, damping } => {
+                modifier::Modifier::Spring { frequency, damping }
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 enum AnimatedPropertyTarget {
     Joined(AnimatedPropertyField),
     Separate(Vec<AnimatedPropertyField>),
+    Expression(String),
+    Path(SplinePath),
+    Constraint(Constraint),
 }
 
-impl From<&animation_clip::AnimatedPropertyTarget> for AnimatedPropertyTarget {
-    fn from(target: &animation_clip::AnimatedPropertyTarget) -> Self {
-        match target {
+impl AnimatedPropertyTarget {
+    /// Converts keyframes to another type for a migration, returning false if the animation
+    /// can't be converted. Expressions are kept, since they're evaluated as whatever type their
+    /// property is.
+    fn convert(&mut self, to: property::PropertyType) -> bool {
+        let to_count = to.num_fields();
+        match self {
+            AnimatedPropertyTarget::Joined(field) => field.convert(to),
+            AnimatedPropertyTarget::Separate(fields) if to_count == 1 => {
+                // The first field becomes the whole value
+                fields.truncate(1);
+                let mut field = fields.pop().unwrap();
+                let converted = field.convert(to);
+                *self = AnimatedPropertyTarget::Joined(field);
+                converted
+            }
+            AnimatedPropertyTarget::Separate(_) if to == property::PropertyType::Gradient => false,
+            AnimatedPropertyTarget::Separate(fields) => {
+                // Missing fields are held at the new type's default
+                let default_fields: Vec<_> = to.default_value().fields().collect();
+                fields.truncate(to_count);
+                for &default_field in &default_fields[fields.len()..to_count] {
+                    fields.push(AnimatedPropertyField {
+                        local_offset_frames: 0,
+                        start_anchor: None,
+                        start_value: PropertyValue::Float(default_field),
+                        segments: Vec::new(),
+                        pre_extrapolation: Extrapolation::Constant,
+                        post_extrapolation: Extrapolation::Constant,
+                    });
+                }
+                true
+            }
+            AnimatedPropertyTarget::Expression(_) => true,
+            AnimatedPropertyTarget::Path(_) | AnimatedPropertyTarget::Constraint(_) => false,
+        }
+    }
+
+    pub fn from(
+        target: &animation_clip::AnimatedPropertyTarget,
+        schema: &GeneratorSchema,
+        clip_schemas: &ClipSchemas,
+    ) -> Option<Self> {
+        Some(match target {
             animation_clip::AnimatedPropertyTarget::Joined(field) => {
                 AnimatedPropertyTarget::Joined(AnimatedPropertyField::from(field))
             }
@@ -437,13 +1780,28 @@ impl From<&animation_clip::AnimatedPropertyTarget> for AnimatedPropertyTarget {
                     fields.iter().map(AnimatedPropertyField::from).collect(),
                 )
             }
-        }
+            animation_clip::AnimatedPropertyTarget::Expression(expression) => {
+                AnimatedPropertyTarget::Expression(expression.source.clone())
+            }
+            animation_clip::AnimatedPropertyTarget::Path(path) => {
+                AnimatedPropertyTarget::Path(SplinePath::from(path, schema))
+            }
+            animation_clip::AnimatedPropertyTarget::Constraint(constraint) => {
+                AnimatedPropertyTarget::Constraint(Constraint::from(
+                    constraint,
+                    schema,
+                    clip_schemas,
+                )?)
+            }
+        })
     }
-}
 
-impl Into<animation_clip::AnimatedPropertyTarget> for AnimatedPropertyTarget {
-    fn into(self) -> animation_clip::AnimatedPropertyTarget {
-        match self {
+    pub fn into(
+        self,
+        schema: &GeneratorSchema,
+        clip_schemas: &ClipSchemas,
+    ) -> Option<animation_clip::AnimatedPropertyTarget> {
+        Some(match self {
             AnimatedPropertyTarget::Joined(field) => {
                 animation_clip::AnimatedPropertyTarget::Joined(field.into())
             }
@@ -452,6 +1810,225 @@ impl Into<animation_clip::AnimatedPropertyTarget> for AnimatedPropertyTarget {
                     fields.into_iter().map(|field| field.into()).collect(),
                 )
             }
+            AnimatedPropertyTarget::Expression(source) => {
+                let bytecode = compile_expression(&source).unwrap_or_else(|err| {
+                    eprintln!("Couldn't compile expression \"{}\": {}", source, err);
+                    Vec::new()
+                });
+                animation_clip::AnimatedPropertyTarget::Expression(expression::Expression {
+                    source,
+                    bytecode,
+                })
+            }
+            AnimatedPropertyTarget::Path(path) => {
+                animation_clip::AnimatedPropertyTarget::Path(path.into(schema))
+            }
+            AnimatedPropertyTarget::Constraint(constraint) => {
+                animation_clip::AnimatedPropertyTarget::Constraint(
+                    constraint.into(schema, clip_schemas)?,
+                )
+            }
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum Constraint {
+    LookAt {
+        eye: PropertyBinding,
+        target: ConstraintTarget,
+    },
+    Follow {
+        target: ConstraintTarget,
+        offset: [f32; 3],
+    },
+    DampedFollow {
+        target: ConstraintTarget,
+        offset: [f32; 3],
+        half_life: f32,
+    },
+}
+
+impl Constraint {
+    fn target_mut(&mut self) -> &mut ConstraintTarget {
+        match self {
+            Constraint::LookAt { target, .. }
+            | Constraint::Follow { target, .. }
+            | Constraint::DampedFollow { target, .. } => target,
+        }
+    }
+
+    pub fn from(
+        constraint: &constraint::Constraint,
+        schema: &GeneratorSchema,
+        clip_schemas: &ClipSchemas,
+    ) -> Option<Self> {
+        // Constraints following a deleted clip aren't saved
+        let target = ConstraintTarget::from(constraint.target(), clip_schemas)?;
+        Some(match *constraint {
+            constraint::Constraint::LookAt { eye, .. } => Constraint::LookAt {
+                eye: PropertyBinding::from(eye, schema),
+                target,
+            },
+            constraint::Constraint::Follow { offset, .. } => Constraint::Follow {
+                target,
+                offset: offset.into(),
+            },
+            constraint::Constraint::DampedFollow {
+                offset, half_life, ..
+            } => Constraint::DampedFollow {
+                target,
+                offset: offset.into(),
+                half_life,
+            },
+        })
+    }
+
+    pub fn into(
+        self,
+        schema: &GeneratorSchema,
+        clip_schemas: &ClipSchemas,
+    ) -> Option<constraint::Constraint> {
+        Some(match self {
+            Constraint::LookAt { eye, target } => constraint::Constraint::LookAt {
+                eye: eye.into(schema)?,
+                target: target.into(clip_schemas)?,
+            },
+            Constraint::Follow { target, offset } => constraint::Constraint::Follow {
+                target: target.into(clip_schemas)?,
+                offset: offset.into(),
+            },
+            Constraint::DampedFollow {
+                target,
+                offset,
+                half_life,
+            } => constraint::Constraint::DampedFollow {
+                target: target.into(clip_schemas)?,
+                offset: offset.into(),
+                half_life,
+                cache: RefCell::new(None),
+            },
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ConstraintTarget {
+    clip: u32,
+    property: PropertyBinding,
+}
+
+impl ConstraintTarget {
+    pub fn from(target: constraint::ConstraintTarget, clip_schemas: &ClipSchemas) -> Option<Self> {
+        let target_schema = clip_schemas.get(&target.clip.clip_id())?;
+        Some(ConstraintTarget {
+            clip: target.clip.clip_id(),
+            property: PropertyBinding::from(target.property, target_schema),
+        })
+    }
+
+    pub fn into(self, clip_schemas: &ClipSchemas) -> Option<constraint::ConstraintTarget> {
+        let target_schema = clip_schemas.get(&self.clip)?;
+        Some(constraint::ConstraintTarget {
+            clip: clip::ClipReference::new(self.clip),
+            property: self.property.into(target_schema)?,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SplinePath {
+    kind: SplineKind,
+    points: Vec<[f32; 3]>,
+    progress: AnimatedPropertyField,
+    #[serde(default)]
+    orient_target: Option<PropertyBinding>,
+}
+
+impl SplinePath {
+    pub fn from(path: &spline_path::SplinePath, schema: &GeneratorSchema) -> Self {
+        SplinePath {
+            kind: SplineKind::from(path.kind),
+            points: path.points.iter().map(|&point| point.into()).collect(),
+            progress: AnimatedPropertyField::from(&path.progress),
+            orient_target: path
+                .orient_target
+                .map(|binding| PropertyBinding::from(binding, schema)),
+        }
+    }
+
+    pub fn into(self, schema: &GeneratorSchema) -> spline_path::SplinePath {
+        spline_path::SplinePath::new(
+            (&self.kind).into(),
+            self.points.into_iter().map(math::Vector3::from).collect(),
+            self.progress.into(),
+            self.orient_target.and_then(|binding| binding.into(schema)),
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum SplineKind {
+    CatmullRom,
+    Centripetal,
+}
+
+impl From<spline_path::SplineKind> for SplineKind {
+    fn from(kind: spline_path::SplineKind) -> Self {
+        match kind {
+            spline_path::SplineKind::CatmullRom => SplineKind::CatmullRom,
+            spline_path::SplineKind::Centripetal => SplineKind::Centripetal,
+        }
+    }
+}
+
+impl Into<spline_path::SplineKind> for &SplineKind {
+    fn into(self) -> spline_path::SplineKind {
+        match self {
+            SplineKind::CatmullRom => spline_path::SplineKind::CatmullRom,
+            SplineKind::Centripetal => spline_path::SplineKind::Centripetal,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct PropertyBinding {
+    group_name: String,
+    property_name: String,
+}
+
+impl PropertyBinding {
+    pub fn from(binding: binding::PropertyBinding, schema: &GeneratorSchema) -> Self {
+        let group = &schema.groups[binding.group];
+        PropertyBinding {
+            group_name: group.name.to_string(),
+            property_name: group.properties[binding.prop].name.to_string(),
+        }
+    }
+
+    pub fn into(self, schema: &GeneratorSchema) -> Option<binding::PropertyBinding> {
+        let group_index = schema
+            .groups
+            .iter()
+            .position(|group| group.name == self.group_name);
+        let prop_index = group_index.and_then(|group_index| {
+            schema.groups[group_index]
+                .properties
+                .iter()
+                .position(|prop| prop.name == self.property_name)
+        });
+
+        match (group_index, prop_index) {
+            (Some(group_index), Some(prop_index)) => {
+                Some(binding::PropertyBinding::new(group_index, prop_index))
+            }
+            _ => {
+                eprintln!(
+                    "Couldn't find property {} in group {} on schema {}, so it will be unbound.",
+                    self.property_name, self.group_name, schema.name
+                );
+                None
+            }
         }
     }
 }
@@ -459,20 +2036,47 @@ impl Into<animation_clip::AnimatedPropertyTarget> for AnimatedPropertyTarget {
 #[derive(Serialize, Deserialize)]
 struct AnimatedPropertyField {
     local_offset_frames: i32,
+    #[serde(default)]
+    start_anchor: Option<BeatPosition>,
     start_value: PropertyValue,
     segments: Vec<CurveSegment>,
+    #[serde(default)]
+    pre_extrapolation: Extrapolation,
+    #[serde(default)]
+    post_extrapolation: Extrapolation,
+}
+
+impl AnimatedPropertyField {
+    /// Converts the field's keyframes to another type, returning false if any can't be converted.
+    fn convert(&mut self, to: property::PropertyType) -> bool {
+        let values = iter::once(&mut self.start_value).chain(
+            self.segments
+                .iter_mut()
+                .map(|segment| &mut segment.end_value),
+        );
+        for value in values {
+            match convert_value((&*value).into(), to) {
+                Some(converted) => *value = PropertyValue::from(converted),
+                None => return false,
+            }
+        }
+        true
+    }
 }
 
 impl From<&animation_clip::AnimatedPropertyField> for AnimatedPropertyField {
     fn from(field: &animation_clip::AnimatedPropertyField) -> Self {
         AnimatedPropertyField {
             local_offset_frames: field.local_offset_frames,
+            start_anchor: field.start_anchor.map(BeatPosition::from),
             start_value: PropertyValue::from(field.start_value),
             segments: field
                 .segments
                 .iter()
                 .map(|segment| CurveSegment::from(segment))
                 .collect(),
+            pre_extrapolation: Extrapolation::from(field.pre_extrapolation),
+            post_extrapolation: Extrapolation::from(field.post_extrapolation),
         }
     }
 }
@@ -481,8 +2085,50 @@ impl Into<animation_clip::AnimatedPropertyField> for AnimatedPropertyField {
     fn into(self) -> animation_clip::AnimatedPropertyField {
         animation_clip::AnimatedPropertyField {
             local_offset_frames: self.local_offset_frames,
+            start_anchor: self.start_anchor.as_ref().map(|anchor| anchor.into()),
             start_value: (&self.start_value).into(),
             segments: self.segments.iter().map(|segment| segment.into()).collect(),
+            pre_extrapolation: (&self.pre_extrapolation).into(),
+            post_extrapolation: (&self.post_extrapolation).into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum Extrapolation {
+    Constant,
+    Linear,
+    Cycle,
+    CycleWithOffset,
+    PingPong,
+}
+
+impl Default for Extrapolation {
+    fn default() -> Self {
+        Extrapolation::Constant
+    }
+}
+
+impl From<animation_clip::Extrapolation> for Extrapolation {
+    fn from(extrapolation: animation_clip::Extrapolation) -> Self {
+        match extrapolation {
+            animation_clip::Extrapolation::Constant => Extrapolation::Constant,
+            animation_clip::Extrapolation::Linear => Extrapolation::Linear,
+            animation_clip::Extrapolation::Cycle => Extrapolation::Cycle,
+            animation_clip::Extrapolation::CycleWithOffset => Extrapolation::CycleWithOffset,
+            animation_clip::Extrapolation::PingPong => Extrapolation::PingPong,
+        }
+    }
+}
+
+impl Into<animation_clip::Extrapolation> for &Extrapolation {
+    fn into(self) -> animation_clip::Extrapolation {
+        match self {
+            Extrapolation::Constant => animation_clip::Extrapolation::Constant,
+            Extrapolation::Linear => animation_clip::Extrapolation::Linear,
+            Extrapolation::Cycle => animation_clip::Extrapolation::Cycle,
+            Extrapolation::CycleWithOffset => animation_clip::Extrapolation::CycleWithOffset,
+            Extrapolation::PingPong => animation_clip::Extrapolation::PingPong,
         }
     }
 }
@@ -490,16 +2136,25 @@ impl Into<animation_clip::AnimatedPropertyField> for AnimatedPropertyField {
 #[derive(Serialize, Deserialize)]
 struct CurveSegment {
     duration_frames: u32,
+    #[serde(default)]
+    end_anchor: Option<BeatPosition>,
     end_value: PropertyValue,
     interpolation: CurveInterpolation,
+    #[serde(default)]
+    rotation_spline: bool,
+    #[serde(default)]
+    color_space: ColorSpace,
 }
 
 impl From<&animation_clip::CurveSegment> for CurveSegment {
     fn from(segment: &animation_clip::CurveSegment) -> Self {
         CurveSegment {
             duration_frames: segment.duration_frames,
+            end_anchor: segment.end_anchor.map(BeatPosition::from),
             end_value: PropertyValue::from(segment.end_value),
             interpolation: CurveInterpolation::from(&segment.interpolation),
+            rotation_spline: segment.rotation_spline,
+            color_space: ColorSpace::from(segment.color_space),
         }
     }
 }
@@ -508,8 +2163,11 @@ impl Into<animation_clip::CurveSegment> for &CurveSegment {
     fn into(self) -> animation_clip::CurveSegment {
         animation_clip::CurveSegment {
             duration_frames: self.duration_frames,
+            end_anchor: self.end_anchor.as_ref().map(|anchor| anchor.into()),
             end_value: (&self.end_value).into(),
             interpolation: (&self.interpolation).into(),
+            rotation_spline: self.rotation_spline,
+            color_space: (&self.color_space).into(),
         }
     }
 }
@@ -518,6 +2176,9 @@ impl Into<animation_clip::CurveSegment> for &CurveSegment {
 enum CurveInterpolation {
     Linear,
     CubicBezier(CubicBezier),
+    Step,
+    StepNext,
+    Ease(Easing),
 }
 
 impl From<&animation_clip::CurveInterpolation> for CurveInterpolation {
@@ -527,6 +2188,11 @@ impl From<&animation_clip::CurveInterpolation> for CurveInterpolation {
             animation_clip::CurveInterpolation::CubicBezier(bezier) => {
                 CurveInterpolation::CubicBezier(CubicBezier::from(bezier))
             }
+            animation_clip::CurveInterpolation::Step => CurveInterpolation::Step,
+            animation_clip::CurveInterpolation::StepNext => CurveInterpolation::StepNext,
+            animation_clip::CurveInterpolation::Ease(easing) => {
+                CurveInterpolation::Ease(Easing::from(*easing))
+            }
         }
     }
 }
@@ -538,6 +2204,119 @@ impl Into<animation_clip::CurveInterpolation> for &CurveInterpolation {
             CurveInterpolation::CubicBezier(bezier) => {
                 animation_clip::CurveInterpolation::CubicBezier(bezier.into())
             }
+            CurveInterpolation::Step => animation_clip::CurveInterpolation::Step,
+            CurveInterpolation::StepNext => animation_clip::CurveInterpolation::StepNext,
+            CurveInterpolation::Ease(easing) => {
+                animation_clip::CurveInterpolation::Ease(easing.into())
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum Easing {
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+impl From<easing::Easing> for Easing {
+    fn from(easing: easing::Easing) -> Self {
+        match easing {
+            easing::Easing::QuadIn => Easing::QuadIn,
+            easing::Easing::QuadOut => Easing::QuadOut,
+            easing::Easing::QuadInOut => Easing::QuadInOut,
+            easing::Easing::CubicIn => Easing::CubicIn,
+            easing::Easing::CubicOut => Easing::CubicOut,
+            easing::Easing::CubicInOut => Easing::CubicInOut,
+            easing::Easing::ExpoIn => Easing::ExpoIn,
+            easing::Easing::ExpoOut => Easing::ExpoOut,
+            easing::Easing::ExpoInOut => Easing::ExpoInOut,
+            easing::Easing::BackIn => Easing::BackIn,
+            easing::Easing::BackOut => Easing::BackOut,
+            easing::Easing::BackInOut => Easing::BackInOut,
+            easing::Easing::ElasticIn => Easing::ElasticIn,
+            easing::Easing::ElasticOut => Easing::ElasticOut,
+            easing::Easing::ElasticInOut => Easing::ElasticInOut,
+            easing::Easing::BounceIn => Easing::BounceIn,
+            easing::Easing::BounceOut => Easing::BounceOut,
+            easing::Easing::BounceInOut => Easing::BounceInOut,
+        }
+    }
+}
+
+impl Into<easing::Easing> for &Easing {
+    fn into(self) -> easing::Easing {
+        match self {
+            Easing::QuadIn => easing::Easing::QuadIn,
+            Easing::QuadOut => easing::Easing::QuadOut,
+            Easing::QuadInOut => easing::Easing::QuadInOut,
+            Easing::CubicIn => easing::Easing::CubicIn,
+            Easing::CubicOut => easing::Easing::CubicOut,
+            Easing::CubicInOut => easing::Easing::CubicInOut,
+            Easing::ExpoIn => easing::Easing::ExpoIn,
+            Easing::ExpoOut => easing::Easing::ExpoOut,
+            Easing::ExpoInOut => easing::Easing::ExpoInOut,
+            Easing::BackIn => easing::Easing::BackIn,
+            Easing::BackOut => easing::Easing::BackOut,
+            Easing::BackInOut => easing::Easing::BackInOut,
+            Easing::ElasticIn => easing::Easing::ElasticIn,
+            Easing::ElasticOut => easing::Easing::ElasticOut,
+            Easing::ElasticInOut => easing::Easing::ElasticInOut,
+            Easing::BounceIn => easing::Easing::BounceIn,
+            Easing::BounceOut => easing::Easing::BounceOut,
+            Easing::BounceInOut => easing::Easing::BounceInOut,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum ColorSpace {
+    LinearRgb,
+    Oklab,
+    Oklch,
+    Hsv,
+}
+
+impl Default for ColorSpace {
+    fn default() -> Self {
+        ColorSpace::LinearRgb
+    }
+}
+
+impl From<math::ColorSpace> for ColorSpace {
+    fn from(space: math::ColorSpace) -> Self {
+        match space {
+            math::ColorSpace::LinearRgb => ColorSpace::LinearRgb,
+            math::ColorSpace::Oklab => ColorSpace::Oklab,
+            math::ColorSpace::Oklch => ColorSpace::Oklch,
+            math::ColorSpace::Hsv => ColorSpace::Hsv,
+        }
+    }
+}
+
+impl Into<math::ColorSpace> for &ColorSpace {
+    fn into(self) -> math::ColorSpace {
+        match self {
+            ColorSpace::LinearRgb => math::ColorSpace::LinearRgb,
+            ColorSpace::Oklab => math::ColorSpace::Oklab,
+            ColorSpace::Oklch => math::ColorSpace::Oklch,
+            ColorSpace::Hsv => math::ColorSpace::Hsv,
         }
     }
 }
@@ -583,3 +2362,424 @@ struct Point {
     x: f32,
     y: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A save from before versions were recorded, with a camera clip, an animation of it, and a
+    /// light clip driven by the camera.
+    const OLD_SAVE: &str = r#"(
+        tracks: [
+            (clips: [(
+                id: 1,
+                name: "Camera",
+                schema: "Old Camera",
+                animation: None,
+                offset_frames: 0,
+                duration_frames: 100,
+                property_groups: [
+                    (name: "Lens", defaults: [
+                        (name: "fov", value: Float(60.0), is_override: false),
+                        (name: "focus", value: Float(2.0), is_override: false),
+                        (name: "parent", value: ClipReference(None), is_override: false),
+                    ]),
+                    (name: "Position", defaults: [
+                        (name: "position", value: Vec3(x: 3.0, y: 1.0, z: 2.0), is_override: false),
+                    ]),
+                ],
+            )]),
+            (clips: [(
+                id: 2,
+                name: "Camera animation",
+                schema: "Old Camera",
+                animation: Some((
+                    target_clip: 1,
+                    properties: [
+                        (
+                            group_name: "Lens",
+                            property_name: "fov",
+                            target: Joined((
+                                local_offset_frames: 0,
+                                start_value: Float(60.0),
+                                segments: [(
+                                    duration_frames: 10,
+                                    end_value: Float(90.0),
+                                    interpolation: Linear,
+                                )],
+                            )),
+                        ),
+                        (
+                            group_name: "Position",
+                            property_name: "position",
+                            target: Constraint(Follow(
+                                target: (
+                                    clip: 3,
+                                    property: (group_name: "Light", property_name: "position"),
+                                ),
+                                offset: (0.0, 0.0, 0.0),
+                            )),
+                        ),
+                    ],
+                )),
+                offset_frames: 0,
+                duration_frames: 10,
+                property_groups: [],
+            )]),
+            (clips: [(
+                id: 3,
+                name: "Light",
+                schema: "Light",
+                animation: None,
+                offset_frames: 0,
+                duration_frames: 100,
+                property_groups: [
+                    (name: "Light", defaults: [
+                        (
+                            name: "brightness",
+                            value: Float(1.0),
+                            is_override: false,
+                            driver: Some((
+                                source_clip: 1,
+                                source: (group_name: "Lens", property_name: "focus"),
+                            )),
+                        ),
+                        (name: "position", value: Vec3(x: 0.0, y: 0.0, z: 0.0), is_override: false),
+                    ]),
+                ],
+            )]),
+        ],
+    )"#;
+
+    fn migrate_old_save(migration: Migration) -> (Timeline, MigrationReport) {
+        let mut timeline: Timeline = ron::de::from_str(OLD_SAVE).unwrap();
+        let mut report = MigrationReport::new(timeline.version);
+        timeline.apply_migration(&migration, &mut report);
+        (timeline, report)
+    }
+
+    fn clip(timeline: &Timeline, clip_id: u32) -> &Clip {
+        timeline
+            .tracks
+            .iter()
+            .flat_map(|track| track.clips.iter())
+            .find(|clip| clip.id == clip_id)
+            .unwrap()
+    }
+
+    fn group_names(clip: &Clip) -> Vec<&str> {
+        clip.property_groups
+            .iter()
+            .map(|group| &group.name as &str)
+            .collect()
+    }
+
+    fn defaults<'clip>(clip: &'clip Clip, group_name: &str) -> Vec<&'clip PropertyDefault> {
+        clip.property_groups
+            .iter()
+            .filter(|group| group.name == group_name)
+            .flat_map(|group| group.defaults.iter())
+            .collect()
+    }
+
+    fn default_names<'clip>(clip: &'clip Clip, group_name: &str) -> Vec<&'clip str> {
+        defaults(clip, group_name)
+            .into_iter()
+            .map(|default| &default.name as &str)
+            .collect()
+    }
+
+    fn default_value(
+        clip: &Clip,
+        group_name: &str,
+        property_name: &str,
+    ) -> property::PropertyValue {
+        let default = defaults(clip, group_name)
+            .into_iter()
+            .find(|default| default.name == property_name)
+            .unwrap();
+        (&default.value).into()
+    }
+
+    fn animated_bindings(clip: &Clip) -> Vec<(&str, &str)> {
+        clip.animation
+            .as_ref()
+            .unwrap()
+            .properties
+            .iter()
+            .map(|property| {
+                (
+                    &property.group_name as &str,
+                    &property.property_name as &str,
+                )
+            })
+            .collect()
+    }
+
+    fn driver_source(clip: &Clip) -> Option<(&str, &str)> {
+        let driver = defaults(clip, "Light")[0].driver.as_ref()?;
+        Some((
+            &driver.source.group_name as &str,
+            &driver.source.property_name as &str,
+        ))
+    }
+
+    #[test]
+    fn migrate_updates_the_version() {
+        let mut timeline: Timeline = ron::de::from_str(OLD_SAVE).unwrap();
+        let report = timeline.migrate();
+        assert_eq!(timeline.version, SAVE_VERSION);
+        assert_eq!(report.from_version, 0);
+        assert!(report.migrated.is_empty());
+        assert!(report.dropped.is_empty());
+    }
+
+    #[test]
+    fn migrate_applies_migrations_newer_than_the_save() {
+        let save = |version: u32| {
+            format!(
+                r#"(
+                    version: {},
+                    tracks: [(clips: [(
+                        id: 1,
+                        name: "Launch",
+                        schema: "Launch Scene",
+                        animation: None,
+                        offset_frames: 0,
+                        duration_frames: 100,
+                        property_groups: [
+                            (name: "clouds", defaults: [
+                                (name: "gen once <-> live", value: Float(1.0), is_override: false),
+                            ]),
+                        ],
+                    )])],
+                )"#,
+                version
+            )
+        };
+
+        let mut timeline: Timeline = ron::de::from_str(&save(1)).unwrap();
+        let report = timeline.migrate();
+        let launch = clip(&timeline, 1);
+        assert_eq!(default_names(launch, "clouds"), vec!["live"]);
+        assert!(default_value(launch, "clouds", "live") == property::PropertyValue::Bool(true));
+        assert_eq!(report.from_version, 1);
+        assert_eq!(report.migrated.len(), 2);
+
+        // Saves from the version that added the migrations already use the new names
+        let mut timeline: Timeline = ron::de::from_str(&save(2)).unwrap();
+        let report = timeline.migrate();
+        assert_eq!(
+            default_names(clip(&timeline, 1), "clouds"),
+            vec!["gen once <-> live"]
+        );
+        assert!(report.migrated.is_empty());
+    }
+
+    #[test]
+    fn rename_schema() {
+        let (timeline, report) = migrate_old_save(Migration::RenameSchema {
+            from: "Old Camera",
+            to: "Camera",
+        });
+        assert_eq!(clip(&timeline, 1).schema, "Camera");
+        assert_eq!(clip(&timeline, 2).schema, "Camera");
+        assert_eq!(clip(&timeline, 3).schema, "Light");
+        assert_eq!(
+            report.migrated,
+            vec!["Renamed schema Old Camera to Camera on 2 clips."]
+        );
+        assert!(report.dropped.is_empty());
+
+        // Nothing is reported for migrations that don't apply to the save
+        let (_, report) = migrate_old_save(Migration::RenameSchema {
+            from: "Missing",
+            to: "Camera",
+        });
+        assert!(report.migrated.is_empty());
+        assert!(report.dropped.is_empty());
+    }
+
+    #[test]
+    fn rename_group() {
+        let (timeline, report) = migrate_old_save(Migration::RenameGroup {
+            schema: "Old Camera",
+            from: "Lens",
+            to: "Optics",
+        });
+        assert_eq!(group_names(clip(&timeline, 1)), vec!["Optics", "Position"]);
+        assert_eq!(
+            animated_bindings(clip(&timeline, 2)),
+            vec![("Optics", "fov"), ("Position", "position")]
+        );
+        assert_eq!(driver_source(clip(&timeline, 3)), Some(("Optics", "focus")));
+
+        // The light's own group has a different schema, so it isn't renamed
+        assert_eq!(group_names(clip(&timeline, 3)), vec!["Light"]);
+        assert_eq!(
+            report.migrated,
+            vec!["Renamed group Lens to Optics on schema Old Camera in 3 places."]
+        );
+        assert!(report.dropped.is_empty());
+    }
+
+    #[test]
+    fn rename_property() {
+        let (timeline, report) = migrate_old_save(Migration::RenameProperty {
+            schema: "Old Camera",
+            group: "Lens",
+            from: "fov",
+            to: "field_of_view",
+        });
+        assert_eq!(
+            default_names(clip(&timeline, 1), "Lens"),
+            vec!["field_of_view", "focus", "parent"]
+        );
+        assert_eq!(
+            animated_bindings(clip(&timeline, 2)),
+            vec![("Lens", "field_of_view"), ("Position", "position")]
+        );
+        assert_eq!(
+            report.migrated,
+            vec!["Renamed property fov to field_of_view in group Lens of schema Old Camera in 2 places."]
+        );
+        assert!(report.dropped.is_empty());
+    }
+
+    #[test]
+    fn move_property() {
+        let (timeline, report) = migrate_old_save(Migration::MoveProperty {
+            schema: "Old Camera",
+            property: "focus",
+            from_group: "Lens",
+            to_group: "Focus",
+        });
+        let camera = clip(&timeline, 1);
+        assert_eq!(group_names(camera), vec!["Lens", "Position", "Focus"]);
+        assert_eq!(default_names(camera, "Lens"), vec!["fov", "parent"]);
+        assert_eq!(default_names(camera, "Focus"), vec!["focus"]);
+        assert!(default_value(camera, "Focus", "focus") == property::PropertyValue::Float(2.));
+        assert_eq!(driver_source(clip(&timeline, 3)), Some(("Focus", "focus")));
+        assert_eq!(
+            report.migrated,
+            vec!["Moved property focus of schema Old Camera from group Lens to Focus in 2 places."]
+        );
+        assert!(report.dropped.is_empty());
+    }
+
+    #[test]
+    fn convert_property() {
+        let (timeline, report) = migrate_old_save(Migration::ConvertProperty {
+            schema: "Old Camera",
+            group: "Lens",
+            property: "fov",
+            to: property::PropertyType::Vec2,
+        });
+        let fov = default_value(clip(&timeline, 1), "Lens", "fov")
+            .into_vec2()
+            .unwrap();
+        assert_eq!((fov.x, fov.y), (60., 60.));
+        match &clip(&timeline, 2).animation.as_ref().unwrap().properties[0].target {
+            AnimatedPropertyTarget::Joined(field) => {
+                let start: property::PropertyValue = (&field.start_value).into();
+                let end: property::PropertyValue = (&field.segments[0].end_value).into();
+                let (start, end) = (start.into_vec2().unwrap(), end.into_vec2().unwrap());
+                assert_eq!((start.x, start.y), (60., 60.));
+                assert_eq!((end.x, end.y), (90., 90.));
+            }
+            _ => panic!("The converted animation should still be keyframed"),
+        }
+        assert_eq!(
+            report.migrated,
+            vec!["Converted property fov in group Lens of schema Old Camera in 2 places."]
+        );
+        assert!(report.dropped.is_empty());
+    }
+
+    #[test]
+    fn convert_property_drops_what_cant_be_converted() {
+        // Constraints can't be converted, so the animation is deleted
+        let (timeline, report) = migrate_old_save(Migration::ConvertProperty {
+            schema: "Old Camera",
+            group: "Position",
+            property: "position",
+            to: property::PropertyType::Float,
+        });
+        assert!(
+            default_value(clip(&timeline, 1), "Position", "position")
+                == property::PropertyValue::Float(3.)
+        );
+        assert_eq!(animated_bindings(clip(&timeline, 2)), vec![("Lens", "fov")]);
+        assert_eq!(
+            report.migrated,
+            vec!["Converted property position in group Position of schema Old Camera in 2 places."]
+        );
+        assert_eq!(
+            report.dropped,
+            vec!["Couldn't convert the animation of property position on clip \"Camera animation\", so it will be deleted."]
+        );
+
+        // Clip references can't be converted, so the value is reset
+        let (timeline, report) = migrate_old_save(Migration::ConvertProperty {
+            schema: "Old Camera",
+            group: "Lens",
+            property: "parent",
+            to: property::PropertyType::Float,
+        });
+        assert_eq!(
+            default_names(clip(&timeline, 1), "Lens"),
+            vec!["fov", "focus"]
+        );
+        assert_eq!(
+            report.migrated,
+            vec!["Converted property parent in group Lens of schema Old Camera in 1 places."]
+        );
+        assert_eq!(
+            report.dropped,
+            vec!["Couldn't convert property parent of clip \"Camera\", so it will be reset."]
+        );
+    }
+
+    #[test]
+    fn remove_property() {
+        let (timeline, report) = migrate_old_save(Migration::RemoveProperty {
+            schema: "Old Camera",
+            group: "Lens",
+            property: "focus",
+        });
+        assert_eq!(
+            default_names(clip(&timeline, 1), "Lens"),
+            vec!["fov", "parent"]
+        );
+        assert_eq!(driver_source(clip(&timeline, 3)), None);
+        assert!(report.migrated.is_empty());
+        assert_eq!(
+            report.dropped,
+            vec![
+                "Property focus in group Lens of schema Old Camera was removed from 1 clips.",
+                "The driver on clip \"Light\" used property focus in group Lens of schema Old Camera, which was removed.",
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_property_removes_constraints_following_it() {
+        let (timeline, report) = migrate_old_save(Migration::RemoveProperty {
+            schema: "Light",
+            group: "Light",
+            property: "position",
+        });
+        assert_eq!(
+            default_names(clip(&timeline, 3), "Light"),
+            vec!["brightness"]
+        );
+        assert_eq!(animated_bindings(clip(&timeline, 2)), vec![("Lens", "fov")]);
+        assert_eq!(
+            report.dropped,
+            vec![
+                "Property position in group Light of schema Light was removed from 1 clips.",
+                "The constraint on clip \"Camera animation\" used property position in group Light of schema Light, which was removed.",
+            ]
+        );
+    }
+}
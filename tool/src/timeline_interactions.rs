@@ -1,10 +1,17 @@
 use crate::editor_state::EditorState;
 use engine::animation::animation_clip::{
-    AnimatedPropertyField, AnimatedPropertyTarget, CurveInterpolation, CurveSegment,
+    AnimatedPropertyField, AnimatedPropertyTarget, CurveInterpolation, CurveSegment, Extrapolation,
 };
+use engine::animation::clip::ClipReference;
 use engine::animation::property::PropertyValue;
-use engine::animation::timeline::{Clip, ClipSource, Timeline, Track};
-use std::{iter, slice};
+use engine::animation::spline_path::{SplineKind, SplinePath};
+use engine::animation::tempo_map::BeatPosition;
+use engine::animation::timeline::{Clip, ClipAnchor, ClipSource, TimeBase, Timeline, Track};
+use engine::math::{ColorSpace, Vector3};
+use std::collections::HashMap;
+use std::iter;
+
+const BEAT_SNAP_PADDING: u32 = 32;
 
 pub fn deselect_all_clips(timeline: &mut Timeline) {
     for track in &mut timeline.tracks {
@@ -71,6 +78,79 @@ pub fn remove_clip(track: &mut Track, index: usize) -> Clip {
     removed_clip
 }
 
+/// Updates the clips a clip references, for when they've been given new IDs. This covers clip
+/// reference properties and keyframes, drivers, and an animation clip's target and constraints.
+/// IDs that aren't in `id_map` are left as they are.
+pub fn remap_clip_references(clip: &mut Clip, id_map: &HashMap<u32, u32>) {
+    let remap = |reference: &mut ClipReference| {
+        if let Some(&new_id) = id_map.get(&reference.clip_id()) {
+            *reference = ClipReference::new(new_id);
+        }
+    };
+    let remap_value = |value: &mut PropertyValue| {
+        if let PropertyValue::ClipReference(Some(reference)) = value {
+            remap(reference);
+        }
+    };
+
+    for default in clip
+        .property_groups
+        .iter_mut()
+        .flat_map(|group| group.defaults.iter_mut())
+    {
+        remap_value(&mut default.value);
+        if let Some(driver) = &mut default.driver {
+            remap(&mut driver.source_clip);
+        }
+    }
+
+    if let ClipSource::Animation(animation) = &mut clip.source {
+        remap(&mut animation.target_clip);
+        for property in &mut animation.properties {
+            match &mut property.target {
+                AnimatedPropertyTarget::Joined(field) => {
+                    remap_value(&mut field.start_value);
+                    for segment in &mut field.segments {
+                        remap_value(&mut segment.end_value);
+                    }
+                }
+                AnimatedPropertyTarget::Constraint(constraint) => {
+                    remap(&mut constraint.target_mut().clip);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Inserts clips at the given track indices and frames, after giving them the new IDs in `id_map`
+/// and updating the references between them. Clips that don't fit where they're put go on a new
+/// track.
+pub fn insert_remapped_clips(
+    timeline: &mut Timeline,
+    clips: Vec<(Clip, usize, u32)>,
+    id_map: &HashMap<u32, u32>,
+) {
+    for (mut clip, track_index, start_frame) in clips {
+        if let Some(&new_id) = id_map.get(&clip.id) {
+            clip.id = new_id;
+        }
+        remap_clip_references(&mut clip, id_map);
+
+        while timeline.tracks.len() <= track_index {
+            timeline.tracks.push(Track::default());
+        }
+        let track = &mut timeline.tracks[track_index];
+        if can_fit_clip(track, start_frame, clip.duration_frames, false) {
+            insert_clip(track, clip, start_frame).ok().unwrap();
+        } else {
+            let mut track = Track::default();
+            insert_clip(&mut track, clip, start_frame).ok().unwrap();
+            timeline.tracks.push(track);
+        }
+    }
+}
+
 pub fn insert_clip(track: &mut Track, mut clip: Clip, position_frames: u32) -> Result<(), Clip> {
     // Find the index of the clip that will come before this one
     let before_index = track
@@ -124,12 +204,23 @@ pub fn insert_keyframe(
     // Special case: if the position is before the start time, do a swap operation
     if position_frames < field.local_offset_frames {
         let new_curve_duration = (field.local_offset_frames - position_frames) as u32;
+        // New segments keep the rotation spline and colour space of the segment next to them, so
+        // adding keyframes doesn't change how the rest of the curve is interpolated
+        let (rotation_spline, color_space) = field
+            .segments
+            .first()
+            .map_or((false, ColorSpace::LinearRgb), |segment| {
+                (segment.rotation_spline, segment.color_space)
+            });
         field.segments.insert(
             0,
             CurveSegment {
                 duration_frames: new_curve_duration,
+                end_anchor: field.start_anchor.take(),
                 end_value: field.start_value,
                 interpolation: CurveInterpolation::Linear,
+                rotation_spline,
+                color_space,
             },
         );
         field.local_offset_frames = position_frames;
@@ -175,14 +266,19 @@ pub fn insert_keyframe(
         let new_segment_duration = (position_frames - segment_start_time) as u32;
         let old_segment_duration = segment.duration_frames - new_segment_duration;
         segment.duration_frames = old_segment_duration;
+        let rotation_spline = segment.rotation_spline;
+        let color_space = segment.color_space;
 
         // Insert the new segment as needed
         field.segments.insert(
             segment_index,
             CurveSegment {
                 duration_frames: new_segment_duration,
+                end_anchor: None,
                 end_value: value,
                 interpolation: CurveInterpolation::Linear,
+                rotation_spline,
+                color_space,
             },
         );
 
@@ -195,10 +291,19 @@ pub fn insert_keyframe(
             .fold(field.local_offset_frames, |last_segment_end, segment| {
                 last_segment_end + segment.duration_frames as i32
             });
+        let (rotation_spline, color_space) = field
+            .segments
+            .last()
+            .map_or((false, ColorSpace::LinearRgb), |segment| {
+                (segment.rotation_spline, segment.color_space)
+            });
         field.segments.push(CurveSegment {
             duration_frames: (position_frames - last_segment_end) as u32,
+            end_anchor: None,
             end_value: value,
             interpolation: CurveInterpolation::Linear,
+            rotation_spline,
+            color_space,
         });
 
         true
@@ -215,6 +320,7 @@ pub fn delete_keyframe(field: &mut AnimatedPropertyField, index: Option<usize>)
             };
 
             field.start_value = first_segment.end_value;
+            field.start_anchor = first_segment.end_anchor;
             field.local_offset_frames += first_segment.duration_frames as i32;
             field.segments.remove(0);
         }
@@ -227,6 +333,118 @@ pub fn delete_keyframe(field: &mut AnimatedPropertyField, index: Option<usize>)
     }
 }
 
+/// Replaces the keyframes of a field from `position_frames` to the end of `pasted` with the
+/// keyframes of `pasted`, moved so its first keyframe is at `position_frames`. The field keeps its
+/// extrapolation.
+pub fn paste_keyframes(
+    field: &mut AnimatedPropertyField,
+    mut pasted: AnimatedPropertyField,
+    position_frames: i32,
+) {
+    let pasted_end_frames = position_frames + pasted.duration_frames() as i32;
+    let pasted_offset = position_frames - pasted.local_offset_frames;
+    let (rotation_spline, color_space) = field
+        .segments
+        .first()
+        .map_or((false, ColorSpace::LinearRgb), |segment| {
+            (segment.rotation_spline, segment.color_space)
+        });
+
+    // Each keyframe is kept with the segment leading up to it. The first keyframe doesn't have
+    // one, so it gets a linear segment in case it ends up after another keyframe.
+    let field_keyframes = |field: &mut AnimatedPropertyField| {
+        let first_keyframe = CurveSegment {
+            duration_frames: 0,
+            end_anchor: field.start_anchor,
+            end_value: field.start_value,
+            interpolation: CurveInterpolation::Linear,
+            rotation_spline,
+            color_space,
+        };
+        iter::once(first_keyframe)
+            .chain(field.segments.drain(..))
+            .scan(field.local_offset_frames, |keyframe_frames, segment| {
+                *keyframe_frames += segment.duration_frames as i32;
+                Some((*keyframe_frames, segment))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut keyframes: Vec<_> = field_keyframes(field)
+        .into_iter()
+        .filter(|&(frame, _)| frame < position_frames || frame > pasted_end_frames)
+        .chain(
+            field_keyframes(&mut pasted)
+                .into_iter()
+                .map(|(frame, segment)| (frame + pasted_offset, segment)),
+        )
+        .collect();
+    keyframes.sort_by_key(|&(frame, _)| frame);
+
+    let mut keyframes = keyframes.into_iter();
+    let (first_frame, first_keyframe) = keyframes.next().unwrap();
+    field.local_offset_frames = first_frame;
+    field.start_anchor = first_keyframe.end_anchor;
+    field.start_value = first_keyframe.end_value;
+    let mut last_frame = first_frame;
+    for (frame, mut segment) in keyframes {
+        segment.duration_frames = (frame - last_frame) as u32;
+        field.segments.push(segment);
+        last_frame = frame;
+    }
+}
+
+/// Creates a path for a target to switch to. A joined `Vec3` target becomes a path through its
+/// keyframe values, travelled over the same time as the keyframes. Other targets get a short
+/// straight path.
+pub fn convert_to_path(target: &AnimatedPropertyTarget) -> SplinePath {
+    let (points, progress) = match target {
+        AnimatedPropertyTarget::Joined(field) => {
+            let points = iter::once(field.start_value)
+                .chain(field.segments.iter().map(|segment| segment.end_value))
+                .filter_map(|value| value.into_vec3())
+                .collect();
+
+            let duration_frames = field.duration_frames();
+            let segments = if duration_frames > 0 {
+                vec![CurveSegment {
+                    duration_frames,
+                    end_anchor: field.segments.last().and_then(|segment| segment.end_anchor),
+                    end_value: PropertyValue::Float(1.),
+                    interpolation: CurveInterpolation::Linear,
+                    rotation_spline: false,
+                    color_space: ColorSpace::LinearRgb,
+                }]
+            } else {
+                Vec::new()
+            };
+
+            let progress = AnimatedPropertyField {
+                local_offset_frames: field.local_offset_frames,
+                start_anchor: field.start_anchor,
+                start_value: PropertyValue::Float(0.),
+                segments,
+                pre_extrapolation: Extrapolation::Constant,
+                post_extrapolation: Extrapolation::Constant,
+            };
+            (points, progress)
+        }
+        _ => {
+            let progress = AnimatedPropertyField {
+                local_offset_frames: 0,
+                start_anchor: None,
+                start_value: PropertyValue::Float(0.),
+                segments: Vec::new(),
+                pre_extrapolation: Extrapolation::Constant,
+                post_extrapolation: Extrapolation::Constant,
+            };
+            (vec![Vector3::default(), Vector3::unit_z()], progress)
+        }
+    };
+
+    SplinePath::new(SplineKind::default(), points, progress, None)
+}
+
 pub fn move_selected_clips(
     timeline: &mut Timeline,
     editor_state: &mut EditorState,
@@ -527,14 +745,11 @@ pub fn resize_selected_clips_left(
             // (because that would mess things up pretty badly!)
             // review: maybe we do want to do something with the time field though?
             if let ClipSource::Animation(animation) = &mut clip.source {
-                let animation_fields =
-                    animation
-                        .properties
-                        .iter_mut()
-                        .flat_map(|prop| match &mut prop.target {
-                            AnimatedPropertyTarget::Joined(field) => slice::from_mut(field),
-                            AnimatedPropertyTarget::Separate(fields) => fields,
-                        });
+                let property_fields = animation
+                    .properties
+                    .iter_mut()
+                    .flat_map(|prop| prop.target.fields_mut());
+                let animation_fields = animation.weight_property.iter_mut().chain(property_fields);
                 for animation_field in animation_fields {
                     // todo: is this the correct direction?
                     animation_field.local_offset_frames -= real_offset;
@@ -596,6 +811,7 @@ pub fn resize_selected_clips_right(
                 continue;
             }
 
+            let original_duration = clip.duration_frames;
             if real_offset > 0 {
                 clip.duration_frames += real_offset as u32;
             } else {
@@ -603,13 +819,17 @@ pub fn resize_selected_clips_right(
             }
 
             // Scale the time property as needed
-            /*if let ClipSource::Animation(ref mut animation_clip) = clip.source {
-                let scale_factor = clip.duration_frames as f32 / original_duration as f32;
-                animation_clip.time_property.local_offset_frames = (animation_clip.time_property.local_offset_frames as f32 * scale_factor) as i32;
-                for segment in &mut animation_clip.time_property.segments {
-                    segment.duration_frames = (segment.duration_frames as f32 * scale_factor) as u32;
+            if let ClipSource::Animation(animation_clip) = &mut clip.source {
+                if let Some(time_property) = &mut animation_clip.time_property {
+                    let scale_factor = clip.duration_frames as f32 / original_duration as f32;
+                    time_property.local_offset_frames =
+                        (time_property.local_offset_frames as f32 * scale_factor) as i32;
+                    for segment in &mut time_property.segments {
+                        segment.duration_frames =
+                            (segment.duration_frames as f32 * scale_factor) as u32;
+                    }
                 }
-            }*/
+            }
 
             if let Some(next_clip) = track.clips.get_mut(clip_index + 1) {
                 if real_offset > 0 {
@@ -624,13 +844,145 @@ pub fn resize_selected_clips_right(
     editor_state.drag_offset = frame_offset;
 }
 
+/// Finds the musical position of a frame for anchoring something to it. Frames are whole numbers,
+/// so if the frame is the closest one to a beat, the position is put exactly on that beat to stop
+/// it from drifting when the tempo changes.
+pub fn frame_to_anchor(frame: i32, time_base: TimeBase) -> BeatPosition {
+    let beats = time_base.frame_to_beats(frame as f32);
+    let nearest_beat = beats.round();
+    let beats = if time_base.beats_to_frame(nearest_beat).round() as i32 == frame {
+        nearest_beat
+    } else {
+        beats
+    };
+    time_base.tempo_map.beats_to_position(beats)
+}
+
+fn anchor_to_frame(anchor: BeatPosition, time_base: TimeBase) -> i32 {
+    time_base.position_to_frame(anchor).round() as i32
+}
+
+pub fn clip_anchor_at(clip_start_time: u32, clip: &Clip, time_base: TimeBase) -> ClipAnchor {
+    ClipAnchor {
+        start: frame_to_anchor(clip_start_time as i32, time_base),
+        end: frame_to_anchor((clip_start_time + clip.duration_frames) as i32, time_base),
+    }
+}
+
+/// Updates the anchors of a field's keyframes to where the keyframes currently are.
+pub fn update_field_anchors(
+    field: &mut AnimatedPropertyField,
+    clip_start_time: u32,
+    time_base: TimeBase,
+) {
+    let mut keyframe_time = clip_start_time as i32 + field.local_offset_frames;
+    if let Some(anchor) = &mut field.start_anchor {
+        *anchor = frame_to_anchor(keyframe_time, time_base);
+    }
+    for segment in &mut field.segments {
+        keyframe_time += segment.duration_frames as i32;
+        if let Some(anchor) = &mut segment.end_anchor {
+            *anchor = frame_to_anchor(keyframe_time, time_base);
+        }
+    }
+}
+
+/// Updates the anchors of selected clips and their keyframes after they've been moved or resized.
+pub fn update_selected_clip_anchors(timeline: &mut Timeline, frames_per_second: f32) {
+    let time_base = TimeBase {
+        frames_per_second,
+        tempo_map: &timeline.tempo_map,
+    };
+    for track in &mut timeline.tracks {
+        let mut last_clip_end = 0;
+        for clip in &mut track.clips {
+            let clip_start_time = last_clip_end + clip.offset_frames;
+            last_clip_end = clip_start_time + clip.duration_frames;
+            if !clip.is_selected {
+                continue;
+            }
+
+            if clip.anchor.is_some() {
+                clip.anchor = Some(clip_anchor_at(clip_start_time, clip, time_base));
+            }
+            if let ClipSource::Animation(animation) = &mut clip.source {
+                for field in animation.fields_mut() {
+                    update_field_anchors(field, clip_start_time, time_base);
+                }
+            }
+        }
+    }
+}
+
+/// Moves anchored clips and keyframes back to their musical positions after the tempo map has
+/// changed. Clips that aren't anchored keep their position, unless an anchored clip before them
+/// in the track now overlaps them, in which case they're pushed later.
+pub fn retime_anchored_items(timeline: &mut Timeline, frames_per_second: f32) {
+    let time_base = TimeBase {
+        frames_per_second,
+        tempo_map: &timeline.tempo_map,
+    };
+    for track in &mut timeline.tracks {
+        let mut old_last_clip_end = 0;
+        let mut new_last_clip_end = 0;
+        for clip in &mut track.clips {
+            let old_start_time = old_last_clip_end + clip.offset_frames;
+            old_last_clip_end = old_start_time + clip.duration_frames;
+
+            let (start_time, end_time) = match clip.anchor {
+                Some(anchor) => (
+                    anchor_to_frame(anchor.start, time_base).max(0) as u32,
+                    anchor_to_frame(anchor.end, time_base).max(0) as u32,
+                ),
+                None => (old_start_time, old_last_clip_end),
+            };
+            let start_time = start_time.max(new_last_clip_end);
+            let end_time = end_time.max(start_time + 1);
+            clip.offset_frames = start_time - new_last_clip_end;
+            clip.duration_frames = end_time - start_time;
+            new_last_clip_end = end_time;
+
+            if let ClipSource::Animation(animation) = &mut clip.source {
+                for field in animation.fields_mut() {
+                    retime_anchored_keyframes(field, start_time, time_base);
+                }
+            }
+        }
+    }
+}
+
+fn retime_anchored_keyframes(
+    field: &mut AnimatedPropertyField,
+    clip_start_time: u32,
+    time_base: TimeBase,
+) {
+    let mut old_keyframe_time = field.local_offset_frames;
+    if let Some(anchor) = field.start_anchor {
+        field.local_offset_frames = anchor_to_frame(anchor, time_base) - clip_start_time as i32;
+    }
+
+    // Keyframes can't be moved before the previous one, so they might get pushed later
+    let mut last_keyframe_time = field.local_offset_frames;
+    for segment in &mut field.segments {
+        old_keyframe_time += segment.duration_frames as i32;
+        let keyframe_time = match segment.end_anchor {
+            Some(anchor) => anchor_to_frame(anchor, time_base) - clip_start_time as i32,
+            None => old_keyframe_time,
+        }
+        .max(last_keyframe_time);
+        segment.duration_frames = (keyframe_time - last_keyframe_time) as u32;
+        last_keyframe_time = keyframe_time;
+    }
+}
+
 /// Finds all snapping points for dragging. Essentially, this means that if the mouse is near one
 /// of these points relative to it's start position, it should snap to the next smallest one(?)
 /// Each (non-selected) clip generates two snapping targets, one at the start and one at the end.
+/// Every beat in the tempo map is also a target, which includes the start of every bar.
 /// Each selected clip has two snapping sources, also one at each end.
 /// The result is a permutation of the two, by finding the required movement of the mouse to make
 /// each source reach each target.
-pub fn get_snapping_points(timeline: &Timeline) -> Vec<i32> {
+pub fn get_snapping_points(timeline: &Timeline, time_base: TimeBase) -> Vec<i32> {
     let all_clips: Vec<_> = timeline
         .tracks
         .iter()
@@ -644,6 +996,16 @@ pub fn get_snapping_points(timeline: &Timeline) -> Vec<i32> {
         })
         .collect();
 
+    // Beats can be snapped to a little past the end of the project, to allow extending it
+    let project_end_time = all_clips
+        .iter()
+        .map(|&(clip_start_time, clip)| clip_start_time + clip.duration_frames)
+        .max()
+        .unwrap_or(0);
+    let end_beat = time_base.frame_to_beats(project_end_time as f32) as u32 + BEAT_SNAP_PADDING;
+    let beat_targets =
+        (0..=end_beat).map(|beat| time_base.beats_to_frame(beat as f32).round() as u32);
+
     let snap_targets: Vec<_> = all_clips
         .iter()
         .filter(|&&(_, clip)| !clip.is_selected)
@@ -651,6 +1013,7 @@ pub fn get_snapping_points(timeline: &Timeline) -> Vec<i32> {
             iter::once(clip_start_time).chain(iter::once(clip_start_time + clip.duration_frames))
         })
         .chain(iter::once(0))
+        .chain(beat_targets)
         .collect();
 
     let mut snap_offsets: Vec<_> = all_clips
@@ -693,3 +1056,277 @@ pub fn snap_offset(offset: i32, snapping_points: &[i32], threshold: i32) -> i32
         )
         .0
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::NoAudioPlayer;
+    use crate::history::{History, DEFAULT_HISTORY_DEPTH};
+    use crate::recycle_bin::RecycleBin;
+    use crate::serialize::serialize_timeline;
+    use engine::animation::animation_clip::{AnimatedProperty, AnimationClip, BlendMode};
+    use engine::animation::schema::GeneratorSchema;
+    use engine::generator::GENERATOR_SCHEMAS;
+    use ron::ser::Serializer;
+    use std::cell::RefCell;
+
+    fn grading_schema() -> &'static GeneratorSchema {
+        GENERATOR_SCHEMAS
+            .iter()
+            .find(|schema| schema.name == "Grading")
+            .unwrap()
+    }
+
+    fn generator_clip(id: u32, offset_frames: u32, duration_frames: u32) -> Clip {
+        let schema = grading_schema();
+        Clip {
+            id,
+            name: "Grading".to_string(),
+            schema,
+            source: ClipSource::Unloaded,
+            offset_frames,
+            duration_frames,
+            anchor: None,
+            property_groups: schema
+                .groups
+                .iter()
+                .map(|group| group.instantiate())
+                .collect(),
+            is_selected: false,
+        }
+    }
+
+    /// A track with a generator clip from frame 10 to 110, and a track with an animation clip from
+    /// frame 30 to 70 that moves the first clip's exposure from 2 to 4 over its first 20 frames,
+    /// followed by a generator clip from frame 100 to 160.
+    fn test_timeline() -> Timeline {
+        let animation_clip = Clip {
+            id: 2,
+            name: "Animation".to_string(),
+            schema: grading_schema(),
+            source: ClipSource::Animation(AnimationClip {
+                target_clip: ClipReference::new(1),
+                time_property: None,
+                blend_mode: BlendMode::Replace,
+                weight_property: None,
+                is_time_collapsed: true,
+                is_weight_collapsed: true,
+                properties: vec![AnimatedProperty {
+                    group_index: 0,
+                    property_index: 0,
+                    is_collapsed: false,
+                    target: AnimatedPropertyTarget::Joined(AnimatedPropertyField {
+                        local_offset_frames: 0,
+                        start_anchor: None,
+                        start_value: PropertyValue::Float(2.),
+                        segments: vec![CurveSegment {
+                            duration_frames: 20,
+                            end_anchor: None,
+                            end_value: PropertyValue::Float(4.),
+                            interpolation: CurveInterpolation::Linear,
+                            rotation_spline: false,
+                            color_space: ColorSpace::LinearRgb,
+                        }],
+                        pre_extrapolation: Extrapolation::Constant,
+                        post_extrapolation: Extrapolation::Constant,
+                    }),
+                    modifiers: Vec::new(),
+                    spring_cache: RefCell::new(None),
+                }],
+            }),
+            offset_frames: 30,
+            duration_frames: 40,
+            anchor: None,
+            property_groups: Vec::new(),
+            is_selected: false,
+        };
+
+        Timeline {
+            tracks: vec![
+                Track {
+                    clips: vec![generator_clip(1, 10, 100)],
+                },
+                Track {
+                    clips: vec![animation_clip, generator_clip(3, 30, 60)],
+                },
+            ],
+            ..Timeline::default()
+        }
+    }
+
+    fn select(timeline: &mut Timeline, clip_id: u32) {
+        for clip in timeline
+            .tracks
+            .iter_mut()
+            .flat_map(|track| track.clips.iter_mut())
+        {
+            clip.is_selected = clip.id == clip_id;
+        }
+    }
+
+    fn animated_field(timeline: &mut Timeline) -> &mut AnimatedPropertyField {
+        let animation = timeline
+            .tracks
+            .iter_mut()
+            .flat_map(|track| track.clips.iter_mut())
+            .filter_map(|clip| match &mut clip.source {
+                ClipSource::Animation(animation) => Some(animation),
+                _ => None,
+            })
+            .next()
+            .unwrap();
+        &mut animation.properties[0].target.fields_mut()[0]
+    }
+
+    fn serialized(timeline: &Timeline) -> String {
+        let mut serializer = Serializer::new(None, false);
+        serialize_timeline(timeline, &RecycleBin::default(), &mut serializer).unwrap();
+        serializer.into_output_string()
+    }
+
+    /// Runs an edit, checking that undoing it gives back the timeline from before it and redoing
+    /// it gives back the edited timeline. The edit returns its inverse, which is then run to check
+    /// it also gives back the timeline from before the edit.
+    fn check_edit(
+        mut timeline: Timeline,
+        edit: impl FnOnce(&mut Timeline) -> Box<dyn FnOnce(&mut Timeline)>,
+    ) {
+        let mut recycle_bin = RecycleBin::default();
+        let mut history = History::new(&timeline, &recycle_bin, DEFAULT_HISTORY_DEPTH);
+        let before = serialized(&timeline);
+        let inverse = edit(&mut timeline);
+        let after = serialized(&timeline);
+        assert_ne!(after, before);
+        assert!(history.record(&timeline, &recycle_bin));
+
+        assert!(history.undo(&mut timeline, &mut recycle_bin).unwrap());
+        assert_eq!(serialized(&timeline), before);
+        assert!(history.redo(&mut timeline, &mut recycle_bin).unwrap());
+        assert_eq!(serialized(&timeline), after);
+
+        inverse(&mut timeline);
+        assert_eq!(serialized(&timeline), before);
+    }
+
+    #[test]
+    fn insert_clip_undoes_and_redoes() {
+        check_edit(test_timeline(), |timeline| {
+            insert_clip(&mut timeline.tracks[1], generator_clip(4, 0, 20), 75)
+                .ok()
+                .unwrap();
+            assert_eq!(timeline.tracks[1].clips[1].offset_frames, 5);
+            assert_eq!(timeline.tracks[1].clips[2].offset_frames, 5);
+            Box::new(|timeline| {
+                remove_clip(&mut timeline.tracks[1], 1);
+            })
+        });
+    }
+
+    #[test]
+    fn remove_clip_undoes_and_redoes() {
+        check_edit(test_timeline(), |timeline| {
+            let removed = remove_clip(&mut timeline.tracks[1], 0);
+            assert_eq!(timeline.tracks[1].clips[0].offset_frames, 100);
+            Box::new(move |timeline| {
+                insert_clip(&mut timeline.tracks[1], removed, 30)
+                    .ok()
+                    .unwrap();
+            })
+        });
+    }
+
+    #[test]
+    fn move_selected_clips_undoes_and_redoes() {
+        let mut timeline = test_timeline();
+        select(&mut timeline, 1);
+        check_edit(timeline, |timeline| {
+            let mut audio_player = NoAudioPlayer::new();
+            let mut editor_state = EditorState::new(60., &mut audio_player);
+            move_selected_clips(timeline, &mut editor_state, 20);
+            assert_eq!(timeline.tracks[0].clips[0].offset_frames, 30);
+            Box::new(|timeline| {
+                let mut audio_player = NoAudioPlayer::new();
+                let mut editor_state = EditorState::new(60., &mut audio_player);
+                move_selected_clips(timeline, &mut editor_state, -20);
+            })
+        });
+    }
+
+    #[test]
+    fn change_selected_clip_tracks_undoes_and_redoes() {
+        let mut timeline = test_timeline();
+        select(&mut timeline, 3);
+        check_edit(timeline, |timeline| {
+            let mut audio_player = NoAudioPlayer::new();
+            let mut editor_state = EditorState::new(60., &mut audio_player);
+            change_selected_clip_tracks(timeline, &mut editor_state, 1);
+            assert_eq!(timeline.tracks.len(), 3);
+            assert_eq!(timeline.tracks[2].clips[0].offset_frames, 100);
+            Box::new(|timeline| {
+                let mut audio_player = NoAudioPlayer::new();
+                let mut editor_state = EditorState::new(60., &mut audio_player);
+                change_selected_clip_tracks(timeline, &mut editor_state, -1);
+            })
+        });
+    }
+
+    #[test]
+    fn resize_selected_clips_left_undoes_and_redoes() {
+        let mut timeline = test_timeline();
+        select(&mut timeline, 2);
+        check_edit(timeline, |timeline| {
+            let mut audio_player = NoAudioPlayer::new();
+            let mut editor_state = EditorState::new(60., &mut audio_player);
+            resize_selected_clips_left(timeline, &mut editor_state, -10, 1);
+            assert_eq!(timeline.tracks[1].clips[0].duration_frames, 50);
+            assert_eq!(animated_field(timeline).local_offset_frames, 10);
+            Box::new(|timeline| {
+                let mut audio_player = NoAudioPlayer::new();
+                let mut editor_state = EditorState::new(60., &mut audio_player);
+                resize_selected_clips_left(timeline, &mut editor_state, 10, 1);
+            })
+        });
+    }
+
+    #[test]
+    fn resize_selected_clips_right_undoes_and_redoes() {
+        let mut timeline = test_timeline();
+        select(&mut timeline, 2);
+        check_edit(timeline, |timeline| {
+            let mut audio_player = NoAudioPlayer::new();
+            let mut editor_state = EditorState::new(60., &mut audio_player);
+            resize_selected_clips_right(timeline, &mut editor_state, 10, 1);
+            assert_eq!(timeline.tracks[1].clips[0].duration_frames, 50);
+            assert_eq!(timeline.tracks[1].clips[1].offset_frames, 20);
+            Box::new(|timeline| {
+                let mut audio_player = NoAudioPlayer::new();
+                let mut editor_state = EditorState::new(60., &mut audio_player);
+                resize_selected_clips_right(timeline, &mut editor_state, -10, 1);
+            })
+        });
+    }
+
+    #[test]
+    fn insert_keyframe_undoes_and_redoes() {
+        check_edit(test_timeline(), |timeline| {
+            assert!(insert_keyframe(
+                animated_field(timeline),
+                10,
+                PropertyValue::Float(3.)
+            ));
+            assert_eq!(animated_field(timeline).segments.len(), 2);
+            Box::new(|timeline| delete_keyframe(animated_field(timeline), Some(0)))
+        });
+    }
+
+    #[test]
+    fn delete_keyframe_undoes_and_redoes() {
+        check_edit(test_timeline(), |timeline| {
+            delete_keyframe(animated_field(timeline), Some(0));
+            assert!(animated_field(timeline).segments.is_empty());
+            Box::new(|timeline| {
+                insert_keyframe(animated_field(timeline), 20, PropertyValue::Float(4.));
+            })
+        });
+    }
+}
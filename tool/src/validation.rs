@@ -0,0 +1,507 @@
+use engine::animation::animation_clip::{AnimatedPropertyField, AnimatedPropertyTarget};
+use engine::animation::property::{PropertyType, PropertyValue};
+use engine::animation::timeline::{Clip, ClipSource, Timeline};
+use std::collections::HashMap;
+use std::iter;
+
+/// The most items the exporter can write to each stream, and the most animated properties an
+/// animation clip, or segments a field, can have. Counts are exported as a `u8`, and clip
+/// references as a `u8` index with `!0` meaning no clip.
+const MAX_EXPORTED_COUNT: usize = u8::MAX as usize;
+
+/// Each clip by ID, with its start and end frames.
+type ClipRanges<'timeline> = HashMap<u32, (&'timeline Clip, u64, u64)>;
+
+/// A problem found in a timeline, which will cause it to export incorrectly or not at all.
+pub struct ValidationIssue {
+    /// The clip with the problem, if it's on a single clip.
+    pub clip_id: Option<u32>,
+    pub message: String,
+}
+
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    pub fn print(&self) {
+        if self.issues.is_empty() {
+            eprintln!("The timeline is valid.");
+            return;
+        }
+
+        eprintln!("Found {} problems in the timeline:", self.issues.len());
+        for issue in &self.issues {
+            eprintln!("  {}", issue.message);
+        }
+    }
+
+    fn add(&mut self, clip: &Clip, message: String) {
+        self.issues.push(ValidationIssue {
+            clip_id: Some(clip.id),
+            message: format!("Clip \"{}\": {}", clip.name, message),
+        });
+    }
+}
+
+/// Checks a timeline for problems the exporter would silently drop or get wrong, so they can be
+/// fixed before exporting.
+pub fn validate_timeline(timeline: &Timeline) -> ValidationReport {
+    let mut report = ValidationReport { issues: Vec::new() };
+
+    // Tracks store each clip's offset from the one before it, so clips on a track can only
+    // overlap once their frames are too large for the exporter and wrap around
+    let mut clip_ranges = HashMap::new();
+    for track in &timeline.tracks {
+        let mut last_clip_end = 0u64;
+        for clip in &track.clips {
+            let clip_start = last_clip_end + clip.offset_frames as u64;
+            let clip_end = clip_start + clip.duration_frames as u64;
+            if clip_end > u32::MAX as u64 {
+                report.add(
+                    clip,
+                    "ends after the last frame that can be exported, so it overlaps earlier clips."
+                        .to_string(),
+                );
+            }
+            last_clip_end = clip_end;
+            clip_ranges.insert(clip.id, (clip, clip_start, clip_end));
+        }
+    }
+
+    let totals = StreamTotals::count(timeline, &clip_ranges);
+    for &(stream_name, total) in &totals.streams() {
+        if total > MAX_EXPORTED_COUNT {
+            report.issues.push(ValidationIssue {
+                clip_id: None,
+                message: format!(
+                    "The timeline has {} {}, but only {} can be exported.",
+                    total, stream_name, MAX_EXPORTED_COUNT
+                ),
+            });
+        }
+    }
+
+    for track in &timeline.tracks {
+        for clip in &track.clips {
+            match &clip.source {
+                ClipSource::Animation(_) => {
+                    validate_animation_clip(clip, &clip_ranges, &mut report)
+                }
+                _ => validate_generator_clip(clip, &clip_ranges, &mut report),
+            }
+        }
+    }
+
+    report
+}
+
+/// How many items the exporter writes to each of its streams. The player reads each total as a
+/// `u8` to split up the streams, so a total that doesn't fit corrupts everything after it.
+#[derive(Default)]
+struct StreamTotals {
+    tempo_changes: usize,
+    generator_clips: usize,
+    animation_clips: usize,
+    animated_props: usize,
+    fields: usize,
+    segments: usize,
+    modifiers: usize,
+    expressions: usize,
+    paths: usize,
+    drivers: usize,
+    constraints: usize,
+}
+
+impl StreamTotals {
+    /// Counts the items the same way the exporter writes them, leaving out overridden properties
+    /// and anything that follows a deleted clip.
+    fn count(timeline: &Timeline, clip_ranges: &ClipRanges) -> Self {
+        let mut totals = StreamTotals {
+            tempo_changes: timeline.tempo_map.changes.len(),
+            ..StreamTotals::default()
+        };
+
+        for clip in timeline.tracks.iter().flat_map(|track| track.clips.iter()) {
+            let animation = match &clip.source {
+                ClipSource::Animation(animation) => animation,
+                _ => {
+                    totals.generator_clips += 1;
+                    totals.drivers += clip
+                        .property_groups
+                        .iter()
+                        .flat_map(|group| group.defaults.iter())
+                        .filter(|default| match &default.driver {
+                            Some(driver) => {
+                                !default.is_override
+                                    && clip_ranges.contains_key(&driver.source_clip.clip_id())
+                            }
+                            None => false,
+                        })
+                        .count();
+                    continue;
+                }
+            };
+
+            // The exporter can't write an animation clip without its target at all
+            totals.animation_clips += 1;
+            let target_clip = match clip_ranges.get(&animation.target_clip.clip_id()) {
+                Some(&(target_clip, _, _)) => target_clip,
+                None => continue,
+            };
+
+            for field in animation
+                .time_property
+                .iter()
+                .chain(animation.weight_property.iter())
+            {
+                totals.add_field(field);
+            }
+
+            for property in &animation.properties {
+                let is_overridden = target_clip
+                    .property_groups
+                    .get(property.group_index)
+                    .and_then(|group| group.defaults.get(property.property_index))
+                    .map_or(false, |default| default.is_override);
+                if is_overridden {
+                    continue;
+                }
+
+                match &property.target {
+                    AnimatedPropertyTarget::Joined(field) => totals.add_field(field),
+                    AnimatedPropertyTarget::Separate(fields) => {
+                        for field in fields {
+                            totals.add_field(field);
+                        }
+                    }
+                    AnimatedPropertyTarget::Expression(_) => totals.expressions += 1,
+                    AnimatedPropertyTarget::Path(path) => {
+                        totals.paths += 1;
+                        totals.add_field(&path.progress);
+                    }
+                    AnimatedPropertyTarget::Constraint(constraint) => {
+                        if !clip_ranges.contains_key(&constraint.target().clip.clip_id()) {
+                            continue;
+                        }
+                        totals.constraints += 1;
+                    }
+                }
+                totals.animated_props += 1;
+                totals.modifiers += property.modifiers.len();
+            }
+        }
+
+        totals
+    }
+
+    fn add_field(&mut self, field: &AnimatedPropertyField) {
+        self.fields += 1;
+        self.segments += field.segments.len();
+    }
+
+    /// Each stream's total with a description of what's in it. Generator and animation clips are
+    /// both in the clip stream, and animation clips also have their own.
+    fn streams(&self) -> [(&'static str, usize); 11] {
+        [
+            ("tempo changes", self.tempo_changes),
+            ("clips", self.generator_clips + self.animation_clips),
+            ("animation clips", self.animation_clips),
+            ("animated properties", self.animated_props),
+            ("keyframed fields", self.fields),
+            ("keyframe segments", self.segments),
+            ("modifiers", self.modifiers),
+            ("expressions", self.expressions),
+            ("paths", self.paths),
+            ("drivers", self.drivers),
+            ("constraints", self.constraints),
+        ]
+    }
+}
+
+fn validate_generator_clip(clip: &Clip, clip_ranges: &ClipRanges, report: &mut ValidationReport) {
+    if clip.property_groups.len() != clip.schema.groups.len() {
+        report.add(
+            clip,
+            format!(
+                "has {} property groups, but schema {} has {}.",
+                clip.property_groups.len(),
+                clip.schema.name,
+                clip.schema.groups.len()
+            ),
+        );
+        return;
+    }
+
+    for (group, schema_group) in clip.property_groups.iter().zip(clip.schema.groups.iter()) {
+        if group.defaults.len() != schema_group.properties.len() {
+            report.add(
+                clip,
+                format!(
+                    "has {} properties in group {}, but schema {} has {}.",
+                    group.defaults.len(),
+                    schema_group.name,
+                    clip.schema.name,
+                    schema_group.properties.len()
+                ),
+            );
+            continue;
+        }
+
+        for (default, schema_prop) in group.defaults.iter().zip(schema_group.properties.iter()) {
+            validate_value(
+                clip,
+                default.value,
+                schema_prop.value_type,
+                &schema_prop.name,
+                clip_ranges,
+                report,
+            );
+        }
+    }
+}
+
+fn validate_animation_clip(clip: &Clip, clip_ranges: &ClipRanges, report: &mut ValidationReport) {
+    let animation = match &clip.source {
+        ClipSource::Animation(animation) => animation,
+        _ => return,
+    };
+
+    // The exporter can't write an animation clip without its target
+    let (_, clip_start, clip_end) = clip_ranges[&clip.id];
+    let target_clip = match clip_ranges.get(&animation.target_clip.clip_id()) {
+        Some(&(target_clip, target_start, target_end)) => {
+            if target_start >= clip_end || clip_start >= target_end {
+                report.add(
+                    clip,
+                    format!(
+                        "never overlaps its target \"{}\", so it won't do anything.",
+                        target_clip.name
+                    ),
+                );
+            }
+            target_clip
+        }
+        None => {
+            report.add(clip, "targets a clip that has been deleted.".to_string());
+            return;
+        }
+    };
+    if target_clip.schema.name != clip.schema.name {
+        report.add(
+            clip,
+            format!(
+                "animates schema {}, but its target \"{}\" is schema {}.",
+                clip.schema.name, target_clip.name, target_clip.schema.name
+            ),
+        );
+        return;
+    }
+
+    if animation.properties.len() > MAX_EXPORTED_COUNT {
+        report.add(
+            clip,
+            format!(
+                "animates {} properties, but only {} can be exported.",
+                animation.properties.len(),
+                MAX_EXPORTED_COUNT
+            ),
+        );
+    }
+
+    let time_and_weight_fields = animation
+        .time_property
+        .iter()
+        .map(|field| ("time", field))
+        .chain(
+            animation
+                .weight_property
+                .iter()
+                .map(|field| ("weight", field)),
+        );
+    for (name, field) in time_and_weight_fields {
+        validate_field(clip, field, PropertyType::Float, name, clip_ranges, report);
+    }
+
+    for property in &animation.properties {
+        let schema_prop = match clip
+            .schema
+            .groups
+            .get(property.group_index)
+            .and_then(|group| group.properties.get(property.property_index))
+        {
+            Some(schema_prop) => schema_prop,
+            None => {
+                report.add(
+                    clip,
+                    format!(
+                        "animates property {} of group {}, which schema {} doesn't have.",
+                        property.property_index, property.group_index, clip.schema.name
+                    ),
+                );
+                continue;
+            }
+        };
+
+        let is_overridden = target_clip
+            .property_groups
+            .get(property.group_index)
+            .and_then(|group| group.defaults.get(property.property_index))
+            .map_or(false, |default| default.is_override);
+        if is_overridden {
+            report.add(
+                clip,
+                format!(
+                    "animates {}, which is overridden on \"{}\", so the animation won't be exported.",
+                    schema_prop.name, target_clip.name
+                ),
+            );
+        }
+
+        let value_type = schema_prop.value_type;
+        match &property.target {
+            AnimatedPropertyTarget::Joined(field) => {
+                validate_field(
+                    clip,
+                    field,
+                    value_type,
+                    &schema_prop.name,
+                    clip_ranges,
+                    report,
+                );
+            }
+            AnimatedPropertyTarget::Separate(fields) => {
+                if fields.len() != value_type.num_fields() {
+                    report.add(
+                        clip,
+                        format!(
+                            "animates {} in {} separate fields, but it has {}.",
+                            schema_prop.name,
+                            fields.len(),
+                            value_type.num_fields()
+                        ),
+                    );
+                }
+                for field in fields {
+                    validate_field(
+                        clip,
+                        field,
+                        PropertyType::Float,
+                        &schema_prop.name,
+                        clip_ranges,
+                        report,
+                    );
+                }
+            }
+            AnimatedPropertyTarget::Path(path) => {
+                if value_type != PropertyType::Vec3 {
+                    report.add(
+                        clip,
+                        format!(
+                            "animates {} along a path, but it isn't a Vec3.",
+                            schema_prop.name
+                        ),
+                    );
+                }
+                validate_field(
+                    clip,
+                    &path.progress,
+                    PropertyType::Float,
+                    &schema_prop.name,
+                    clip_ranges,
+                    report,
+                );
+            }
+            AnimatedPropertyTarget::Constraint(constraint) => {
+                let constraint_target = constraint.target().clip.clip_id();
+                if !clip_ranges.contains_key(&constraint_target) {
+                    report.add(
+                        clip,
+                        format!(
+                            "constrains {} to a clip that has been deleted.",
+                            schema_prop.name
+                        ),
+                    );
+                }
+            }
+            AnimatedPropertyTarget::Expression(_) => {}
+        }
+    }
+}
+
+fn validate_field(
+    clip: &Clip,
+    field: &AnimatedPropertyField,
+    value_type: PropertyType,
+    name: &str,
+    clip_ranges: &ClipRanges,
+    report: &mut ValidationReport,
+) {
+    if field.segments.len() > MAX_EXPORTED_COUNT {
+        report.add(
+            clip,
+            format!(
+                "has a field with {} keyframe segments, but only {} can be exported.",
+                field.segments.len(),
+                MAX_EXPORTED_COUNT
+            ),
+        );
+    }
+
+    let keyframe_values =
+        iter::once(field.start_value).chain(field.segments.iter().map(|segment| segment.end_value));
+    for value in keyframe_values {
+        validate_value(clip, value, value_type, name, clip_ranges, report);
+    }
+}
+
+fn validate_value(
+    clip: &Clip,
+    value: PropertyValue,
+    value_type: PropertyType,
+    name: &str,
+    clip_ranges: &ClipRanges,
+    report: &mut ValidationReport,
+) {
+    if value.get_type() != value_type {
+        report.add(
+            clip,
+            format!(
+                "has a {} value for {}, where a {} is expected.",
+                type_name(value.get_type()),
+                name,
+                type_name(value_type)
+            ),
+        );
+    }
+
+    if let PropertyValue::ClipReference(Some(reference)) = value {
+        if !clip_ranges.contains_key(&reference.clip_id()) {
+            report.add(
+                clip,
+                format!("{} references a clip that has been deleted.", name),
+            );
+        }
+    }
+}
+
+fn type_name(value_type: PropertyType) -> &'static str {
+    match value_type {
+        PropertyType::Float => "Float",
+        PropertyType::Vec2 => "Vec2",
+        PropertyType::Vec3 => "Vec3",
+        PropertyType::Vec4 => "Vec4",
+        PropertyType::RgbColor => "RgbColor",
+        PropertyType::RgbaColor => "RgbaColor",
+        PropertyType::Rotation => "Rotation",
+        PropertyType::ClipReference => "ClipReference",
+        PropertyType::Int => "Int",
+        PropertyType::Bool => "Bool",
+        PropertyType::Enum(_) => "Enum",
+        PropertyType::Gradient => "Gradient",
+    }
+}
@@ -390,3 +390,433 @@ pub fn param_list(_attr: TokenStream, item: TokenStream) -> TokenStream {
 
     result_stream.into()
 }
+
+struct SchemaField {
+    ident: syn::Ident,
+    names: Vec<String>,
+    value_type: proc_macro2::TokenStream,
+    is_array: bool,
+    metadata: SchemaFieldMetadata,
+}
+
+#[derive(Default)]
+struct SchemaFieldMetadata {
+    default_fields: Option<Vec<f32>>,
+    soft_min: Option<f32>,
+    soft_max: Option<f32>,
+    min: Option<f32>,
+    max: Option<f32>,
+    step: Option<f32>,
+    unit: Option<String>,
+    options: Option<Vec<String>>,
+    description: Option<String>,
+}
+
+struct SchemaFieldGroup {
+    name: String,
+    fields: Vec<SchemaField>,
+}
+
+/// Finds the `PropertyType` variant for a field type, along with how many fields its values have.
+fn schema_value_type(ty: &str) -> Option<(&'static str, usize)> {
+    match ty {
+        "f32" => Some(("Float", 1)),
+        "Vector2" => Some(("Vec2", 2)),
+        "Vector3" => Some(("Vec3", 3)),
+        "Vector4" => Some(("Vec4", 4)),
+        "RgbColor" => Some(("RgbColor", 3)),
+        "RgbaColor" => Some(("RgbaColor", 4)),
+        "Quaternion" => Some(("Rotation", 3)),
+        "Option < ClipReference >" => Some(("ClipReference", 1)),
+        "i32" => Some(("Int", 1)),
+        "bool" => Some(("Bool", 1)),
+        "u32" => Some(("Enum", 1)),
+        // A stop count, then the position and colour of each of the `MAX_GRADIENT_STOPS` stops
+        "Gradient" => Some(("Gradient", 1 + 5 * 8)),
+        _ => None,
+    }
+}
+
+fn string_attr_value(lit: &syn::Lit) -> syn::Result<String> {
+    match lit {
+        syn::Lit::Str(lit_str) => Ok(lit_str.value()),
+        lit => Err(syn::Error::new(lit.span(), "Expected a string")),
+    }
+}
+
+/// Floats can be given as numbers or strings, since negative numbers can't be used in attributes.
+fn float_attr_value(lit: &syn::Lit) -> syn::Result<f32> {
+    match lit {
+        syn::Lit::Float(lit_float) => Ok(lit_float.value() as f32),
+        syn::Lit::Int(lit_int) => Ok(lit_int.value() as f32),
+        syn::Lit::Str(lit_str) => lit_str
+            .value()
+            .trim()
+            .parse()
+            .map_err(|_| syn::Error::new(lit.span(), "Expected a number")),
+        lit => Err(syn::Error::new(lit.span(), "Expected a number")),
+    }
+}
+
+/// Parses a comma-separated list of numbers, like `"0, 1, 0"`.
+fn float_list_attr_value(lit: &syn::Lit) -> syn::Result<Vec<f32>> {
+    match lit {
+        syn::Lit::Str(lit_str) => lit_str
+            .value()
+            .split(',')
+            .map(|field| field.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| syn::Error::new(lit.span(), "Expected a list of numbers")),
+        lit => float_attr_value(lit).map(|value| vec![value]),
+    }
+}
+
+fn parse_schema_groups(fields: &syn::FieldsNamed) -> syn::Result<Vec<SchemaFieldGroup>> {
+    let mut groups: Vec<SchemaFieldGroup> = Vec::new();
+
+    for field in &fields.named {
+        let field_ident = field.ident.clone().unwrap();
+        let mut name = None;
+        let mut metadata = SchemaFieldMetadata::default();
+
+        for attr in &field.attrs {
+            match attr.parse_meta()? {
+                // Doc comments become the property's description
+                syn::Meta::NameValue(meta) if meta.ident.to_string() == "doc" => {
+                    let line = string_attr_value(&meta.lit)?;
+                    let description = metadata.description.get_or_insert_with(String::new);
+                    if !description.is_empty() {
+                        description.push(' ');
+                    }
+                    description.push_str(line.trim());
+                }
+                // #[group = "name"] starts a new group
+                syn::Meta::NameValue(meta) if meta.ident.to_string() == "group" => {
+                    groups.push(SchemaFieldGroup {
+                        name: string_attr_value(&meta.lit)?,
+                        fields: Vec::new(),
+                    });
+                }
+                // #[property(name = "name")] overrides the name shown in the tool
+                syn::Meta::List(meta) if meta.ident.to_string() == "property" => {
+                    for nested in &meta.nested {
+                        match nested {
+                            syn::NestedMeta::Meta(syn::Meta::NameValue(value)) => {
+                                match value.ident.to_string().as_str() {
+                                    "name" => name = Some(string_attr_value(&value.lit)?),
+                                    "default" => {
+                                        metadata.default_fields =
+                                            Some(float_list_attr_value(&value.lit)?)
+                                    }
+                                    "soft_min" => {
+                                        metadata.soft_min = Some(float_attr_value(&value.lit)?)
+                                    }
+                                    "soft_max" => {
+                                        metadata.soft_max = Some(float_attr_value(&value.lit)?)
+                                    }
+                                    "min" => metadata.min = Some(float_attr_value(&value.lit)?),
+                                    "max" => metadata.max = Some(float_attr_value(&value.lit)?),
+                                    "step" => metadata.step = Some(float_attr_value(&value.lit)?),
+                                    "unit" => metadata.unit = Some(string_attr_value(&value.lit)?),
+                                    "options" => {
+                                        let options = string_attr_value(&value.lit)?;
+                                        metadata.options = Some(
+                                            options
+                                                .split(',')
+                                                .map(|option| option.trim().to_string())
+                                                .collect(),
+                                        );
+                                    }
+                                    _ => {
+                                        return Err(syn::Error::new(
+                                            value.span(),
+                                            "Unknown property attribute",
+                                        ));
+                                    }
+                                }
+                            }
+                            nested => {
+                                return Err(syn::Error::new(
+                                    nested.span(),
+                                    "Unknown property attribute",
+                                ));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Fields before the first #[group] go in an unnamed group
+        if groups.is_empty() {
+            groups.push(SchemaFieldGroup {
+                name: String::new(),
+                fields: Vec::new(),
+            });
+        }
+
+        let name = name.unwrap_or_else(|| field_ident.to_string().replace('_', " "));
+        let (element_type, names, is_array) = match &field.ty {
+            // Arrays become one property for each element, numbered from 1
+            syn::Type::Array(array) => {
+                let len = match &array.len {
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Int(len),
+                        ..
+                    }) => len.value() as usize,
+                    len => {
+                        return Err(syn::Error::new(
+                            len.span(),
+                            "Array properties must have a literal length",
+                        ));
+                    }
+                };
+                let names = (1..=len).map(|index| format!("{}{}", name, index));
+                (&*array.elem, names.collect(), true)
+            }
+            ty => (ty, vec![name], false),
+        };
+
+        let type_str = element_type.into_token_stream().to_string();
+        let value_type = match schema_value_type(&type_str) {
+            Some((value_type, field_count)) => {
+                if let Some(default_fields) = &metadata.default_fields {
+                    if default_fields.len() != field_count {
+                        return Err(syn::Error::new(
+                            field.span(),
+                            format!(
+                                "Property {} needs {} default fields, but has {}",
+                                field_ident,
+                                field_count,
+                                default_fields.len()
+                            ),
+                        ));
+                    }
+                }
+                let value_ident = syn::Ident::new(value_type, element_type.span());
+                match (value_type, &metadata.options) {
+                    ("Enum", Some(options)) => quote! { #value_ident(&[#(#options),*]) },
+                    ("Enum", None) => {
+                        return Err(syn::Error::new(
+                            field.span(),
+                            format!("Enum property {} needs a list of options", field_ident),
+                        ));
+                    }
+                    (_, Some(_)) => {
+                        return Err(syn::Error::new(
+                            field.span(),
+                            format!("Property {} has options but isn't an enum", field_ident),
+                        ));
+                    }
+                    (_, None) => quote! { #value_ident },
+                }
+            }
+            None => {
+                return Err(syn::Error::new(
+                    element_type.span(),
+                    format!(
+                        "Property {} has unknown field type: {}",
+                        field_ident, type_str
+                    ),
+                ));
+            }
+        };
+
+        groups.last_mut().unwrap().fields.push(SchemaField {
+            ident: field_ident,
+            names,
+            value_type,
+            is_array,
+            metadata,
+        });
+    }
+
+    Ok(groups)
+}
+
+fn float_tokens(value: f32) -> proc_macro2::TokenStream {
+    if value == std::f32::INFINITY {
+        quote! { core::f32::INFINITY }
+    } else if value == std::f32::NEG_INFINITY {
+        quote! { core::f32::NEG_INFINITY }
+    } else if value.is_sign_negative() {
+        let literal = proc_macro2::Literal::f32_unsuffixed(-value);
+        quote! { -#literal }
+    } else {
+        let literal = proc_macro2::Literal::f32_unsuffixed(value);
+        quote! { #literal }
+    }
+}
+
+fn option_tokens(value: Option<proc_macro2::TokenStream>) -> proc_macro2::TokenStream {
+    match value {
+        Some(value) => quote! { Some(#value) },
+        None => quote! { None },
+    }
+}
+
+/// A range with only one end given is unbounded at the other.
+fn range_tokens(min: Option<f32>, max: Option<f32>) -> proc_macro2::TokenStream {
+    if min.is_none() && max.is_none() {
+        return quote! { None };
+    }
+    let min = float_tokens(min.unwrap_or(std::f32::NEG_INFINITY));
+    let max = float_tokens(max.unwrap_or(std::f32::INFINITY));
+    quote! { Some((#min, #max)) }
+}
+
+/// Generates a generator's schema groups from a struct of its properties, along with a
+/// `from_properties` function that reads the struct from a clip's property values.
+///
+/// `#[group = "name"]` on a field starts a new group. Properties are named after their fields
+/// unless renamed with `#[property(name = "name")]`. Array fields become a property for each
+/// element, with the element's number appended to the name.
+///
+/// `#[property(...)]` also takes metadata the editor uses:
+///  - `default = "0, 1, 0"`: the fields of the value new clips start with. Rotations are given as
+///    euler angles in degrees.
+///  - `min` and `max`: the hard range, which every field is clamped to.
+///  - `soft_min` and `soft_max`: the range the value can be dragged in.
+///  - `step`: how much the value changes for each pixel it's dragged.
+///  - `unit = "degrees"`: shown after the value.
+///  - `options = "a, b, c"`: the names of an enum's values. These are required for `u32` fields,
+///    which are enums, and are kept in release builds.
+///
+/// Numbers can be given as strings, which is needed for negative numbers. A field's doc comment
+/// becomes the property's description. None of this is included in release builds.
+///
+/// A `PropertyBinding` constant is also generated for each non-array field, named after the field
+/// in upper case with a `_BINDING` suffix.
+#[proc_macro_derive(Properties, attributes(group, property))]
+pub fn derive_properties(item: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(item as syn::DeriveInput);
+    let fields = match &ast.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => fields,
+        _ => {
+            return syn::Error::new(
+                Span::call_site().into(),
+                "#[derive(Properties)] can only be used on structs with named fields",
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
+    let groups = match parse_schema_groups(fields) {
+        Ok(groups) => groups,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let mut groups_stream = proc_macro2::TokenStream::new();
+    let mut bindings_stream = proc_macro2::TokenStream::new();
+    let mut fields_stream = proc_macro2::TokenStream::new();
+    for (group_index, group) in groups.iter().enumerate() {
+        let group_name = &group.name;
+        let group_index = proc_macro2::Literal::usize_unsuffixed(group_index);
+        let mut properties_stream = proc_macro2::TokenStream::new();
+        let mut prop_index = 0;
+
+        for field in &group.fields {
+            let field_ident = &field.ident;
+            let value_type = &field.value_type;
+            let mut getters = Vec::new();
+
+            let metadata = &field.metadata;
+            let default_fields = option_tokens(metadata.default_fields.as_ref().map(|fields| {
+                let fields = fields.iter().map(|&field| float_tokens(field));
+                quote! { &[#(#fields),*] }
+            }));
+            let soft_range = range_tokens(metadata.soft_min, metadata.soft_max);
+            let hard_range = range_tokens(metadata.min, metadata.max);
+            let step = option_tokens(metadata.step.map(float_tokens));
+            let unit = option_tokens(metadata.unit.as_ref().map(|unit| quote! { #unit }));
+            let description = option_tokens(
+                metadata
+                    .description
+                    .as_ref()
+                    .map(|description| quote! { #description }),
+            );
+
+            for name in &field.names {
+                properties_stream.extend(quote! {
+                    crate::animation::schema::SchemaProperty {
+                        #[cfg(debug_assertions)]
+                        name: #name,
+                        value_type: crate::animation::property::PropertyType::#value_type,
+                        #[cfg(debug_assertions)]
+                        default_fields: #default_fields,
+                        #[cfg(debug_assertions)]
+                        soft_range: #soft_range,
+                        #[cfg(debug_assertions)]
+                        hard_range: #hard_range,
+                        #[cfg(debug_assertions)]
+                        step: #step,
+                        #[cfg(debug_assertions)]
+                        unit: #unit,
+                        #[cfg(debug_assertions)]
+                        description: #description,
+                    },
+                });
+                let prop_literal = proc_macro2::Literal::usize_unsuffixed(prop_index);
+                getters.push(quote! {
+                    crate::animation::property::prop(properties, #group_index, #prop_literal)
+                });
+                prop_index += 1;
+            }
+
+            if field.is_array {
+                fields_stream.extend(quote! {
+                    #field_ident: [#(#getters),*],
+                });
+            } else {
+                let getter = &getters[0];
+                fields_stream.extend(quote! {
+                    #field_ident: #getter,
+                });
+
+                let binding_prop_index = proc_macro2::Literal::usize_unsuffixed(prop_index - 1);
+                let binding_ident = syn::Ident::new(
+                    &format!("{}_BINDING", field_ident.to_string().to_uppercase()),
+                    field_ident.span(),
+                );
+                bindings_stream.extend(quote! {
+                    pub const #binding_ident: crate::binding::PropertyBinding =
+                        crate::binding::PropertyBinding {
+                            group: #group_index,
+                            prop: #binding_prop_index,
+                        };
+                });
+            }
+        }
+
+        groups_stream.extend(quote! {
+            crate::animation::schema::SchemaGroup {
+                #[cfg(debug_assertions)]
+                name: #group_name,
+                properties: &[#properties_stream],
+            },
+        });
+    }
+
+    let struct_ident = &ast.ident;
+    let result_stream = quote! {
+        impl #struct_ident {
+            pub const GROUPS: &'static [crate::animation::schema::SchemaGroup] = &[#groups_stream];
+
+            #bindings_stream
+
+            pub fn from_properties(
+                properties: &[&[crate::animation::clip::ClipPropertyValue]],
+            ) -> Self {
+                #struct_ident {
+                    #fields_stream
+                }
+            }
+        }
+    };
+
+    result_stream.into()
+}